@@ -0,0 +1,183 @@
+//! Command-line arguments.
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use solana_program::pubkey::Pubkey;
+
+use crate::output::Format;
+
+/// Operates fanitrade lotteries and Fanitrax markets.
+#[derive(Parser, Debug)]
+#[clap(name = "fanitrade-cli", version)]
+pub struct Cli {
+    /// Config file with the program id, RPC URL and keypair
+    /// [default: ~/.config/fanitrade/cli.toml]
+    #[clap(long, global = true)]
+    pub config: Option<PathBuf>,
+    #[clap(long, global = true, value_enum, default_value = "table")]
+    pub output: Format,
+    /// Simulate transactions instead of sending them
+    #[clap(long, global = true)]
+    pub dry_run: bool,
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create a one-off lottery administered by the configured keypair
+    CreateLottery(CreateLottery),
+    /// List the program's lotteries
+    ListLotteries,
+    /// Show a lottery
+    ShowLottery { lottery: Pubkey },
+    /// Draw a lottery from its revealed randomness, or from its oracle's proof,
+    /// and tally its tickets
+    Draw { lottery: Pubkey },
+    /// Withdraw a lottery's collected fees to the admin
+    WithdrawFees {
+        lottery: Pubkey,
+        /// Amount to withdraw [default: all collected fees]
+        #[clap(long)]
+        amount: Option<u64>,
+    },
+    /// Create the Fanitrax account administered by the configured keypair
+    InitTrax,
+    /// Create a Fanitrax market administered by the configured keypair
+    CreateMarket(CreateMarket),
+    /// List the program's markets
+    ListMarkets,
+    /// Stop a market from taking bets
+    PauseMarket {
+        market: Pubkey,
+        /// Reopen the market instead
+        #[clap(long)]
+        resume: bool,
+    },
+    /// Settle a bet, paying out its account to the configured keypair
+    Settle {
+        bet: Pubkey,
+        #[clap(long)]
+        final_price: String,
+        /// `won` or `lost`
+        #[clap(long)]
+        result: String,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub struct CreateLottery {
+    #[clap(long)]
+    pub name: String,
+    /// Token tickets are paid in
+    #[clap(long)]
+    pub mint: Pubkey,
+    #[clap(long)]
+    pub ticket_price: u64,
+    /// Unix timestamp ticket sales open at [default: now]
+    #[clap(long)]
+    pub start: Option<i64>,
+    /// Seconds ticket sales stay open
+    #[clap(long, default_value_t = 86_400)]
+    pub duration: u64,
+    /// Admin fee, in basis points of the pot
+    #[clap(long, default_value_t = 1_000)]
+    pub fee_bps: u16,
+    /// Share of the pot per prize tier, in basis points
+    #[clap(long, value_delimiter = ',', default_value = "5000,3000,1000")]
+    pub prize_split: Vec<u16>,
+    #[clap(long, default_value_t = 3)]
+    pub pick_count: u8,
+    #[clap(long, default_value_t = 10)]
+    pub max_number: u8,
+    /// Seconds winners have to claim their prize
+    #[clap(long, default_value_t = 604_800)]
+    pub claim_period: u64,
+    /// Tokens locked in the pot until the randomness is revealed
+    #[clap(long, default_value_t = 0)]
+    pub bond: u64,
+    /// VRF oracle program to draw from instead of commit-reveal
+    #[clap(long)]
+    pub oracle: Option<Pubkey>,
+    /// Keep tickets in one ledger account with room for this many, instead
+    /// of an account per ticket
+    #[clap(long)]
+    pub ledger_capacity: Option<u64>,
+    /// Mint every ticket as a token; prizes go to whoever holds it. Pick-N
+    /// lotteries only
+    #[clap(long)]
+    pub ticket_nfts: bool,
+    /// Share of the ticket price paid to referrers, in basis points
+    #[clap(long, default_value_t = 0)]
+    pub referral_bps: u16,
+    /// Tickets to sell by the end of sales, or the lottery is cancelled and
+    /// refunded
+    #[clap(long, default_value_t = 0)]
+    pub min_entries: u64,
+    /// Tickets on sale [default: no cap]
+    #[clap(long, default_value_t = 0)]
+    pub max_entries: u64,
+    /// File of the wallets allowed to play, one per line, each optionally
+    /// followed by a comma and the tickets it may buy [default: anyone]
+    #[clap(long)]
+    pub allowlist: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct CreateMarket {
+    /// Fanitrax account the market belongs to
+    #[clap(long)]
+    pub trax: Pubkey,
+    /// Traded pair, e.g. SOL/USDC
+    #[clap(long)]
+    pub pair: String,
+    #[clap(long)]
+    pub last_price: u64,
+    #[clap(long)]
+    pub upper_limit: u64,
+    #[clap(long)]
+    pub lower_limit: u64,
+    #[clap(long, default_value_t = 0)]
+    pub apy: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn arguments_are_consistent() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn parses_global_flags_after_the_subcommand() {
+        let cli = Cli::try_parse_from([
+            "fanitrade-cli",
+            "create-lottery",
+            "--name",
+            "Weekly",
+            "--mint",
+            "11111111111111111111111111111111",
+            "--ticket-price",
+            "100",
+            "--prize-split",
+            "6000,2000",
+            "--dry-run",
+            "--output",
+            "json",
+        ])
+        .unwrap();
+        assert!(cli.dry_run);
+        assert_eq!(cli.output, Format::Json);
+        match cli.command {
+            Command::CreateLottery(args) => {
+                assert_eq!(args.prize_split, vec![6_000, 2_000]);
+                assert_eq!(args.pick_count, 3);
+            }
+            other => panic!("parsed {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! The CLI's config file, `~/.config/fanitrade/cli.toml` by default:
+//!
+//! ```toml
+//! program_id = "..."
+//! rpc_url = "http://127.0.0.1:8899"
+//! keypair_path = "~/.config/solana/id.json"
+//! ```
+use std::{error::Error, fs, path::PathBuf};
+
+use serde::Deserialize;
+use solana_program::pubkey::Pubkey;
+
+const DEFAULT_RPC_URL: &str = "http://127.0.0.1:8899";
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Base58 address of the deployed program.
+    pub program_id: String,
+    #[serde(default = "default_rpc_url")]
+    pub rpc_url: String,
+    /// Keypair that signs and pays for transactions; a leading `~` is the
+    /// home directory.
+    #[serde(default = "default_keypair_path")]
+    pub keypair_path: String,
+}
+
+fn default_rpc_url() -> String {
+    DEFAULT_RPC_URL.to_string()
+}
+
+fn default_keypair_path() -> String {
+    "~/.config/solana/id.json".to_string()
+}
+
+/// Where the config is read from when `--config` isn't given.
+pub fn default_path() -> Option<PathBuf> {
+    dirs_next::config_dir().map(|dir| dir.join("fanitrade").join("cli.toml"))
+}
+
+impl Config {
+    pub fn load(path: &PathBuf) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("can't read config {}: {}", path.display(), error))?;
+        Self::parse(&text)
+            .map_err(|error| format!("invalid config {}: {}", path.display(), error).into())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    pub fn program_id(&self) -> Result<Pubkey, Box<dyn Error>> {
+        self.program_id
+            .parse()
+            .map_err(|_| format!("program_id {:?} isn't a valid address", self.program_id).into())
+    }
+
+    pub fn keypair_path(&self) -> PathBuf {
+        match self.keypair_path.strip_prefix("~/") {
+            Some(rest) => dirs_next::home_dir().unwrap_or_default().join(rest),
+            None => PathBuf::from(&self.keypair_path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fills_in_defaults() {
+        let config = Config::parse("program_id = \"11111111111111111111111111111111\"").unwrap();
+        assert_eq!(config.rpc_url, DEFAULT_RPC_URL);
+        assert_eq!(config.program_id().unwrap(), Pubkey::default());
+        assert!(config.keypair_path().ends_with(".config/solana/id.json"));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_keys() {
+        assert!(Config::parse("program_id = \"x\"\nrpc = \"y\"").is_err());
+        assert!(Config::parse("rpc_url = \"y\"").is_err());
+    }
+}
@@ -0,0 +1,493 @@
+//! `fanitrade-cli`: operates lotteries and Fanitrax markets from the command
+//! line. See `fanitrade-cli --help`.
+mod cli;
+mod config;
+mod output;
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::Parser;
+use fanitrade_client::{
+    allowlist, instruction, ledger, pda, rpc,
+    state::{LotteryDetails, MarketDetails, ResultDetails, TraxDetails},
+    MARKET_OPEN, MARKET_PAUSED,
+};
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    signature::{read_keypair_file, Keypair, Signer},
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+
+use crate::{
+    cli::{Cli, Command, CreateLottery, CreateMarket},
+    config::Config,
+    output::{Format, LotteryView, MarketView, TransactionReport},
+};
+
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Tickets counted per `TallyTickets` transaction, which passes each ticket.
+const TALLY_BATCH: u64 = 20;
+/// Tickets counted per `TallyTickets` transaction of a compact lottery, which
+/// only passes the ledger.
+const LEDGER_TALLY_BATCH: u64 = 200;
+
+struct Context {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    payer: Keypair,
+    format: Format,
+    dry_run: bool,
+}
+
+impl Context {
+    fn new(cli: &Cli) -> Result<Self> {
+        let path = match &cli.config {
+            Some(path) => path.clone(),
+            None => config::default_path().ok_or("can't find the config directory")?,
+        };
+        let config = Config::load(&path)?;
+        let keypair_path = config.keypair_path();
+        let payer = read_keypair_file(&keypair_path)
+            .map_err(|error| format!("can't read keypair {}: {}", keypair_path.display(), error))?;
+        Ok(Self {
+            rpc: RpcClient::new_with_commitment(
+                config.rpc_url.clone(),
+                CommitmentConfig::confirmed(),
+            ),
+            program_id: config.program_id()?,
+            payer,
+            format: cli.output,
+            dry_run: cli.dry_run,
+        })
+    }
+
+    fn print<T: Serialize>(&self, view: &T) {
+        match self.format {
+            Format::Table => println!("{}", output::key_values(view)),
+            Format::Json => println!("{}", output::json(view)),
+        }
+    }
+
+    fn print_list<T: Serialize>(&self, columns: &[&str], rows: &[T]) {
+        match self.format {
+            Format::Table => println!("{}", output::table(columns, rows)),
+            Format::Json => println!("{}", output::json(&rows)),
+        }
+    }
+
+    /// Sends `instructions` signed by the payer and `signers`, or simulates
+    /// them with `--dry-run`, and reports the outcome.
+    fn submit(
+        &self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+        accounts: BTreeMap<&'static str, String>,
+    ) -> Result<()> {
+        let mut all_signers: Vec<&dyn Signer> = vec![&self.payer];
+        all_signers.extend(signers.iter().map(|signer| *signer as &dyn Signer));
+        let transaction =
+            rpc::build_transaction(&self.rpc, instructions, &self.payer.pubkey(), &all_signers)?;
+        let mut report = TransactionReport {
+            dry_run: self.dry_run,
+            accounts,
+            ..TransactionReport::default()
+        };
+        if self.dry_run {
+            let simulation = self.rpc.simulate_transaction(&transaction)?.value;
+            report.error = simulation.err.map(|error| error.to_string());
+            report.logs = simulation.logs.unwrap_or_default();
+        } else {
+            let signature = self.rpc.send_and_confirm_transaction(&transaction)?;
+            report.signature = Some(signature.to_string());
+        }
+        self.print(&report);
+        match &report.error {
+            Some(error) => Err(format!("simulation failed: {}", error).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Creates the payer's associated token account for `mint` if it is
+    /// missing.
+    fn payer_token_account(
+        &self,
+        mint: &Pubkey,
+        instructions: &mut Vec<Instruction>,
+    ) -> Result<Pubkey> {
+        let payer = self.payer.pubkey();
+        let address = get_associated_token_address(&payer, mint);
+        if self
+            .rpc
+            .get_account_with_commitment(&address, self.rpc.commitment())?
+            .value
+            .is_none()
+        {
+            instructions.push(create_associated_token_account(&payer, &payer, mint));
+        }
+        Ok(address)
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Root of the allowlist in `path`: a wallet per line, optionally followed by
+/// `,` and its ticket allocation.
+fn allowlist_root(path: &Path) -> Result<[u8; 32]> {
+    let mut leaves = Vec::new();
+    for line in fs::read_to_string(path)?.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (wallet, allocation) = match line.split_once(',') {
+            Some((wallet, allocation)) => (wallet, allocation.trim().parse()?),
+            None => (line, 0),
+        };
+        leaves.push(allowlist::leaf(&wallet.trim().parse()?, allocation));
+    }
+    if leaves.is_empty() {
+        return Err(format!("{} lists no wallets", path.display()).into());
+    }
+    Ok(allowlist::root(&leaves))
+}
+
+fn create_lottery(context: &Context, args: &CreateLottery) -> Result<()> {
+    let admin = context.payer.pubkey();
+    let lottery_start = args.start.unwrap_or_else(now);
+    let mut details = LotteryDetails {
+        admin,
+        name: args.name.clone(),
+        is_initialized: 0,
+        is_ended: 0,
+        lottery_start: lottery_start.to_string(),
+        lottery_end: lottery_start
+            .saturating_add(args.duration as i64)
+            .to_string(),
+        ticket_price: args.ticket_price,
+        amount_in_pot: 0,
+        total_entries: 0,
+        token_mint: args.mint,
+        series: Pubkey::default(),
+        round: 0,
+        fee_bps: args.fee_bps,
+        prize_split: args.prize_split.clone(),
+        pick_count: args.pick_count,
+        max_number: args.max_number,
+        winning_numbers: Vec::new(),
+        claim_period: args.claim_period,
+        fees_collected: 0,
+        bond_amount: args.bond,
+        randomness_commitment: [0; 32],
+        commit_slot: 0,
+        randomness: [0; 32],
+        is_revealed: 0,
+        is_refunding: 0,
+        randomness_oracle: args.oracle.unwrap_or_default(),
+        ticket_ledger: Pubkey::default(),
+        ticket_nfts: u64::from(args.ticket_nfts),
+        referral_bps: args.referral_bps,
+        referral_rewards: 0,
+        sponsored_prizes: 0,
+        min_entries: args.min_entries,
+        max_entries: args.max_entries,
+        allowlist_root: match &args.allowlist {
+            Some(path) => allowlist_root(path)?,
+            None => [0; 32],
+        },
+        close_slot: 0,
+        sponsored_amount: 0,
+    };
+    let lottery = Keypair::new();
+    let ticket_ledger = Keypair::new();
+    let program_id = &context.program_id;
+    let (pot_authority, _) = pda::find_pot_authority(program_id, &lottery.pubkey());
+    let mut instructions = vec![rpc::create_state_account(
+        &context.rpc,
+        program_id,
+        &admin,
+        &lottery.pubkey(),
+        instruction::lottery_space(&details),
+    )?];
+    let mut signers = vec![&lottery];
+    if let Some(capacity) = args.ledger_capacity {
+        details.ticket_ledger = ticket_ledger.pubkey();
+        instructions.push(rpc::create_state_account(
+            &context.rpc,
+            program_id,
+            &admin,
+            &ticket_ledger.pubkey(),
+            ledger::space(capacity),
+        )?);
+        signers.push(&ticket_ledger);
+    }
+    // The pot has to exist before create_lottery moves a bond into it.
+    instructions.extend([
+        create_associated_token_account(&admin, &pot_authority, &args.mint),
+        instruction::create_lottery(program_id, &lottery.pubkey(), &details),
+    ]);
+    let mut accounts = BTreeMap::from([
+        ("lottery", lottery.pubkey().to_string()),
+        (
+            "pot",
+            pda::pot_address(program_id, &lottery.pubkey(), &args.mint).to_string(),
+        ),
+    ]);
+    if args.ledger_capacity.is_some() {
+        accounts.insert("ticket_ledger", ticket_ledger.pubkey().to_string());
+    }
+    context.submit(&instructions, &signers, accounts)
+}
+
+fn list_lotteries(context: &Context) -> Result<()> {
+    let mut lotteries: Vec<(Pubkey, LotteryDetails)> =
+        rpc::get_program_states(&context.rpc, &context.program_id)?;
+    lotteries.sort_by_key(|(a, _)| a.to_string());
+    let views: Vec<LotteryView> = lotteries
+        .iter()
+        .map(|(address, details)| LotteryView::new(address, details))
+        .collect();
+    context.print_list(
+        &[
+            "address",
+            "name",
+            "status",
+            "lottery_end",
+            "ticket_price",
+            "total_entries",
+            "amount_in_pot",
+        ],
+        &views,
+    );
+    Ok(())
+}
+
+fn show_lottery(context: &Context, lottery: &Pubkey) -> Result<()> {
+    let details = rpc::get_lottery(&context.rpc, lottery)?;
+    context.print(&LotteryView::new(lottery, &details));
+    Ok(())
+}
+
+/// Draws a lottery unless it already was, then tallies its tickets in
+/// batches until the prizes can be claimed. A dry run simulates the first
+/// transaction still to send.
+fn draw(context: &Context, lottery: &Pubkey) -> Result<()> {
+    let details = rpc::get_lottery(&context.rpc, lottery)?;
+    let program_id = &context.program_id;
+    let payer = context.payer.pubkey();
+    let accounts = BTreeMap::from([
+        ("lottery", lottery.to_string()),
+        (
+            "results",
+            pda::find_results_address(program_id, lottery).0.to_string(),
+        ),
+    ]);
+    let mut tallied = match rpc::get_results(&context.rpc, program_id, lottery) {
+        Ok(results) => results.tallied,
+        Err(_) => {
+            let instruction = if details.randomness_oracle == Pubkey::default() {
+                instruction::draw_lottery(program_id, lottery, &payer)
+            } else {
+                instruction::fulfill_randomness(program_id, lottery, &details, &payer)
+            };
+            context.submit(&[instruction], &[], accounts.clone())?;
+            if context.dry_run {
+                return Ok(());
+            }
+            rpc::get_results(&context.rpc, program_id, lottery)?.tallied
+        }
+    };
+    let batch = if details.ticket_ledger == Pubkey::default() {
+        TALLY_BATCH
+    } else {
+        LEDGER_TALLY_BATCH
+    };
+    while tallied < details.total_entries {
+        let count = batch.min(details.total_entries - tallied);
+        let instruction = instruction::tally_tickets(program_id, lottery, &details, tallied, count);
+        context.submit(&[instruction], &[], accounts.clone())?;
+        if context.dry_run {
+            break;
+        }
+        tallied += count;
+    }
+    Ok(())
+}
+
+fn withdraw_fees(context: &Context, lottery: &Pubkey, amount: Option<u64>) -> Result<()> {
+    let details = rpc::get_lottery(&context.rpc, lottery)?;
+    let amount = amount.unwrap_or(details.fees_collected);
+    let mut instructions = Vec::new();
+    let destination = context.payer_token_account(&details.token_mint, &mut instructions)?;
+    instructions.push(instruction::withdraw(
+        &context.program_id,
+        lottery,
+        &details.token_mint,
+        &context.payer.pubkey(),
+        amount,
+    ));
+    let accounts = BTreeMap::from([
+        ("lottery", lottery.to_string()),
+        ("destination", destination.to_string()),
+        ("amount", amount.to_string()),
+    ]);
+    context.submit(&instructions, &[], accounts)
+}
+
+fn init_trax(context: &Context) -> Result<()> {
+    let admin = context.payer.pubkey();
+    let details = TraxDetails {
+        admin,
+        is_initialized: 0,
+        trax_pool_amount: 0,
+        total_entries: 0,
+        total_markets: 0,
+        active_markets: 0,
+    };
+    let trax = Keypair::new();
+    let instructions = [
+        rpc::create_state_account(
+            &context.rpc,
+            &context.program_id,
+            &admin,
+            &trax.pubkey(),
+            borsh::BorshSerialize::try_to_vec(&details)?.len(),
+        )?,
+        instruction::initialize_trax(&context.program_id, &trax.pubkey(), &details),
+    ];
+    let accounts = BTreeMap::from([("trax", trax.pubkey().to_string())]);
+    context.submit(&instructions, &[&trax], accounts)
+}
+
+fn create_market(context: &Context, args: &CreateMarket) -> Result<()> {
+    let admin = context.payer.pubkey();
+    let details = MarketDetails {
+        admin,
+        trax_pub: args.trax.to_string(),
+        market_pair: args.pair.clone(),
+        last_price: args.last_price,
+        upper_floor_limit: args.upper_limit,
+        lower_floor_limit: args.lower_limit,
+        market_status: MARKET_OPEN,
+        markey_apy: args.apy,
+        options_count: 0,
+        amount_in_pool: 0,
+    };
+    let market = Keypair::new();
+    let instructions = [
+        rpc::create_state_account(
+            &context.rpc,
+            &context.program_id,
+            &admin,
+            &market.pubkey(),
+            borsh::BorshSerialize::try_to_vec(&details)?.len(),
+        )?,
+        instruction::create_market(&context.program_id, &market.pubkey(), &details),
+    ];
+    let accounts = BTreeMap::from([("market", market.pubkey().to_string())]);
+    context.submit(&instructions, &[&market], accounts)
+}
+
+fn list_markets(context: &Context) -> Result<()> {
+    let mut markets: Vec<(Pubkey, MarketDetails)> =
+        rpc::get_program_states(&context.rpc, &context.program_id)?;
+    markets.sort_by_key(|(a, _)| a.to_string());
+    let views: Vec<MarketView> = markets
+        .iter()
+        .map(|(address, details)| MarketView::new(address, details))
+        .collect();
+    context.print_list(
+        &[
+            "address",
+            "market_pair",
+            "status",
+            "last_price",
+            "options_count",
+            "amount_in_pool",
+        ],
+        &views,
+    );
+    Ok(())
+}
+
+fn pause_market(context: &Context, market: &Pubkey, resume: bool) -> Result<()> {
+    let status = if resume { MARKET_OPEN } else { MARKET_PAUSED };
+    let instruction = instruction::set_market_status(
+        &context.program_id,
+        market,
+        &context.payer.pubkey(),
+        status,
+    );
+    let accounts = BTreeMap::from([("market", market.to_string())]);
+    context.submit(&[instruction], &[], accounts)
+}
+
+fn settle(context: &Context, bet: &Pubkey, final_price: &str, result: &str) -> Result<()> {
+    if result != "won" && result != "lost" {
+        return Err(format!("result should be `won` or `lost`, not {:?}", result).into());
+    }
+    let bet_details = rpc::get_bet(&context.rpc, bet)?;
+    let details = ResultDetails {
+        player: bet_details
+            .player
+            .parse()
+            .map_err(|_| format!("bet player {:?} isn't an address", bet_details.player))?,
+        options_market: bet_details.options_market.parse().map_err(|_| {
+            format!(
+                "bet market {:?} isn't an address",
+                bet_details.options_market
+            )
+        })?,
+        final_price: final_price.to_string(),
+        result_status: result.to_string(),
+    };
+    let instruction =
+        instruction::settle_option(&context.program_id, bet, &context.payer.pubkey(), &details);
+    let accounts = BTreeMap::from([
+        ("bet", bet.to_string()),
+        ("player", details.player.to_string()),
+    ]);
+    context.submit(&[instruction], &[], accounts)
+}
+
+fn run(cli: &Cli) -> Result<()> {
+    let context = Context::new(cli)?;
+    match &cli.command {
+        Command::CreateLottery(args) => create_lottery(&context, args),
+        Command::ListLotteries => list_lotteries(&context),
+        Command::ShowLottery { lottery } => show_lottery(&context, lottery),
+        Command::Draw { lottery } => draw(&context, lottery),
+        Command::WithdrawFees { lottery, amount } => withdraw_fees(&context, lottery, *amount),
+        Command::InitTrax => init_trax(&context),
+        Command::CreateMarket(args) => create_market(&context, args),
+        Command::ListMarkets => list_markets(&context),
+        Command::PauseMarket { market, resume } => pause_market(&context, market, *resume),
+        Command::Settle {
+            bet,
+            final_price,
+            result,
+        } => settle(&context, bet, final_price, result),
+    }
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(error) = run(&cli) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,275 @@
+//! Human-readable and JSON renderings of program accounts and transactions.
+use std::collections::BTreeMap;
+
+use fanitrade_client::{
+    state::{LotteryDetails, MarketDetails},
+    MARKET_OPEN, MARKET_PAUSED,
+};
+use serde::Serialize;
+use serde_json::Value;
+use solana_program::pubkey::Pubkey;
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LotteryView {
+    pub address: String,
+    pub name: String,
+    pub status: &'static str,
+    pub admin: String,
+    pub token_mint: String,
+    pub lottery_start: String,
+    pub lottery_end: String,
+    pub ticket_price: u64,
+    pub total_entries: u64,
+    pub amount_in_pot: u64,
+    pub fees_collected: u64,
+    pub fee_bps: u16,
+    pub prize_split: Vec<u16>,
+    pub pick_count: u8,
+    pub max_number: u8,
+    /// Empty until the draw.
+    pub winning_numbers: Vec<u8>,
+    pub claim_period: u64,
+    pub bond_amount: u64,
+    /// `commit-reveal`, or the address of the oracle program.
+    pub randomness: String,
+    /// Empty for one-off lotteries.
+    pub series: String,
+    pub round: u64,
+}
+
+fn lottery_status(details: &LotteryDetails) -> &'static str {
+    if details.is_refunding != 0 {
+        "refunding"
+    } else if details.is_ended != 0 {
+        "drawn"
+    } else if details.is_revealed != 0 {
+        "revealed"
+    } else if details.commit_slot != 0 {
+        "committed"
+    } else {
+        "open"
+    }
+}
+
+impl LotteryView {
+    pub fn new(address: &Pubkey, details: &LotteryDetails) -> Self {
+        let is_drawn = details.is_ended != 0 && details.is_refunding == 0;
+        Self {
+            address: address.to_string(),
+            name: details.name.clone(),
+            status: lottery_status(details),
+            admin: details.admin.to_string(),
+            token_mint: details.token_mint.to_string(),
+            lottery_start: details.lottery_start.clone(),
+            lottery_end: details.lottery_end.clone(),
+            ticket_price: details.ticket_price,
+            total_entries: details.total_entries,
+            amount_in_pot: details.amount_in_pot,
+            fees_collected: details.fees_collected,
+            fee_bps: details.fee_bps,
+            prize_split: details.prize_split.clone(),
+            pick_count: details.pick_count,
+            max_number: details.max_number,
+            winning_numbers: if is_drawn {
+                details.winning_numbers.clone()
+            } else {
+                Vec::new()
+            },
+            claim_period: details.claim_period,
+            bond_amount: details.bond_amount,
+            randomness: if details.randomness_oracle == Pubkey::default() {
+                "commit-reveal".to_string()
+            } else {
+                details.randomness_oracle.to_string()
+            },
+            series: if details.series == Pubkey::default() {
+                String::new()
+            } else {
+                details.series.to_string()
+            },
+            round: details.round,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct MarketView {
+    pub address: String,
+    pub market_pair: String,
+    pub status: String,
+    pub admin: String,
+    pub trax: String,
+    pub last_price: u64,
+    pub upper_floor_limit: u64,
+    pub lower_floor_limit: u64,
+    pub apy: u64,
+    pub options_count: u64,
+    pub amount_in_pool: u64,
+}
+
+impl MarketView {
+    pub fn new(address: &Pubkey, details: &MarketDetails) -> Self {
+        Self {
+            address: address.to_string(),
+            market_pair: details.market_pair.clone(),
+            status: match details.market_status {
+                MARKET_OPEN => "open".to_string(),
+                MARKET_PAUSED => "paused".to_string(),
+                other => other.to_string(),
+            },
+            admin: details.admin.to_string(),
+            trax: details.trax_pub.clone(),
+            last_price: details.last_price,
+            upper_floor_limit: details.upper_floor_limit,
+            lower_floor_limit: details.lower_floor_limit,
+            apy: details.markey_apy,
+            options_count: details.options_count,
+            amount_in_pool: details.amount_in_pool,
+        }
+    }
+}
+
+/// What a command did with its transaction.
+#[derive(Serialize, Debug, Default)]
+pub struct TransactionReport {
+    pub dry_run: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+    /// Why the simulation failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<String>,
+    /// Accounts the transaction creates or acts on, by role.
+    #[serde(flatten)]
+    pub accounts: BTreeMap<&'static str, String>,
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join(", "),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Renders rows under `columns`, each column as wide as its widest cell.
+/// `columns` are field names of the serialized rows.
+pub fn table<T: Serialize>(columns: &[&str], rows: &[T]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            let value = serde_json::to_value(row).unwrap_or(Value::Null);
+            columns
+                .iter()
+                .map(|column| cell(value.get(column).unwrap_or(&Value::Null)))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            cells
+                .iter()
+                .map(|row| row[index].chars().count())
+                .chain([column.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let line = |row: Vec<String>| {
+        row.iter()
+            .zip(&widths)
+            .map(|(text, width)| format!("{:<width$}", text, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let mut lines = vec![line(
+        columns.iter().map(|column| column.to_uppercase()).collect(),
+    )];
+    lines.extend(cells.into_iter().map(line));
+    lines.join("\n")
+}
+
+/// Renders the fields of `view` one per line, as `field: value`.
+pub fn key_values<T: Serialize>(view: &T) -> String {
+    let fields = match serde_json::to_value(view) {
+        Ok(Value::Object(fields)) => fields,
+        _ => return String::new(),
+    };
+    let width = fields.keys().map(String::len).max().unwrap_or_default() + 1;
+    fields
+        .iter()
+        .map(|(key, value)| {
+            format!(
+                "{:<width$} {}",
+                format!("{}:", key),
+                cell(value),
+                width = width
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn json<T: Serialize>(view: &T) -> String {
+    serde_json::to_string_pretty(view).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Row {
+        name: &'static str,
+        entries: u64,
+        split: Vec<u16>,
+    }
+
+    #[test]
+    fn table_pads_columns() {
+        let rows = [
+            Row {
+                name: "Weekly",
+                entries: 12,
+                split: vec![5_000, 3_000],
+            },
+            Row {
+                name: "X",
+                entries: 3,
+                split: Vec::new(),
+            },
+        ];
+        assert_eq!(
+            table(&["name", "entries", "split"], &rows),
+            "NAME    ENTRIES  SPLIT\nWeekly  12       5000, 3000\nX       3"
+        );
+    }
+
+    #[test]
+    fn key_values_aligns_fields() {
+        let mut report = TransactionReport {
+            dry_run: true,
+            error: Some("custom program error: 0x1c".to_string()),
+            ..TransactionReport::default()
+        };
+        report.accounts.insert("market", "Market111".to_string());
+        assert_eq!(
+            key_values(&report),
+            "dry_run: true\nerror:   custom program error: 0x1c\nmarket:  Market111"
+        );
+    }
+}
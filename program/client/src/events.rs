@@ -0,0 +1,206 @@
+//! Decodes the program's events from transaction logs.
+//!
+//! The program emits each event with `sol_log_data`, which the runtime logs as
+//! `Program data: <base64 discriminator> <base64 Borsh event>`; see
+//! `fanitrade_utils::events`. [`parse_logs`] picks out the lines logged by the
+//! program itself, skipping data logged by programs it calls or that call it.
+use solana_program::pubkey::Pubkey;
+use thiserror::Error;
+
+pub use fanitrade_utils::events::{
+    Event, LotteryCreated, LotteryDrawn, MarketCreated, OptionPlaced, OptionSettled, PotSponsored,
+    PrizeClaimed, PrizeSponsored, TicketPurchased,
+};
+
+const DATA_PREFIX: &str = "Program data: ";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgramEvent {
+    LotteryCreated(LotteryCreated),
+    TicketPurchased(TicketPurchased),
+    LotteryDrawn(LotteryDrawn),
+    PrizeClaimed(PrizeClaimed),
+    PotSponsored(PotSponsored),
+    PrizeSponsored(PrizeSponsored),
+    MarketCreated(MarketCreated),
+    OptionPlaced(OptionPlaced),
+    OptionSettled(OptionSettled),
+}
+
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    #[error("invalid base64 in program data: {0}")]
+    Base64(#[from] base64::DecodeError),
+    /// The discriminator matched an event but its data didn't.
+    #[error("invalid {name} event: {source}")]
+    Borsh {
+        name: &'static str,
+        source: std::io::Error,
+    },
+}
+
+fn decode_as<E: Event>(
+    data: &[u8],
+    wrap: fn(E) -> ProgramEvent,
+) -> Result<ProgramEvent, DecodeError> {
+    E::try_from_slice(data)
+        .map(wrap)
+        .map_err(|source| DecodeError::Borsh {
+            name: E::NAME,
+            source,
+        })
+}
+
+/// Decodes the fields of one `sol_log_data` call. Data that isn't one of the
+/// program's events is `None`.
+pub fn decode(fields: &[&[u8]]) -> Result<Option<ProgramEvent>, DecodeError> {
+    let (discriminator, data) = match fields {
+        [discriminator, data] => (*discriminator, *data),
+        _ => return Ok(None),
+    };
+    let event = if discriminator == LotteryCreated::discriminator() {
+        decode_as(data, ProgramEvent::LotteryCreated)?
+    } else if discriminator == TicketPurchased::discriminator() {
+        decode_as(data, ProgramEvent::TicketPurchased)?
+    } else if discriminator == LotteryDrawn::discriminator() {
+        decode_as(data, ProgramEvent::LotteryDrawn)?
+    } else if discriminator == PrizeClaimed::discriminator() {
+        decode_as(data, ProgramEvent::PrizeClaimed)?
+    } else if discriminator == PotSponsored::discriminator() {
+        decode_as(data, ProgramEvent::PotSponsored)?
+    } else if discriminator == PrizeSponsored::discriminator() {
+        decode_as(data, ProgramEvent::PrizeSponsored)?
+    } else if discriminator == MarketCreated::discriminator() {
+        decode_as(data, ProgramEvent::MarketCreated)?
+    } else if discriminator == OptionPlaced::discriminator() {
+        decode_as(data, ProgramEvent::OptionPlaced)?
+    } else if discriminator == OptionSettled::discriminator() {
+        decode_as(data, ProgramEvent::OptionSettled)?
+    } else {
+        return Ok(None);
+    };
+    Ok(Some(event))
+}
+
+/// Decodes a single `Program data:` log line. Other lines are `None`.
+pub fn parse_log(line: &str) -> Result<Option<ProgramEvent>, DecodeError> {
+    let encoded = match line.strip_prefix(DATA_PREFIX) {
+        Some(encoded) => encoded,
+        None => return Ok(None),
+    };
+    let fields = encoded
+        .split_whitespace()
+        .map(base64::decode)
+        .collect::<Result<Vec<_>, _>>()?;
+    let fields: Vec<&[u8]> = fields.iter().map(Vec::as_slice).collect();
+    decode(&fields)
+}
+
+/// The events `program_id` emitted in a transaction's logs, in order.
+pub fn parse_logs<S: AsRef<str>>(
+    program_id: &Pubkey,
+    logs: &[S],
+) -> Result<Vec<ProgramEvent>, DecodeError> {
+    let program_id = program_id.to_string();
+    let mut invoked: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    for line in logs {
+        let line = line.as_ref();
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("Program"), Some(program), Some("invoke")) => invoked.push(program),
+            (Some("Program"), Some(program), Some(outcome))
+                if (outcome == "success" || outcome.starts_with("failed"))
+                    && invoked.last() == Some(&program) =>
+            {
+                invoked.pop();
+            }
+            _ if invoked.last() == Some(&program_id.as_str()) => {
+                if let Some(event) = parse_log(line)? {
+                    events.push(event);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data_line<E: Event>(event: &E) -> String {
+        format!(
+            "{}{} {}",
+            DATA_PREFIX,
+            base64::encode(E::discriminator()),
+            base64::encode(event.try_to_vec().unwrap())
+        )
+    }
+
+    fn prize_claimed() -> PrizeClaimed {
+        PrizeClaimed {
+            lottery: Pubkey::new_unique(),
+            ticket: Pubkey::new_unique(),
+            player: Pubkey::new_unique(),
+            amount: 250,
+        }
+    }
+
+    #[test]
+    fn parse_log_round_trips_events() {
+        let event = prize_claimed();
+        assert_eq!(
+            parse_log(&data_line(&event)).unwrap(),
+            Some(ProgramEvent::PrizeClaimed(event))
+        );
+        assert_eq!(
+            parse_log("Program log: Prize of 250 claimed").unwrap(),
+            None
+        );
+        let unknown = format!("{}{}", DATA_PREFIX, base64::encode([1, 2, 3]));
+        assert_eq!(parse_log(&unknown).unwrap(), None);
+
+        let truncated = format!(
+            "{}{} {}",
+            DATA_PREFIX,
+            base64::encode(PrizeClaimed::discriminator()),
+            base64::encode([0; 4])
+        );
+        assert!(matches!(
+            parse_log(&truncated),
+            Err(DecodeError::Borsh {
+                name: "PrizeClaimed",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn parse_logs_only_reads_the_program_s_own_data() {
+        let program_id = Pubkey::new_unique();
+        let caller = Pubkey::new_unique();
+        let event = prize_claimed();
+        let logs = vec![
+            format!("Program {} invoke [1]", caller),
+            data_line(&prize_claimed()),
+            format!("Program {} invoke [2]", program_id),
+            format!("Program {} invoke [3]", spl_token::id()),
+            data_line(&prize_claimed()),
+            format!("Program {} success", spl_token::id()),
+            data_line(&event),
+            format!(
+                "Program {} consumed 5000 of 200000 compute units",
+                program_id
+            ),
+            format!("Program {} success", program_id),
+            data_line(&prize_claimed()),
+            format!("Program {} success", caller),
+        ];
+        assert_eq!(
+            parse_logs(&program_id, &logs).unwrap(),
+            vec![ProgramEvent::PrizeClaimed(event)]
+        );
+    }
+}
@@ -0,0 +1,1523 @@
+//! Builders for the program's instructions.
+//!
+//! Each builder takes the program id first and lists the accounts in the
+//! order the processor reads them, including the optional tails some
+//! instructions take. Accounts the program creates (tickets, results, series
+//! rounds) are derived here; accounts the caller creates beforehand (lotteries,
+//! series, markets, bets) are passed in.
+use borsh::BorshSerialize;
+use fanitrade_utils::{
+    allowlist::AllowlistProof,
+    crowdfunding::{self, CampaignDetails, DonateRequest},
+    sponsor::{PrizeClaimRequest, PrizeRequest, SponsorRequest},
+    ClaimRequest, CommitRequest, LotteryDetails, LotterySeries, MarketDetails, MarketStatusRequest,
+    OptionsBetDetails, ResultDetails, RevealRequest, TallyRequest, TicketDetails, TraxDetails,
+    WithdrawRequest,
+};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::pda::{
+    find_allowlist_entry_address, find_metadata_address, find_pot_authority, find_prize_address,
+    find_receipt_address, find_referral_address, find_requester_address, find_results_address,
+    find_round_address, find_share_address, find_sponsorship_address, find_ticket_address,
+    find_ticket_mint, pot_address, proof_address,
+};
+
+const CREATE_LOTTERY: u8 = 0;
+const PLAY: u8 = 1;
+const WITHDRAW: u8 = 2;
+const CREATE_SERIES: u8 = 3;
+const START_NEXT_ROUND: u8 = 4;
+const INITIALIZE_TRAX: u8 = 5;
+const CREATE_MARKET: u8 = 6;
+const PLACE_OPTION: u8 = 7;
+const SETTLE_OPTION: u8 = 8;
+const DRAW_LOTTERY: u8 = 9;
+const CLAIM_PRIZE: u8 = 10;
+const EXPIRE_PRIZES: u8 = 11;
+const COMMIT_RANDOMNESS: u8 = 12;
+const REVEAL_RANDOMNESS: u8 = 13;
+const SLASH_BOND: u8 = 14;
+const CLAIM_REFUND: u8 = 15;
+const REQUEST_RANDOMNESS: u8 = 16;
+const FULFILL_RANDOMNESS: u8 = 17;
+const SET_MARKET_STATUS: u8 = 18;
+const VOID_TICKET: u8 = 19;
+const CLAIM_REFERRAL_REWARDS: u8 = 20;
+const SPONSOR_POT: u8 = 21;
+const SPONSOR_PRIZE: u8 = 22;
+const CLAIM_SPONSORED_PRIZE: u8 = 23;
+const RECLAIM_SPONSORED_PRIZE: u8 = 24;
+const CANCEL_LOTTERY: u8 = 25;
+const TALLY_TICKETS: u8 = 26;
+const CLOSE_SALES: u8 = 27;
+const RECLAIM_SPONSORSHIP: u8 = 28;
+
+fn instruction(
+    program_id: &Pubkey,
+    tag: u8,
+    data: &impl BorshSerialize,
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let mut buffer = vec![tag];
+    // Serializing into a Vec can't fail.
+    data.serialize(&mut buffer).unwrap();
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: buffer,
+    }
+}
+
+fn campaign_instruction(
+    program_id: &Pubkey,
+    tag: u8,
+    data: &impl BorshSerialize,
+    accounts: Vec<AccountMeta>,
+) -> Instruction {
+    let mut instruction = instruction(program_id, tag, data, accounts);
+    instruction.data.insert(0, crowdfunding::NAMESPACE);
+    instruction
+}
+
+/// Space to allocate for a lottery account, including room for the winning
+/// numbers the draw writes.
+pub fn lottery_space(details: &LotteryDetails) -> usize {
+    // Serializing into a Vec can't fail.
+    details.try_to_vec().unwrap().len() - details.winning_numbers.len()
+        + usize::from(details.pick_count)
+}
+
+/// Initializes `lottery`, an account the admin created for the program with
+/// [`lottery_space`] bytes. If `details` has a bond, it is moved from the
+/// admin's associated token account into the pot. If it has a
+/// `ticket_ledger`, that account has to be created the same way, with
+/// `fanitrade_utils::ledger::space` bytes.
+pub fn create_lottery(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    details: &LotteryDetails,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*lottery, false),
+        AccountMeta::new_readonly(details.admin, true),
+    ];
+    if details.bond_amount > 0 {
+        accounts.extend([
+            AccountMeta::new(
+                get_associated_token_address(&details.admin, &details.token_mint),
+                false,
+            ),
+            AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+            AccountMeta::new(pot_address(program_id, lottery, &details.token_mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]);
+    }
+    if details.ticket_ledger != Pubkey::default() {
+        accounts.push(AccountMeta::new(details.ticket_ledger, false));
+    }
+    instruction(program_id, CREATE_LOTTERY, details, accounts)
+}
+
+/// Buys ticket `ticket_index`, which has to be the lottery's `total_entries`,
+/// picking `numbers`. The player pays the ticket price from their associated
+/// token account and the rent of the ticket account.
+pub fn play(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+    ticket_index: u64,
+    numbers: &[u8],
+) -> Instruction {
+    play_with_ticket(
+        program_id,
+        lottery,
+        &find_ticket_address(program_id, lottery, ticket_index).0,
+        token_mint,
+        player,
+        ticket_index,
+        numbers,
+    )
+}
+
+/// Buys ticket `ticket_index` of a lottery with `ticket_nfts` set, like
+/// [`play`], and mints its token to the player's associated token account.
+/// The player also pays the rent of the mint and its metadata.
+pub fn play_nft(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+    ticket_index: u64,
+    numbers: &[u8],
+) -> Instruction {
+    let ticket_mint = find_ticket_mint(
+        program_id,
+        &find_ticket_address(program_id, lottery, ticket_index).0,
+    )
+    .0;
+    let mut instruction = play(
+        program_id,
+        lottery,
+        token_mint,
+        player,
+        ticket_index,
+        numbers,
+    );
+    instruction.accounts.extend([
+        AccountMeta::new(ticket_mint, false),
+        AccountMeta::new(find_metadata_address(program_id, &ticket_mint).0, false),
+        AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+        AccountMeta::new(get_associated_token_address(player, &ticket_mint), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ]);
+    instruction
+}
+
+/// Buys the next ticket of a compact lottery, appending it to `ledger`, the
+/// lottery's `ticket_ledger`. Only the ticket price is paid.
+pub fn play_compact(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    ledger: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+    numbers: &[u8],
+) -> Instruction {
+    play_with_ticket(program_id, lottery, ledger, token_mint, player, 0, numbers)
+}
+
+fn play_with_ticket(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    ticket: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+    ticket_index: u64,
+    numbers: &[u8],
+) -> Instruction {
+    let mut ticket_number_arr = [0; 128];
+    ticket_number_arr[..numbers.len()].copy_from_slice(numbers);
+    let ticket_data = TicketDetails {
+        player: player.to_string(),
+        ticket_count: 1,
+        ticket_number_arr,
+        lottery: *lottery,
+        ticket_index,
+        claimed: 0,
+    };
+    instruction(
+        program_id,
+        PLAY,
+        &ticket_data,
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new(*ticket, false),
+            AccountMeta::new(*player, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(pot_address(program_id, lottery, token_mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(get_associated_token_address(player, token_mint), false),
+        ],
+    )
+}
+
+/// Adds the player's `proof` to an instruction of any of the `play` builders,
+/// for a lottery with `allowlist_root` set. Call it before appending
+/// [`referral_accounts`].
+pub fn with_allowlist_proof(
+    program_id: &Pubkey,
+    mut instruction: Instruction,
+    proof: &AllowlistProof,
+) -> Instruction {
+    // Serializing into a Vec can't fail.
+    proof.serialize(&mut instruction.data).unwrap();
+    if proof.allocation > 0 {
+        let lottery = instruction.accounts[0].pubkey;
+        let player = instruction.accounts[2].pubkey;
+        instruction.accounts.push(AccountMeta::new(
+            find_allowlist_entry_address(program_id, &lottery, &player).0,
+            false,
+        ));
+    }
+    instruction
+}
+
+/// Accounts to append to an instruction of any of the `play` builders to buy
+/// the ticket through `referrer`, in a lottery with `referral_bps` set.
+pub fn referral_accounts(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    player: &Pubkey,
+    referrer: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(*referrer, false),
+        AccountMeta::new(
+            find_referral_address(program_id, lottery, referrer).0,
+            false,
+        ),
+        AccountMeta::new(find_referral_address(program_id, lottery, player).0, false),
+    ]
+}
+
+/// Pays the referral rewards `referrer` earned in a drawn lottery to their
+/// associated token account.
+pub fn claim_referral_rewards(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    referrer: &Pubkey,
+) -> Instruction {
+    instruction(
+        program_id,
+        CLAIM_REFERRAL_REWARDS,
+        &(),
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new(
+                find_referral_address(program_id, lottery, referrer).0,
+                false,
+            ),
+            AccountMeta::new_readonly(*referrer, true),
+            AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+            AccountMeta::new(pot_address(program_id, lottery, token_mint), false),
+            AccountMeta::new(get_associated_token_address(referrer, token_mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+/// Adds `amount` from the sponsor's associated token account to the lottery's
+/// pot. `name` is shown for the sponsor if this is their first contribution.
+pub fn sponsor_pot(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    sponsor: &Pubkey,
+    amount: u64,
+    name: &str,
+) -> Instruction {
+    instruction(
+        program_id,
+        SPONSOR_POT,
+        &SponsorRequest {
+            amount,
+            name: name.to_string(),
+        },
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new(
+                find_sponsorship_address(program_id, lottery, sponsor).0,
+                false,
+            ),
+            AccountMeta::new(*sponsor, true),
+            AccountMeta::new(get_associated_token_address(sponsor, token_mint), false),
+            AccountMeta::new(pot_address(program_id, lottery, token_mint), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+/// Returns what the sponsor added to the pot of a refunding lottery to their
+/// associated token account.
+pub fn reclaim_sponsorship(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    sponsor: &Pubkey,
+) -> Instruction {
+    instruction(
+        program_id,
+        RECLAIM_SPONSORSHIP,
+        &(),
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new(
+                find_sponsorship_address(program_id, lottery, sponsor).0,
+                false,
+            ),
+            AccountMeta::new_readonly(*sponsor, true),
+            AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+            AccountMeta::new(pot_address(program_id, lottery, token_mint), false),
+            AccountMeta::new(get_associated_token_address(sponsor, token_mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+    )
+}
+
+/// Puts up `amount` of `prize_mint` from the sponsor's associated token
+/// account for the winners of `tier`. `prize_index` has to be the lottery's
+/// `sponsored_prizes`.
+pub fn sponsor_prize(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    prize_mint: &Pubkey,
+    sponsor: &Pubkey,
+    prize_index: u64,
+    tier: u8,
+    amount: u64,
+) -> Instruction {
+    let pot_authority = find_pot_authority(program_id, lottery).0;
+    instruction(
+        program_id,
+        SPONSOR_PRIZE,
+        &PrizeRequest { tier, amount },
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new(
+                find_prize_address(program_id, lottery, prize_index).0,
+                false,
+            ),
+            AccountMeta::new(*sponsor, true),
+            AccountMeta::new_readonly(*prize_mint, false),
+            AccountMeta::new(get_associated_token_address(sponsor, prize_mint), false),
+            AccountMeta::new_readonly(pot_authority, false),
+            AccountMeta::new(
+                get_associated_token_address(&pot_authority, prize_mint),
+                false,
+            ),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+    )
+}
+
+/// Pays ticket `ticket_index` its share of sponsored prize `prize_index` to
+/// the player's associated token account for `prize_mint`. The player funds
+/// the receipt of the share. For compact
+/// lotteries the ticket is read from the ledger; for lotteries with ticket
+/// tokens, `player` is the holder of the ticket's token.
+pub fn claim_sponsored_prize(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    details: &LotteryDetails,
+    prize_mint: &Pubkey,
+    player: &Pubkey,
+    prize_index: u64,
+    ticket_index: u64,
+) -> Instruction {
+    let pot_authority = find_pot_authority(program_id, lottery).0;
+    let prize = find_prize_address(program_id, lottery, prize_index).0;
+    let ticket = if details.ticket_ledger != Pubkey::default() {
+        details.ticket_ledger
+    } else {
+        find_ticket_address(program_id, lottery, ticket_index).0
+    };
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*lottery, false),
+        AccountMeta::new_readonly(find_results_address(program_id, lottery).0, false),
+        AccountMeta::new(prize, false),
+        AccountMeta::new(
+            find_share_address(program_id, &prize, ticket_index).0,
+            false,
+        ),
+        AccountMeta::new_readonly(ticket, false),
+        AccountMeta::new(*player, true),
+        AccountMeta::new_readonly(pot_authority, false),
+        AccountMeta::new(
+            get_associated_token_address(&pot_authority, prize_mint),
+            false,
+        ),
+        AccountMeta::new(get_associated_token_address(player, prize_mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    if details.ticket_nfts != 0 {
+        accounts.push(ticket_holder_account(
+            program_id,
+            lottery,
+            player,
+            ticket_index,
+        ));
+    }
+    let mut instruction = instruction(
+        program_id,
+        CLAIM_SPONSORED_PRIZE,
+        &PrizeClaimRequest { prize_index },
+        accounts,
+    );
+    if details.ticket_ledger != Pubkey::default() {
+        // Serializing into a Vec can't fail.
+        ClaimRequest { ticket_index }
+            .serialize(&mut instruction.data)
+            .unwrap();
+    }
+    instruction
+}
+
+/// Returns to the sponsor what wasn't paid out of sponsored prize
+/// `prize_index`: after the claim deadline of a drawn lottery, or at any time
+/// once it is refunding.
+pub fn reclaim_sponsored_prize(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    details: &LotteryDetails,
+    prize_mint: &Pubkey,
+    sponsor: &Pubkey,
+    prize_index: u64,
+) -> Instruction {
+    let pot_authority = find_pot_authority(program_id, lottery).0;
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*lottery, false),
+        AccountMeta::new(
+            find_prize_address(program_id, lottery, prize_index).0,
+            false,
+        ),
+        AccountMeta::new_readonly(*sponsor, true),
+        AccountMeta::new_readonly(pot_authority, false),
+        AccountMeta::new(
+            get_associated_token_address(&pot_authority, prize_mint),
+            false,
+        ),
+        AccountMeta::new(get_associated_token_address(sponsor, prize_mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    if details.is_refunding == 0 {
+        accounts.push(AccountMeta::new(
+            find_results_address(program_id, lottery).0,
+            false,
+        ));
+    }
+    instruction(
+        program_id,
+        RECLAIM_SPONSORED_PRIZE,
+        &PrizeClaimRequest { prize_index },
+        accounts,
+    )
+}
+
+/// Withdraws `amount` of the lottery's collected fees to the admin's
+/// associated token account.
+pub fn withdraw(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    admin: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    instruction(
+        program_id,
+        WITHDRAW,
+        &WithdrawRequest { amount },
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new_readonly(*admin, true),
+            AccountMeta::new_readonly(*token_mint, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(pot_address(program_id, lottery, token_mint), false),
+            AccountMeta::new(get_associated_token_address(admin, token_mint), false),
+            AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+        ],
+    )
+}
+
+/// Initializes `series`, an account the admin created for the program.
+pub fn create_series(program_id: &Pubkey, series: &Pubkey, details: &LotterySeries) -> Instruction {
+    instruction(
+        program_id,
+        CREATE_SERIES,
+        details,
+        vec![
+            AccountMeta::new(*series, false),
+            AccountMeta::new_readonly(details.admin, true),
+        ],
+    )
+}
+
+/// Opens `round`, which has to be the series' `current_round`. The payer
+/// funds the round and its pot; from the second round on, the previous
+/// round's pot is carried over.
+pub fn start_next_round(
+    program_id: &Pubkey,
+    series: &Pubkey,
+    token_mint: &Pubkey,
+    payer: &Pubkey,
+    round: u64,
+) -> Instruction {
+    let round_account = find_round_address(program_id, series, round).0;
+    let mut accounts = vec![
+        AccountMeta::new(*series, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(round_account, false),
+        AccountMeta::new_readonly(find_pot_authority(program_id, &round_account).0, false),
+        AccountMeta::new(pot_address(program_id, &round_account, token_mint), false),
+        AccountMeta::new_readonly(*token_mint, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    if round > 0 {
+        let previous_round = find_round_address(program_id, series, round - 1).0;
+        accounts.extend([
+            AccountMeta::new(previous_round, false),
+            AccountMeta::new_readonly(find_pot_authority(program_id, &previous_round).0, false),
+            AccountMeta::new(pot_address(program_id, &previous_round, token_mint), false),
+        ]);
+    }
+    instruction(program_id, START_NEXT_ROUND, &(), accounts)
+}
+
+/// Initializes `trax`, an account the admin created for the program.
+pub fn initialize_trax(program_id: &Pubkey, trax: &Pubkey, details: &TraxDetails) -> Instruction {
+    instruction(
+        program_id,
+        INITIALIZE_TRAX,
+        details,
+        vec![
+            AccountMeta::new(*trax, false),
+            AccountMeta::new_readonly(details.admin, true),
+        ],
+    )
+}
+
+/// Initializes `market`, an account the admin created for the program.
+pub fn create_market(program_id: &Pubkey, market: &Pubkey, details: &MarketDetails) -> Instruction {
+    instruction(
+        program_id,
+        CREATE_MARKET,
+        details,
+        vec![
+            AccountMeta::new(*market, false),
+            AccountMeta::new_readonly(details.admin, true),
+        ],
+    )
+}
+
+/// Sets a market's status to `MARKET_OPEN` or `MARKET_PAUSED`.
+pub fn set_market_status(
+    program_id: &Pubkey,
+    market: &Pubkey,
+    admin: &Pubkey,
+    market_status: u64,
+) -> Instruction {
+    instruction(
+        program_id,
+        SET_MARKET_STATUS,
+        &MarketStatusRequest { market_status },
+        vec![
+            AccountMeta::new(*market, false),
+            AccountMeta::new_readonly(*admin, true),
+        ],
+    )
+}
+
+/// Records a bet in `bet`, an account the trader created for the program
+/// and funded with the amount bet.
+pub fn place_option(
+    program_id: &Pubkey,
+    market: &Pubkey,
+    bet: &Pubkey,
+    trader: &Pubkey,
+    details: &OptionsBetDetails,
+) -> Instruction {
+    instruction(
+        program_id,
+        PLACE_OPTION,
+        details,
+        vec![
+            AccountMeta::new(*market, false),
+            AccountMeta::new(*bet, false),
+            AccountMeta::new_readonly(*trader, true),
+        ],
+    )
+}
+
+/// Settles `bet`, paying its lamports to the settler.
+pub fn settle_option(
+    program_id: &Pubkey,
+    bet: &Pubkey,
+    settler: &Pubkey,
+    details: &ResultDetails,
+) -> Instruction {
+    instruction(
+        program_id,
+        SETTLE_OPTION,
+        details,
+        vec![
+            AccountMeta::new(*bet, false),
+            AccountMeta::new(*settler, true),
+        ],
+    )
+}
+
+fn draw_accounts(program_id: &Pubkey, lottery: &Pubkey, payer: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(*lottery, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(find_results_address(program_id, lottery).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]
+}
+
+/// Draws a lottery whose randomness has been revealed. The payer funds the
+/// results account. The tickets of a pick-N lottery are then counted with
+/// [`tally_tickets`].
+pub fn draw_lottery(program_id: &Pubkey, lottery: &Pubkey, payer: &Pubkey) -> Instruction {
+    instruction(
+        program_id,
+        DRAW_LOTTERY,
+        &(),
+        draw_accounts(program_id, lottery, payer),
+    )
+}
+
+/// Counts the winners among `count` tickets of a drawn pick-N lottery, from
+/// ticket `first` on, which should be the results' `tallied`. Passes those
+/// tickets, or the ticket ledger of a compact lottery.
+pub fn tally_tickets(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    details: &LotteryDetails,
+    first: u64,
+    count: u64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*lottery, false),
+        AccountMeta::new(find_results_address(program_id, lottery).0, false),
+    ];
+    if details.ticket_ledger != Pubkey::default() {
+        accounts.push(AccountMeta::new_readonly(details.ticket_ledger, false));
+    } else {
+        accounts.extend((first..first + count).map(|index| {
+            AccountMeta::new_readonly(find_ticket_address(program_id, lottery, index).0, false)
+        }));
+    }
+    instruction(program_id, TALLY_TICKETS, &TallyRequest { count }, accounts)
+}
+
+/// Pays the prize of a winning ticket to its player's associated token account.
+pub fn claim_prize(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+    ticket_index: u64,
+) -> Instruction {
+    instruction(
+        program_id,
+        CLAIM_PRIZE,
+        &(),
+        claim_prize_accounts(
+            program_id,
+            lottery,
+            &find_ticket_address(program_id, lottery, ticket_index).0,
+            token_mint,
+            player,
+        ),
+    )
+}
+
+/// Pays the prize of ticket `ticket_index` of a lottery with `ticket_nfts` set
+/// to `holder`, whose associated token account holds the ticket's token.
+pub fn claim_prize_nft(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    holder: &Pubkey,
+    ticket_index: u64,
+) -> Instruction {
+    let mut instruction = claim_prize(program_id, lottery, token_mint, holder, ticket_index);
+    instruction.accounts.push(ticket_holder_account(
+        program_id,
+        lottery,
+        holder,
+        ticket_index,
+    ));
+    instruction
+}
+
+fn ticket_holder_account(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    holder: &Pubkey,
+    ticket_index: u64,
+) -> AccountMeta {
+    let ticket = find_ticket_address(program_id, lottery, ticket_index).0;
+    AccountMeta::new_readonly(
+        get_associated_token_address(holder, &find_ticket_mint(program_id, &ticket).0),
+        false,
+    )
+}
+
+/// Pays the prize of ticket `ticket_index` of a compact lottery, read from
+/// `ledger`, to its player's associated token account.
+pub fn claim_prize_compact(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    ledger: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+    ticket_index: u64,
+) -> Instruction {
+    instruction(
+        program_id,
+        CLAIM_PRIZE,
+        &ClaimRequest { ticket_index },
+        claim_prize_accounts(program_id, lottery, ledger, token_mint, player),
+    )
+}
+
+fn claim_prize_accounts(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    ticket: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(*lottery, false),
+        AccountMeta::new(find_results_address(program_id, lottery).0, false),
+        AccountMeta::new(*ticket, false),
+        AccountMeta::new_readonly(*player, true),
+        AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+        AccountMeta::new(pot_address(program_id, lottery, token_mint), false),
+        AccountMeta::new(get_associated_token_address(player, token_mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+}
+
+/// Expires the unclaimed prizes of a drawn lottery. For a series round,
+/// `latest_round` is the series' latest round, which receives the prizes;
+/// it is ignored for one-off lotteries.
+pub fn expire_prizes(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    details: &LotteryDetails,
+    latest_round: u64,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*lottery, false),
+        AccountMeta::new(find_results_address(program_id, lottery).0, false),
+    ];
+    if details.series != Pubkey::default() {
+        accounts.push(AccountMeta::new_readonly(details.series, false));
+        if latest_round != details.round {
+            let latest_round_account =
+                find_round_address(program_id, &details.series, latest_round).0;
+            accounts.extend([
+                AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+                AccountMeta::new(pot_address(program_id, lottery, &details.token_mint), false),
+                AccountMeta::new(latest_round_account, false),
+                AccountMeta::new(
+                    pot_address(program_id, &latest_round_account, &details.token_mint),
+                    false,
+                ),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ]);
+        }
+    }
+    instruction(program_id, EXPIRE_PRIZES, &(), accounts)
+}
+
+/// Commits the admin to `commitment`, the hash of their secret as computed by
+/// `fanitrade_utils::randomness::commitment`.
+pub fn commit_randomness(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    admin: &Pubkey,
+    commitment: [u8; 32],
+) -> Instruction {
+    instruction(
+        program_id,
+        COMMIT_RANDOMNESS,
+        &CommitRequest { commitment },
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new_readonly(*admin, true),
+        ],
+    )
+}
+
+/// Records the slot ticket sales closed at, after `lottery_end`. Until
+/// `CLOSE_GRACE` after it, `closer` has to be the lottery's admin. The secret
+/// can be revealed two slots later, once the next slot's hash is in `SlotHashes`.
+pub fn close_sales(program_id: &Pubkey, lottery: &Pubkey, closer: &Pubkey) -> Instruction {
+    instruction(
+        program_id,
+        CLOSE_SALES,
+        &(),
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new_readonly(*closer, true),
+        ],
+    )
+}
+
+/// Reveals the admin's committed secret. If the lottery has a bond, it is
+/// returned to the admin's associated token account.
+pub fn reveal_randomness(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    details: &LotteryDetails,
+    secret: Vec<u8>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*lottery, false),
+        AccountMeta::new_readonly(details.admin, true),
+        AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+    ];
+    if details.bond_amount > 0 {
+        accounts.extend([
+            AccountMeta::new(
+                get_associated_token_address(&details.admin, &details.token_mint),
+                false,
+            ),
+            AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+            AccountMeta::new(pot_address(program_id, lottery, &details.token_mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]);
+    }
+    instruction(
+        program_id,
+        REVEAL_RANDOMNESS,
+        &RevealRequest { secret },
+        accounts,
+    )
+}
+
+/// Puts a lottery whose randomness wasn't revealed in time into refund mode.
+/// What of the bond the tickets can't share evenly is returned to the admin's
+/// associated token account.
+pub fn slash_bond(program_id: &Pubkey, lottery: &Pubkey, details: &LotteryDetails) -> Instruction {
+    let mut accounts = vec![AccountMeta::new(*lottery, false)];
+    if details.unshared_bond() > 0 {
+        accounts.extend([
+            AccountMeta::new(
+                get_associated_token_address(&details.admin, &details.token_mint),
+                false,
+            ),
+            AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+            AccountMeta::new(pot_address(program_id, lottery, &details.token_mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]);
+    }
+    instruction(program_id, SLASH_BOND, &(), accounts)
+}
+
+/// Puts a lottery that didn't sell its `min_entries` into refund mode. If the
+/// lottery's bond is still in the pot, it is returned to the admin's
+/// associated token account.
+pub fn cancel_lottery(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    details: &LotteryDetails,
+) -> Instruction {
+    let mut accounts = vec![AccountMeta::new(*lottery, false)];
+    if details.bond_amount > 0 && details.is_revealed == 0 {
+        accounts.extend([
+            AccountMeta::new(
+                get_associated_token_address(&details.admin, &details.token_mint),
+                false,
+            ),
+            AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+            AccountMeta::new(pot_address(program_id, lottery, &details.token_mint), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ]);
+    }
+    instruction(program_id, CANCEL_LOTTERY, &(), accounts)
+}
+
+/// Refunds a ticket of a refunding lottery to its player's associated token
+/// account.
+pub fn claim_refund(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+    ticket_index: u64,
+) -> Instruction {
+    instruction(
+        program_id,
+        CLAIM_REFUND,
+        &(),
+        claim_refund_accounts(
+            program_id,
+            lottery,
+            &find_ticket_address(program_id, lottery, ticket_index).0,
+            token_mint,
+            player,
+        ),
+    )
+}
+
+/// Refunds ticket `ticket_index` of a refunding lottery with `ticket_nfts` set
+/// to `holder`, whose associated token account holds the ticket's token.
+pub fn claim_refund_nft(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    holder: &Pubkey,
+    ticket_index: u64,
+) -> Instruction {
+    let mut instruction = claim_refund(program_id, lottery, token_mint, holder, ticket_index);
+    instruction.accounts.push(ticket_holder_account(
+        program_id,
+        lottery,
+        holder,
+        ticket_index,
+    ));
+    instruction
+}
+
+/// Voids ticket `ticket_index`, whose token was burned, so the draw skips it.
+pub fn void_ticket(program_id: &Pubkey, lottery: &Pubkey, ticket_index: u64) -> Instruction {
+    let ticket = find_ticket_address(program_id, lottery, ticket_index).0;
+    instruction(
+        program_id,
+        VOID_TICKET,
+        &(),
+        vec![
+            AccountMeta::new_readonly(*lottery, false),
+            AccountMeta::new(ticket, false),
+            AccountMeta::new_readonly(find_ticket_mint(program_id, &ticket).0, false),
+        ],
+    )
+}
+
+/// Refunds ticket `ticket_index` of a refunding compact lottery, read from
+/// `ledger`, to its player's associated token account.
+pub fn claim_refund_compact(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    ledger: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+    ticket_index: u64,
+) -> Instruction {
+    instruction(
+        program_id,
+        CLAIM_REFUND,
+        &ClaimRequest { ticket_index },
+        claim_refund_accounts(program_id, lottery, ledger, token_mint, player),
+    )
+}
+
+fn claim_refund_accounts(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    ticket: &Pubkey,
+    token_mint: &Pubkey,
+    player: &Pubkey,
+) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(*lottery, false),
+        AccountMeta::new(*ticket, false),
+        AccountMeta::new_readonly(*player, true),
+        AccountMeta::new_readonly(find_pot_authority(program_id, lottery).0, false),
+        AccountMeta::new(pot_address(program_id, lottery, token_mint), false),
+        AccountMeta::new(get_associated_token_address(player, token_mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+}
+
+/// Asks the lottery's oracle for randomness. The payer funds the oracle's
+/// proof account.
+pub fn request_randomness(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    oracle_program_id: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    instruction(
+        program_id,
+        REQUEST_RANDOMNESS,
+        &(),
+        vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(find_requester_address(program_id, lottery).0, false),
+            AccountMeta::new(proof_address(program_id, oracle_program_id, lottery), false),
+            AccountMeta::new_readonly(*oracle_program_id, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Draws a lottery from its oracle's fulfilled proof account. The payer funds
+/// the results account.
+pub fn fulfill_randomness(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    details: &LotteryDetails,
+    payer: &Pubkey,
+) -> Instruction {
+    let mut accounts = vec![AccountMeta::new_readonly(
+        proof_address(program_id, &details.randomness_oracle, lottery),
+        false,
+    )];
+    accounts.extend(draw_accounts(program_id, lottery, payer));
+    instruction(program_id, FULFILL_RANDOMNESS, &(), accounts)
+}
+
+/// Initializes `campaign`, an account the admin created for the program with
+/// room for exactly `details`.
+pub fn create_campaign(
+    program_id: &Pubkey,
+    campaign: &Pubkey,
+    details: &CampaignDetails,
+) -> Instruction {
+    campaign_instruction(
+        program_id,
+        crowdfunding::CREATE_CAMPAIGN,
+        details,
+        vec![
+            AccountMeta::new(*campaign, false),
+            AccountMeta::new_readonly(details.admin, true),
+        ],
+    )
+}
+
+/// Pays `amount` lamports out of `campaign` to its admin.
+pub fn withdraw_donations(
+    program_id: &Pubkey,
+    campaign: &Pubkey,
+    admin: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    campaign_instruction(
+        program_id,
+        crowdfunding::WITHDRAW,
+        &WithdrawRequest { amount },
+        vec![
+            AccountMeta::new(*campaign, false),
+            AccountMeta::new(*admin, true),
+        ],
+    )
+}
+
+/// Donates `amount` lamports from `donor` to `campaign`, creating the donor's
+/// receipt on their first donation.
+pub fn donate(program_id: &Pubkey, campaign: &Pubkey, donor: &Pubkey, amount: u64) -> Instruction {
+    campaign_instruction(
+        program_id,
+        crowdfunding::DONATE,
+        &DonateRequest { amount },
+        vec![
+            AccountMeta::new(*campaign, false),
+            AccountMeta::new(find_receipt_address(program_id, campaign, donor).0, false),
+            AccountMeta::new(*donor, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+    )
+}
+
+/// Pays `donor`'s donations to a campaign that missed its goal back to them,
+/// closing their receipt. Anyone can send it.
+pub fn refund(program_id: &Pubkey, campaign: &Pubkey, donor: &Pubkey) -> Instruction {
+    campaign_instruction(
+        program_id,
+        crowdfunding::REFUND,
+        &(),
+        vec![
+            AccountMeta::new(*campaign, false),
+            AccountMeta::new(find_receipt_address(program_id, campaign, donor).0, false),
+            AccountMeta::new(*donor, false),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshDeserialize;
+
+    use super::*;
+
+    fn details(bond_amount: u64) -> LotteryDetails {
+        LotteryDetails {
+            admin: Pubkey::new_unique(),
+            name: "Lottery".to_string(),
+            is_initialized: 0,
+            is_ended: 0,
+            lottery_start: "0".to_string(),
+            lottery_end: "100".to_string(),
+            ticket_price: 100,
+            amount_in_pot: 0,
+            total_entries: 0,
+            token_mint: Pubkey::new_unique(),
+            series: Pubkey::default(),
+            round: 0,
+            fee_bps: 1_000,
+            prize_split: vec![5_000, 3_000, 1_000],
+            pick_count: 3,
+            max_number: 10,
+            winning_numbers: Vec::new(),
+            claim_period: 3_600,
+            fees_collected: 0,
+            bond_amount,
+            randomness_commitment: [0; 32],
+            commit_slot: 0,
+            randomness: [0; 32],
+            is_revealed: 0,
+            is_refunding: 0,
+            randomness_oracle: Pubkey::default(),
+            ticket_ledger: Pubkey::default(),
+            ticket_nfts: 0,
+            referral_bps: 0,
+            referral_rewards: 0,
+            sponsored_prizes: 0,
+            min_entries: 0,
+            max_entries: 0,
+            allowlist_root: [0; 32],
+            close_slot: 0,
+            sponsored_amount: 0,
+        }
+    }
+
+    #[test]
+    fn lottery_space_fits_drawn_lottery() {
+        let mut details = details(0);
+        let space = lottery_space(&details);
+        details.winning_numbers = vec![1, 2, 3];
+        assert_eq!(space, details.try_to_vec().unwrap().len());
+        assert_eq!(lottery_space(&details), space);
+    }
+
+    #[test]
+    fn create_lottery_passes_bond_accounts_only_with_a_bond() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let instruction = create_lottery(&program_id, &lottery, &details(0));
+        assert_eq!(instruction.accounts.len(), 2);
+        assert_eq!(instruction.data[0], CREATE_LOTTERY);
+
+        let details = details(50);
+        let instruction = create_lottery(&program_id, &lottery, &details);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_eq!(
+            instruction.accounts[4].pubkey,
+            pot_address(&program_id, &lottery, &details.token_mint)
+        );
+        assert!(instruction.accounts[1].is_signer);
+    }
+
+    #[test]
+    fn cancel_lottery_returns_only_a_bond_still_in_the_pot() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let mut details = details(50);
+        let instruction = cancel_lottery(&program_id, &lottery, &details);
+        assert_eq!(instruction.data, vec![CANCEL_LOTTERY]);
+        assert_eq!(instruction.accounts.len(), 5);
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            get_associated_token_address(&details.admin, &details.token_mint)
+        );
+
+        details.is_revealed = 1;
+        let instruction = cancel_lottery(&program_id, &lottery, &details);
+        assert_eq!(instruction.accounts.len(), 1);
+        assert!(!instruction.accounts[0].is_signer);
+    }
+
+    #[test]
+    fn slash_bond_returns_only_an_unshared_bond() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let mut details = details(50);
+        let instruction = slash_bond(&program_id, &lottery, &details);
+        assert_eq!(instruction.data, vec![SLASH_BOND]);
+        assert_eq!(instruction.accounts.len(), 5);
+
+        details.total_entries = 5;
+        let instruction = slash_bond(&program_id, &lottery, &details);
+        assert_eq!(instruction.accounts.len(), 1);
+
+        details.total_entries = 3;
+        let instruction = slash_bond(&program_id, &lottery, &details);
+        assert_eq!(instruction.accounts.len(), 5);
+    }
+
+    #[test]
+    fn play_encodes_ticket() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let instruction = play(
+            &program_id,
+            &lottery,
+            &Pubkey::new_unique(),
+            &player,
+            4,
+            &[1, 2, 3],
+        );
+        assert_eq!(instruction.data[0], PLAY);
+        let ticket = TicketDetails::try_from_slice(&instruction.data[1..]).unwrap();
+        assert_eq!(ticket.player, player.to_string());
+        assert_eq!(&ticket.ticket_number_arr[..4], &[1, 2, 3, 0]);
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            find_ticket_address(&program_id, &lottery, 4).0
+        );
+    }
+
+    #[test]
+    fn fulfill_randomness_passes_proof_then_draw_accounts() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let mut details = details(0);
+        details.total_entries = 2;
+        details.randomness_oracle = Pubkey::new_unique();
+        let draw = draw_lottery(&program_id, &lottery, &payer);
+        let fulfill = fulfill_randomness(&program_id, &lottery, &details, &payer);
+        assert_eq!(draw.accounts.len(), 4);
+        assert_eq!(
+            draw.accounts[2].pubkey,
+            find_results_address(&program_id, &lottery).0
+        );
+        assert_eq!(
+            fulfill.accounts[0].pubkey,
+            proof_address(&program_id, &details.randomness_oracle, &lottery)
+        );
+        assert_eq!(fulfill.accounts[1..], draw.accounts[..]);
+    }
+
+    #[test]
+    fn ticket_nft_claims_pass_the_holders_token_account() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let ticket = find_ticket_address(&program_id, &lottery, 2).0;
+        let ticket_mint = find_ticket_mint(&program_id, &ticket).0;
+
+        let instruction = play_nft(&program_id, &lottery, &mint, &player, 2, &[1, 2, 3]);
+        assert_eq!(instruction.accounts.len(), 13);
+        assert_eq!(instruction.accounts[7].pubkey, ticket_mint);
+        assert_eq!(
+            instruction.accounts[10].pubkey,
+            get_associated_token_address(&player, &ticket_mint)
+        );
+
+        let holder = Pubkey::new_unique();
+        let instruction = claim_prize_nft(&program_id, &lottery, &mint, &holder, 2);
+        assert_eq!(instruction.accounts[3].pubkey, holder);
+        assert_eq!(
+            instruction.accounts[8].pubkey,
+            get_associated_token_address(&holder, &ticket_mint)
+        );
+        assert_eq!(
+            void_ticket(&program_id, &lottery, 2).accounts[2].pubkey,
+            ticket_mint
+        );
+    }
+
+    #[test]
+    fn referral_records_are_per_lottery_and_wallet() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let referrer = Pubkey::new_unique();
+
+        let accounts = referral_accounts(&program_id, &lottery, &player, &referrer);
+        assert_eq!(accounts[0].pubkey, referrer);
+        assert!(!accounts[0].is_writable);
+        assert_eq!(
+            accounts[2].pubkey,
+            find_referral_address(&program_id, &lottery, &player).0
+        );
+
+        let claim = claim_referral_rewards(&program_id, &lottery, &mint, &referrer);
+        assert_eq!(claim.data, vec![CLAIM_REFERRAL_REWARDS]);
+        assert_eq!(claim.accounts[1].pubkey, accounts[1].pubkey);
+        assert!(claim.accounts[2].is_signer);
+        assert_ne!(
+            find_referral_address(&program_id, &Pubkey::new_unique(), &referrer).0,
+            accounts[1].pubkey
+        );
+    }
+
+    #[test]
+    fn allowlist_proofs_follow_the_ticket() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let play = play(&program_id, &lottery, &mint, &player, 0, &[1, 2, 3]);
+
+        let proof = AllowlistProof {
+            allocation: 0,
+            proof: vec![[7; 32]],
+        };
+        let open = with_allowlist_proof(&program_id, play.clone(), &proof);
+        assert_eq!(open.accounts, play.accounts);
+        let mut data = &open.data[1..];
+        TicketDetails::deserialize(&mut data).unwrap();
+        assert_eq!(AllowlistProof::try_from_slice(data).unwrap(), proof);
+
+        let capped = AllowlistProof {
+            allocation: 2,
+            ..proof
+        };
+        let capped = with_allowlist_proof(&program_id, play.clone(), &capped);
+        assert_eq!(capped.accounts.len(), play.accounts.len() + 1);
+        assert_eq!(
+            capped.accounts.last().unwrap().pubkey,
+            find_allowlist_entry_address(&program_id, &lottery, &player).0
+        );
+    }
+
+    #[test]
+    fn sponsored_prize_claims_name_their_prize_and_share_receipt() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let prize_mint = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let mut details = details(0);
+        details.total_entries = 2;
+        details.sponsored_prizes = 2;
+        let prize = find_prize_address(&program_id, &lottery, 1).0;
+
+        let claim =
+            claim_sponsored_prize(&program_id, &lottery, &details, &prize_mint, &player, 1, 0);
+        assert_eq!(claim.data[0], CLAIM_SPONSORED_PRIZE);
+        assert_eq!(claim.data[1..], 1u64.to_le_bytes());
+        assert_eq!(claim.accounts[2].pubkey, prize);
+        assert_eq!(
+            claim.accounts[3].pubkey,
+            find_share_address(&program_id, &prize, 0).0
+        );
+        assert_eq!(
+            claim.accounts[4].pubkey,
+            find_ticket_address(&program_id, &lottery, 0).0
+        );
+        assert!(claim.accounts[5].is_signer && claim.accounts[5].is_writable);
+
+        details.ticket_ledger = Pubkey::new_unique();
+        let claim =
+            claim_sponsored_prize(&program_id, &lottery, &details, &prize_mint, &player, 1, 5);
+        assert_eq!(claim.data[9..], 5u64.to_le_bytes());
+        assert_eq!(claim.accounts[4].pubkey, details.ticket_ledger);
+    }
+
+    #[test]
+    fn tallies_pass_the_next_tickets() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let mut details = details(0);
+        details.total_entries = 10;
+
+        let tally = tally_tickets(&program_id, &lottery, &details, 4, 3);
+        assert_eq!(
+            tally.data,
+            [vec![TALLY_TICKETS], 3u64.to_le_bytes().to_vec()].concat()
+        );
+        assert_eq!(tally.accounts.len(), 5);
+        assert_eq!(
+            tally.accounts[2].pubkey,
+            find_ticket_address(&program_id, &lottery, 4).0
+        );
+        assert_eq!(
+            tally.accounts[4].pubkey,
+            find_ticket_address(&program_id, &lottery, 6).0
+        );
+    }
+
+    #[test]
+    fn compact_lotteries_pass_their_ledger_as_the_ticket() {
+        let program_id = Pubkey::new_unique();
+        let lottery = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let mut details = details(0);
+        details.total_entries = 1_000;
+        details.ticket_ledger = Pubkey::new_unique();
+        let ledger = details.ticket_ledger;
+
+        let instruction = create_lottery(&program_id, &lottery, &details);
+        assert_eq!(instruction.accounts[2].pubkey, ledger);
+        let instruction = tally_tickets(&program_id, &lottery, &details, 0, 1_000);
+        assert_eq!(instruction.accounts.len(), 3);
+        assert_eq!(instruction.accounts[2].pubkey, ledger);
+
+        let instruction = play_compact(
+            &program_id,
+            &lottery,
+            &ledger,
+            &details.token_mint,
+            &player,
+            &[4, 5, 6],
+        );
+        assert_eq!(instruction.accounts[1].pubkey, ledger);
+        let instruction = claim_prize_compact(
+            &program_id,
+            &lottery,
+            &ledger,
+            &details.token_mint,
+            &player,
+            999,
+        );
+        assert_eq!(instruction.data[0], CLAIM_PRIZE);
+        assert_eq!(
+            ClaimRequest::try_from_slice(&instruction.data[1..])
+                .unwrap()
+                .ticket_index,
+            999
+        );
+        assert_eq!(instruction.accounts[2].pubkey, ledger);
+    }
+
+    #[test]
+    fn start_next_round_carries_over_from_the_second_round() {
+        let program_id = Pubkey::new_unique();
+        let series = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        assert_eq!(
+            start_next_round(&program_id, &series, &mint, &payer, 0)
+                .accounts
+                .len(),
+            10
+        );
+        let instruction = start_next_round(&program_id, &series, &mint, &payer, 1);
+        assert_eq!(instruction.accounts.len(), 13);
+        assert_eq!(
+            instruction.accounts[10].pubkey,
+            find_round_address(&program_id, &series, 0).0
+        );
+    }
+
+    #[test]
+    fn campaign_instructions_are_namespaced() {
+        let program_id = Pubkey::new_unique();
+        let campaign = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let instruction = withdraw_donations(&program_id, &campaign, &admin, 7);
+        assert_eq!(
+            instruction.data[..2],
+            [crowdfunding::NAMESPACE, crowdfunding::WITHDRAW]
+        );
+        assert_eq!(
+            WithdrawRequest::try_from_slice(&instruction.data[2..])
+                .unwrap()
+                .amount,
+            7
+        );
+        let donor = Pubkey::new_unique();
+        let instruction = donate(&program_id, &campaign, &donor, 9);
+        assert_eq!(
+            instruction.data[..2],
+            [crowdfunding::NAMESPACE, crowdfunding::DONATE]
+        );
+        assert_eq!(
+            instruction.accounts[1].pubkey,
+            find_receipt_address(&program_id, &campaign, &donor).0
+        );
+        assert_eq!(
+            refund(&program_id, &campaign, &donor).accounts[1],
+            instruction.accounts[1]
+        );
+    }
+}
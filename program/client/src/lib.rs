@@ -0,0 +1,38 @@
+//! Client for the fanitrade lottery, Fanitrax and crowdfunding program.
+//!
+//! * [`instruction`] builds every instruction of the program, with its
+//!   accounts in the order the processor reads them.
+//! * [`pda`] derives the program's addresses.
+//! * [`events`] decodes the events the program logs.
+//! * [`rpc`] fetches and decodes program accounts and assembles transactions
+//!   on top of `RpcClient`. It is behind the default `rpc` feature, so
+//!   on-chain code and other programs can use the builders alone.
+//!
+//! The state types are the program's own, re-exported from [`state`].
+pub mod events;
+pub mod instruction;
+pub mod pda;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+
+pub use fanitrade_utils::{
+    allowlist, error::LotteryError, ledger, oracle, MARKET_OPEN, MARKET_PAUSED, TICKET_VOID,
+};
+
+/// Account and instruction data types of the program.
+pub mod state {
+    pub use fanitrade_utils::{
+        allowlist::{AllowlistEntry, AllowlistProof},
+        crowdfunding::{CampaignDetails, DonateRequest, DonationReceipt},
+        nft::TicketMetadata,
+        oracle::RandomnessProof,
+        referral::ReferralRecord,
+        sponsor::{
+            PrizeClaimRequest, PrizeRequest, ShareReceipt, SponsorRequest, SponsoredPrize,
+            Sponsorship,
+        },
+        ClaimRequest, CommitRequest, DrawResults, LotteryDetails, LotterySeries, MarketDetails,
+        MarketStatusRequest, OptionsBetDetails, ResultDetails, RevealRequest, TallyRequest,
+        TicketDetails, TraxDetails, WithdrawRequest,
+    };
+}
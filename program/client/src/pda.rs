@@ -0,0 +1,28 @@
+//! Addresses derived from the program id.
+use solana_program::pubkey::Pubkey;
+use spl_associated_token_account::get_associated_token_address;
+
+pub use fanitrade_utils::{
+    allowlist::find_allowlist_entry_address,
+    crowdfunding::find_receipt_address,
+    find_pot_authority, find_results_address, find_round_address, find_ticket_address,
+    nft::{find_metadata_address, find_ticket_mint},
+    oracle::{find_proof_address, find_requester_address},
+    referral::find_referral_address,
+    sponsor::{find_prize_address, find_share_address, find_sponsorship_address},
+};
+
+/// Token account holding a lottery's pot: the associated token account of
+/// its pot authority.
+pub fn pot_address(program_id: &Pubkey, lottery: &Pubkey, token_mint: &Pubkey) -> Pubkey {
+    get_associated_token_address(&find_pot_authority(program_id, lottery).0, token_mint)
+}
+
+/// Oracle proof account answering a lottery's randomness request.
+pub fn proof_address(program_id: &Pubkey, oracle_program_id: &Pubkey, lottery: &Pubkey) -> Pubkey {
+    find_proof_address(
+        oracle_program_id,
+        &find_requester_address(program_id, lottery).0,
+    )
+    .0
+}
@@ -0,0 +1,164 @@
+//! Account fetching and transaction assembly on top of `RpcClient`.
+use borsh::BorshDeserialize;
+use fanitrade_utils::{
+    DrawResults, LotteryDetails, LotterySeries, MarketDetails, OptionsBetDetails, TicketDetails,
+    TraxDetails,
+};
+use solana_client::{client_error::ClientError, rpc_client::RpcClient};
+use solana_program::{instruction::Instruction, pubkey::Pubkey, system_instruction};
+use solana_sdk::{signature::Signature, signers::Signers, transaction::Transaction};
+use thiserror::Error;
+
+use crate::pda::{find_results_address, find_ticket_address};
+
+/// `getMultipleAccounts` takes at most this many addresses per call.
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Rpc(#[from] Box<ClientError>),
+    #[error("account {0} doesn't exist")]
+    AccountNotFound(Pubkey),
+    #[error("account {address} isn't a {kind}: {source}")]
+    Decode {
+        address: Pubkey,
+        kind: &'static str,
+        source: std::io::Error,
+    },
+}
+
+impl From<ClientError> for Error {
+    fn from(error: ClientError) -> Self {
+        Error::Rpc(Box::new(error))
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+fn decode<T: BorshDeserialize>(address: &Pubkey, data: &[u8]) -> Result<T> {
+    T::try_from_slice(data).map_err(|source| Error::Decode {
+        address: *address,
+        kind: std::any::type_name::<T>()
+            .rsplit("::")
+            .next()
+            .unwrap_or_default(),
+        source,
+    })
+}
+
+/// Fetches a program account and decodes it as `T`.
+pub fn get_state<T: BorshDeserialize>(rpc: &RpcClient, address: &Pubkey) -> Result<T> {
+    let account = rpc.get_account(address)?;
+    decode(address, &account.data)
+}
+
+pub fn get_lottery(rpc: &RpcClient, address: &Pubkey) -> Result<LotteryDetails> {
+    get_state(rpc, address)
+}
+
+pub fn get_series(rpc: &RpcClient, address: &Pubkey) -> Result<LotterySeries> {
+    get_state(rpc, address)
+}
+
+pub fn get_trax(rpc: &RpcClient, address: &Pubkey) -> Result<TraxDetails> {
+    get_state(rpc, address)
+}
+
+pub fn get_market(rpc: &RpcClient, address: &Pubkey) -> Result<MarketDetails> {
+    get_state(rpc, address)
+}
+
+pub fn get_bet(rpc: &RpcClient, address: &Pubkey) -> Result<OptionsBetDetails> {
+    get_state(rpc, address)
+}
+
+pub fn get_results(rpc: &RpcClient, program_id: &Pubkey, lottery: &Pubkey) -> Result<DrawResults> {
+    get_state(rpc, &find_results_address(program_id, lottery).0)
+}
+
+/// Fetches the first `total_entries` tickets of a lottery, in index order.
+pub fn get_tickets(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    total_entries: u64,
+) -> Result<Vec<TicketDetails>> {
+    let addresses: Vec<Pubkey> = (0..total_entries)
+        .map(|index| find_ticket_address(program_id, lottery, index).0)
+        .collect();
+    let mut tickets = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        for (address, account) in chunk.iter().zip(rpc.get_multiple_accounts(chunk)?) {
+            let account = account.ok_or(Error::AccountNotFound(*address))?;
+            tickets.push(decode(address, &account.data)?);
+        }
+    }
+    Ok(tickets)
+}
+
+/// Fetches every account of the program that decodes as `T`. Accounts are
+/// told apart by layout only, so this is meant for listings, not for
+/// validating an account's type.
+pub fn get_program_states<T: BorshDeserialize>(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<Vec<(Pubkey, T)>> {
+    Ok(rpc
+        .get_program_accounts(program_id)?
+        .into_iter()
+        .filter_map(|(address, account)| {
+            T::try_from_slice(&account.data)
+                .ok()
+                .map(|state| (address, state))
+        })
+        .collect())
+}
+
+/// Builds the system instruction creating a rent-exempt account of `space`
+/// bytes owned by the program, for the instructions that initialize an
+/// account the caller created.
+pub fn create_state_account(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    account: &Pubkey,
+    space: usize,
+) -> Result<Instruction> {
+    let lamports = rpc.get_minimum_balance_for_rent_exemption(space)?;
+    Ok(system_instruction::create_account(
+        payer,
+        account,
+        lamports,
+        space as u64,
+        program_id,
+    ))
+}
+
+/// Assembles `instructions` into a transaction paid by `payer` and signed by
+/// `signers`, which have to include the payer, against the latest blockhash.
+pub fn build_transaction<T: Signers>(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &T,
+) -> Result<Transaction> {
+    let blockhash = rpc.get_latest_blockhash()?;
+    Ok(Transaction::new_signed_with_payer(
+        instructions,
+        Some(payer),
+        signers,
+        blockhash,
+    ))
+}
+
+/// Builds, sends and confirms a transaction of `instructions`.
+pub fn send<T: Signers>(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &T,
+) -> Result<Signature> {
+    let transaction = build_transaction(rpc, instructions, payer, signers)?;
+    Ok(rpc.send_and_confirm_transaction(&transaction)?)
+}
@@ -0,0 +1,177 @@
+//! Runs the client against a local validator with the program deployed:
+//!
+//! ```text
+//! solana-test-validator --bpf-program <PROGRAM_ID> target/deploy/fanitrade_utils.so
+//! FANITRADE_PROGRAM_ID=<PROGRAM_ID> cargo test -p fanitrade-client -- --ignored
+//! ```
+//!
+//! `FANITRADE_RPC_URL` overrides the default `http://127.0.0.1:8899`.
+use fanitrade_client::{instruction, pda, rpc, state::LotteryDetails};
+use solana_client::rpc_client::RpcClient;
+use solana_program::{program_pack::Pack, pubkey::Pubkey, system_instruction};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    native_token::LAMPORTS_PER_SOL,
+    signature::{Keypair, Signer},
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+
+const TICKET_PRICE: u64 = 100;
+
+fn connect() -> (RpcClient, Pubkey, Keypair) {
+    let url =
+        std::env::var("FANITRADE_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+    let program_id = std::env::var("FANITRADE_PROGRAM_ID")
+        .expect("FANITRADE_PROGRAM_ID should be the deployed program")
+        .parse()
+        .unwrap();
+    let rpc = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+    let payer = Keypair::new();
+    let signature = rpc
+        .request_airdrop(&payer.pubkey(), 10 * LAMPORTS_PER_SOL)
+        .unwrap();
+    while !rpc.confirm_transaction(&signature).unwrap() {
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+    (rpc, program_id, payer)
+}
+
+fn create_mint(rpc: &RpcClient, payer: &Keypair) -> Pubkey {
+    let mint = Keypair::new();
+    let lamports = rpc
+        .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)
+        .unwrap();
+    let player_tokens = get_associated_token_address(&payer.pubkey(), &mint.pubkey());
+    rpc::send(
+        rpc,
+        &[
+            system_instruction::create_account(
+                &payer.pubkey(),
+                &mint.pubkey(),
+                lamports,
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_mint(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &payer.pubkey(),
+                None,
+                0,
+            )
+            .unwrap(),
+            create_associated_token_account(&payer.pubkey(), &payer.pubkey(), &mint.pubkey()),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &mint.pubkey(),
+                &player_tokens,
+                &payer.pubkey(),
+                &[],
+                10 * TICKET_PRICE,
+            )
+            .unwrap(),
+        ],
+        &payer.pubkey(),
+        &[payer, &mint],
+    )
+    .unwrap();
+    mint.pubkey()
+}
+
+fn lottery_details(admin: Pubkey, token_mint: Pubkey) -> LotteryDetails {
+    LotteryDetails {
+        admin,
+        name: "Localnet lottery".to_string(),
+        is_initialized: 0,
+        is_ended: 0,
+        lottery_start: "0".to_string(),
+        lottery_end: i64::MAX.to_string(),
+        ticket_price: TICKET_PRICE,
+        amount_in_pot: 0,
+        total_entries: 0,
+        token_mint,
+        series: Pubkey::default(),
+        round: 0,
+        fee_bps: 1_000,
+        prize_split: vec![5_000, 3_000, 1_000],
+        pick_count: 3,
+        max_number: 10,
+        winning_numbers: Vec::new(),
+        claim_period: 3_600,
+        fees_collected: 0,
+        bond_amount: 0,
+        randomness_commitment: [0; 32],
+        commit_slot: 0,
+        randomness: [0; 32],
+        is_revealed: 0,
+        is_refunding: 0,
+        randomness_oracle: Pubkey::default(),
+        ticket_ledger: Pubkey::default(),
+        ticket_nfts: 0,
+        referral_bps: 0,
+        referral_rewards: 0,
+        sponsored_prizes: 0,
+        min_entries: 0,
+        max_entries: 0,
+        allowlist_root: [0; 32],
+        close_slot: 0,
+        sponsored_amount: 0,
+    }
+}
+
+#[test]
+#[ignore = "needs solana-test-validator with the program deployed"]
+fn create_lottery_and_play() {
+    let (rpc, program_id, payer) = connect();
+    let mint = create_mint(&rpc, &payer);
+
+    let lottery = Keypair::new();
+    let details = lottery_details(payer.pubkey(), mint);
+    let (pot_authority, _) = pda::find_pot_authority(&program_id, &lottery.pubkey());
+    rpc::send(
+        &rpc,
+        &[
+            rpc::create_state_account(
+                &rpc,
+                &program_id,
+                &payer.pubkey(),
+                &lottery.pubkey(),
+                instruction::lottery_space(&details),
+            )
+            .unwrap(),
+            instruction::create_lottery(&program_id, &lottery.pubkey(), &details),
+            create_associated_token_account(&payer.pubkey(), &pot_authority, &mint),
+        ],
+        &payer.pubkey(),
+        &[&payer, &lottery],
+    )
+    .unwrap();
+
+    rpc::send(
+        &rpc,
+        &[instruction::play(
+            &program_id,
+            &lottery.pubkey(),
+            &mint,
+            &payer.pubkey(),
+            0,
+            &[1, 2, 3],
+        )],
+        &payer.pubkey(),
+        &[&payer],
+    )
+    .unwrap();
+
+    let state = rpc::get_lottery(&rpc, &lottery.pubkey()).unwrap();
+    assert_eq!(state.is_initialized, 1);
+    assert_eq!(state.total_entries, 1);
+    assert_eq!(state.amount_in_pot, TICKET_PRICE);
+    let tickets = rpc::get_tickets(&rpc, &program_id, &lottery.pubkey(), 1).unwrap();
+    assert_eq!(tickets[0].player, payer.pubkey().to_string());
+    assert_eq!(&tickets[0].ticket_number_arr[..3], &[1, 2, 3]);
+    let lotteries: Vec<(Pubkey, LotteryDetails)> =
+        rpc::get_program_states(&rpc, &program_id).unwrap();
+    assert!(lotteries
+        .iter()
+        .any(|(address, _)| *address == lottery.pubkey()));
+}
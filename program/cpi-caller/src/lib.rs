@@ -0,0 +1,60 @@
+//! Buys lottery tickets for a PDA vault through `fanitrade_utils::cpi`, the
+//! way a partner program would. Only meant for the program's CPI tests.
+//!
+//! Its one instruction takes the lottery program followed by the accounts of
+//! `Play`, with the vault as the player, and the ticket's `TicketDetails` as
+//! data.
+use borsh::BorshDeserialize;
+use fanitrade_utils::{cpi, CpiContext, TicketDetails};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    entrypoint::ProgramResult,
+    msg,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// The vault buying the tickets, at `["vault"]`.
+pub fn find_vault_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"vault"], program_id)
+}
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let lottery_program = next_account_info(accounts_iter)?;
+    let lottery = next_account_info(accounts_iter)?;
+    let ticket = next_account_info(accounts_iter)?;
+    let vault = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+    let pot = next_account_info(accounts_iter)?;
+    let token_program = next_account_info(accounts_iter)?;
+    let vault_tokens = next_account_info(accounts_iter)?;
+
+    let (vault_key, vault_bump) = find_vault_address(program_id);
+    if vault_key != *vault.key {
+        msg!("vault isn't the program's vault");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let accounts = cpi::accounts::Play {
+        lottery: lottery.clone(),
+        ticket: ticket.clone(),
+        player: vault.clone(),
+        system_program: system_program.clone(),
+        pot: pot.clone(),
+        token_program: token_program.clone(),
+        player_tokens: vault_tokens.clone(),
+    };
+    let seeds: &[&[u8]] = &[b"vault", &[vault_bump]];
+    cpi::play(
+        CpiContext::new_with_signer(lottery_program.clone(), accounts, &[seeds]),
+        TicketDetails::try_from_slice(instruction_data)?,
+    )
+}
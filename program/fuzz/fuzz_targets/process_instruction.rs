@@ -0,0 +1,8 @@
+#![no_main]
+
+use fanitrade_fuzz::{run, Scenario};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|scenario: Scenario| {
+    run(&scenario);
+});
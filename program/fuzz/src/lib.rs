@@ -0,0 +1,977 @@
+//! Fuzz harness for `fanitrade_utils::process_instruction`.
+//!
+//! A [`Scenario`] sets up a small world of in-memory accounts around one
+//! lottery, a market and the wallets playing with them, then runs a few
+//! instructions against it. Instruction data is either raw bytes or a
+//! well-formed request with fuzzed fields, and accounts are either the layout
+//! the instruction expects (optionally with two entries swapped) or an
+//! arbitrary selection from the world. After every successful instruction
+//! [`run`] asserts that
+//!
+//! * lamports and token amounts are conserved,
+//! * accounts the program doesn't own were only changed by the programs it
+//!   invoked, and never lost lamports otherwise,
+//! * read-only accounts are untouched.
+//!
+//! Failed instructions are rolled back like the runtime does; a panic
+//! anywhere is a finding in itself.
+use arbitrary::Arbitrary;
+use borsh::BorshSerialize;
+use fanitrade_utils::{
+    allowlist::{self, AllowlistProof},
+    find_pot_authority, find_results_address, find_ticket_address, oracle, process_instruction,
+    randomness, sponsor, CommitRequest, LotteryDetails, MarketDetails, OptionsBetDetails,
+    ResultDetails, RevealRequest, TicketDetails, TraxDetails, WithdrawRequest,
+};
+use solana_program::{
+    account_info::AccountInfo, bpf_loader, clock::Clock, program_option::COption,
+    program_pack::Pack, pubkey::Pubkey, rent::Rent, system_program, sysvar,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+mod stubs;
+
+pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+/// Size of the buffer behind every account, so the stubbed system program
+/// can create accounts in place.
+pub const DATA_CAPACITY: usize = 10 * 1024;
+
+const LOTTERY_END: i64 = 1_000_000;
+const COMMIT_SLOT: u64 = 5;
+const CLOSE_SLOT: u64 = 8;
+const SECRET: &[u8] = b"fuzz secret";
+const WALLET_LAMPORTS: u64 = 10_000_000_000;
+
+/// Accounts of the world, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Name {
+    Lottery,
+    Admin,
+    Player,
+    Stranger,
+    PotAuthority,
+    Pot,
+    Mint,
+    AdminTokens,
+    PlayerTokens,
+    StrangerTokens,
+    Ticket0,
+    Ticket1,
+    Results,
+    Blank,
+    Market,
+    Bet,
+    OracleRequester,
+    SystemProgram,
+    TokenProgram,
+    AssociatedTokenProgram,
+    RentSysvar,
+    SlotHashesSysvar,
+    Sponsorship,
+    Share,
+}
+
+const NAMES: [Name; 24] = [
+    Name::Lottery,
+    Name::Admin,
+    Name::Player,
+    Name::Stranger,
+    Name::PotAuthority,
+    Name::Pot,
+    Name::Mint,
+    Name::AdminTokens,
+    Name::PlayerTokens,
+    Name::StrangerTokens,
+    Name::Ticket0,
+    Name::Ticket1,
+    Name::Results,
+    Name::Blank,
+    Name::Market,
+    Name::Bet,
+    Name::OracleRequester,
+    Name::SystemProgram,
+    Name::TokenProgram,
+    Name::AssociatedTokenProgram,
+    Name::RentSysvar,
+    Name::SlotHashesSysvar,
+    Name::Sponsorship,
+    Name::Share,
+];
+
+impl Name {
+    fn can_sign(self) -> bool {
+        matches!(self, Name::Admin | Name::Player | Name::Stranger)
+    }
+}
+
+#[derive(Arbitrary, Clone, Copy, Debug)]
+pub enum Phase {
+    Open,
+    Committed,
+    Closed,
+    Revealed,
+    Drawn,
+    Refunding,
+}
+
+/// Starting state of the lottery and the clock.
+#[derive(Arbitrary, Debug)]
+pub struct Setup {
+    pub phase: Phase,
+    pub ticket_price: u16,
+    pub pot: u32,
+    pub fees: u32,
+    pub bond: u16,
+    pub entries: u8,
+    pub pick_count: u8,
+    pub randomness: [u8; 32],
+    /// Seconds from `lottery_end` to now.
+    pub now_offset: i32,
+    pub player_tokens: u32,
+    pub min_entries: u8,
+    pub max_entries: u8,
+    /// Only the player and the stranger may buy tickets.
+    pub allowlisted: bool,
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum Payload {
+    Raw(Vec<u8>),
+    Empty,
+    Ticket {
+        numbers: [u8; 3],
+        for_stranger: bool,
+    },
+    Amount(u64),
+    Commitment {
+        matching: bool,
+    },
+    Secret {
+        matching: bool,
+    },
+    Trax {
+        pool: u64,
+    },
+    Market {
+        pair: String,
+        last_price: u64,
+    },
+    Bet {
+        strike: u64,
+        amount: u64,
+    },
+    Settlement {
+        price: String,
+    },
+    Lottery {
+        price: u64,
+        fee_bps: u16,
+        prize_split: Vec<u16>,
+        pick_count: u8,
+    },
+    Sponsor {
+        amount: u64,
+        name: String,
+    },
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct AccountRef {
+    pub index: u8,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+#[derive(Arbitrary, Debug)]
+pub enum Accounts {
+    Expected,
+    Swapped(u8, u8),
+    Arbitrary(Vec<AccountRef>),
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct Step {
+    pub tag: u8,
+    pub payload: Payload,
+    pub accounts: Accounts,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct Scenario {
+    pub setup: Setup,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct FuzzAccount {
+    name: Name,
+    key: Pubkey,
+    lamports: u64,
+    /// Always `DATA_CAPACITY` bytes; the account's data is `data[..data_len]`.
+    data: Vec<u8>,
+    data_len: usize,
+    owner: Pubkey,
+    executable: bool,
+}
+
+impl FuzzAccount {
+    fn data(&self) -> &[u8] {
+        &self.data[..self.data_len]
+    }
+
+    fn token_amount(&self) -> u64 {
+        if self.owner != spl_token::id() || self.data_len != spl_token::state::Account::LEN {
+            return 0;
+        }
+        spl_token::state::Account::unpack(self.data())
+            .map(|account| account.amount)
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct World {
+    accounts: Vec<FuzzAccount>,
+}
+
+fn key_of(name: Name) -> Pubkey {
+    Pubkey::new_from_array([name as u8 + 100; 32])
+}
+
+impl World {
+    fn new(setup: &Setup) -> Self {
+        let lottery = key_of(Name::Lottery);
+        let (pot_authority, _) = find_pot_authority(&PROGRAM_ID, &lottery);
+        let mint = key_of(Name::Mint);
+        let admin = key_of(Name::Admin);
+        let player = key_of(Name::Player);
+        let stranger = key_of(Name::Stranger);
+        let rent = Rent::default();
+
+        let entries = u64::from(setup.entries % 3);
+        let details = lottery_details(setup);
+        let pot = pot_amount(setup);
+        let fees = u64::from(setup.fees);
+        let bond = u64::from(setup.bond);
+
+        let mut world = World {
+            accounts: Vec::with_capacity(NAMES.len()),
+        };
+        for name in NAMES {
+            let (key, lamports, data, owner, executable) = match name {
+                Name::Lottery => (lottery, 0, details.try_to_vec().unwrap(), PROGRAM_ID, false),
+                Name::Admin | Name::Player | Name::Stranger => (
+                    key_of(name),
+                    WALLET_LAMPORTS,
+                    Vec::new(),
+                    system_program::id(),
+                    false,
+                ),
+                Name::PotAuthority => (pot_authority, 0, Vec::new(), system_program::id(), false),
+                Name::Pot => (
+                    get_associated_token_address(&pot_authority, &mint),
+                    0,
+                    token_account(&mint, &pot_authority, pot + fees + bond),
+                    spl_token::id(),
+                    false,
+                ),
+                Name::Mint => (mint, 0, mint_account(&admin), spl_token::id(), false),
+                Name::AdminTokens => (
+                    get_associated_token_address(&admin, &mint),
+                    0,
+                    token_account(&mint, &admin, 0),
+                    spl_token::id(),
+                    false,
+                ),
+                Name::PlayerTokens => (
+                    get_associated_token_address(&player, &mint),
+                    0,
+                    token_account(&mint, &player, u64::from(setup.player_tokens)),
+                    spl_token::id(),
+                    false,
+                ),
+                Name::StrangerTokens => (
+                    key_of(name),
+                    0,
+                    token_account(&mint, &stranger, u64::from(setup.player_tokens)),
+                    spl_token::id(),
+                    false,
+                ),
+                Name::Ticket0 | Name::Ticket1 => {
+                    let index = if name == Name::Ticket0 { 0 } else { 1 };
+                    let (key, _) = find_ticket_address(&PROGRAM_ID, &lottery, index);
+                    if index < entries {
+                        let ticket = TicketDetails {
+                            player: player.to_string(),
+                            ticket_count: 1,
+                            ticket_number_arr: [index as u8 + 1; 128],
+                            lottery,
+                            ticket_index: index,
+                            claimed: 0,
+                        };
+                        (key, 0, ticket.try_to_vec().unwrap(), PROGRAM_ID, false)
+                    } else {
+                        (key, 0, Vec::new(), system_program::id(), false)
+                    }
+                }
+                Name::Results => (
+                    find_results_address(&PROGRAM_ID, &lottery).0,
+                    0,
+                    Vec::new(),
+                    system_program::id(),
+                    false,
+                ),
+                Name::Blank => (key_of(name), 0, vec![0; 512], PROGRAM_ID, false),
+                Name::Market => (
+                    key_of(name),
+                    0,
+                    MarketDetails {
+                        admin,
+                        trax_pub: Pubkey::default().to_string(),
+                        market_pair: "SOL/USDC".to_string(),
+                        last_price: 100,
+                        upper_floor_limit: 120,
+                        lower_floor_limit: 80,
+                        market_status: 1,
+                        markey_apy: 5,
+                        options_count: 0,
+                        amount_in_pool: 0,
+                    }
+                    .try_to_vec()
+                    .unwrap(),
+                    PROGRAM_ID,
+                    false,
+                ),
+                Name::Bet => (key_of(name), 0, vec![0; 256], PROGRAM_ID, false),
+                Name::OracleRequester => (
+                    oracle::find_requester_address(&PROGRAM_ID, &lottery).0,
+                    0,
+                    Vec::new(),
+                    system_program::id(),
+                    false,
+                ),
+                Name::SystemProgram => {
+                    (system_program::id(), 1, Vec::new(), bpf_loader::id(), true)
+                }
+                Name::TokenProgram => (spl_token::id(), 1, Vec::new(), bpf_loader::id(), true),
+                Name::AssociatedTokenProgram => (
+                    spl_associated_token_account::id(),
+                    1,
+                    Vec::new(),
+                    bpf_loader::id(),
+                    true,
+                ),
+                Name::RentSysvar => (
+                    sysvar::rent::id(),
+                    1,
+                    bincode::serialize(&rent).unwrap(),
+                    sysvar::id(),
+                    false,
+                ),
+                Name::SlotHashesSysvar => (
+                    sysvar::slot_hashes::id(),
+                    1,
+                    slot_hashes(&[(10, 3), (6, 2), (4, 1)]),
+                    sysvar::id(),
+                    false,
+                ),
+                Name::Sponsorship => (
+                    sponsor::find_sponsorship_address(&PROGRAM_ID, &lottery, &stranger).0,
+                    0,
+                    Vec::new(),
+                    system_program::id(),
+                    false,
+                ),
+                Name::Share => (
+                    sponsor::find_share_address(&PROGRAM_ID, &key_of(Name::Blank), 0).0,
+                    0,
+                    Vec::new(),
+                    system_program::id(),
+                    false,
+                ),
+            };
+            let data_len = data.len();
+            let lamports = match (name, lamports) {
+                (_, 0) if data_len > 0 => rent.minimum_balance(data_len),
+                _ => lamports,
+            };
+            let mut buffer = data;
+            buffer.resize(DATA_CAPACITY, 0);
+            world.accounts.push(FuzzAccount {
+                name,
+                key,
+                lamports,
+                data: buffer,
+                data_len,
+                owner,
+                executable,
+            });
+        }
+        world
+    }
+
+    fn key(&self, name: Name) -> Pubkey {
+        self.accounts[name as usize].key
+    }
+
+    fn lamports(&self) -> u128 {
+        self.accounts
+            .iter()
+            .map(|account| u128::from(account.lamports))
+            .sum()
+    }
+
+    fn tokens(&self) -> u128 {
+        self.accounts
+            .iter()
+            .map(|account| u128::from(account.token_amount()))
+            .sum()
+    }
+}
+
+/// The world's lottery, in the state `setup` asks for.
+/// Allowlist of the player and the stranger, without allocations.
+fn allowlist_leaves() -> [[u8; 32]; 2] {
+    [
+        allowlist::leaf(&key_of(Name::Player), 0),
+        allowlist::leaf(&key_of(Name::Stranger), 0),
+    ]
+}
+
+/// Prize money in the pot, which covers at least the tickets sold.
+fn pot_amount(setup: &Setup) -> u64 {
+    let ticket_sales = (u64::from(setup.ticket_price) + 1) * u64::from(setup.entries % 3);
+    u64::from(setup.pot).max(ticket_sales)
+}
+
+fn lottery_details(setup: &Setup) -> LotteryDetails {
+    let admin = key_of(Name::Admin);
+    let mint = key_of(Name::Mint);
+    let entries = u64::from(setup.entries % 3);
+    let ticket_price = u64::from(setup.ticket_price) + 1;
+    let pot = pot_amount(setup);
+    let fees = u64::from(setup.fees);
+    let bond = u64::from(setup.bond);
+    let pick_count = setup.pick_count % 4;
+    let mut details = LotteryDetails {
+        admin,
+        name: "Fuzz lottery".to_string(),
+        is_initialized: 1,
+        is_ended: 0,
+        lottery_start: "0".to_string(),
+        lottery_end: LOTTERY_END.to_string(),
+        ticket_price,
+        amount_in_pot: pot,
+        total_entries: entries,
+        token_mint: mint,
+        series: Pubkey::default(),
+        round: 0,
+        fee_bps: 1_000,
+        prize_split: vec![5_000, 3_000, 1_000],
+        pick_count,
+        max_number: 10,
+        winning_numbers: vec![0; pick_count as usize],
+        claim_period: 3_600,
+        fees_collected: fees,
+        bond_amount: bond,
+        randomness_commitment: [0; 32],
+        commit_slot: 0,
+        randomness: [0; 32],
+        is_revealed: 0,
+        is_refunding: 0,
+        randomness_oracle: Pubkey::default(),
+        ticket_ledger: Pubkey::default(),
+        ticket_nfts: 0,
+        referral_bps: 0,
+        referral_rewards: 0,
+        sponsored_prizes: 0,
+        min_entries: u64::from(setup.min_entries % 4),
+        max_entries: u64::from(setup.max_entries % 4),
+        allowlist_root: [0; 32],
+        close_slot: 0,
+        sponsored_amount: 0,
+    };
+    if setup.allowlisted {
+        details.allowlist_root = allowlist::root(&allowlist_leaves());
+    }
+    if !matches!(setup.phase, Phase::Open) {
+        details.randomness_commitment = randomness::commitment(SECRET);
+        details.commit_slot = COMMIT_SLOT;
+    }
+    if matches!(setup.phase, Phase::Closed | Phase::Revealed | Phase::Drawn) {
+        details.close_slot = CLOSE_SLOT;
+    }
+    match setup.phase {
+        Phase::Open | Phase::Committed | Phase::Closed => {}
+        Phase::Revealed => {
+            details.randomness = setup.randomness;
+            details.is_revealed = 1;
+        }
+        Phase::Drawn => {
+            details.randomness = setup.randomness;
+            details.is_revealed = 1;
+            details.is_ended = 1;
+        }
+        Phase::Refunding => {
+            details.amount_in_pot = pot.saturating_sub(ticket_price * entries);
+            details.is_refunding = 1;
+            details.is_ended = 1;
+        }
+    }
+    details
+}
+
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    }
+    .pack_into_slice(&mut data);
+    data
+}
+
+fn mint_account(authority: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    spl_token::state::Mint {
+        mint_authority: COption::Some(*authority),
+        supply: u64::MAX,
+        decimals: 0,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+    .pack_into_slice(&mut data);
+    data
+}
+
+fn slot_hashes(entries: &[(u64, u8)]) -> Vec<u8> {
+    let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+    for (slot, hash) in entries {
+        data.extend_from_slice(&slot.to_le_bytes());
+        data.extend_from_slice(&[*hash; 32]);
+    }
+    data
+}
+
+/// Accounts `tag` expects, as laid out by the handlers.
+fn expected_accounts(tag: u8) -> Vec<(Name, bool, bool)> {
+    use Name::*;
+    let signer = |name| (name, true, true);
+    let writable = |name| (name, false, true);
+    let readonly = |name| (name, false, false);
+    match tag {
+        0 | 3 | 5 | 6 => vec![writable(Blank), signer(Admin)],
+        1 => vec![
+            writable(Lottery),
+            writable(Ticket0),
+            signer(Player),
+            readonly(SystemProgram),
+            writable(Pot),
+            readonly(TokenProgram),
+            writable(PlayerTokens),
+        ],
+        2 => vec![
+            writable(Lottery),
+            signer(Admin),
+            readonly(Mint),
+            readonly(TokenProgram),
+            writable(Pot),
+            writable(AdminTokens),
+            readonly(PotAuthority),
+        ],
+        7 => vec![writable(Market), writable(Bet), signer(Player)],
+        8 => vec![writable(Bet), signer(Stranger)],
+        9 => vec![
+            writable(Lottery),
+            signer(Stranger),
+            writable(Results),
+            readonly(SystemProgram),
+        ],
+        10 => vec![
+            writable(Lottery),
+            writable(Results),
+            writable(Ticket0),
+            signer(Player),
+            readonly(PotAuthority),
+            writable(Pot),
+            writable(PlayerTokens),
+            readonly(TokenProgram),
+        ],
+        11 => vec![writable(Lottery), writable(Results)],
+        26 => vec![
+            writable(Lottery),
+            writable(Results),
+            readonly(Ticket0),
+            readonly(Ticket1),
+        ],
+        12 => vec![writable(Lottery), signer(Admin)],
+        13 => vec![
+            writable(Lottery),
+            signer(Admin),
+            readonly(SlotHashesSysvar),
+            writable(AdminTokens),
+            readonly(PotAuthority),
+            writable(Pot),
+            readonly(TokenProgram),
+        ],
+        27 => vec![writable(Lottery), signer(Admin)],
+        14 | 25 => vec![
+            writable(Lottery),
+            writable(AdminTokens),
+            readonly(PotAuthority),
+            writable(Pot),
+            readonly(TokenProgram),
+        ],
+        15 => vec![
+            writable(Lottery),
+            writable(Ticket0),
+            signer(Player),
+            readonly(PotAuthority),
+            writable(Pot),
+            writable(PlayerTokens),
+            readonly(TokenProgram),
+        ],
+        18 => vec![writable(Market), signer(Admin)],
+        19 => vec![readonly(Lottery), writable(Ticket0), readonly(Mint)],
+        20 => vec![
+            writable(Lottery),
+            writable(Blank),
+            signer(Player),
+            readonly(PotAuthority),
+            writable(Pot),
+            writable(PlayerTokens),
+            readonly(TokenProgram),
+        ],
+        21 => vec![
+            writable(Lottery),
+            writable(Sponsorship),
+            signer(Stranger),
+            writable(StrangerTokens),
+            writable(Pot),
+            readonly(SystemProgram),
+            readonly(TokenProgram),
+        ],
+        28 => vec![
+            writable(Lottery),
+            writable(Sponsorship),
+            signer(Stranger),
+            readonly(PotAuthority),
+            writable(Pot),
+            writable(StrangerTokens),
+            readonly(TokenProgram),
+        ],
+        23 => vec![
+            readonly(Lottery),
+            readonly(Results),
+            writable(Blank),
+            writable(Share),
+            readonly(Ticket0),
+            signer(Player),
+            readonly(PotAuthority),
+            writable(Pot),
+            writable(PlayerTokens),
+            readonly(TokenProgram),
+            readonly(SystemProgram),
+        ],
+        24 => vec![
+            readonly(Lottery),
+            writable(Blank),
+            signer(Stranger),
+            readonly(PotAuthority),
+            writable(Pot),
+            writable(StrangerTokens),
+            readonly(TokenProgram),
+            writable(Results),
+        ],
+        16 => vec![
+            writable(Lottery),
+            signer(Stranger),
+            readonly(OracleRequester),
+            writable(Blank),
+            readonly(TokenProgram),
+            readonly(SystemProgram),
+        ],
+        _ => vec![writable(Lottery), signer(Admin)],
+    }
+}
+
+fn instruction_data(world: &World, setup: &Setup, tag: u8, payload: &Payload) -> Vec<u8> {
+    let mut data = vec![tag];
+    let body = match payload {
+        Payload::Raw(bytes) => bytes.clone(),
+        Payload::Empty => Vec::new(),
+        Payload::Ticket {
+            numbers,
+            for_stranger,
+        } => {
+            let player = if *for_stranger {
+                world.key(Name::Stranger)
+            } else {
+                world.key(Name::Player)
+            };
+            let mut ticket_number_arr = [0; 128];
+            ticket_number_arr[..3].copy_from_slice(numbers);
+            let mut ticket = TicketDetails {
+                player: player.to_string(),
+                ticket_count: 1,
+                ticket_number_arr,
+                lottery: Pubkey::default(),
+                ticket_index: 0,
+                claimed: 0,
+            }
+            .try_to_vec()
+            .unwrap();
+            if setup.allowlisted {
+                let proof = AllowlistProof {
+                    allocation: 0,
+                    proof: allowlist::proof(&allowlist_leaves(), usize::from(*for_stranger)),
+                };
+                proof.serialize(&mut ticket).unwrap();
+            }
+            ticket
+        }
+        Payload::Amount(amount) => WithdrawRequest { amount: *amount }.try_to_vec().unwrap(),
+        Payload::Commitment { matching } => CommitRequest {
+            commitment: randomness::commitment(if *matching { SECRET } else { b"other" }),
+        }
+        .try_to_vec()
+        .unwrap(),
+        Payload::Secret { matching } => RevealRequest {
+            secret: if *matching {
+                SECRET.to_vec()
+            } else {
+                b"other".to_vec()
+            },
+        }
+        .try_to_vec()
+        .unwrap(),
+        Payload::Trax { pool } => TraxDetails {
+            admin: world.key(Name::Admin),
+            is_initialized: 0,
+            trax_pool_amount: *pool,
+            total_entries: 0,
+            total_markets: 0,
+            active_markets: 0,
+        }
+        .try_to_vec()
+        .unwrap(),
+        Payload::Market { pair, last_price } => MarketDetails {
+            admin: world.key(Name::Admin),
+            trax_pub: String::new(),
+            market_pair: pair.clone(),
+            last_price: *last_price,
+            upper_floor_limit: 0,
+            lower_floor_limit: 0,
+            market_status: 0,
+            markey_apy: 0,
+            options_count: 0,
+            amount_in_pool: 0,
+        }
+        .try_to_vec()
+        .unwrap(),
+        Payload::Bet { strike, amount } => OptionsBetDetails {
+            player: world.key(Name::Player).to_string(),
+            options_market: world.key(Name::Market).to_string(),
+            options_bet: 1,
+            options_strike: *strike,
+            options_spread: 0,
+            options_bet_start: 0,
+            options_bet_end: 0,
+            options_duration: 0,
+            options_bet_amount: *amount,
+            options_bet_result: "undecided".to_string(),
+        }
+        .try_to_vec()
+        .unwrap(),
+        Payload::Settlement { price } => ResultDetails {
+            player: world.key(Name::Player),
+            options_market: world.key(Name::Market),
+            final_price: price.clone(),
+            result_status: "won".to_string(),
+        }
+        .try_to_vec()
+        .unwrap(),
+        Payload::Lottery {
+            price,
+            fee_bps,
+            prize_split,
+            pick_count,
+        } => LotteryDetails {
+            name: "Fuzzed".to_string(),
+            ticket_price: *price,
+            fee_bps: *fee_bps,
+            prize_split: prize_split.clone(),
+            pick_count: *pick_count,
+            bond_amount: 0,
+            ..lottery_details(setup)
+        }
+        .try_to_vec()
+        .unwrap(),
+        Payload::Sponsor { amount, name } => sponsor::SponsorRequest {
+            amount: *amount,
+            name: name.clone(),
+        }
+        .try_to_vec()
+        .unwrap(),
+    };
+    data.extend_from_slice(&body);
+    data
+}
+
+fn step_accounts(world: &World, tag: u8, step_accounts: &Accounts) -> Vec<(usize, bool, bool)> {
+    let mut accounts: Vec<(usize, bool, bool)> = match step_accounts {
+        Accounts::Expected | Accounts::Swapped(..) => expected_accounts(tag)
+            .into_iter()
+            .map(|(name, is_signer, is_writable)| (name as usize, is_signer, is_writable))
+            .collect(),
+        Accounts::Arbitrary(refs) => refs
+            .iter()
+            .take(16)
+            .map(|account| {
+                (
+                    usize::from(account.index) % world.accounts.len(),
+                    account.is_signer,
+                    account.is_writable,
+                )
+            })
+            .collect(),
+    };
+    if let Accounts::Swapped(a, b) = *step_accounts {
+        if !accounts.is_empty() {
+            let len = accounts.len();
+            accounts.swap(usize::from(a) % len, usize::from(b) % len);
+        }
+    }
+    accounts
+}
+
+/// Runs one instruction, returning whether it succeeded. On failure the
+/// world is left as it was.
+fn execute(world: &mut World, setup: &Setup, step: &Step) -> bool {
+    let tag = step.tag % 29;
+    let data = instruction_data(world, setup, tag, &step.payload);
+    let refs = step_accounts(world, tag, &step.accounts);
+
+    // The runtime unifies privileges per key across the instruction, and
+    // only keys with a private key in the world can sign.
+    let mut privileges = vec![(false, false); world.accounts.len()];
+    for &(index, is_signer, is_writable) in &refs {
+        let privilege = &mut privileges[index];
+        privilege.0 |= is_signer && world.accounts[index].name.can_sign();
+        privilege.1 |= is_writable && !world.accounts[index].executable;
+    }
+
+    let before = world.clone();
+    let (result, data_lens) = {
+        let infos: Vec<AccountInfo> = world
+            .accounts
+            .iter_mut()
+            .zip(&privileges)
+            .map(|(account, &(is_signer, is_writable))| {
+                let FuzzAccount {
+                    key,
+                    lamports,
+                    data,
+                    data_len,
+                    owner,
+                    executable,
+                    ..
+                } = account;
+                AccountInfo::new(
+                    key,
+                    is_signer,
+                    is_writable,
+                    lamports,
+                    &mut data[..*data_len],
+                    owner,
+                    *executable,
+                    0,
+                )
+            })
+            .collect();
+        let accounts: Vec<AccountInfo> = refs
+            .iter()
+            .map(|&(index, ..)| infos[index].clone())
+            .collect();
+        let result = process_instruction(&PROGRAM_ID, &accounts, &data);
+        let data_lens: Vec<usize> = infos.iter().map(AccountInfo::data_len).collect();
+        (result, data_lens)
+    };
+    for (account, data_len) in world.accounts.iter_mut().zip(data_lens) {
+        account.data_len = data_len;
+    }
+    let cpi = stubs::take_cpi_log();
+
+    // The runtime rejects instructions that write to accounts passed
+    // read-only, so those are failures rather than broken invariants.
+    let wrote_read_only = before
+        .accounts
+        .iter()
+        .zip(&world.accounts)
+        .zip(&privileges)
+        .any(|((old, new), &(_, is_writable))| !is_writable && old != new);
+    if result.is_err() || cpi.failed || wrote_read_only {
+        *world = before;
+        return false;
+    }
+    check_invariants(&before, world, &privileges, &cpi);
+    true
+}
+
+fn check_invariants(
+    before: &World,
+    after: &World,
+    privileges: &[(bool, bool)],
+    cpi: &stubs::CpiLog,
+) {
+    assert_eq!(
+        before.lamports(),
+        after.lamports(),
+        "lamports not conserved"
+    );
+    assert_eq!(before.tokens(), after.tokens(), "tokens not conserved");
+    for ((old, new), &(_, is_writable)) in
+        before.accounts.iter().zip(&after.accounts).zip(privileges)
+    {
+        if is_writable && old.owner != PROGRAM_ID && !cpi.touched.contains(&old.key) {
+            assert_eq!(
+                old.data(),
+                new.data(),
+                "foreign account {:?} had its data changed",
+                old.name
+            );
+            assert_eq!(
+                old.owner, new.owner,
+                "foreign account {:?} was reassigned",
+                old.name
+            );
+            assert!(
+                new.lamports >= old.lamports,
+                "foreign account {:?} was debited",
+                old.name
+            );
+        }
+    }
+}
+
+/// Runs `scenario`, panicking if an invariant is broken. Returns how many
+/// steps succeeded.
+pub fn run(scenario: &Scenario) -> usize {
+    stubs::install();
+    let mut world = World::new(&scenario.setup);
+    stubs::set_clock(Clock {
+        slot: 10,
+        unix_timestamp: LOTTERY_END.saturating_add(i64::from(scenario.setup.now_offset)),
+        ..Clock::default()
+    });
+    scenario
+        .steps
+        .iter()
+        .take(8)
+        .filter(|step| execute(&mut world, &scenario.setup, step))
+        .count()
+}
@@ -0,0 +1,223 @@
+//! Off-chain stand-ins for the syscalls the program makes: sysvars come from
+//! the current scenario, and cross-program invocations of the system, token
+//! and associated token programs are executed in-process with the runtime's
+//! privilege checks.
+use std::{cell::RefCell, collections::HashSet, slice, sync::Once};
+
+use solana_program::{
+    account_info::AccountInfo,
+    clock::Clock,
+    entrypoint::ProgramResult,
+    instruction::Instruction,
+    program_error::ProgramError,
+    program_pack::Pack,
+    program_stubs::{self, SyscallStubs},
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction::SystemInstruction,
+    system_program,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+use crate::{DATA_CAPACITY, PROGRAM_ID};
+
+thread_local! {
+    static CLOCK: RefCell<Clock> = RefCell::new(Clock::default());
+    static CPI: RefCell<CpiLog> = RefCell::new(CpiLog::default());
+}
+
+/// What the cross-program invocations of one instruction did.
+#[derive(Default)]
+pub struct CpiLog {
+    /// Accounts passed writable to an invoked program, which may legitimately
+    /// change accounts the fuzzed program doesn't own.
+    pub touched: HashSet<Pubkey>,
+    /// An invocation failed. The runtime aborts the transaction in that case,
+    /// even if the program ignores the error.
+    pub failed: bool,
+}
+
+pub fn set_clock(clock: Clock) {
+    CLOCK.with(|current| *current.borrow_mut() = clock);
+}
+
+pub fn take_cpi_log() -> CpiLog {
+    CPI.with(|log| std::mem::take(&mut *log.borrow_mut()))
+}
+
+pub fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        program_stubs::set_syscall_stubs(Box::new(FuzzStubs));
+    });
+}
+
+struct FuzzStubs;
+
+impl SyscallStubs for FuzzStubs {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = CLOCK.with(|clock| clock.borrow().clone());
+        unsafe { *(var_addr as *mut Clock) = clock };
+        solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        let result = invoke(instruction, account_infos, signers_seeds);
+        if result.is_err() {
+            CPI.with(|log| log.borrow_mut().failed = true);
+        }
+        result
+    }
+}
+
+fn invoke(
+    instruction: &Instruction,
+    account_infos: &[AccountInfo],
+    signers_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let pda_signers = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &PROGRAM_ID))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+
+    let mut accounts = Vec::with_capacity(instruction.accounts.len());
+    for meta in &instruction.accounts {
+        let info = account_infos
+            .iter()
+            .find(|info| *info.key == meta.pubkey)
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if meta.is_signer && !info.is_signer && !pda_signers.contains(info.key) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if meta.is_writable && !info.is_writable {
+            return Err(ProgramError::InvalidArgument);
+        }
+        let mut info = info.clone();
+        info.is_signer = meta.is_signer;
+        info.is_writable = meta.is_writable;
+        if meta.is_writable {
+            CPI.with(|log| log.borrow_mut().touched.insert(meta.pubkey));
+        }
+        accounts.push(info);
+    }
+
+    if instruction.program_id == spl_token::id() {
+        spl_token::processor::Processor::process(&spl_token::id(), &accounts, &instruction.data)
+    } else if instruction.program_id == system_program::id() {
+        let system_instruction = bincode::deserialize(&instruction.data)
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+        process_system_instruction(system_instruction, &accounts)
+    } else if instruction.program_id == spl_associated_token_account::id() {
+        create_associated_token_account(&accounts)
+    } else {
+        Err(ProgramError::IncorrectProgramId)
+    }
+}
+
+fn process_system_instruction(
+    instruction: SystemInstruction,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let from = accounts.first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let to = accounts.get(1).ok_or(ProgramError::NotEnoughAccountKeys)?;
+    if !from.is_signer || *from.owner != system_program::id() {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    match instruction {
+        SystemInstruction::CreateAccount {
+            lamports,
+            space,
+            owner,
+        } => {
+            if !to.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            create_account(from, to, lamports, space as usize, &owner)
+        }
+        SystemInstruction::Transfer { lamports } => move_lamports(from, to, lamports),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    let remaining = from
+        .lamports()
+        .checked_sub(lamports)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    **from.try_borrow_mut_lamports()? = remaining;
+    **to.try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+fn create_account(
+    from: &AccountInfo,
+    to: &AccountInfo,
+    lamports: u64,
+    space: usize,
+    owner: &Pubkey,
+) -> ProgramResult {
+    if to.lamports() != 0 || !to.data_is_empty() || *to.owner != system_program::id() {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    if space > DATA_CAPACITY {
+        return Err(ProgramError::InvalidArgument);
+    }
+    move_lamports(from, to, lamports)?;
+    let mut data = to.try_borrow_mut_data()?;
+    // Safety: every system account of the world is backed by a zeroed buffer
+    // of `DATA_CAPACITY` bytes, so growing the slice stays inside it.
+    *data = unsafe { slice::from_raw_parts_mut(data.as_mut_ptr(), space) };
+    drop(data);
+    to.assign(owner);
+    Ok(())
+}
+
+fn create_associated_token_account(accounts: &[AccountInfo]) -> ProgramResult {
+    let [payer, associated_account, wallet, mint, _system_program, _token_program, rent_sysvar, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if get_associated_token_address(wallet.key, mint.key) != *associated_account.key {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let lamports = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+    create_account(
+        payer,
+        associated_account,
+        lamports,
+        spl_token::state::Account::LEN,
+        &spl_token::id(),
+    )?;
+    let initialize = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        associated_account.key,
+        mint.key,
+        wallet.key,
+    )?;
+    spl_token::processor::Processor::process(
+        &spl_token::id(),
+        &[
+            associated_account.clone(),
+            mint.clone(),
+            wallet.clone(),
+            rent_sysvar.clone(),
+        ],
+        &initialize.data,
+    )
+}
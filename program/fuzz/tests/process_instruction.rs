@@ -0,0 +1,126 @@
+use arbitrary::{Arbitrary, Unstructured};
+use fanitrade_fuzz::{run, Accounts, Payload, Phase, Scenario, Setup, Step};
+use proptest::{prelude::*, test_runner::FileFailurePersistence};
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        cases: 512,
+        failure_persistence: Some(Box::new(FileFailurePersistence::WithSource("regressions"))),
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn arbitrary_scenarios_keep_invariants(bytes in proptest::collection::vec(any::<u8>(), 0..2048)) {
+        if let Ok(scenario) = Scenario::arbitrary(&mut Unstructured::new(&bytes)) {
+            run(&scenario);
+        }
+    }
+}
+
+fn setup(phase: Phase, now_offset: i32) -> Setup {
+    Setup {
+        phase,
+        ticket_price: 99,
+        pot: 1_000,
+        fees: 100,
+        bond: 50,
+        entries: 0,
+        pick_count: 3,
+        randomness: [9; 32],
+        now_offset,
+        player_tokens: 1_000,
+        min_entries: 0,
+        max_entries: 0,
+        allowlisted: false,
+    }
+}
+
+fn step(tag: u8, payload: Payload) -> Step {
+    Step {
+        tag,
+        payload,
+        accounts: Accounts::Expected,
+    }
+}
+
+#[test]
+fn harness_reaches_play_and_withdraw() {
+    let scenario = Scenario {
+        setup: setup(Phase::Open, -100),
+        steps: vec![
+            step(
+                1,
+                Payload::Ticket {
+                    numbers: [1, 2, 3],
+                    for_stranger: false,
+                },
+            ),
+            step(2, Payload::Amount(100)),
+        ],
+    };
+    assert_eq!(run(&scenario), 2);
+}
+
+#[test]
+fn harness_reaches_close_sales() {
+    let scenario = Scenario {
+        setup: setup(Phase::Committed, 100),
+        steps: vec![step(27, Payload::Empty)],
+    };
+    assert_eq!(run(&scenario), 1);
+}
+
+#[test]
+fn harness_reaches_reveal_and_draw() {
+    let scenario = Scenario {
+        setup: setup(Phase::Closed, 100),
+        steps: vec![
+            step(13, Payload::Secret { matching: true }),
+            step(9, Payload::Empty),
+        ],
+    };
+    assert_eq!(run(&scenario), 2);
+}
+
+#[test]
+fn harness_reaches_draw_and_tally() {
+    let scenario = Scenario {
+        setup: Setup {
+            entries: 2,
+            ..setup(Phase::Revealed, 100)
+        },
+        steps: vec![step(9, Payload::Empty), step(26, Payload::Amount(2))],
+    };
+    assert_eq!(run(&scenario), 2);
+}
+
+#[test]
+fn harness_reaches_cancel_and_refund() {
+    let scenario = Scenario {
+        setup: Setup {
+            entries: 1,
+            min_entries: 2,
+            ..setup(Phase::Open, 100)
+        },
+        steps: vec![step(25, Payload::Empty), step(15, Payload::Empty)],
+    };
+    assert_eq!(run(&scenario), 2);
+}
+
+#[test]
+fn harness_reaches_allowlisted_play() {
+    let scenario = Scenario {
+        setup: Setup {
+            allowlisted: true,
+            ..setup(Phase::Open, -100)
+        },
+        steps: vec![step(
+            1,
+            Payload::Ticket {
+                numbers: [1, 2, 3],
+                for_stranger: false,
+            },
+        )],
+    };
+    assert_eq!(run(&scenario), 1);
+}
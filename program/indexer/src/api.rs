@@ -0,0 +1,109 @@
+//! The read-only JSON API.
+//!
+//! | Route | Returns |
+//! |---|---|
+//! | `GET /status` | finalized and latest indexed slots |
+//! | `GET /lotteries` | every lottery |
+//! | `GET /lotteries/<address>` | a lottery and its draw |
+//! | `GET /lotteries/<address>/winners` | its winning tickets |
+//! | `GET /winners?limit=<n>` | the latest winning tickets of all lotteries |
+//! | `GET /players/<address>/tickets` | a player's tickets and prizes |
+//! | `GET /players/<address>/bets` | a player's bets and settlements |
+//! | `GET /markets` | every market |
+//! | `GET /markets/pnl`, `GET /markets/<address>/pnl` | staked, paid out and PnL |
+use serde_json::{json, Value};
+use tiny_http::{Header, Response, Server};
+
+use crate::store::Store;
+
+const DEFAULT_LIMIT: u32 = 100;
+
+/// Serves requests on `listen` until the server fails.
+pub fn serve(listen: &str, store: Store) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let server = Server::http(listen)?;
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    for request in server.incoming_requests() {
+        let (status, body) = if *request.method() == tiny_http::Method::Get {
+            route(&store, request.url())
+        } else {
+            (405, json!({ "error": "only GET is supported" }))
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(error) = request.respond(response) {
+            eprintln!("can't respond: {}", error);
+        }
+    }
+    Ok(())
+}
+
+/// Answers `url` with a status code and JSON body.
+pub fn route(store: &Store, url: &str) -> (u16, Value) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let parameter = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    };
+    let limit = match parameter("limit").map(str::parse::<u32>) {
+        None => DEFAULT_LIMIT,
+        Some(Ok(limit)) => limit,
+        Some(Err(_)) => return (400, json!({ "error": "invalid limit" })),
+    };
+
+    let result = match segments.as_slice() {
+        ["status"] => store.finalized_slot().and_then(|finalized_slot| {
+            Ok(json!({
+                "finalized_slot": finalized_slot,
+                "latest_slot": store.latest_slot()?,
+            }))
+        }),
+        ["lotteries"] => store.lotteries().map(Value::from),
+        ["lotteries", address] => match store.lottery(address) {
+            Ok(Some(lottery)) => Ok(lottery),
+            Ok(None) => return (404, json!({ "error": "lottery not found" })),
+            Err(error) => Err(error),
+        },
+        ["lotteries", address, "winners"] => store.winners(Some(address), limit).map(Value::from),
+        ["winners"] => store.winners(parameter("lottery"), limit).map(Value::from),
+        ["players", player, "tickets"] => store.player_tickets(player).map(Value::from),
+        ["players", player, "bets"] => store.player_bets(player).map(Value::from),
+        ["markets"] => store.markets().map(Value::from),
+        ["markets", "pnl"] => store.market_pnl(None).map(Value::from),
+        ["markets", address, "pnl"] => store.market_pnl(Some(address)).map(Value::from),
+        _ => return (404, json!({ "error": "no such route" })),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err(error) => (500, json!({ "error": error.to_string() })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_answer_json() {
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(route(&store, "/lotteries"), (200, json!([])));
+        assert_eq!(route(&store, "/lotteries/unknown").0, 404);
+        assert_eq!(
+            route(&store, "/winners?limit=5&lottery=x"),
+            (200, json!([]))
+        );
+        assert_eq!(route(&store, "/winners?limit=many").0, 400);
+        assert_eq!(
+            route(&store, "/status"),
+            (200, json!({ "finalized_slot": 0, "latest_slot": 0 }))
+        );
+        assert_eq!(route(&store, "/nowhere").0, 404);
+    }
+}
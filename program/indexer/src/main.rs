@@ -0,0 +1,124 @@
+//! `fanitrade-indexer`: indexes the program's lotteries, tickets, draws,
+//! markets, bets and settlements into SQLite and serves them as JSON.
+//!
+//! On start it catches up over RPC from the last finalized slot it indexed,
+//! then follows confirmed account changes and logs over websockets. One
+//! writer thread applies everything to the database; the API reads it
+//! through its own connection. See `api` for the routes.
+mod api;
+mod records;
+mod store;
+mod subscribe;
+mod sync;
+
+use std::{
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Parser;
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::{records::Update, store::Store};
+
+/// Indexes fanitrade lotteries and Fanitrax markets and serves queries over
+/// them.
+#[derive(Parser, Debug)]
+#[clap(name = "fanitrade-indexer", version)]
+struct Args {
+    #[clap(long, default_value = "http://127.0.0.1:8899")]
+    rpc_url: String,
+    /// [default: the RPC URL's websocket endpoint]
+    #[clap(long)]
+    ws_url: Option<String>,
+    #[clap(long)]
+    program_id: Pubkey,
+    #[clap(long, default_value = "fanitrade.sqlite")]
+    db: String,
+    /// Address the API listens on
+    #[clap(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+    /// Seconds between finality checks
+    #[clap(long, default_value = "10")]
+    finality_interval: u64,
+}
+
+/// The websocket endpoint of a validator's RPC URL, which listens one port
+/// above it.
+fn ws_url(rpc_url: &str) -> String {
+    let url = rpc_url.replacen("http", "ws", 1);
+    match url.rsplit_once(':') {
+        Some((host, port)) => match port.trim_end_matches('/').parse::<u16>() {
+            Ok(port) => format!("{}:{}", host, port + 1),
+            Err(_) => url,
+        },
+        None => url,
+    }
+}
+
+fn main() -> sync::Result<()> {
+    let args = Args::parse();
+    let ws_url = args.ws_url.clone().unwrap_or_else(|| ws_url(&args.rpc_url));
+    let rpc = RpcClient::new_with_commitment(args.rpc_url.clone(), CommitmentConfig::confirmed());
+    let mut store = Store::open(&args.db)?;
+
+    let api_store = Store::open(&args.db)?;
+    let listen = args.listen.clone();
+    thread::spawn(move || {
+        if let Err(error) = api::serve(&listen, api_store) {
+            eprintln!("API stopped: {}", error);
+        }
+    });
+
+    // Subscribe before catching up, so nothing falls in between.
+    let (sender, updates) = mpsc::channel();
+    subscribe::spawn_logs(ws_url.clone(), args.program_id, sender.clone());
+    subscribe::spawn_accounts(ws_url, args.program_id, sender);
+    sync::backfill(&rpc, &args.program_id, &mut store)?;
+    eprintln!("caught up to slot {}", store.latest_slot()?);
+
+    let interval = Duration::from_secs(args.finality_interval);
+    let mut last_check = Instant::now();
+    // Set until a resync succeeds, so a failed one is retried.
+    let mut resync = false;
+    loop {
+        match updates.recv_timeout(interval) {
+            Ok(Update::Account {
+                address,
+                account,
+                slot,
+            }) => store.apply_account(&address, &account, slot)?,
+            Ok(Update::Events { origin, events }) => store.apply_events(&origin, &events)?,
+            Ok(Update::Resync) => resync = true,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Err("subscriptions stopped".into()),
+        }
+        if last_check.elapsed() >= interval {
+            last_check = Instant::now();
+            match sync::check_finality(&rpc, &mut store) {
+                Ok(rolled_back) => resync |= rolled_back,
+                Err(error) => eprintln!("can't check finality: {}", error),
+            }
+        }
+        if resync {
+            match sync::backfill(&rpc, &args.program_id, &mut store) {
+                Ok(()) => resync = false,
+                Err(error) => eprintln!("can't resync: {}", error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ws_url_follows_the_rpc_port() {
+        assert_eq!(ws_url("http://127.0.0.1:8899"), "ws://127.0.0.1:8900");
+        assert_eq!(ws_url("https://rpc.example.com"), "wss://rpc.example.com");
+    }
+}
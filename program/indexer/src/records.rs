@@ -0,0 +1,195 @@
+//! Program accounts and events as the indexer stores them.
+//!
+//! Program accounts carry no type tag, so [`ProgramAccount::decode`] tells
+//! them apart by which state type their data deserializes to exactly.
+use borsh::BorshDeserialize;
+use fanitrade_client::{
+    events::ProgramEvent,
+    state::{DrawResults, LotteryDetails, MarketDetails, OptionsBetDetails, TicketDetails},
+};
+use solana_program::pubkey::Pubkey;
+
+/// The program accounts the indexer keeps. The large ones are boxed, so
+/// updates stay cheap to pass between threads.
+#[derive(Debug)]
+pub enum ProgramAccount {
+    Lottery(Box<LotteryDetails>),
+    Ticket(Box<TicketDetails>),
+    Results(DrawResults),
+    Market(MarketDetails),
+    Bet(OptionsBetDetails),
+}
+
+impl ProgramAccount {
+    /// Decodes the data of a program account. Series, Fanitrax and
+    /// crowdfunding accounts aren't indexed and are `None`.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if let Ok(lottery) = LotteryDetails::try_from_slice(data) {
+            return Some(Self::Lottery(Box::new(lottery)));
+        }
+        if let Ok(ticket) = TicketDetails::try_from_slice(data) {
+            return Some(Self::Ticket(Box::new(ticket)));
+        }
+        if let Ok(results) = DrawResults::try_from_slice(data) {
+            return Some(Self::Results(results));
+        }
+        if let Ok(market) = MarketDetails::try_from_slice(data) {
+            return Some(Self::Market(market));
+        }
+        OptionsBetDetails::try_from_slice(data).ok().map(Self::Bet)
+    }
+}
+
+pub fn lottery_status(details: &LotteryDetails) -> &'static str {
+    if details.is_refunding != 0 {
+        "refunding"
+    } else if details.is_ended != 0 {
+        "drawn"
+    } else if details.is_revealed != 0 {
+        "revealed"
+    } else if details.commit_slot != 0 {
+        "committed"
+    } else {
+        "open"
+    }
+}
+
+/// Name of an event's kind, as stored in the `events` table.
+pub fn event_kind(event: &ProgramEvent) -> &'static str {
+    match event {
+        ProgramEvent::LotteryCreated(_) => "LotteryCreated",
+        ProgramEvent::TicketPurchased(_) => "TicketPurchased",
+        ProgramEvent::LotteryDrawn(_) => "LotteryDrawn",
+        ProgramEvent::PrizeClaimed(_) => "PrizeClaimed",
+        ProgramEvent::PotSponsored(_) => "PotSponsored",
+        ProgramEvent::PrizeSponsored(_) => "PrizeSponsored",
+        ProgramEvent::MarketCreated(_) => "MarketCreated",
+        ProgramEvent::OptionPlaced(_) => "OptionPlaced",
+        ProgramEvent::OptionSettled(_) => "OptionSettled",
+    }
+}
+
+/// Where an update came from, so replays and reorgs can be resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Origin {
+    pub slot: u64,
+    /// Transaction that emitted an event; empty for account snapshots.
+    pub signature: String,
+}
+
+/// Something the writer applies to the store.
+#[derive(Debug)]
+pub enum Update {
+    Account {
+        address: Pubkey,
+        account: ProgramAccount,
+        slot: u64,
+    },
+    Events {
+        origin: Origin,
+        events: Vec<ProgramEvent>,
+    },
+    /// A subscription dropped and came back: whatever happened in between
+    /// has to be fetched.
+    Resync,
+}
+
+#[cfg(test)]
+mod tests {
+    use borsh::BorshSerialize;
+
+    use super::*;
+
+    fn lottery() -> LotteryDetails {
+        LotteryDetails {
+            admin: Pubkey::new_unique(),
+            name: "Lottery".to_string(),
+            is_initialized: 1,
+            is_ended: 0,
+            lottery_start: "0".to_string(),
+            lottery_end: "100".to_string(),
+            ticket_price: 100,
+            amount_in_pot: 0,
+            total_entries: 0,
+            token_mint: Pubkey::new_unique(),
+            series: Pubkey::default(),
+            round: 0,
+            fee_bps: 1_000,
+            prize_split: vec![5_000, 3_000, 1_000],
+            pick_count: 3,
+            max_number: 10,
+            winning_numbers: vec![0; 3],
+            claim_period: 3_600,
+            fees_collected: 0,
+            bond_amount: 0,
+            randomness_commitment: [0; 32],
+            commit_slot: 0,
+            randomness: [0; 32],
+            is_revealed: 0,
+            is_refunding: 0,
+            randomness_oracle: Pubkey::default(),
+            ticket_ledger: Pubkey::default(),
+            ticket_nfts: 0,
+            referral_bps: 0,
+            referral_rewards: 0,
+            sponsored_prizes: 0,
+            min_entries: 0,
+            max_entries: 0,
+            allowlist_root: [0; 32],
+            close_slot: 0,
+            sponsored_amount: 0,
+        }
+    }
+
+    #[test]
+    fn decode_tells_account_types_apart() {
+        let data = lottery().try_to_vec().unwrap();
+        assert!(matches!(
+            ProgramAccount::decode(&data),
+            Some(ProgramAccount::Lottery(_))
+        ));
+
+        let ticket = TicketDetails {
+            player: Pubkey::new_unique().to_string(),
+            ticket_count: 1,
+            ticket_number_arr: [0; 128],
+            lottery: Pubkey::new_unique(),
+            ticket_index: 0,
+            claimed: 0,
+        };
+        assert!(matches!(
+            ProgramAccount::decode(&ticket.try_to_vec().unwrap()),
+            Some(ProgramAccount::Ticket(_))
+        ));
+
+        let results = DrawResults {
+            lottery: Pubkey::new_unique(),
+            winning_numbers: vec![1, 2, 3],
+            winning_tickets: Vec::new(),
+            tallied: 3,
+            winners_per_tier: vec![1, 0, 0, 0],
+            first_winners: vec![2, u64::MAX, u64::MAX, u64::MAX],
+            prize_per_ticket: vec![5, 0, 0],
+            claim_deadline: 10,
+            unclaimed: 5,
+            is_expired: 0,
+        };
+        assert!(matches!(
+            ProgramAccount::decode(&results.try_to_vec().unwrap()),
+            Some(ProgramAccount::Results(_))
+        ));
+
+        // A donation receipt is neither.
+        assert!(ProgramAccount::decode(&[0; 72]).is_none());
+    }
+
+    #[test]
+    fn lottery_status_follows_the_draw() {
+        let mut details = lottery();
+        assert_eq!(lottery_status(&details), "open");
+        details.commit_slot = 7;
+        assert_eq!(lottery_status(&details), "committed");
+        details.is_ended = 1;
+        assert_eq!(lottery_status(&details), "drawn");
+    }
+}
@@ -0,0 +1,838 @@
+//! The SQLite database the indexer writes and the API reads.
+//!
+//! Every row records the slot it was observed at:
+//!
+//! * Account rows hold the latest state of an account. An update only
+//!   replaces a row observed at the same or an earlier slot, so replayed or
+//!   out-of-order notifications can't roll state back.
+//! * Event rows are keyed by transaction signature and position, so
+//!   replaying a transaction is a no-op.
+//! * [`Store::rollback`] drops everything observed from a slot on, for when
+//!   that slot turns out to be on an abandoned fork.
+use borsh::{BorshDeserialize, BorshSerialize};
+use fanitrade_client::{
+    events::ProgramEvent,
+    state::{DrawResults, LotteryDetails, MarketDetails, OptionsBetDetails, TicketDetails},
+    TICKET_VOID,
+};
+use rusqlite::{params, types::ValueRef, Connection, OptionalExtension, Params};
+use serde_json::{Map, Value};
+use solana_program::pubkey::Pubkey;
+
+use crate::records::{event_kind, lottery_status, Origin, ProgramAccount};
+
+pub type Result<T> = rusqlite::Result<T>;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS lotteries (
+    address TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    admin TEXT NOT NULL,
+    token_mint TEXT NOT NULL,
+    series TEXT NOT NULL,
+    round INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    lottery_start INTEGER,
+    lottery_end INTEGER,
+    ticket_price INTEGER NOT NULL,
+    total_entries INTEGER NOT NULL,
+    amount_in_pot INTEGER NOT NULL,
+    fees_collected INTEGER NOT NULL,
+    winning_numbers TEXT NOT NULL,
+    slot INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tickets (
+    address TEXT PRIMARY KEY,
+    lottery TEXT NOT NULL,
+    player TEXT NOT NULL,
+    ticket_index INTEGER NOT NULL,
+    numbers TEXT NOT NULL,
+    claimed INTEGER NOT NULL,
+    slot INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS tickets_by_player ON tickets (player);
+CREATE INDEX IF NOT EXISTS tickets_by_lottery ON tickets (lottery, ticket_index);
+CREATE TABLE IF NOT EXISTS results (
+    lottery TEXT PRIMARY KEY,
+    data BLOB NOT NULL,
+    slot INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS winners (
+    lottery TEXT NOT NULL,
+    ticket_index INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    PRIMARY KEY (lottery, ticket_index)
+);
+CREATE TABLE IF NOT EXISTS draws (
+    lottery TEXT PRIMARY KEY,
+    winning_numbers TEXT NOT NULL,
+    total_entries INTEGER NOT NULL,
+    winners_per_tier TEXT NOT NULL,
+    prize_per_ticket TEXT NOT NULL,
+    fee INTEGER NOT NULL,
+    rollover INTEGER NOT NULL,
+    claim_deadline INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS markets (
+    address TEXT PRIMARY KEY,
+    admin TEXT NOT NULL,
+    market_pair TEXT NOT NULL,
+    status INTEGER NOT NULL,
+    last_price INTEGER NOT NULL,
+    upper_floor_limit INTEGER NOT NULL,
+    lower_floor_limit INTEGER NOT NULL,
+    apy INTEGER NOT NULL,
+    options_count INTEGER NOT NULL,
+    amount_in_pool INTEGER NOT NULL,
+    slot INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS bets (
+    address TEXT PRIMARY KEY,
+    market TEXT NOT NULL,
+    player TEXT NOT NULL,
+    options_bet INTEGER NOT NULL,
+    options_strike INTEGER NOT NULL,
+    options_spread INTEGER NOT NULL,
+    bet_start INTEGER NOT NULL,
+    bet_end INTEGER NOT NULL,
+    amount INTEGER NOT NULL,
+    result TEXT NOT NULL,
+    slot INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS bets_by_player ON bets (player);
+CREATE INDEX IF NOT EXISTS bets_by_market ON bets (market);
+CREATE TABLE IF NOT EXISTS settlements (
+    bet TEXT PRIMARY KEY,
+    market TEXT NOT NULL,
+    player TEXT NOT NULL,
+    settler TEXT NOT NULL,
+    final_price TEXT NOT NULL,
+    result TEXT NOT NULL,
+    payout INTEGER NOT NULL,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS settlements_by_market ON settlements (market);
+CREATE TABLE IF NOT EXISTS events (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    data BLOB NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS events_by_slot ON events (slot);
+CREATE TABLE IF NOT EXISTS indexer_state (
+    key TEXT PRIMARY KEY,
+    value INTEGER NOT NULL
+);
+";
+
+/// Tables rolled back with [`Store::rollback`].
+const SLOTTED_TABLES: [&str; 9] = [
+    "lotteries",
+    "tickets",
+    "results",
+    "winners",
+    "draws",
+    "markets",
+    "bets",
+    "settlements",
+    "events",
+];
+
+const FINALIZED_SLOT: &str = "finalized_slot";
+
+pub struct Store {
+    connection: Connection,
+}
+
+fn json_list<T: serde::Serialize>(values: &[T]) -> String {
+    serde_json::to_string(values).unwrap_or_default()
+}
+
+impl Store {
+    pub fn open(path: &str) -> Result<Self> {
+        let connection = Connection::open(path)?;
+        // Lets the API read while the writer writes.
+        connection.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> Result<Self> {
+        let connection = Connection::open_in_memory()?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { connection })
+    }
+
+    /// Records the state of `address` as of `slot`.
+    pub fn apply_account(
+        &mut self,
+        address: &Pubkey,
+        account: &ProgramAccount,
+        slot: u64,
+    ) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        match account {
+            ProgramAccount::Lottery(lottery) => {
+                upsert_lottery(&transaction, address, lottery, slot)?
+            }
+            ProgramAccount::Ticket(ticket) => upsert_ticket(&transaction, address, ticket, slot)?,
+            ProgramAccount::Results(results) => upsert_results(&transaction, results, slot)?,
+            ProgramAccount::Market(market) => upsert_market(&transaction, address, market, slot)?,
+            ProgramAccount::Bet(bet) => upsert_bet(&transaction, address, bet, slot)?,
+        }
+        transaction.commit()
+    }
+
+    /// Records the events of one transaction. Replayed transactions are
+    /// skipped.
+    pub fn apply_events(&mut self, origin: &Origin, events: &[ProgramEvent]) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        for (index, event) in events.iter().enumerate() {
+            let data = match event {
+                ProgramEvent::LotteryCreated(event) => event.try_to_vec(),
+                ProgramEvent::TicketPurchased(event) => event.try_to_vec(),
+                ProgramEvent::LotteryDrawn(event) => event.try_to_vec(),
+                ProgramEvent::PrizeClaimed(event) => event.try_to_vec(),
+                ProgramEvent::PotSponsored(event) => event.try_to_vec(),
+                ProgramEvent::PrizeSponsored(event) => event.try_to_vec(),
+                ProgramEvent::MarketCreated(event) => event.try_to_vec(),
+                ProgramEvent::OptionPlaced(event) => event.try_to_vec(),
+                ProgramEvent::OptionSettled(event) => event.try_to_vec(),
+            }
+            .unwrap_or_default();
+            let inserted = transaction.execute(
+                "INSERT OR IGNORE INTO events (signature, event_index, slot, kind, data)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    origin.signature,
+                    index,
+                    origin.slot,
+                    event_kind(event),
+                    data
+                ],
+            )?;
+            if inserted == 0 {
+                continue;
+            }
+            match event {
+                ProgramEvent::LotteryDrawn(drawn) => {
+                    transaction.execute(
+                        "INSERT OR REPLACE INTO draws (lottery, winning_numbers, total_entries,
+                             winners_per_tier, prize_per_ticket, fee, rollover, claim_deadline,
+                             signature, slot)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                        params![
+                            drawn.lottery.to_string(),
+                            json_list(&drawn.winning_numbers),
+                            drawn.total_entries,
+                            json_list(&drawn.winners_per_tier),
+                            json_list(&drawn.prize_per_ticket),
+                            drawn.fee,
+                            drawn.rollover,
+                            drawn.claim_deadline,
+                            origin.signature,
+                            origin.slot,
+                        ],
+                    )?;
+                }
+                ProgramEvent::OptionSettled(settled) => {
+                    transaction.execute(
+                        "INSERT OR REPLACE INTO settlements (bet, market, player, settler,
+                             final_price, result, payout, signature, slot)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                        params![
+                            settled.bet.to_string(),
+                            settled.market.to_string(),
+                            settled.player.to_string(),
+                            settled.settler.to_string(),
+                            settled.final_price,
+                            settled.result,
+                            settled.payout,
+                            origin.signature,
+                            origin.slot,
+                        ],
+                    )?;
+                }
+                // The accounts these touch are indexed from account updates.
+                _ => {}
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Drops everything observed at `slot` or later.
+    pub fn rollback(&mut self, slot: u64) -> Result<()> {
+        let transaction = self.connection.transaction()?;
+        for table in SLOTTED_TABLES {
+            transaction.execute(&format!("DELETE FROM {} WHERE slot >= ?1", table), [slot])?;
+        }
+        transaction.commit()
+    }
+
+    /// Latest slot anything was observed at.
+    pub fn latest_slot(&self) -> Result<u64> {
+        let query = SLOTTED_TABLES
+            .iter()
+            .map(|table| format!("SELECT MAX(slot) AS slot FROM {}", table))
+            .collect::<Vec<_>>()
+            .join(" UNION ALL ");
+        self.connection.query_row(
+            &format!("SELECT COALESCE(MAX(slot), 0) FROM ({})", query),
+            [],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn finalized_slot(&self) -> Result<u64> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT value FROM indexer_state WHERE key = ?1",
+                [FINALIZED_SLOT],
+                |row| row.get(0),
+            )
+            .optional()?
+            .unwrap_or(0))
+    }
+
+    pub fn set_finalized_slot(&self, slot: u64) -> Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO indexer_state (key, value) VALUES (?1, ?2)",
+            params![FINALIZED_SLOT, slot],
+        )?;
+        Ok(())
+    }
+
+    /// Transactions with events observed after `after` up to `up_to`, with
+    /// the slot each was observed at.
+    pub fn signatures_between(&self, after: u64, up_to: u64) -> Result<Vec<(String, u64)>> {
+        let mut statement = self.connection.prepare(
+            "SELECT DISTINCT signature, slot FROM events WHERE slot > ?1 AND slot <= ?2
+             ORDER BY slot",
+        )?;
+        let rows = statement.query_map([after, up_to], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Runs a read-only query, returning each row as a JSON object keyed by
+    /// column name.
+    fn query_json<P: Params>(&self, sql: &str, params: P) -> Result<Vec<Value>> {
+        let mut statement = self.connection.prepare(sql)?;
+        let names: Vec<String> = statement
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let rows = statement.query_map(params, |row| {
+            let mut object = Map::new();
+            for (index, name) in names.iter().enumerate() {
+                let value = match row.get_ref(index)? {
+                    ValueRef::Null => Value::Null,
+                    ValueRef::Integer(integer) => integer.into(),
+                    ValueRef::Real(real) => real.into(),
+                    // Lists are stored as JSON text.
+                    ValueRef::Text(text) if text.starts_with(b"[") => {
+                        serde_json::from_slice(text).unwrap_or(Value::Null)
+                    }
+                    ValueRef::Text(text) => String::from_utf8_lossy(text).into(),
+                    ValueRef::Blob(blob) => blob.to_vec().into(),
+                };
+                object.insert(name.clone(), value);
+            }
+            Ok(Value::Object(object))
+        })?;
+        rows.collect()
+    }
+
+    pub fn lotteries(&self) -> Result<Vec<Value>> {
+        self.query_json("SELECT * FROM lotteries ORDER BY lottery_start DESC", [])
+    }
+
+    pub fn lottery(&self, address: &str) -> Result<Option<Value>> {
+        let mut lottery = match self
+            .query_json("SELECT * FROM lotteries WHERE address = ?1", [address])?
+            .pop()
+        {
+            Some(lottery) => lottery,
+            None => return Ok(None),
+        };
+        let draw = self
+            .query_json(
+                "SELECT winning_numbers, total_entries, winners_per_tier, prize_per_ticket, fee,
+                     rollover, claim_deadline, signature, slot
+                 FROM draws WHERE lottery = ?1",
+                [address],
+            )?
+            .pop();
+        lottery["draw"] = draw.unwrap_or(Value::Null);
+        Ok(Some(lottery))
+    }
+
+    /// All tickets of `player`, newest first, with what each won.
+    pub fn player_tickets(&self, player: &str) -> Result<Vec<Value>> {
+        self.query_json(
+            "SELECT t.address, t.lottery, l.name AS lottery_name, t.ticket_index, t.numbers,
+                 COALESCE(w.amount, 0) AS prize, t.claimed, t.slot
+             FROM tickets t
+             LEFT JOIN lotteries l ON l.address = t.lottery
+             LEFT JOIN winners w ON w.lottery = t.lottery AND w.ticket_index = t.ticket_index
+             WHERE t.player = ?1
+             ORDER BY t.slot DESC, t.ticket_index DESC",
+            [player],
+        )
+    }
+
+    /// Winning tickets, newest draws first, optionally of one lottery only.
+    pub fn winners(&self, lottery: Option<&str>, limit: u32) -> Result<Vec<Value>> {
+        self.query_json(
+            "SELECT w.lottery, l.name AS lottery_name, w.ticket_index, t.address AS ticket,
+                 t.player, w.amount, t.claimed, w.slot
+             FROM winners w
+             LEFT JOIN lotteries l ON l.address = w.lottery
+             LEFT JOIN tickets t ON t.lottery = w.lottery AND t.ticket_index = w.ticket_index
+             WHERE ?1 IS NULL OR w.lottery = ?1
+             ORDER BY w.slot DESC, w.ticket_index
+             LIMIT ?2",
+            params![lottery, limit],
+        )
+    }
+
+    pub fn markets(&self) -> Result<Vec<Value>> {
+        self.query_json("SELECT * FROM markets ORDER BY market_pair", [])
+    }
+
+    /// Per market: what was staked, what settlements paid out and the
+    /// difference.
+    pub fn market_pnl(&self, market: Option<&str>) -> Result<Vec<Value>> {
+        self.query_json(
+            "SELECT m.address AS market, m.market_pair,
+                 (SELECT COUNT(*) FROM bets b WHERE b.market = m.address) AS bets,
+                 (SELECT COALESCE(SUM(b.amount), 0) FROM bets b WHERE b.market = m.address)
+                     AS staked,
+                 (SELECT COUNT(*) FROM settlements s WHERE s.market = m.address) AS settled,
+                 (SELECT COUNT(*) FROM settlements s
+                     WHERE s.market = m.address AND s.result = 'won') AS won,
+                 (SELECT COUNT(*) FROM settlements s
+                     WHERE s.market = m.address AND s.result = 'lost') AS lost,
+                 (SELECT COALESCE(SUM(s.payout), 0) FROM settlements s
+                     WHERE s.market = m.address) AS paid_out,
+                 (SELECT COALESCE(SUM(b.amount), 0) FROM bets b WHERE b.market = m.address)
+                     - (SELECT COALESCE(SUM(s.payout), 0) FROM settlements s
+                         WHERE s.market = m.address) AS pnl
+             FROM markets m
+             WHERE ?1 IS NULL OR m.address = ?1
+             ORDER BY m.market_pair",
+            [market],
+        )
+    }
+
+    /// All bets of `player`, newest first, with their settlement if any.
+    pub fn player_bets(&self, player: &str) -> Result<Vec<Value>> {
+        self.query_json(
+            "SELECT b.address, b.market, m.market_pair, b.options_bet, b.options_strike,
+                 b.bet_start, b.bet_end, b.amount, COALESCE(s.result, b.result) AS result,
+                 s.final_price, s.payout, b.slot
+             FROM bets b
+             LEFT JOIN markets m ON m.address = b.market
+             LEFT JOIN settlements s ON s.bet = b.address
+             WHERE b.player = ?1
+             ORDER BY b.bet_start DESC",
+            [player],
+        )
+    }
+}
+
+fn upsert_lottery(
+    connection: &Connection,
+    address: &Pubkey,
+    lottery: &LotteryDetails,
+    slot: u64,
+) -> Result<()> {
+    let series = if lottery.series == Pubkey::default() {
+        String::new()
+    } else {
+        lottery.series.to_string()
+    };
+    let winning_numbers: &[u8] = if lottery.is_ended != 0 && lottery.is_refunding == 0 {
+        &lottery.winning_numbers
+    } else {
+        &[]
+    };
+    connection.execute(
+        "INSERT INTO lotteries (address, name, admin, token_mint, series, round, status,
+             lottery_start, lottery_end, ticket_price, total_entries, amount_in_pot,
+             fees_collected, winning_numbers, slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+         ON CONFLICT (address) DO UPDATE SET
+             name = excluded.name, status = excluded.status,
+             lottery_start = excluded.lottery_start, lottery_end = excluded.lottery_end,
+             total_entries = excluded.total_entries, amount_in_pot = excluded.amount_in_pot,
+             fees_collected = excluded.fees_collected,
+             winning_numbers = excluded.winning_numbers, slot = excluded.slot
+         WHERE excluded.slot >= lotteries.slot",
+        params![
+            address.to_string(),
+            lottery.name,
+            lottery.admin.to_string(),
+            lottery.token_mint.to_string(),
+            series,
+            lottery.round,
+            lottery_status(lottery),
+            lottery.lottery_start.parse::<i64>().ok(),
+            lottery.lottery_end.parse::<i64>().ok(),
+            lottery.ticket_price,
+            lottery.total_entries,
+            lottery.amount_in_pot,
+            lottery.fees_collected,
+            json_list(winning_numbers),
+            slot,
+        ],
+    )?;
+    Ok(())
+}
+
+fn upsert_ticket(
+    connection: &Connection,
+    address: &Pubkey,
+    ticket: &TicketDetails,
+    slot: u64,
+) -> Result<()> {
+    // Unpicked slots of the number array are zero.
+    let numbers: Vec<u8> = ticket
+        .ticket_number_arr
+        .iter()
+        .copied()
+        .take_while(|number| *number != 0)
+        .collect();
+    connection.execute(
+        "INSERT INTO tickets (address, lottery, player, ticket_index, numbers, claimed, slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT (address) DO UPDATE SET claimed = excluded.claimed, slot = excluded.slot
+         WHERE excluded.slot >= tickets.slot",
+        params![
+            address.to_string(),
+            ticket.lottery.to_string(),
+            ticket.player,
+            ticket.ticket_index,
+            json_list(&numbers),
+            ticket.claimed,
+            slot,
+        ],
+    )?;
+
+    let results = connection
+        .query_row(
+            "SELECT data FROM results WHERE lottery = ?1",
+            [ticket.lottery.to_string()],
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+        .optional()?
+        .and_then(|data| DrawResults::try_from_slice(&data).ok());
+    if let Some(results) = results {
+        if ticket.claimed != TICKET_VOID {
+            upsert_winner(connection, &results, ticket.ticket_index, &numbers, slot)?;
+        }
+    }
+    Ok(())
+}
+
+/// Records `results` and the prizes of the lottery's known tickets. Results
+/// only list the winning numbers and the prize of each tier, so tickets
+/// observed later are matched against them as they come in.
+fn upsert_results(connection: &Connection, results: &DrawResults, slot: u64) -> Result<()> {
+    let lottery = results.lottery.to_string();
+    let updated = connection.execute(
+        "INSERT INTO results (lottery, data, slot) VALUES (?1, ?2, ?3)
+         ON CONFLICT (lottery) DO UPDATE SET data = excluded.data, slot = excluded.slot
+         WHERE excluded.slot >= results.slot",
+        params![lottery, results.try_to_vec().unwrap_or_default(), slot],
+    )?;
+    if updated == 0 {
+        return Ok(());
+    }
+
+    let mut statement = connection.prepare(
+        "SELECT ticket_index, numbers FROM tickets WHERE lottery = ?1 AND claimed != ?2",
+    )?;
+    let tickets = statement
+        .query_map(params![lottery, TICKET_VOID], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+    for (ticket_index, numbers) in tickets {
+        let numbers: Vec<u8> = serde_json::from_str(&numbers).unwrap_or_default();
+        upsert_winner(connection, results, ticket_index, &numbers, slot)?;
+    }
+    Ok(())
+}
+
+/// Records the prize of ticket `ticket_index` with `numbers`, if it won one.
+fn upsert_winner(
+    connection: &Connection,
+    results: &DrawResults,
+    ticket_index: u64,
+    numbers: &[u8],
+    slot: u64,
+) -> Result<()> {
+    let amount = results.prize(results.tier(ticket_index, numbers));
+    if amount == 0 {
+        return Ok(());
+    }
+    connection.execute(
+        "INSERT INTO winners (lottery, ticket_index, amount, slot) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (lottery, ticket_index) DO UPDATE SET
+             amount = excluded.amount, slot = excluded.slot
+         WHERE excluded.slot >= winners.slot",
+        params![results.lottery.to_string(), ticket_index, amount, slot],
+    )?;
+    Ok(())
+}
+
+fn upsert_market(
+    connection: &Connection,
+    address: &Pubkey,
+    market: &MarketDetails,
+    slot: u64,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO markets (address, admin, market_pair, status, last_price,
+             upper_floor_limit, lower_floor_limit, apy, options_count, amount_in_pool, slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+         ON CONFLICT (address) DO UPDATE SET
+             status = excluded.status, last_price = excluded.last_price,
+             upper_floor_limit = excluded.upper_floor_limit,
+             lower_floor_limit = excluded.lower_floor_limit, apy = excluded.apy,
+             options_count = excluded.options_count,
+             amount_in_pool = excluded.amount_in_pool, slot = excluded.slot
+         WHERE excluded.slot >= markets.slot",
+        params![
+            address.to_string(),
+            market.admin.to_string(),
+            market.market_pair,
+            market.market_status,
+            market.last_price,
+            market.upper_floor_limit,
+            market.lower_floor_limit,
+            market.markey_apy,
+            market.options_count,
+            market.amount_in_pool,
+            slot,
+        ],
+    )?;
+    Ok(())
+}
+
+fn upsert_bet(
+    connection: &Connection,
+    address: &Pubkey,
+    bet: &OptionsBetDetails,
+    slot: u64,
+) -> Result<()> {
+    connection.execute(
+        "INSERT INTO bets (address, market, player, options_bet, options_strike, options_spread,
+             bet_start, bet_end, amount, result, slot)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+         ON CONFLICT (address) DO UPDATE SET result = excluded.result, slot = excluded.slot
+         WHERE excluded.slot >= bets.slot",
+        params![
+            address.to_string(),
+            bet.options_market,
+            bet.player,
+            bet.options_bet,
+            bet.options_strike,
+            bet.options_spread,
+            bet.options_bet_start,
+            bet.options_bet_end,
+            bet.options_bet_amount,
+            bet.options_bet_result,
+            slot,
+        ],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use fanitrade_client::events::OptionSettled;
+
+    use super::*;
+
+    fn market(status: u64) -> MarketDetails {
+        MarketDetails {
+            admin: Pubkey::new_unique(),
+            trax_pub: Pubkey::new_unique().to_string(),
+            market_pair: "SOL/USDC".to_string(),
+            last_price: 100,
+            upper_floor_limit: 120,
+            lower_floor_limit: 80,
+            market_status: status,
+            markey_apy: 5,
+            options_count: 0,
+            amount_in_pool: 0,
+        }
+    }
+
+    fn bet(market: &Pubkey, player: &Pubkey, amount: u64) -> OptionsBetDetails {
+        OptionsBetDetails {
+            player: player.to_string(),
+            options_market: market.to_string(),
+            options_bet: 1,
+            options_strike: 100,
+            options_spread: 5,
+            options_bet_start: 0,
+            options_bet_end: 60,
+            options_duration: 60,
+            options_bet_amount: amount,
+            options_bet_result: "undecided".to_string(),
+        }
+    }
+
+    fn settled(bet: &Pubkey, market: &Pubkey, player: &Pubkey, payout: u64) -> ProgramEvent {
+        ProgramEvent::OptionSettled(OptionSettled {
+            bet: *bet,
+            market: *market,
+            player: *player,
+            settler: Pubkey::new_unique(),
+            final_price: "110".to_string(),
+            result: "won".to_string(),
+            payout,
+        })
+    }
+
+    fn origin(slot: u64, signature: &str) -> Origin {
+        Origin {
+            slot,
+            signature: signature.to_string(),
+        }
+    }
+
+    #[test]
+    fn older_account_updates_do_not_overwrite_newer_ones() {
+        let mut store = Store::open_in_memory().unwrap();
+        let address = Pubkey::new_unique();
+        store
+            .apply_account(&address, &ProgramAccount::Market(market(0)), 20)
+            .unwrap();
+        store
+            .apply_account(&address, &ProgramAccount::Market(market(1)), 10)
+            .unwrap();
+        let markets = store.markets().unwrap();
+        assert_eq!(markets[0]["status"], 0);
+        assert_eq!(markets[0]["slot"], 20);
+    }
+
+    #[test]
+    fn replayed_events_are_applied_once_and_rolled_back_by_slot() {
+        let mut store = Store::open_in_memory().unwrap();
+        let market_address = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        store
+            .apply_account(&market_address, &ProgramAccount::Market(market(1)), 1)
+            .unwrap();
+        store
+            .apply_account(
+                &first,
+                &ProgramAccount::Bet(bet(&market_address, &player, 700)),
+                2,
+            )
+            .unwrap();
+        store
+            .apply_account(
+                &second,
+                &ProgramAccount::Bet(bet(&market_address, &player, 300)),
+                3,
+            )
+            .unwrap();
+        let events = [settled(&first, &market_address, &player, 400)];
+        store.apply_events(&origin(4, "a"), &events).unwrap();
+        store.apply_events(&origin(4, "a"), &events).unwrap();
+
+        let pnl = store.market_pnl(None).unwrap();
+        assert_eq!(pnl[0]["staked"], 1_000);
+        assert_eq!(pnl[0]["paid_out"], 400);
+        assert_eq!(pnl[0]["pnl"], 600);
+        assert_eq!(pnl[0]["won"], 1);
+        assert_eq!(store.player_bets(&player.to_string()).unwrap().len(), 2);
+        assert_eq!(
+            store.signatures_between(3, 4).unwrap(),
+            vec![("a".to_string(), 4)]
+        );
+        assert_eq!(store.latest_slot().unwrap(), 4);
+
+        store.rollback(3).unwrap();
+        let pnl = store.market_pnl(Some(&market_address.to_string())).unwrap();
+        assert_eq!(pnl[0]["staked"], 700);
+        assert_eq!(pnl[0]["paid_out"], 0);
+        assert_eq!(store.latest_slot().unwrap(), 2);
+    }
+
+    #[test]
+    fn winners_join_tickets_and_lotteries() {
+        let mut store = Store::open_in_memory().unwrap();
+        let lottery = Pubkey::new_unique();
+        let player = Pubkey::new_unique();
+        let ticket = |ticket_index: u64, numbers: [u8; 3]| {
+            let mut ticket_number_arr = [0; 128];
+            ticket_number_arr[..3].copy_from_slice(&numbers);
+            TicketDetails {
+                player: player.to_string(),
+                ticket_count: 1,
+                ticket_number_arr,
+                lottery,
+                ticket_index,
+                claimed: 0,
+            }
+        };
+        let ticket_address = Pubkey::new_unique();
+        store
+            .apply_account(
+                &ticket_address,
+                &ProgramAccount::Ticket(Box::new(ticket(1, [4, 5, 6]))),
+                5,
+            )
+            .unwrap();
+        let results = DrawResults {
+            lottery,
+            winning_numbers: vec![6, 5, 4],
+            winning_tickets: Vec::new(),
+            tallied: 2,
+            winners_per_tier: vec![1, 0, 0, 1],
+            first_winners: vec![1, u64::MAX, u64::MAX, 0],
+            prize_per_ticket: vec![50, 20],
+            claim_deadline: 100,
+            unclaimed: 50,
+            is_expired: 0,
+        };
+        store
+            .apply_account(&Pubkey::new_unique(), &ProgramAccount::Results(results), 6)
+            .unwrap();
+        // Observed after the results, and a loser.
+        store
+            .apply_account(
+                &Pubkey::new_unique(),
+                &ProgramAccount::Ticket(Box::new(ticket(0, [1, 2, 3]))),
+                7,
+            )
+            .unwrap();
+
+        let winners = store.winners(Some(&lottery.to_string()), 10).unwrap();
+        assert_eq!(winners.len(), 1);
+        assert_eq!(winners[0]["player"], player.to_string());
+        assert_eq!(winners[0]["ticket"], ticket_address.to_string());
+        assert_eq!(winners[0]["amount"], 50);
+        let tickets = store.player_tickets(&player.to_string()).unwrap();
+        assert_eq!(tickets[0]["prize"], 0);
+        assert_eq!(tickets[1]["numbers"], serde_json::json!([4, 5, 6]));
+        assert_eq!(tickets[1]["prize"], 50);
+        assert!(store.winners(Some("elsewhere"), 10).unwrap().is_empty());
+    }
+}
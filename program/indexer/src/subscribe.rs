@@ -0,0 +1,115 @@
+//! Websocket subscriptions to the program's logs and accounts.
+//!
+//! Each subscription runs on its own thread and forwards what it receives to
+//! the writer as [`Update`]s. When a subscription drops, it reconnects and
+//! sends [`Update::Resync`], since whatever happened while it was down has
+//! to be fetched over RPC.
+use std::{sync::mpsc::Sender, thread, time::Duration};
+
+use fanitrade_client::events::parse_logs;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    pubsub_client::PubsubClient,
+    rpc_config::{
+        RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcTransactionLogsConfig,
+        RpcTransactionLogsFilter,
+    },
+};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig};
+
+use crate::records::{Origin, ProgramAccount, Update};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// Streams the events the program emits in confirmed transactions.
+pub fn spawn_logs(ws_url: String, program_id: Pubkey, updates: Sender<Update>) {
+    thread::spawn(move || loop {
+        match PubsubClient::logs_subscribe(
+            &ws_url,
+            RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        ) {
+            Ok((_subscription, receiver)) => {
+                while let Ok(response) = receiver.recv() {
+                    let logs = response.value;
+                    if logs.err.is_some() {
+                        continue;
+                    }
+                    let events = match parse_logs(&program_id, &logs.logs) {
+                        Ok(events) => events,
+                        Err(error) => {
+                            eprintln!("skipping events of {}: {}", logs.signature, error);
+                            continue;
+                        }
+                    };
+                    if events.is_empty() {
+                        continue;
+                    }
+                    let origin = Origin {
+                        slot: response.context.slot,
+                        signature: logs.signature,
+                    };
+                    if updates.send(Update::Events { origin, events }).is_err() {
+                        return;
+                    }
+                }
+                eprintln!("logs subscription dropped, reconnecting");
+            }
+            Err(error) => eprintln!("can't subscribe to logs: {}", error),
+        }
+        thread::sleep(RECONNECT_DELAY);
+        if updates.send(Update::Resync).is_err() {
+            return;
+        }
+    });
+}
+
+/// Streams confirmed changes to the program's accounts.
+pub fn spawn_accounts(ws_url: String, program_id: Pubkey, updates: Sender<Update>) {
+    thread::spawn(move || loop {
+        let config = RpcProgramAccountsConfig {
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcAccountInfoConfig::default()
+            },
+            ..RpcProgramAccountsConfig::default()
+        };
+        match PubsubClient::program_subscribe(&ws_url, &program_id, Some(config)) {
+            Ok((_subscription, receiver)) => {
+                while let Ok(response) = receiver.recv() {
+                    let keyed = response.value;
+                    let (address, data) = match (
+                        keyed.pubkey.parse::<Pubkey>(),
+                        keyed.account.decode::<Account>(),
+                    ) {
+                        (Ok(address), Some(account)) => (address, account.data),
+                        _ => continue,
+                    };
+                    // Closed accounts keep their last indexed state.
+                    let account = match ProgramAccount::decode(&data) {
+                        Some(account) => account,
+                        None => continue,
+                    };
+                    let update = Update::Account {
+                        address,
+                        account,
+                        slot: response.context.slot,
+                    };
+                    if updates.send(update).is_err() {
+                        return;
+                    }
+                }
+                eprintln!("account subscription dropped, reconnecting");
+            }
+            Err(error) => eprintln!("can't subscribe to accounts: {}", error),
+        }
+        thread::sleep(RECONNECT_DELAY);
+        if updates.send(Update::Resync).is_err() {
+            return;
+        }
+    });
+}
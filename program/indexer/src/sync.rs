@@ -0,0 +1,137 @@
+//! Catching up over RPC, and detecting indexed transactions that a reorg
+//! dropped.
+use std::{error::Error, str::FromStr};
+
+use fanitrade_client::events::parse_logs;
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::{commitment_config::CommitmentConfig, signature::Signature};
+use solana_transaction_status::UiTransactionEncoding;
+
+use crate::{
+    records::{Origin, ProgramAccount},
+    store::Store,
+};
+
+pub type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+/// Signatures fetched per `getSignaturesForAddress` call, the RPC maximum.
+const SIGNATURE_PAGE: usize = 1_000;
+/// Signatures checked per `getSignatureStatuses` call, the RPC maximum.
+const STATUS_PAGE: usize = 256;
+
+/// Indexes every transaction of the program after the last finalized slot,
+/// oldest first, then a snapshot of all its accounts.
+///
+/// Running this again is harmless: events already indexed are skipped and
+/// account rows only move forward.
+pub fn backfill(rpc: &RpcClient, program_id: &Pubkey, store: &mut Store) -> Result<()> {
+    let since = store.finalized_slot()?;
+    let mut signatures = Vec::new();
+    let mut before = None;
+    'pages: loop {
+        let page = rpc.get_signatures_for_address_with_config(
+            program_id,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: Some(SIGNATURE_PAGE),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )?;
+        let last_page = page.len() < SIGNATURE_PAGE;
+        for status in page {
+            if status.slot <= since {
+                break 'pages;
+            }
+            let signature = Signature::from_str(&status.signature)?;
+            before = Some(signature);
+            // Failed transactions change nothing.
+            if status.err.is_none() {
+                signatures.push(signature);
+            }
+        }
+        if last_page {
+            break;
+        }
+    }
+    for signature in signatures.iter().rev() {
+        index_transaction(rpc, program_id, store, signature)?;
+    }
+
+    // The slot is read first, so an account changing meanwhile is only ever
+    // recorded as older than it is and gets replaced by its notification.
+    let slot = rpc.get_slot()?;
+    for (address, account) in rpc.get_program_accounts(program_id)? {
+        if let Some(account) = ProgramAccount::decode(&account.data) {
+            store.apply_account(&address, &account, slot)?;
+        }
+    }
+    Ok(())
+}
+
+fn index_transaction(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    store: &mut Store,
+    signature: &Signature,
+) -> Result<()> {
+    let transaction = rpc.get_transaction(signature, UiTransactionEncoding::Json)?;
+    let logs = transaction
+        .transaction
+        .meta
+        .and_then(|meta| meta.log_messages)
+        .unwrap_or_default();
+    let events = match parse_logs(program_id, &logs) {
+        Ok(events) => events,
+        Err(error) => {
+            eprintln!("skipping events of {}: {}", signature, error);
+            return Ok(());
+        }
+    };
+    if !events.is_empty() {
+        let origin = Origin {
+            slot: transaction.slot,
+            signature: signature.to_string(),
+        };
+        store.apply_events(&origin, &events)?;
+    }
+    Ok(())
+}
+
+/// Moves the finalized slot forward, checking that every transaction
+/// indexed up to it was finalized at the slot it was indexed at.
+///
+/// If one wasn't, its fork was abandoned: everything from its slot on is
+/// rolled back and `true` returned, so the caller can [`backfill`] again.
+pub fn check_finality(rpc: &RpcClient, store: &mut Store) -> Result<bool> {
+    let finalized = rpc.get_slot_with_commitment(CommitmentConfig::finalized())?;
+    let previous = store.finalized_slot()?;
+    if finalized <= previous {
+        return Ok(false);
+    }
+
+    let mut fork_slot: Option<u64> = None;
+    let indexed = store.signatures_between(previous, finalized)?;
+    for page in indexed.chunks(STATUS_PAGE) {
+        let signatures = page
+            .iter()
+            .map(|(signature, _)| Signature::from_str(signature))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let statuses = rpc.get_signature_statuses_with_history(&signatures)?.value;
+        for ((_, slot), status) in page.iter().zip(statuses) {
+            let landed = status.map(|status| status.slot);
+            if landed != Some(*slot) {
+                fork_slot = Some(fork_slot.map_or(*slot, |fork_slot| fork_slot.min(*slot)));
+            }
+        }
+    }
+
+    if let Some(slot) = fork_slot {
+        eprintln!("slot {} was abandoned, rolling back", slot);
+        store.rollback(slot)?;
+        return Ok(true);
+    }
+    store.set_finalized_slot(finalized)?;
+    Ok(false)
+}
@@ -0,0 +1,98 @@
+//! Mock VRF oracle implementing the `fanitrade_utils::oracle` interface.
+//!
+//! It does no cryptography: whoever signs `fulfill` is trusted to supply the
+//! randomness. Only meant for local tests of oracle-drawn lotteries.
+use borsh::{BorshDeserialize, BorshSerialize};
+use fanitrade_utils::oracle::{find_proof_address, RandomnessProof};
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    clock::Clock,
+    entrypoint::ProgramResult,
+    msg,
+    program::invoke_signed,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    rent::Rent,
+    system_instruction,
+    sysvar::Sysvar,
+};
+
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    match instruction_data.split_first() {
+        Some((0, _)) => request(program_id, accounts),
+        Some((1, randomness)) => fulfill(program_id, accounts, randomness),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn request(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let proof_account = next_account_info(accounts_iter)?;
+    let requester = next_account_info(accounts_iter)?;
+    let payer = next_account_info(accounts_iter)?;
+    let system_program = next_account_info(accounts_iter)?;
+
+    if !requester.is_signer || !payer.is_signer {
+        msg!("requester and payer should be signers");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (proof_key, bump) = find_proof_address(program_id, requester.key);
+    if proof_key != *proof_account.key {
+        msg!("proof_account isn't the requester's proof account");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    let proof = RandomnessProof {
+        requester: *requester.key,
+        request_slot: Clock::get()?.slot,
+        is_fulfilled: 0,
+        randomness: [0; 32],
+    };
+    let space = proof.try_to_vec()?.len();
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            proof_account.key,
+            Rent::get()?.minimum_balance(space),
+            space as u64,
+            program_id,
+        ),
+        &[payer.clone(), proof_account.clone(), system_program.clone()],
+        &[&[b"randomness", requester.key.as_ref(), &[bump]]],
+    )?;
+    proof.serialize(&mut &mut proof_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+fn fulfill(program_id: &Pubkey, accounts: &[AccountInfo], randomness: &[u8]) -> ProgramResult {
+    let accounts_iter = &mut accounts.iter();
+    let proof_account = next_account_info(accounts_iter)?;
+    let authority = next_account_info(accounts_iter)?;
+
+    if proof_account.owner != program_id {
+        msg!("proof_account isn't owned by the oracle");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if !authority.is_signer {
+        msg!("authority should be signer");
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let mut proof = RandomnessProof::try_from_slice(*proof_account.data.borrow())?;
+    if proof.is_fulfilled != 0 {
+        msg!("Request has already been fulfilled");
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    proof.randomness = randomness
+        .try_into()
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+    proof.is_fulfilled = 1;
+    proof.serialize(&mut &mut proof_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
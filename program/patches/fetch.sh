@@ -0,0 +1,20 @@
+#!/bin/sh
+# Unpacks the published solana-program-test into solana-program-test/, with
+# the SPL programs it bundles, and applies solana-program-test.patch to it.
+# See README.md.
+set -eu
+
+version=1.9.29
+sha256=68d944137ef6ff8b2a2cddffc81153c874947e4f38b8f134a2330565cb664903
+
+cd "$(dirname "$0")"
+crate=$(mktemp)
+trap 'rm -f "$crate"' EXIT
+curl -sSfL -o "$crate" \
+    "https://static.crates.io/crates/solana-program-test/solana-program-test-$version.crate"
+echo "$sha256  $crate" | sha256sum -c --quiet
+
+rm -rf solana-program-test
+mkdir solana-program-test
+tar -xzf "$crate" -C solana-program-test --strip-components=1
+patch -s -d solana-program-test -p1 < solana-program-test.patch
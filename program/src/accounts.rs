@@ -0,0 +1,553 @@
+//! Typed instruction accounts.
+//!
+//! Each instruction declares the accounts it takes as a struct with the
+//! [`accounts!`] macro, in the order clients pass them, together with the
+//! checks every call needs:
+//!
+//! * `mut`: the account is passed writable,
+//! * `signer`: the account signed the transaction, or `signer(error)` to fail
+//!   with `error` instead of `MissingRequiredSignature`,
+//! * `owner`: the account is owned by the program, or `owner(key)` by `key`,
+//! * `zero`: the account's data is all zeroes, so it hasn't been initialized,
+//! * `rent_exempt`: the account holds enough lamports to be rent exempt,
+//! * `address(key)`: the account is `key`, e.g. a program or sysvar,
+//! * `has_one(Data::field)`: the account's data, deserialized as `Data`, has
+//!   `field` set to the key of the struct's account of the same name, e.g.
+//!   `has_one(LotteryDetails::admin)`,
+//! * `seeds(seed, ...)`: the account is the program's PDA for `seeds`, which
+//!   may refer to the other fields. Its bump is recorded in
+//!   [`Context::bumps`](crate::context::Context) under the field's name,
+//! * `token_mint(key)`, `token_authority(key)`: the account is a token
+//!   account of mint `key`, or owned by `key`.
+//!
+//! `process_instruction` parses the struct with [`Accounts::try_accounts`]
+//! before the handler runs: all accounts are taken first, then each field's
+//! constraints are checked in order. A failed check logs the field's name and
+//! key, so handlers only check what depends on instruction data or on
+//! account data beyond `has_one`. Accounts after the declared ones are left in
+//! `Context::remaining_accounts`; optional tails are parsed from there with
+//! `Context::try_remaining`.
+//!
+//! Each struct also lists its accounts with [`IdlAccounts`] for the program's
+//! IDL, which the `schema` binary writes.
+use std::collections::BTreeMap;
+
+use solana_program::{
+    account_info::AccountInfo, instruction::AccountMeta, msg, program_error::ProgramError,
+    program_pack::Pack, pubkey::Pubkey,
+};
+
+/// A set of accounts parsed and validated from the front of an instruction's
+/// accounts.
+pub trait Accounts<'info>: ToAccountMetas + ToAccountInfos<'info> + Sized {
+    /// Takes the accounts `Self` needs from the front of `accounts`, checking
+    /// their constraints and recording PDA bumps in `bumps`.
+    fn try_accounts(
+        program_id: &Pubkey,
+        accounts: &mut &[AccountInfo<'info>],
+        instruction_data: &[u8],
+        bumps: &mut BTreeMap<String, u8>,
+    ) -> Result<Self, ProgramError>;
+}
+
+/// An instruction's accounts, listed by [`accounts!`] for the IDL.
+pub struct IdlAccounts {
+    /// Attributes of the accounts struct, like its docs.
+    pub attributes: &'static [&'static str],
+    pub accounts: Vec<IdlAccount>,
+}
+
+/// An account of an instruction.
+pub struct IdlAccount {
+    pub name: &'static str,
+    /// Names of the account's constraints, e.g. `mut` or `signer`.
+    pub constraints: &'static [&'static str],
+    /// Attributes of the account's field, like its docs.
+    pub attributes: &'static [&'static str],
+}
+
+/// Account infos to pass to `invoke` alongside an instruction's metas.
+pub trait ToAccountInfos<'info> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>>;
+}
+
+/// Account metas of an instruction taking these accounts.
+pub trait ToAccountMetas {
+    /// `is_signer` overrides whether the accounts are marked as signers.
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta>;
+}
+
+impl<'info> Accounts<'info> for AccountInfo<'info> {
+    fn try_accounts(
+        _program_id: &Pubkey,
+        accounts: &mut &[AccountInfo<'info>],
+        _instruction_data: &[u8],
+        _bumps: &mut BTreeMap<String, u8>,
+    ) -> Result<Self, ProgramError> {
+        let (account, rest) = accounts
+            .split_first()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        *accounts = rest;
+        Ok(account.clone())
+    }
+}
+
+impl<'info> ToAccountInfos<'info> for AccountInfo<'info> {
+    fn to_account_infos(&self) -> Vec<AccountInfo<'info>> {
+        vec![self.clone()]
+    }
+}
+
+impl<'info> ToAccountMetas for AccountInfo<'info> {
+    fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+        let is_signer = is_signer.unwrap_or(self.is_signer);
+        vec![if self.is_writable {
+            AccountMeta::new(*self.key, is_signer)
+        } else {
+            AccountMeta::new_readonly(*self.key, is_signer)
+        }]
+    }
+}
+
+/// Unpacks a token account for the `token_*` constraints.
+pub(crate) fn unpack_token_account(
+    name: &str,
+    account: &AccountInfo,
+) -> Result<spl_token::state::Account, ProgramError> {
+    if *account.owner != spl_token::id() {
+        msg!("{} {} isn't owned by the token program", name, account.key);
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    spl_token::state::Account::unpack(&account.try_borrow_data()?).map_err(|_| {
+        msg!("{} {} isn't a token account", name, account.key);
+        ProgramError::InvalidAccountData
+    })
+}
+
+/// Checks one constraint of an [`accounts!`] field.
+macro_rules! constraint {
+    (mut, $field:ident, $program_id:ident, $bumps:ident) => {
+        if !$field.is_writable {
+            ::solana_program::msg!("{} {} should be writable", stringify!($field), $field.key);
+            return Err(::solana_program::program_error::ProgramError::InvalidArgument);
+        }
+    };
+    (signer, $field:ident, $program_id:ident, $bumps:ident) => {
+        $crate::accounts::constraint!(
+            signer(::solana_program::program_error::ProgramError::MissingRequiredSignature),
+            $field,
+            $program_id,
+            $bumps
+        );
+    };
+    (signer($error:expr), $field:ident, $program_id:ident, $bumps:ident) => {
+        if !$field.is_signer {
+            ::solana_program::msg!("{} {} should be signer", stringify!($field), $field.key);
+            return Err($error);
+        }
+    };
+    (owner, $field:ident, $program_id:ident, $bumps:ident) => {
+        $crate::accounts::constraint!(owner(*$program_id), $field, $program_id, $bumps);
+    };
+    (owner($owner:expr), $field:ident, $program_id:ident, $bumps:ident) => {
+        if *$field.owner != $owner {
+            ::solana_program::msg!(
+                "{} {} is owned by {}, not {}",
+                stringify!($field),
+                $field.key,
+                $field.owner,
+                $owner
+            );
+            return Err(::solana_program::program_error::ProgramError::IncorrectProgramId);
+        }
+    };
+    (zero, $field:ident, $program_id:ident, $bumps:ident) => {
+        if $field.try_borrow_data()?.iter().any(|byte| *byte != 0) {
+            ::solana_program::msg!(
+                "{} {} is already initialized",
+                stringify!($field),
+                $field.key
+            );
+            return Err(::solana_program::program_error::ProgramError::AccountAlreadyInitialized);
+        }
+    };
+    (rent_exempt, $field:ident, $program_id:ident, $bumps:ident) => {
+        let rent = <::solana_program::rent::Rent as ::solana_program::sysvar::Sysvar>::get()?;
+        if !rent.is_exempt($field.lamports(), $field.data_len()) {
+            ::solana_program::msg!(
+                "{} {} should hold {} lamports to be rent exempt",
+                stringify!($field),
+                $field.key,
+                rent.minimum_balance($field.data_len())
+            );
+            return Err(::solana_program::program_error::ProgramError::InsufficientFunds);
+        }
+    };
+    (address($address:expr), $field:ident, $program_id:ident, $bumps:ident) => {
+        if *$field.key != $address {
+            ::solana_program::msg!(
+                "{} {} isn't {}",
+                stringify!($field),
+                $field.key,
+                stringify!($address)
+            );
+            return Err(::solana_program::program_error::ProgramError::InvalidArgument);
+        }
+    };
+    (has_one($data:ident::$target:ident), $field:ident, $program_id:ident, $bumps:ident) => {
+        let data =
+            <$data as ::borsh::BorshDeserialize>::try_from_slice(&$field.try_borrow_data()?)?;
+        if data.$target != *$target.key {
+            ::solana_program::msg!(
+                "{} {} has {} {}, not {}",
+                stringify!($field),
+                $field.key,
+                stringify!($target),
+                data.$target,
+                $target.key
+            );
+            return Err(::solana_program::program_error::ProgramError::InvalidAccountData);
+        }
+    };
+    (seeds($($seed:expr),+ $(,)?), $field:ident, $program_id:ident, $bumps:ident) => {
+        let (address, bump) = ::solana_program::pubkey::Pubkey::find_program_address(
+            &[$(::core::convert::AsRef::<[u8]>::as_ref(&$seed)),+],
+            $program_id,
+        );
+        if address != *$field.key {
+            ::solana_program::msg!(
+                "{} {} isn't the PDA of its seeds, {}",
+                stringify!($field),
+                $field.key,
+                address
+            );
+            return Err(::solana_program::program_error::ProgramError::InvalidSeeds);
+        }
+        $bumps.insert(stringify!($field).to_string(), bump);
+    };
+    (token_mint($mint:expr), $field:ident, $program_id:ident, $bumps:ident) => {
+        let token_account = $crate::accounts::unpack_token_account(stringify!($field), &$field)?;
+        if token_account.mint != $mint {
+            ::solana_program::msg!(
+                "{} {} holds mint {}, not {}",
+                stringify!($field),
+                $field.key,
+                token_account.mint,
+                $mint
+            );
+            return Err(::solana_program::program_error::ProgramError::InvalidAccountData);
+        }
+    };
+    (token_authority($authority:expr), $field:ident, $program_id:ident, $bumps:ident) => {
+        let token_account = $crate::accounts::unpack_token_account(stringify!($field), &$field)?;
+        if token_account.owner != $authority {
+            ::solana_program::msg!(
+                "{} {} is owned by {}, not {}",
+                stringify!($field),
+                $field.key,
+                token_account.owner,
+                $authority
+            );
+            return Err(::solana_program::program_error::ProgramError::InvalidAccountData);
+        }
+    };
+}
+pub(crate) use constraint;
+
+/// Declares an instruction's accounts struct and implements [`Accounts`],
+/// [`ToAccountInfos`] and [`ToAccountMetas`] for it. Every field is an
+/// `AccountInfo`, followed by its constraints:
+///
+/// ```ignore
+/// accounts! {
+///     pub struct DrawLottery<'info> {
+///         pub lottery: [mut],
+///         pub payer: [mut, signer],
+///         pub results: [mut, seeds(b"results", lottery.key)],
+///         pub system_program: [address(system_program::id())],
+///     }
+/// }
+/// ```
+macro_rules! accounts {
+    (
+        $(#[$attr:meta])*
+        pub struct $name:ident<'info> {
+            $(
+                $(#[$field_attr:meta])*
+                pub $field:ident: [$($constraint:ident $(($($arg:tt)*))?),* $(,)?],
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug)]
+        pub struct $name<'info> {
+            $(
+                $(#[$field_attr])*
+                pub $field: ::solana_program::account_info::AccountInfo<'info>,
+            )*
+        }
+
+        impl<'info> $crate::Accounts<'info> for $name<'info> {
+            #[allow(unused_variables)]
+            fn try_accounts(
+                program_id: &::solana_program::pubkey::Pubkey,
+                accounts: &mut &[::solana_program::account_info::AccountInfo<'info>],
+                instruction_data: &[u8],
+                bumps: &mut ::std::collections::BTreeMap<String, u8>,
+            ) -> Result<Self, ::solana_program::program_error::ProgramError> {
+                $(
+                    let $field = <::solana_program::account_info::AccountInfo<'info> as $crate::Accounts<'info>>::try_accounts(
+                        program_id,
+                        accounts,
+                        instruction_data,
+                        bumps,
+                    )?;
+                )*
+                $(
+                    $(
+                        $crate::accounts::constraint!(
+                            $constraint $(($($arg)*))?,
+                            $field,
+                            program_id,
+                            bumps
+                        );
+                    )*
+                )*
+                Ok(Self { $($field),* })
+            }
+        }
+
+        impl<'info> $name<'info> {
+            /// The struct's docs and accounts as the IDL lists them.
+            pub fn idl() -> $crate::accounts::IdlAccounts {
+                $crate::accounts::IdlAccounts {
+                    attributes: &[$(stringify!($attr)),*],
+                    accounts: vec![$($crate::accounts::IdlAccount {
+                        name: stringify!($field),
+                        constraints: &[$(stringify!($constraint)),*],
+                        attributes: &[$(stringify!($field_attr)),*],
+                    }),*],
+                }
+            }
+        }
+
+        impl<'info> $crate::ToAccountInfos<'info> for $name<'info> {
+            fn to_account_infos(
+                &self,
+            ) -> Vec<::solana_program::account_info::AccountInfo<'info>> {
+                vec![$(self.$field.clone()),*]
+            }
+        }
+
+        impl<'info> $crate::ToAccountMetas for $name<'info> {
+            fn to_account_metas(
+                &self,
+                is_signer: Option<bool>,
+            ) -> Vec<::solana_program::instruction::AccountMeta> {
+                vec![$({
+                    let constraints: &[&str] = &[$(stringify!($constraint)),*];
+                    let signer = is_signer.unwrap_or(constraints.contains(&"signer"));
+                    if constraints.contains(&"mut") {
+                        ::solana_program::instruction::AccountMeta::new(*self.$field.key, signer)
+                    } else {
+                        ::solana_program::instruction::AccountMeta::new_readonly(
+                            *self.$field.key,
+                            signer,
+                        )
+                    }
+                }),*]
+            }
+        }
+    };
+}
+pub(crate) use accounts;
+
+#[cfg(test)]
+mod tests {
+    use borsh::{BorshDeserialize, BorshSerialize};
+    use solana_program::system_program;
+
+    use super::*;
+
+    #[derive(BorshSerialize, BorshDeserialize)]
+    struct State {
+        authority: Pubkey,
+    }
+
+    accounts! {
+        pub struct Example<'info> {
+            pub state: [mut, owner, has_one(State::authority)],
+            pub authority: [signer],
+            pub vault: [seeds(b"vault", state.key)],
+            pub system_program: [address(system_program::id())],
+        }
+    }
+
+    accounts! {
+        /// Takes a new account.
+        pub struct Fresh<'info> {
+            /// Created for the program but not initialized.
+            pub state: [mut, zero],
+        }
+    }
+
+    struct Account {
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        is_signer: bool,
+        is_writable: bool,
+    }
+
+    impl Account {
+        fn new(key: Pubkey, is_signer: bool, is_writable: bool) -> Self {
+            Self {
+                key,
+                owner: system_program::id(),
+                lamports: 0,
+                data: Vec::new(),
+                is_signer,
+                is_writable,
+            }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                self.is_signer,
+                self.is_writable,
+                &mut self.lamports,
+                &mut self.data,
+                &self.owner,
+                false,
+                0,
+            )
+        }
+    }
+
+    fn parse(
+        accounts: &mut [Account],
+    ) -> (Result<Example<'_>, ProgramError>, BTreeMap<String, u8>) {
+        let program_id = Pubkey::new_from_array([1; 32]);
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(Account::info).collect();
+        let mut remaining = &infos[..];
+        let mut bumps = BTreeMap::new();
+        let result = Example::try_accounts(&program_id, &mut remaining, &[], &mut bumps);
+        (result, bumps)
+    }
+
+    fn example() -> Vec<Account> {
+        let program_id = Pubkey::new_from_array([1; 32]);
+        let state = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let (vault, _) = Pubkey::find_program_address(&[b"vault", state.as_ref()], &program_id);
+        let mut state = Account::new(state, false, true);
+        state.owner = program_id;
+        state.data = State { authority }.try_to_vec().unwrap();
+        vec![
+            state,
+            Account::new(authority, true, false),
+            Account::new(vault, false, false),
+            Account::new(system_program::id(), false, false),
+        ]
+    }
+
+    #[test]
+    fn try_accounts_checks_constraints_and_records_bumps() {
+        let program_id = Pubkey::new_from_array([1; 32]);
+        let mut accounts = example();
+        let keys: Vec<Pubkey> = accounts.iter().map(|account| account.key).collect();
+        let (result, bumps) = parse(&mut accounts);
+        let parsed = result.unwrap();
+        let (_, bump) =
+            Pubkey::find_program_address(&[b"vault", parsed.state.key.as_ref()], &program_id);
+        assert_eq!(bumps.get("vault"), Some(&bump));
+        assert_eq!(
+            parsed.to_account_metas(None),
+            vec![
+                AccountMeta::new(keys[0], false),
+                AccountMeta::new_readonly(keys[1], true),
+                AccountMeta::new_readonly(keys[2], false),
+                AccountMeta::new_readonly(keys[3], false),
+            ]
+        );
+        assert_eq!(parsed.to_account_infos().len(), 4);
+
+        let mut accounts = example();
+        accounts[0].is_writable = false;
+        assert_eq!(
+            parse(&mut accounts).0.unwrap_err(),
+            ProgramError::InvalidArgument
+        );
+        let mut accounts = example();
+        accounts[0].owner = Pubkey::new_unique();
+        assert_eq!(
+            parse(&mut accounts).0.unwrap_err(),
+            ProgramError::IncorrectProgramId
+        );
+        let mut accounts = example();
+        accounts[1].key = Pubkey::new_unique();
+        assert_eq!(
+            parse(&mut accounts).0.unwrap_err(),
+            ProgramError::InvalidAccountData
+        );
+        let mut accounts = example();
+        accounts[1].is_signer = false;
+        assert_eq!(
+            parse(&mut accounts).0.unwrap_err(),
+            ProgramError::MissingRequiredSignature
+        );
+        let mut accounts = example();
+        accounts[2].key = Pubkey::new_unique();
+        assert_eq!(
+            parse(&mut accounts).0.unwrap_err(),
+            ProgramError::InvalidSeeds
+        );
+        let mut accounts = example();
+        accounts[3].key = Pubkey::new_unique();
+        assert_eq!(
+            parse(&mut accounts).0.unwrap_err(),
+            ProgramError::InvalidArgument
+        );
+        let mut accounts = example();
+        accounts.pop();
+        assert_eq!(
+            parse(&mut accounts).0.unwrap_err(),
+            ProgramError::NotEnoughAccountKeys
+        );
+    }
+
+    #[test]
+    fn zero_rejects_initialized_accounts() {
+        let program_id = Pubkey::new_from_array([1; 32]);
+        let mut state = Account::new(Pubkey::new_unique(), false, true);
+        state.data = vec![0; 8];
+        let info = state.info();
+        let mut accounts = &[info][..];
+        assert!(Fresh::try_accounts(&program_id, &mut accounts, &[], &mut BTreeMap::new()).is_ok());
+
+        state.data[7] = 1;
+        let info = state.info();
+        let mut accounts = &[info][..];
+        assert_eq!(
+            Fresh::try_accounts(&program_id, &mut accounts, &[], &mut BTreeMap::new()).unwrap_err(),
+            ProgramError::AccountAlreadyInitialized
+        );
+    }
+
+    #[test]
+    fn idl_lists_fields_with_their_constraints_and_docs() {
+        let idl = Example::idl();
+        let names: Vec<&str> = idl.accounts.iter().map(|account| account.name).collect();
+        assert_eq!(names, ["state", "authority", "vault", "system_program"]);
+        assert_eq!(idl.accounts[0].constraints, ["mut", "owner", "has_one"]);
+        assert_eq!(idl.accounts[1].constraints, ["signer"]);
+        assert!(idl.attributes.is_empty() && idl.accounts[0].attributes.is_empty());
+
+        let idl = Fresh::idl();
+        assert_eq!(idl.attributes.len(), 1);
+        assert!(idl.attributes[0].contains("Takes a new account."));
+        assert!(idl.accounts[0].attributes[0].contains("Created for the program"));
+    }
+}
@@ -0,0 +1,176 @@
+//! Allowlists: a lottery with `allowlist_root` set only sells tickets to the
+//! wallets of the Merkle tree with that root.
+//!
+//! * Each leaf is the keccak hash of a wallet and its ticket allocation, see
+//!   [`leaf`]. An allocation of 0 lets the wallet buy any number of tickets.
+//! * Parents hash their two children in sorted order, so a proof is just the
+//!   sibling hashes from the leaf up and doesn't need the leaf's position.
+//!   [`root`] and [`proof`] build them off-chain.
+//! * `play` takes the player's [`AllowlistProof`] after the ticket in its
+//!   instruction data. A player with an allocation also passes their
+//!   [`AllowlistEntry`] at `["allowlist", lottery, wallet]`, counting the
+//!   tickets they bought, which their first ticket creates.
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, keccak::hashv, msg, program_error::ProgramError, pubkey::Pubkey,
+};
+
+use crate::create_pda_account;
+
+/// Follows the `TicketDetails` in the instruction data of `play` for a lottery
+/// with an allowlist.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct AllowlistProof {
+    /// Tickets the player may buy, as in their leaf, or 0 for no limit.
+    pub allocation: u64,
+    /// Sibling hashes from the player's leaf up to the root.
+    pub proof: Vec<[u8; 32]>,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Default, Clone, PartialEq, Eq)]
+pub struct AllowlistEntry {
+    pub lottery: Pubkey,
+    pub wallet: Pubkey,
+    /// Tickets the wallet bought so far.
+    pub tickets: u64,
+}
+
+/// Account size of an [`AllowlistEntry`].
+pub const ENTRY_LEN: usize = 2 * 32 + 8;
+
+/// Derives a wallet's allowlist entry, `["allowlist", lottery, wallet]`.
+pub fn find_allowlist_entry_address(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    wallet: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"allowlist", lottery.as_ref(), wallet.as_ref()],
+        program_id,
+    )
+}
+
+/// Leaf of `wallet` with `allocation` tickets.
+pub fn leaf(wallet: &Pubkey, allocation: u64) -> [u8; 32] {
+    hashv(&[wallet.as_ref(), &allocation.to_le_bytes()]).to_bytes()
+}
+
+fn parent(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    if left <= right {
+        hashv(&[left, right]).to_bytes()
+    } else {
+        hashv(&[right, left]).to_bytes()
+    }
+}
+
+/// Checks that `proof` leads from `leaf` to `root`.
+pub fn verify(root: &[u8; 32], leaf: [u8; 32], proof: &[[u8; 32]]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| parent(&node, sibling))
+        == *root
+}
+
+/// Next level up of a tree. A node without a sibling moves up unchanged.
+fn next_level(level: &[[u8; 32]]) -> Vec<[u8; 32]> {
+    level
+        .chunks(2)
+        .map(|pair| match pair {
+            [left, right] => parent(left, right),
+            [single] => *single,
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+/// Root of the tree over `leaves`, or all zeroes, which means no allowlist,
+/// if there are none.
+pub fn root(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level.first().copied().unwrap_or_default()
+}
+
+/// Proof of `leaves[index]` for the tree over `leaves`.
+pub fn proof(leaves: &[[u8; 32]], mut index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        index /= 2;
+        level = next_level(&level);
+    }
+    proof
+}
+
+/// Reads `wallet`'s allowlist entry for `lottery`, creating it paid by
+/// `payer` if it doesn't exist yet.
+pub(crate) fn load_or_create<'info>(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    wallet: &Pubkey,
+    entry_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<AllowlistEntry, ProgramError> {
+    let (address, bump) = find_allowlist_entry_address(program_id, lottery, wallet);
+    if address != *entry_account.key {
+        msg!(
+            "{} isn't the allowlist entry of {}",
+            entry_account.key,
+            wallet
+        );
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if entry_account.owner == program_id {
+        return Ok(AllowlistEntry::try_from_slice(
+            &entry_account.data.borrow(),
+        )?);
+    }
+    create_pda_account(
+        payer,
+        entry_account,
+        system_program,
+        ENTRY_LEN,
+        program_id,
+        &[b"allowlist", lottery.as_ref(), wallet.as_ref(), &[bump]],
+    )?;
+    Ok(AllowlistEntry {
+        lottery: *lottery,
+        wallet: *wallet,
+        tickets: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_wallet_proves_its_own_allocation() {
+        let wallets: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        let leaves: Vec<[u8; 32]> = wallets
+            .iter()
+            .enumerate()
+            .map(|(allocation, wallet)| leaf(wallet, allocation as u64))
+            .collect();
+        let root = root(&leaves);
+
+        for (index, wallet) in wallets.iter().enumerate() {
+            let proof = proof(&leaves, index);
+            assert!(verify(&root, leaf(wallet, index as u64), &proof));
+            assert!(!verify(&root, leaf(wallet, index as u64 + 1), &proof));
+        }
+        assert!(!verify(&root, leaf(&Pubkey::new_unique(), 0), &[]));
+        assert_eq!(super::root(&leaves[..1]), leaves[0]);
+        assert_eq!(super::root(&[]), [0; 32]);
+        assert_eq!(
+            ENTRY_LEN,
+            AllowlistEntry::default().try_to_vec().unwrap().len()
+        );
+    }
+}
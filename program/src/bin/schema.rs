@@ -0,0 +1,34 @@
+//! Writes `schema.json`, `schema.js` and `schema.d.ts`, the program's Borsh
+//! schemas and their TypeScript declarations, and `idl.json`, its IDL, to the
+//! directory given as the only argument, the current one by default.
+use std::{env, fs, path::PathBuf, process};
+
+use fanitrade_utils::schema;
+
+fn main() {
+    let directory = PathBuf::from(env::args().nth(1).unwrap_or_else(|| ".".to_string()));
+    let definitions = schema::definitions();
+    let (js, ts, idl) = match (
+        schema::to_js(&definitions),
+        schema::to_ts(&definitions),
+        schema::to_idl(&definitions),
+    ) {
+        (Ok(js), Ok(ts), Ok(idl)) => (js, ts, idl),
+        (Err(error), _, _) | (_, Err(error), _) | (_, _, Err(error)) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    for (name, contents) in [
+        ("schema.json", schema::to_json(&definitions)),
+        ("schema.js", js),
+        ("schema.d.ts", ts),
+        ("idl.json", idl),
+    ] {
+        let path = directory.join(name);
+        if let Err(error) = fs::write(&path, contents) {
+            eprintln!("{}: {}", path.display(), error);
+            process::exit(1);
+        }
+    }
+}
@@ -3,6 +3,7 @@
 use crate::{Accounts, ToAccountInfos, ToAccountMetas};
 use solana_program::account_info::AccountInfo;
 use solana_program::instruction::AccountMeta;
+use solana_program::program_error::ProgramError;
 use solana_program::pubkey::Pubkey;
 use std::collections::BTreeMap;
 use std::fmt;
@@ -61,6 +62,20 @@ impl<'a, 'b, 'c, 'info, T: Accounts<'info>> Context<'a, 'b, 'c, 'info, T> {
             bumps,
         }
     }
+
+    /// Parses an optional tail of accounts from the front of
+    /// `remaining_accounts`, recording their bumps in `bumps`.
+    pub fn try_remaining<U: Accounts<'info>>(&mut self) -> Result<U, ProgramError> {
+        let mut remaining_accounts = self.remaining_accounts;
+        let accounts = U::try_accounts(
+            self.program_id,
+            &mut remaining_accounts,
+            &[],
+            &mut self.bumps,
+        )?;
+        self.remaining_accounts = remaining_accounts;
+        Ok(accounts)
+    }
 }
 
 /// Context specifying non-argument inputs for cross-program-invocations.
@@ -0,0 +1,208 @@
+//! Calling the program from other programs, with the `cpi` feature.
+//!
+//! Each function builds an instruction from the accounts in its
+//! [`CpiContext`] and invokes the program with the context's signer seeds,
+//! so a PDA can play or bet on behalf of its program:
+//!
+//! ```ignore
+//! let accounts = fanitrade_utils::cpi::accounts::Play {
+//!     lottery,
+//!     ticket,
+//!     player: vault_authority,
+//!     system_program,
+//!     pot,
+//!     token_program,
+//!     player_tokens: vault_tokens,
+//! };
+//! let seeds: &[&[u8]] = &[b"vault", &[vault_bump]];
+//! fanitrade_utils::cpi::play(
+//!     CpiContext::new_with_signer(fanitrade_program, accounts, &[seeds]),
+//!     ticket_details,
+//! )?;
+//! ```
+//!
+//! `cpi-caller` in this workspace is a complete program doing this.
+use borsh::BorshSerialize;
+use solana_program::{entrypoint::ProgramResult, instruction::Instruction, program::invoke_signed};
+
+use crate::allowlist::AllowlistProof;
+use crate::sponsor::SponsorRequest;
+use crate::{
+    ClaimRequest, CpiContext, OptionsBetDetails, TicketDetails, ToAccountInfos, ToAccountMetas,
+};
+
+/// The accounts each CPI function takes, in instruction order.
+pub mod accounts {
+    pub use crate::{ClaimPrize, ClaimReferralRewards, ClaimRefund, PlaceOption, Play, SponsorPot};
+}
+
+const PLAY: u8 = 1;
+const PLACE_OPTION: u8 = 7;
+const CLAIM_PRIZE: u8 = 10;
+const CLAIM_REFUND: u8 = 15;
+const CLAIM_REFERRAL_REWARDS: u8 = 20;
+const SPONSOR_POT: u8 = 21;
+
+fn instruction<'info, T: ToAccountMetas + ToAccountInfos<'info>>(
+    ctx: &CpiContext<'_, '_, '_, 'info, T>,
+    tag: u8,
+    data: &impl BorshSerialize,
+) -> Result<Instruction, std::io::Error> {
+    let mut buffer = vec![tag];
+    data.serialize(&mut buffer)?;
+    Ok(Instruction {
+        program_id: *ctx.program.key,
+        accounts: ctx.to_account_metas(None),
+        data: buffer,
+    })
+}
+
+fn invoke<'info, T: ToAccountMetas + ToAccountInfos<'info>>(
+    ctx: CpiContext<'_, '_, '_, 'info, T>,
+    tag: u8,
+    data: &impl BorshSerialize,
+) -> ProgramResult {
+    let instruction = instruction(&ctx, tag, data)?;
+    invoke_signed(&instruction, &ctx.to_account_infos(), ctx.signer_seeds)
+}
+
+/// Buys the lottery's next ticket for `ticket.player`. `ctx.accounts.ticket`
+/// is the ticket at the lottery's current `total_entries`, or the ticket
+/// ledger of a compact lottery.
+///
+/// The player pays the ticket account's rent through the system program, so
+/// a PDA playing has to be system-owned, holding no data, and funded with
+/// that rent on top of owning `player_tokens`.
+pub fn play<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::Play<'info>>,
+    ticket: TicketDetails,
+) -> ProgramResult {
+    invoke(ctx, PLAY, &ticket)
+}
+
+/// Buys a ticket like [`play`] in a lottery with an allowlist, proving that
+/// `ticket.player` is on it. A player with an allocation passes their
+/// allowlist entry in `ctx.remaining_accounts`.
+pub fn play_allowlisted<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::Play<'info>>,
+    ticket: TicketDetails,
+    proof: AllowlistProof,
+) -> ProgramResult {
+    invoke(ctx, PLAY, &(ticket, proof))
+}
+
+/// Pays a winning ticket's prize to the player's token account.
+pub fn claim_prize<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::ClaimPrize<'info>>,
+) -> ProgramResult {
+    invoke(ctx, CLAIM_PRIZE, &())
+}
+
+/// Pays the prize of ticket `ticket_index` of a compact lottery, whose ledger
+/// is `ctx.accounts.ticket`.
+pub fn claim_prize_compact<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::ClaimPrize<'info>>,
+    ticket_index: u64,
+) -> ProgramResult {
+    invoke(ctx, CLAIM_PRIZE, &ClaimRequest { ticket_index })
+}
+
+/// Refunds a ticket of a lottery in refund mode.
+pub fn claim_refund<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::ClaimRefund<'info>>,
+) -> ProgramResult {
+    invoke(ctx, CLAIM_REFUND, &())
+}
+
+/// Refunds ticket `ticket_index` of a compact lottery in refund mode, whose
+/// ledger is `ctx.accounts.ticket`.
+pub fn claim_refund_compact<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::ClaimRefund<'info>>,
+    ticket_index: u64,
+) -> ProgramResult {
+    invoke(ctx, CLAIM_REFUND, &ClaimRequest { ticket_index })
+}
+
+/// Pays the referral rewards `ctx.accounts.referrer` earned in a drawn
+/// lottery to its token account.
+pub fn claim_referral_rewards<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::ClaimReferralRewards<'info>>,
+) -> ProgramResult {
+    invoke(ctx, CLAIM_REFERRAL_REWARDS, &())
+}
+
+/// Adds tokens from `ctx.accounts.sponsor_tokens` to the lottery's pot,
+/// e.g. to fund a promotional jackpot from a partner's vault.
+pub fn sponsor_pot<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::SponsorPot<'info>>,
+    request: SponsorRequest,
+) -> ProgramResult {
+    invoke(ctx, SPONSOR_POT, &request)
+}
+
+/// Records `bet` in `ctx.accounts.bet`, a new program-owned account the
+/// caller created rent-exempt, moving `bet.options_bet_amount` lamports from
+/// `ctx.accounts.trader` into it. A PDA trader has to be system-owned and
+/// hold the stake.
+pub fn place_option<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, accounts::PlaceOption<'info>>,
+    bet: OptionsBetDetails,
+) -> ProgramResult {
+    invoke(ctx, PLACE_OPTION, &bet)
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::{account_info::AccountInfo, instruction::AccountMeta, pubkey::Pubkey};
+
+    use super::*;
+
+    #[test]
+    fn instruction_takes_metas_from_the_accounts() {
+        let program_id = Pubkey::new_unique();
+        let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        let mut lamports = [0; 4];
+        let mut data: Vec<Vec<u8>> = vec![Vec::new(); 4];
+        let mut infos: Vec<AccountInfo> = keys
+            .iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((key, lamports), data)| {
+                AccountInfo::new(key, false, false, lamports, data, &program_id, false, 0)
+            })
+            .collect();
+        let program = infos.pop().unwrap();
+        let accounts = accounts::PlaceOption {
+            market: infos[0].clone(),
+            bet: infos[1].clone(),
+            trader: infos[2].clone(),
+        };
+        let bet = OptionsBetDetails {
+            player: keys[2].to_string(),
+            options_market: keys[0].to_string(),
+            options_bet: 1,
+            options_strike: 100,
+            options_spread: 5,
+            options_bet_start: 10,
+            options_bet_end: 20,
+            options_duration: 10,
+            options_bet_amount: 1_000,
+            options_bet_result: "undecided".to_string(),
+        };
+        let ctx = CpiContext::new(program, accounts);
+        let instruction = instruction(&ctx, PLACE_OPTION, &bet).unwrap();
+
+        assert_eq!(instruction.program_id, keys[3]);
+        assert_eq!(
+            instruction.accounts,
+            vec![
+                AccountMeta::new(keys[0], false),
+                AccountMeta::new(keys[1], false),
+                AccountMeta::new_readonly(keys[2], true),
+            ]
+        );
+        assert_eq!(instruction.data[0], PLACE_OPTION);
+        assert_eq!(instruction.data[1..], bet.try_to_vec().unwrap()[..]);
+        assert_eq!(ctx.to_account_infos().len(), 4);
+    }
+}
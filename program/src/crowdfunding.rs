@@ -0,0 +1,306 @@
+//! Crowdfunding campaigns, as used by the React app.
+//!
+//! Campaign instructions share the program with the lottery, so they live
+//! under their own first byte, [`NAMESPACE`]. The second byte picks the
+//! instruction:
+//!
+//! * [`CREATE_CAMPAIGN`] initializes a program account with the
+//!   [`CampaignDetails`] in the instruction data.
+//! * [`DONATE`] moves lamports from the donor to a campaign until its
+//!   deadline, and records them in the donor's [`DonationReceipt`] at
+//!   `["receipt", campaign, donor]`.
+//! * [`WITHDRAW`] pays `amount` lamports out of a campaign to its admin once
+//!   the deadline has passed with the goal reached, leaving the campaign rent
+//!   exempt.
+//! * [`REFUND`] pays a receipt's donations back to its donor, and closes it,
+//!   once the deadline has passed without the goal reached. Anyone can send
+//!   it: the lamports only ever go to the donor.
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo, clock::Clock, entrypoint::ProgramResult, msg, program::invoke,
+    program_error::ProgramError, pubkey::Pubkey, rent::Rent, system_instruction, system_program,
+    sysvar::Sysvar,
+};
+
+use crate::accounts::accounts;
+use crate::error::LotteryError;
+use crate::{create_pda_account, run, Context, WithdrawRequest};
+
+/// First instruction byte of every campaign instruction.
+pub const NAMESPACE: u8 = 128;
+
+pub const CREATE_CAMPAIGN: u8 = 0;
+pub const WITHDRAW: u8 = 1;
+pub const DONATE: u8 = 2;
+pub const REFUND: u8 = 3;
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct CampaignDetails {
+    pub admin: Pubkey,
+    pub name: String,
+    pub description: String,
+    pub image_link: String,
+    /// Lamports donated over the campaign's life, withdrawals and refunds
+    /// included.
+    pub amount_donated: u64,
+    /// Lamports the campaign has to raise for the admin to withdraw.
+    pub goal: u64,
+    /// Unix timestamp donations close at.
+    pub deadline: u64,
+}
+
+impl CampaignDetails {
+    fn has_ended(&self, clock: &Clock) -> bool {
+        clock.unix_timestamp >= 0 && clock.unix_timestamp as u64 >= self.deadline
+    }
+
+    fn is_funded(&self) -> bool {
+        self.amount_donated >= self.goal
+    }
+}
+
+/// What one donor gave to one campaign.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct DonationReceipt {
+    pub campaign: Pubkey,
+    pub donor: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct DonateRequest {
+    pub amount: u64,
+}
+
+pub fn find_receipt_address(
+    program_id: &Pubkey,
+    campaign: &Pubkey,
+    donor: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"receipt", campaign.as_ref(), donor.as_ref()], program_id)
+}
+
+/// Dispatches a campaign instruction; `instruction_data` starts after
+/// [`NAMESPACE`].
+pub fn process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    if instruction_data.is_empty() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    if instruction_data[0] == CREATE_CAMPAIGN {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..],
+            create_campaign,
+        );
+    } else if instruction_data[0] == WITHDRAW {
+        return run(program_id, accounts, &instruction_data[1..], withdraw);
+    } else if instruction_data[0] == DONATE {
+        return run(program_id, accounts, &instruction_data[1..], donate);
+    } else if instruction_data[0] == REFUND {
+        return run(program_id, accounts, &instruction_data[1..], refund);
+    }
+    msg!("Unknown campaign instruction");
+    Err(ProgramError::InvalidInstructionData)
+}
+
+accounts! {
+    pub struct CreateCampaign<'info> {
+        pub campaign: [mut, owner, zero, rent_exempt],
+        pub creator: [signer],
+    }
+}
+
+fn create_campaign(ctx: Context<CreateCampaign>, instruction_data: &[u8]) -> ProgramResult {
+    let CreateCampaign {
+        campaign: writing_account,
+        creator: creator_account,
+    } = &*ctx.accounts;
+
+    let mut input_data = CampaignDetails::try_from_slice(instruction_data)?;
+    if input_data.admin != *creator_account.key {
+        msg!("Invaild instruction data");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.goal == 0 {
+        msg!("goal should be positive");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.has_ended(&Clock::get()?) {
+        msg!("deadline should be in the future");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    input_data.amount_donated = 0;
+
+    input_data.serialize(&mut &mut writing_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+accounts! {
+    pub struct WithdrawDonations<'info> {
+        pub campaign: [mut, owner, has_one(CampaignDetails::admin)],
+        pub admin: [mut, signer],
+    }
+}
+
+fn withdraw(ctx: Context<WithdrawDonations>, instruction_data: &[u8]) -> ProgramResult {
+    let WithdrawDonations {
+        campaign: writing_account,
+        admin: admin_account,
+    } = &*ctx.accounts;
+
+    let campaign_data = CampaignDetails::try_from_slice(*writing_account.data.borrow())?;
+    if !campaign_data.has_ended(&Clock::get()?) {
+        msg!("Donations stay in the campaign until its deadline");
+        return Err(LotteryError::CampaignNotEnded.into());
+    }
+    if !campaign_data.is_funded() {
+        msg!("Campaign didn't reach its goal");
+        return Err(LotteryError::GoalNotReached.into());
+    }
+    let input_data = WithdrawRequest::try_from_slice(instruction_data)?;
+
+    let rent_exemption = Rent::get()?.minimum_balance(writing_account.data_len());
+    let available = writing_account.lamports().saturating_sub(rent_exemption);
+    if input_data.amount > available {
+        msg!("Insufficent balance");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    **writing_account.try_borrow_mut_lamports()? -= input_data.amount;
+    **admin_account.try_borrow_mut_lamports()? = admin_account
+        .lamports()
+        .checked_add(input_data.amount)
+        .ok_or(ProgramError::InvalidArgument)?;
+    Ok(())
+}
+
+accounts! {
+    pub struct Donate<'info> {
+        pub campaign: [mut, owner],
+        /// The donor's receipt, created by their first donation.
+        pub receipt: [mut, seeds(b"receipt", campaign.key, donor.key)],
+        pub donor: [mut, signer],
+        pub system_program: [address(system_program::id())],
+    }
+}
+
+fn donate(ctx: Context<Donate>, instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let Donate {
+        campaign: writing_account,
+        receipt: receipt_account,
+        donor: donator_account,
+        system_program,
+    } = &*ctx.accounts;
+
+    let mut campaign_data = CampaignDetails::try_from_slice(*writing_account.data.borrow())?;
+    let input_data = DonateRequest::try_from_slice(instruction_data)?;
+    if input_data.amount == 0 {
+        msg!("amount should be positive");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if campaign_data.has_ended(&Clock::get()?) {
+        msg!("Campaign has ended");
+        return Err(LotteryError::CampaignEnded.into());
+    }
+
+    let mut receipt_data = if receipt_account.owner == program_id {
+        DonationReceipt::try_from_slice(*receipt_account.data.borrow())?
+    } else {
+        let receipt_data = DonationReceipt {
+            campaign: *writing_account.key,
+            donor: *donator_account.key,
+            amount: 0,
+        };
+        create_pda_account(
+            donator_account,
+            receipt_account,
+            system_program,
+            receipt_data.try_to_vec()?.len(),
+            program_id,
+            &[
+                b"receipt",
+                writing_account.key.as_ref(),
+                donator_account.key.as_ref(),
+                &[ctx.bumps["receipt"]],
+            ],
+        )?;
+        receipt_data
+    };
+
+    invoke(
+        &system_instruction::transfer(donator_account.key, writing_account.key, input_data.amount),
+        &[
+            donator_account.clone(),
+            writing_account.clone(),
+            system_program.clone(),
+        ],
+    )?;
+
+    receipt_data.amount = receipt_data
+        .amount
+        .checked_add(input_data.amount)
+        .ok_or(ProgramError::InvalidArgument)?;
+    campaign_data.amount_donated = campaign_data
+        .amount_donated
+        .checked_add(input_data.amount)
+        .ok_or(ProgramError::InvalidArgument)?;
+
+    campaign_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    receipt_data.serialize(&mut &mut receipt_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+accounts! {
+    pub struct Refund<'info> {
+        pub campaign: [mut, owner],
+        /// The donor's receipt, closed by the refund.
+        pub receipt: [mut, owner, seeds(b"receipt", campaign.key, donor.key)],
+        pub donor: [mut],
+    }
+}
+
+fn refund(ctx: Context<Refund>, instruction_data: &[u8]) -> ProgramResult {
+    if !instruction_data.is_empty() {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let Refund {
+        campaign: writing_account,
+        receipt: receipt_account,
+        donor: donator_account,
+    } = &*ctx.accounts;
+    let campaign_data = CampaignDetails::try_from_slice(*writing_account.data.borrow())?;
+    if !campaign_data.has_ended(&Clock::get()?) {
+        msg!("Refunds open at the campaign's deadline");
+        return Err(LotteryError::CampaignNotEnded.into());
+    }
+    if campaign_data.is_funded() {
+        msg!("Campaign reached its goal");
+        return Err(LotteryError::GoalReached.into());
+    }
+    let receipt_data = DonationReceipt::try_from_slice(*receipt_account.data.borrow())?;
+
+    // The admin can't withdraw from an unfunded campaign, so every receipt's
+    // donations are still in it.
+    **writing_account.try_borrow_mut_lamports()? = writing_account
+        .lamports()
+        .checked_sub(receipt_data.amount)
+        .ok_or(ProgramError::InsufficientFunds)?;
+    // Closing the receipt returns its rent too.
+    let refund = receipt_data
+        .amount
+        .checked_add(receipt_account.lamports())
+        .ok_or(ProgramError::InvalidArgument)?;
+    **receipt_account.try_borrow_mut_lamports()? = 0;
+    receipt_account.try_borrow_mut_data()?.fill(0);
+    **donator_account.try_borrow_mut_lamports()? = donator_account
+        .lamports()
+        .checked_add(refund)
+        .ok_or(ProgramError::InvalidArgument)?;
+    Ok(())
+}
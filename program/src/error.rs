@@ -1,9 +1,10 @@
 //! Error types
+use num_derive::FromPrimitive;
 use solana_program::program_error::ProgramError;
 use thiserror::Error;
 
 /// Errors that may be returned by the Lottery program.
-#[derive(Error, Debug, Copy, Clone)]
+#[derive(Error, Debug, Copy, Clone, FromPrimitive)]
 pub enum LotteryError {
     /// Invalid instruction
     #[error("Invalid Instruction")]
@@ -23,6 +24,126 @@ pub enum LotteryError {
     /// Prize pool is empty
     #[error("Priez pool is empty")]
     EmptyPrizePool,
+    /// Fee and prize tiers add up to more than the whole pot
+    #[error("Invalid prize split")]
+    InvalidPrizeSplit,
+    /// Account isn't the expected round of the series
+    #[error("Invalid round account")]
+    InvalidRoundAccount,
+    /// Previous round hasn't been drawn yet
+    #[error("Round not drawn")]
+    RoundNotDrawn,
+    /// Lottery has already been drawn
+    #[error("Lottery already drawn")]
+    AlreadyDrawn,
+    /// Pick-N rules are invalid or the lottery isn't a pick-N lottery
+    #[error("Invalid game mode")]
+    InvalidGameMode,
+    /// Numbers aren't a valid pick for the lottery
+    #[error("Invalid ticket numbers")]
+    InvalidTicketNumbers,
+    /// Ticket isn't among the draw's winners
+    #[error("Ticket didn't win")]
+    NotAWinner,
+    /// Ticket's prize was already paid out
+    #[error("Prize already claimed")]
+    PrizeAlreadyClaimed,
+    /// Prizes can no longer be claimed
+    #[error("Claim period ended")]
+    ClaimPeriodEnded,
+    /// Prizes can't be expired before the claim deadline
+    #[error("Claim period not ended")]
+    ClaimPeriodNotEnded,
+    /// Ticket sales are over
+    #[error("Lottery closed")]
+    LotteryClosed,
+    /// Ticket sales are still running
+    #[error("Lottery not closed")]
+    LotteryNotClosed,
+    /// Randomness was already committed
+    #[error("Randomness already committed")]
+    AlreadyCommitted,
+    /// Randomness hasn't been committed
+    #[error("Randomness not committed")]
+    NotCommitted,
+    /// Randomness was already revealed, or can no longer be
+    #[error("Randomness already revealed")]
+    AlreadyRevealed,
+    /// Randomness hasn't been revealed
+    #[error("Randomness not revealed")]
+    NotRevealed,
+    /// Revealed secret doesn't match the commitment
+    #[error("Invalid reveal")]
+    InvalidReveal,
+    /// Too late to reveal randomness
+    #[error("Reveal window passed")]
+    RevealWindowPassed,
+    /// Too early to slash the bond
+    #[error("Reveal window not passed")]
+    RevealWindowNotPassed,
+    /// Lottery isn't in refund mode
+    #[error("Lottery not refunding")]
+    NotRefunding,
+    /// Instruction doesn't match the lottery's randomness source
+    #[error("Wrong randomness source")]
+    WrongRandomnessSource,
+    /// Market doesn't take bets
+    #[error("Market paused")]
+    MarketPaused,
+    /// Campaign's deadline has passed
+    #[error("Campaign ended")]
+    CampaignEnded,
+    /// Campaign's deadline hasn't passed yet
+    #[error("Campaign not ended")]
+    CampaignNotEnded,
+    /// Campaign didn't raise its goal
+    #[error("Campaign goal not reached")]
+    GoalNotReached,
+    /// Campaign raised its goal, so donations aren't refunded
+    #[error("Campaign goal reached")]
+    GoalReached,
+    /// Compact lottery's ticket ledger has no room for another ticket
+    #[error("Ticket ledger full")]
+    LedgerFull,
+    /// Ticket's token was burned
+    #[error("Ticket void")]
+    TicketVoid,
+    /// Ticket's token still exists
+    #[error("Ticket token not burned")]
+    TicketNotBurned,
+    /// Player named themselves as their referrer
+    #[error("Self referral")]
+    SelfReferral,
+    /// Player was already referred by another wallet
+    #[error("Referrer changed")]
+    ReferrerChanged,
+    /// Player already referred others, so can't be referred
+    #[error("Referral cycle")]
+    ReferralCycle,
+    /// Lottery is in refund mode
+    #[error("Lottery refunding")]
+    Refunding,
+    /// Lottery sold its `max_entries`
+    #[error("Lottery sold out")]
+    SoldOut,
+    /// Lottery didn't sell its `min_entries`, so can't be drawn
+    #[error("Min entries not reached")]
+    MinEntriesNotReached,
+    /// Lottery sold its `min_entries`, so can't be cancelled
+    #[error("Min entries reached")]
+    MinEntriesReached,
+    /// Player isn't on the lottery's allowlist, or the proof is wrong
+    #[error("Not allowlisted")]
+    NotAllowlisted,
+    /// Player bought all the tickets the allowlist allocates them
+    #[error("Allocation used")]
+    AllocationUsed,
+    /// Draw's tickets haven't all been tallied
+    #[error("Draw not tallied")]
+    NotTallied,
+    /// Only the admin can close sales this soon after `lottery_end`
+    #[error("Close grace not passed")]
+    CloseGraceNotPassed,
 }
 
 impl From<LotteryError> for ProgramError {
@@ -0,0 +1,196 @@
+//! Typed events for indexers.
+//!
+//! Handlers report what happened by emitting one of these structs through
+//! `sol_log_data`, as two fields: the event's [`Event::discriminator`], then
+//! its Borsh serialization. The runtime logs them as
+//! `Program data: <base64 discriminator> <base64 event>`, which the client's
+//! `events` module decodes back into these types.
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{entrypoint::ProgramResult, hash::hashv, log::sol_log_data, pubkey::Pubkey};
+
+pub trait Event: BorshSerialize + BorshDeserialize {
+    const NAME: &'static str;
+
+    /// First 8 bytes of `sha256("event:<NAME>")`.
+    fn discriminator() -> [u8; 8] {
+        let mut discriminator = [0; 8];
+        discriminator.copy_from_slice(&hashv(&[b"event:", Self::NAME.as_bytes()]).to_bytes()[..8]);
+        discriminator
+    }
+}
+
+pub fn emit<E: Event>(event: &E) -> ProgramResult {
+    sol_log_data(&[&E::discriminator(), &event.try_to_vec()?]);
+    Ok(())
+}
+
+/// A one-off lottery or a series round was created.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct LotteryCreated {
+    pub lottery: Pubkey,
+    pub admin: Pubkey,
+    pub token_mint: Pubkey,
+    /// `Pubkey::default()` for one-off lotteries.
+    pub series: Pubkey,
+    pub round: u64,
+    pub ticket_price: u64,
+    pub lottery_start: i64,
+    pub lottery_end: i64,
+    pub pick_count: u8,
+    pub max_number: u8,
+    /// Tokens carried over from the previous round.
+    pub amount_in_pot: u64,
+}
+
+impl Event for LotteryCreated {
+    const NAME: &'static str = "LotteryCreated";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct TicketPurchased {
+    pub lottery: Pubkey,
+    pub ticket: Pubkey,
+    pub player: Pubkey,
+    pub ticket_index: u64,
+    /// The ticket's `pick_count` numbers.
+    pub numbers: Vec<u8>,
+    pub price: u64,
+}
+
+impl Event for TicketPurchased {
+    const NAME: &'static str = "TicketPurchased";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct LotteryDrawn {
+    pub lottery: Pubkey,
+    pub winning_numbers: Vec<u8>,
+    pub total_entries: u64,
+    /// Winning tickets per prize tier, best tier first.
+    pub winners_per_tier: Vec<u64>,
+    /// Prize paid to each winning ticket per tier.
+    pub prize_per_ticket: Vec<u64>,
+    /// Booked as fees, including a one-off lottery's unwon tiers.
+    pub fee: u64,
+    /// Left in the pot for the next round of the series.
+    pub rollover: u64,
+    pub claim_deadline: i64,
+}
+
+impl Event for LotteryDrawn {
+    const NAME: &'static str = "LotteryDrawn";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct PrizeClaimed {
+    pub lottery: Pubkey,
+    pub ticket: Pubkey,
+    pub player: Pubkey,
+    pub amount: u64,
+}
+
+impl Event for PrizeClaimed {
+    const NAME: &'static str = "PrizeClaimed";
+}
+
+/// A sponsor added tokens to a lottery's pot.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct PotSponsored {
+    pub lottery: Pubkey,
+    pub sponsor: Pubkey,
+    /// Name shown for the sponsor.
+    pub name: String,
+    pub amount: u64,
+    /// The pot after the contribution.
+    pub amount_in_pot: u64,
+}
+
+impl Event for PotSponsored {
+    const NAME: &'static str = "PotSponsored";
+}
+
+/// A sponsor put up a prize in another mint for one prize tier.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct PrizeSponsored {
+    pub lottery: Pubkey,
+    pub sponsor: Pubkey,
+    pub prize_index: u64,
+    pub mint: Pubkey,
+    pub tier: u8,
+    pub amount: u64,
+}
+
+impl Event for PrizeSponsored {
+    const NAME: &'static str = "PrizeSponsored";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct MarketCreated {
+    pub market: Pubkey,
+    pub admin: Pubkey,
+    pub market_pair: String,
+    pub last_price: u64,
+    pub upper_floor_limit: u64,
+    pub lower_floor_limit: u64,
+}
+
+impl Event for MarketCreated {
+    const NAME: &'static str = "MarketCreated";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct OptionPlaced {
+    pub market: Pubkey,
+    pub bet: Pubkey,
+    pub trader: Pubkey,
+    pub options_bet: u64,
+    pub options_strike: u64,
+    pub options_bet_end: u64,
+    /// Lamports staked, the balance of the bet account.
+    pub amount: u64,
+}
+
+impl Event for OptionPlaced {
+    const NAME: &'static str = "OptionPlaced";
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct OptionSettled {
+    pub bet: Pubkey,
+    pub market: Pubkey,
+    pub player: Pubkey,
+    pub settler: Pubkey,
+    pub final_price: String,
+    pub result: String,
+    /// Lamports paid out of the bet account to the settler.
+    pub payout: u64,
+}
+
+impl Event for OptionSettled {
+    const NAME: &'static str = "OptionSettled";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discriminators_are_distinct() {
+        let discriminators = [
+            LotteryCreated::discriminator(),
+            TicketPurchased::discriminator(),
+            LotteryDrawn::discriminator(),
+            PrizeClaimed::discriminator(),
+            MarketCreated::discriminator(),
+            OptionPlaced::discriminator(),
+            OptionSettled::discriminator(),
+        ];
+        for (index, discriminator) in discriminators.iter().enumerate() {
+            assert!(!discriminators[..index].contains(discriminator));
+        }
+        assert_eq!(
+            LotteryCreated::discriminator(),
+            hashv(&[b"event:LotteryCreated"]).to_bytes()[..8]
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Compact ticket storage: every ticket of a lottery as a fixed-size record in
+//! one ledger account, instead of a `TicketDetails` account per ticket.
+//!
+//! A lottery created with `ticket_ledger` set keeps its tickets here. The
+//! ledger is a header followed by an append-only array of records, so the
+//! draw and claims index a ticket in O(1) without deserializing the rest:
+//!
+//! ```text
+//! header:  lottery (32) | capacity (8) | len (8)
+//! record:  player (32) | numbers (16) | slot (8) | claimed (8)
+//! ```
+//!
+//! Programs can only allocate 10 KiB per instruction, so the admin creates the
+//! ledger with the system program before `CreateLottery`, owned by this
+//! program and sized with [`space`] for the tickets it expects.
+use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use solana_program::{msg, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::error::LotteryError;
+
+pub const HEADER_LEN: usize = 48;
+pub const RECORD_LEN: usize = 64;
+/// Most numbers a ledger record holds. Compact lotteries pick at most this many.
+pub const MAX_LEDGER_PICKS: usize = 16;
+
+/// Account size of a ledger holding up to `capacity` tickets.
+pub fn space(capacity: u64) -> usize {
+    HEADER_LEN + RECORD_LEN * capacity as usize
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LedgerHeader {
+    /// Lottery the tickets belong to, or the default pubkey until it's created.
+    pub lottery: Pubkey,
+    /// Records the account has room for.
+    pub capacity: u64,
+    /// Tickets sold so far, which is also the index of the next ticket.
+    pub len: u64,
+}
+
+impl LedgerHeader {
+    fn unpack(src: &[u8; HEADER_LEN]) -> Self {
+        let (lottery, capacity, len) = array_refs![src, 32, 8, 8];
+        Self {
+            lottery: Pubkey::new_from_array(*lottery),
+            capacity: u64::from_le_bytes(*capacity),
+            len: u64::from_le_bytes(*len),
+        }
+    }
+
+    fn pack(&self, dst: &mut [u8; HEADER_LEN]) {
+        let (lottery, capacity, len) = mut_array_refs![dst, 32, 8, 8];
+        lottery.copy_from_slice(self.lottery.as_ref());
+        *capacity = self.capacity.to_le_bytes();
+        *len = self.len.to_le_bytes();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TicketRecord {
+    pub player: Pubkey,
+    /// Picked numbers, zero-padded past the lottery's `pick_count`.
+    pub numbers: [u8; MAX_LEDGER_PICKS],
+    /// Slot the ticket was bought in.
+    pub slot: u64,
+    pub claimed: u64,
+}
+
+impl TicketRecord {
+    fn unpack(src: &[u8; RECORD_LEN]) -> Self {
+        let (player, numbers, slot, claimed) = array_refs![src, 32, MAX_LEDGER_PICKS, 8, 8];
+        Self {
+            player: Pubkey::new_from_array(*player),
+            numbers: *numbers,
+            slot: u64::from_le_bytes(*slot),
+            claimed: u64::from_le_bytes(*claimed),
+        }
+    }
+
+    fn pack(&self, dst: &mut [u8; RECORD_LEN]) {
+        let (player, numbers, slot, claimed) = mut_array_refs![dst, 32, MAX_LEDGER_PICKS, 8, 8];
+        player.copy_from_slice(self.player.as_ref());
+        *numbers = self.numbers;
+        *slot = self.slot.to_le_bytes();
+        *claimed = self.claimed.to_le_bytes();
+    }
+}
+
+/// Reads the ledger's header.
+pub fn header(data: &[u8]) -> Result<LedgerHeader, ProgramError> {
+    if data.len() < HEADER_LEN {
+        msg!("Ticket ledger is smaller than its header");
+        return Err(ProgramError::AccountDataTooSmall);
+    }
+    Ok(LedgerHeader::unpack(array_ref![data, 0, HEADER_LEN]))
+}
+
+/// Claims a new ledger for `lottery` and returns its capacity. Fails if the
+/// ledger already belongs to a lottery.
+pub fn initialize(data: &mut [u8], lottery: &Pubkey) -> Result<u64, ProgramError> {
+    if header(data)?.lottery != Pubkey::default() {
+        msg!("Ticket ledger is already in use");
+        return Err(LotteryError::Initialized.into());
+    }
+    let header = LedgerHeader {
+        lottery: *lottery,
+        capacity: ((data.len() - HEADER_LEN) / RECORD_LEN) as u64,
+        len: 0,
+    };
+    header.pack(array_mut_ref![data, 0, HEADER_LEN]);
+    Ok(header.capacity)
+}
+
+/// Reads the ticket at `index`.
+pub fn record(data: &[u8], index: u64) -> Result<TicketRecord, ProgramError> {
+    if index >= header(data)?.len {
+        msg!("Ticket {} isn't in the ledger", index);
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(TicketRecord::unpack(array_ref![
+        data,
+        record_offset(index),
+        RECORD_LEN
+    ]))
+}
+
+/// Overwrites the ticket at `index`, which has to be in the ledger already.
+pub fn write_record(
+    data: &mut [u8],
+    index: u64,
+    record: &TicketRecord,
+) -> Result<(), ProgramError> {
+    if index >= header(data)?.len {
+        msg!("Ticket {} isn't in the ledger", index);
+        return Err(ProgramError::InvalidArgument);
+    }
+    record.pack(array_mut_ref![data, record_offset(index), RECORD_LEN]);
+    Ok(())
+}
+
+/// Appends a ticket and returns its index.
+pub fn append(data: &mut [u8], record: &TicketRecord) -> Result<u64, ProgramError> {
+    let mut header = header(data)?;
+    if header.len >= header.capacity {
+        msg!("Ticket ledger is full");
+        return Err(LotteryError::LedgerFull.into());
+    }
+    let index = header.len;
+    header.len += 1;
+    header.pack(array_mut_ref![data, 0, HEADER_LEN]);
+    record.pack(array_mut_ref![data, record_offset(index), RECORD_LEN]);
+    Ok(index)
+}
+
+fn record_offset(index: u64) -> usize {
+    HEADER_LEN + RECORD_LEN * index as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket(seed: u8) -> TicketRecord {
+        TicketRecord {
+            player: Pubkey::new_from_array([seed; 32]),
+            numbers: [seed; MAX_LEDGER_PICKS],
+            slot: u64::from(seed) * 1_000,
+            claimed: 0,
+        }
+    }
+
+    #[test]
+    fn appends_and_indexes_tickets() {
+        let lottery = Pubkey::new_unique();
+        let mut data = vec![0; space(3) + RECORD_LEN - 1];
+        assert_eq!(initialize(&mut data, &lottery), Ok(3));
+        assert_eq!(
+            initialize(&mut data, &lottery),
+            Err(LotteryError::Initialized.into())
+        );
+
+        for seed in 1..=3 {
+            assert_eq!(append(&mut data, &ticket(seed)), Ok(u64::from(seed) - 1));
+        }
+        assert_eq!(
+            append(&mut data, &ticket(4)),
+            Err(LotteryError::LedgerFull.into())
+        );
+        assert_eq!(
+            header(&data),
+            Ok(LedgerHeader {
+                lottery,
+                capacity: 3,
+                len: 3,
+            })
+        );
+        assert_eq!(record(&data, 1), Ok(ticket(2)));
+        assert_eq!(record(&data, 3), Err(ProgramError::InvalidArgument));
+
+        let mut claimed = ticket(3);
+        claimed.claimed = 1;
+        write_record(&mut data, 2, &claimed).unwrap();
+        assert_eq!(record(&data, 2), Ok(claimed));
+        assert_eq!(record(&data, 0), Ok(ticket(1)));
+    }
+
+    #[test]
+    fn rejects_accounts_smaller_than_the_header() {
+        assert_eq!(
+            header(&[0; HEADER_LEN - 1]),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+        let mut data = vec![0; HEADER_LEN];
+        assert_eq!(initialize(&mut data, &Pubkey::new_unique()), Ok(0));
+    }
+}
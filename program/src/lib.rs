@@ -1,282 +1,3162 @@
-use borsh::{BorshDeserialize, BorshSerialize};
+pub mod accounts;
+pub mod allowlist;
+pub mod context;
+#[cfg(feature = "cpi")]
+pub mod cpi;
+pub mod crowdfunding;
+pub mod error;
+pub mod events;
+pub mod ledger;
+pub mod nft;
+pub mod oracle;
+pub mod pick;
+pub mod randomness;
+pub mod referral;
+pub mod rng;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod sponsor;
+
+use std::collections::BTreeMap;
+
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use solana_program::program::invoke_signed;
 use solana_program::{
-    account_info::{next_account_info, AccountInfo},
-    entrypoint,
+    account_info::AccountInfo,
+    clock::Clock,
     entrypoint::ProgramResult,
+    hash::hashv,
     msg,
     program::invoke,
     program_error::ProgramError,
+    program_pack::Pack,
     pubkey::Pubkey,
     rent::Rent,
-    sysvar::Sysvar,
+    system_instruction, system_program,
+    sysvar::{self, slot_hashes, Sysvar},
 };
-use spl_associated_token_account::get_associated_token_address;
-use spl_token::instruction::transfer_checked;
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+
+pub use crate::accounts::{Accounts, ToAccountInfos, ToAccountMetas};
+pub use crate::context::{Context, CpiContext};
+
+use crate::accounts::accounts;
+use crate::error::LotteryError;
+use crate::events::{
+    emit, LotteryCreated, LotteryDrawn, MarketCreated, OptionPlaced, OptionSettled, PotSponsored,
+    PrizeClaimed, PrizeSponsored, TicketPurchased,
+};
+use crate::ledger::TicketRecord;
+use crate::pick::PickGame;
+use crate::rng::Rng;
 
 use spl_token::instruction::transfer;
 
-fn process_instruction(
+pub fn process_instruction(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    if instruction_data.len() == 0 {
+    if instruction_data.is_empty() {
         return Err(ProgramError::InvalidInstructionData);
     }
 
     if instruction_data[0] == 0 {
-        return create_lottery(
+        return run(
             program_id,
             accounts,
             &instruction_data[1..instruction_data.len()],
+            create_lottery,
         );
     } else if instruction_data[0] == 1 {
-        return play(
+        return run(
             program_id,
             accounts,
             &instruction_data[1..instruction_data.len()],
+            play,
         );
     } else if instruction_data[0] == 2 {
-        return withdraw(
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            withdraw,
+        );
+    } else if instruction_data[0] == 3 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            create_series,
+        );
+    } else if instruction_data[0] == 4 {
+        return run(
             program_id,
             accounts,
             &instruction_data[1..instruction_data.len()],
+            start_next_round,
         );
     } else if instruction_data[0] == 5 {
-        return initialize_trax(
+        return run(
             program_id,
             accounts,
             &instruction_data[1..instruction_data.len()],
+            initialize_trax,
         );
     } else if instruction_data[0] == 6 {
-        return create_market(
+        return run(
             program_id,
             accounts,
             &instruction_data[1..instruction_data.len()],
+            create_market,
         );
     } else if instruction_data[0] == 7 {
-        return place_option(
+        return run(
             program_id,
             accounts,
             &instruction_data[1..instruction_data.len()],
+            place_option,
         );
     } else if instruction_data[0] == 8 {
-        return settle_option(
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            settle_option,
+        );
+    } else if instruction_data[0] == 9 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            draw_lottery,
+        );
+    } else if instruction_data[0] == 10 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            claim_prize,
+        );
+    } else if instruction_data[0] == 11 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            expire_prizes,
+        );
+    } else if instruction_data[0] == 12 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            commit_randomness,
+        );
+    } else if instruction_data[0] == 13 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            reveal_randomness,
+        );
+    } else if instruction_data[0] == 14 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            slash_bond,
+        );
+    } else if instruction_data[0] == 15 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            claim_refund,
+        );
+    } else if instruction_data[0] == 16 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            request_randomness,
+        );
+    } else if instruction_data[0] == 17 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            fulfill_randomness,
+        );
+    } else if instruction_data[0] == 18 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            set_market_status,
+        );
+    } else if instruction_data[0] == 19 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            void_ticket,
+        );
+    } else if instruction_data[0] == 20 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            claim_referral_rewards,
+        );
+    } else if instruction_data[0] == 21 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            sponsor_pot,
+        );
+    } else if instruction_data[0] == 22 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            sponsor_prize,
+        );
+    } else if instruction_data[0] == 23 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            claim_sponsored_prize,
+        );
+    } else if instruction_data[0] == 24 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            reclaim_sponsored_prize,
+        );
+    } else if instruction_data[0] == 25 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            cancel_lottery,
+        );
+    } else if instruction_data[0] == 26 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            tally_tickets,
+        );
+    } else if instruction_data[0] == 27 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            close_sales,
+        );
+    } else if instruction_data[0] == 28 {
+        return run(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+            reclaim_sponsorship,
+        );
+    } else if instruction_data[0] == crowdfunding::NAMESPACE {
+        return crowdfunding::process_instruction(
+            program_id,
+            accounts,
+            &instruction_data[1..instruction_data.len()],
+        );
+    }
+    msg!("Didn't found the entrypoint required");
+    Err(ProgramError::InvalidInstructionData)
+}
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
+
+/// Parses and validates the accounts `handler` takes, then calls it.
+fn run<'info, T: Accounts<'info>>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo<'info>],
+    instruction_data: &[u8],
+    handler: fn(Context<'_, '_, '_, 'info, T>, &[u8]) -> ProgramResult,
+) -> ProgramResult {
+    let mut remaining_accounts = accounts;
+    let mut bumps = BTreeMap::new();
+    let mut parsed = T::try_accounts(
+        program_id,
+        &mut remaining_accounts,
+        instruction_data,
+        &mut bumps,
+    )?;
+    handler(
+        Context::new(program_id, &mut parsed, remaining_accounts, bumps),
+        instruction_data,
+    )
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct LotteryDetails {
+    pub admin: Pubkey,
+    pub name: String,
+    pub is_initialized: u64,
+    pub is_ended: u64,
+    pub lottery_start: String,
+    pub lottery_end: String,
+    pub ticket_price: u64,
+    pub amount_in_pot: u64,
+    pub total_entries: u64,
+    pub token_mint: Pubkey,
+    /// Series this lottery is a round of, or the default pubkey for one-off lotteries.
+    pub series: Pubkey,
+    pub round: u64,
+    /// Admin fee taken from the pot, in basis points.
+    pub fee_bps: u16,
+    /// Share of the pot paid to each prize tier, in basis points.
+    pub prize_split: Vec<u16>,
+    /// Numbers picked per ticket, or 0 if tickets aren't number-matched.
+    pub pick_count: u8,
+    pub max_number: u8,
+    /// Set by the draw. Zeroed until then, so the account keeps its size.
+    pub winning_numbers: Vec<u8>,
+    /// Seconds winners have to claim their prize after the draw.
+    pub claim_period: u64,
+    /// Fees and expired prizes the admin can withdraw.
+    pub fees_collected: u64,
+    /// Tokens the admin locks in the pot at creation, slashed to players if
+    /// the draw's randomness isn't revealed in time.
+    pub bond_amount: u64,
+    pub randomness_commitment: [u8; 32],
+    /// Slot of the commit or oracle request, 0 until then.
+    pub commit_slot: u64,
+    pub randomness: [u8; 32],
+    pub is_revealed: u64,
+    pub is_refunding: u64,
+    /// VRF oracle program drawing this lottery, or the default pubkey for
+    /// commit-reveal.
+    pub randomness_oracle: Pubkey,
+    /// Ledger account holding the lottery's tickets, or the default pubkey if
+    /// each ticket is its own account. See [`ledger`].
+    pub ticket_ledger: Pubkey,
+    /// Nonzero if each ticket is minted as a token whose holder owns the
+    /// ticket, which only pick-N lotteries can do. See [`nft`].
+    pub ticket_nfts: u64,
+    /// Share of the ticket price paid to the player's referrer, in basis
+    /// points. See [`referral`].
+    pub referral_bps: u16,
+    /// Referral rewards not yet withdrawn. They are held in the pot but aren't
+    /// part of `amount_in_pot`.
+    pub referral_rewards: u64,
+    /// Prizes sponsors put up so far, which is also the index of the next
+    /// one. See [`sponsor`].
+    pub sponsored_prizes: u64,
+    /// Tickets the lottery should sell by `lottery_end` to be drawn, or 0.
+    /// Otherwise anyone can cancel it, refunding its tickets.
+    pub min_entries: u64,
+    /// Tickets on sale, or 0 for no cap.
+    pub max_entries: u64,
+    /// Merkle root of the wallets allowed to play and their ticket
+    /// allocations, or all zeroes if anyone can. See [`allowlist`].
+    pub allowlist_root: [u8; 32],
+    /// Slot `CloseSales` ran at, whose successor's hash goes into the
+    /// committed randomness, or 0 until then.
+    pub close_slot: u64,
+    /// Tokens sponsors added to the pot with `SponsorPot`, which they can take
+    /// back if the lottery refunds. See [`sponsor`].
+    pub sponsored_amount: u64,
+}
+
+impl LotteryDetails {
+    /// What of the bond goes back to the admin when it's slashed: all of it
+    /// if no ticket was sold, otherwise what doesn't split evenly between the
+    /// tickets.
+    pub fn unshared_bond(&self) -> u64 {
+        match self.total_entries {
+            0 => self.bond_amount,
+            total_entries => self.bond_amount % total_entries,
+        }
+    }
+}
+
+/// Parses a unix timestamp stored as a decimal string, like `lottery_end`.
+fn parse_timestamp(timestamp: &str) -> Result<i64, ProgramError> {
+    timestamp.parse().map_err(|_| {
+        msg!("{:?} isn't a unix timestamp", timestamp);
+        ProgramError::InvalidAccountData
+    })
+}
+
+fn validate_prize_split(fee_bps: u16, prize_split: &[u16]) -> ProgramResult {
+    let total = prize_split
+        .iter()
+        .fold(u32::from(fee_bps), |acc, bps| acc + u32::from(*bps));
+    if total > 10_000 {
+        msg!("fee_bps and prize_split add up to more than 10000 bps");
+        return Err(LotteryError::InvalidPrizeSplit.into());
+    }
+    Ok(())
+}
+
+fn validate_game(pick_count: u8, max_number: u8, prize_split: &[u16]) -> ProgramResult {
+    if pick_count == 0 {
+        return Ok(());
+    }
+    let game = PickGame::new(pick_count, max_number);
+    if !game.is_valid() {
+        msg!("pick_count should be between 1 and max_number");
+        return Err(LotteryError::InvalidGameMode.into());
+    }
+    if prize_split.len() > game.tier_count() {
+        msg!("prize_split has more tiers than the game has match counts");
+        return Err(LotteryError::InvalidPrizeSplit.into());
+    }
+    Ok(())
+}
+
+accounts! {
+    pub struct CreateLottery<'info> {
+        pub lottery: [mut, owner, zero, rent_exempt],
+        pub admin: [signer(ProgramError::IncorrectProgramId)],
+    }
+}
+
+accounts! {
+    /// Moves a lottery's bond between the admin and the pot. Passed after the
+    /// other accounts when the lottery has a bond.
+    pub struct BondAccounts<'info> {
+        pub admin_tokens: [mut],
+        pub pot_authority: [],
+        pub pot: [mut],
+        pub token_program: [address(spl_token::id())],
+    }
+}
+
+accounts! {
+    /// Ticket ledger of a compact lottery, created beforehand by the admin.
+    /// Passed last, after the bond accounts if any.
+    pub struct LedgerAccount<'info> {
+        pub ledger: [mut, owner, rent_exempt],
+    }
+}
+
+fn create_lottery<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, CreateLottery<'info>>,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    // Cloned, since the bond accounts are parsed from `ctx` further down.
+    let CreateLottery {
+        lottery: writing_account,
+        admin: creator_account,
+    } = ctx.accounts.clone();
+
+    let mut input_data = LotteryDetails::try_from_slice(instruction_data)?;
+
+    if input_data.admin != *creator_account.key {
+        msg!("Invaild instruction data");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    validate_prize_split(input_data.fee_bps, &input_data.prize_split)?;
+    validate_game(
+        input_data.pick_count,
+        input_data.max_number,
+        &input_data.prize_split,
+    )?;
+    if input_data.ticket_nfts != 0 && input_data.pick_count == 0 {
+        msg!("Only pick-N lotteries can mint ticket tokens");
+        return Err(LotteryError::InvalidGameMode.into());
+    }
+    if input_data.referral_bps > 10_000 {
+        msg!("referral_bps should be at most 10000");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.max_entries != 0 && input_data.max_entries < input_data.min_entries {
+        msg!("max_entries should be at least min_entries");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.claim_period > i64::MAX as u64 {
+        msg!("claim_period is too long");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if parse_timestamp(&input_data.lottery_start)? > parse_timestamp(&input_data.lottery_end)? {
+        msg!("lottery_end should come after lottery_start");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.randomness_oracle != Pubkey::default() && input_data.bond_amount > 0 {
+        msg!("Lotteries drawn by an oracle don't take a bond");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.bond_amount > 0 {
+        let BondAccounts {
+            admin_tokens: creator_token_account,
+            pot_authority,
+            pot: lottery_pool_token_account,
+            token_program,
+        } = ctx.try_remaining()?;
+        check_pot(
+            program_id,
+            writing_account.key,
+            &input_data.token_mint,
+            &pot_authority,
+            &lottery_pool_token_account,
+        )?;
+        let transfer_bond_to_pot = transfer(
+            token_program.key,
+            creator_token_account.key,
+            lottery_pool_token_account.key,
+            creator_account.key,
+            &[],
+            input_data.bond_amount,
+        )?;
+        invoke(
+            &transfer_bond_to_pot,
+            &[
+                creator_token_account.clone(),
+                lottery_pool_token_account.clone(),
+                creator_account.clone(),
+                token_program.clone(),
+            ],
+        )?;
+    }
+    if input_data.ticket_ledger != Pubkey::default() {
+        if input_data.ticket_nfts != 0 {
+            msg!("Compact lotteries can't mint ticket tokens");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if usize::from(input_data.pick_count) > ledger::MAX_LEDGER_PICKS {
+            msg!(
+                "Compact lotteries pick at most {} numbers",
+                ledger::MAX_LEDGER_PICKS
+            );
+            return Err(LotteryError::InvalidGameMode.into());
+        }
+        let LedgerAccount {
+            ledger: ledger_account,
+        } = ctx.try_remaining()?;
+        if *ledger_account.key != input_data.ticket_ledger {
+            msg!("ledger_account isn't the lottery's ticket_ledger");
+            return Err(ProgramError::InvalidArgument);
+        }
+        ledger::initialize(
+            &mut ledger_account.try_borrow_mut_data()?,
+            writing_account.key,
+        )?;
+    }
+    input_data.amount_in_pot = 0;
+    input_data.total_entries = 0;
+    input_data.is_initialized = 1;
+    input_data.is_ended = 0;
+    input_data.series = Pubkey::default();
+    input_data.round = 0;
+    input_data.winning_numbers = vec![0; input_data.pick_count as usize];
+    input_data.fees_collected = 0;
+    input_data.randomness_commitment = [0; 32];
+    input_data.commit_slot = 0;
+    input_data.close_slot = 0;
+    input_data.sponsored_amount = 0;
+    input_data.randomness = [0; 32];
+    input_data.is_revealed = 0;
+    input_data.is_refunding = 0;
+    input_data.referral_rewards = 0;
+    input_data.sponsored_prizes = 0;
+
+    input_data.serialize(&mut &mut writing_account.try_borrow_mut_data()?[..])?;
+    emit(&LotteryCreated {
+        lottery: *writing_account.key,
+        admin: input_data.admin,
+        token_mint: input_data.token_mint,
+        series: input_data.series,
+        round: input_data.round,
+        ticket_price: input_data.ticket_price,
+        lottery_start: parse_timestamp(&input_data.lottery_start)?,
+        lottery_end: parse_timestamp(&input_data.lottery_end)?,
+        pick_count: input_data.pick_count,
+        max_number: input_data.max_number,
+        amount_in_pot: input_data.amount_in_pot,
+    })
+}
+
+// Lottery series
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct LotterySeries {
+    pub admin: Pubkey,
+    pub name: String,
+    pub is_initialized: u64,
+    pub token_mint: Pubkey,
+    pub ticket_price: u64,
+    /// Length of each round, in seconds.
+    pub round_duration: u64,
+    pub fee_bps: u16,
+    pub prize_split: Vec<u16>,
+    pub pick_count: u8,
+    pub max_number: u8,
+    pub claim_period: u64,
+    /// Number of rounds started so far, which is also the index of the next round.
+    pub current_round: u64,
+    pub randomness_oracle: Pubkey,
+}
+
+/// Derives the address of a series round from `[series, round]`.
+pub fn find_round_address(program_id: &Pubkey, series: &Pubkey, round: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[series.as_ref(), &round.to_le_bytes()], program_id)
+}
+
+accounts! {
+    pub struct CreateSeries<'info> {
+        pub series: [mut, owner, zero, rent_exempt],
+        pub admin: [signer],
+    }
+}
+
+fn create_series(ctx: Context<CreateSeries>, instruction_data: &[u8]) -> ProgramResult {
+    let CreateSeries {
+        series: writing_account,
+        admin: creator_account,
+    } = &*ctx.accounts;
+
+    let mut input_data = LotterySeries::try_from_slice(instruction_data)?;
+
+    if input_data.admin != *creator_account.key {
+        msg!("Invaild instruction data");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.round_duration == 0 || input_data.round_duration > i64::MAX as u64 {
+        msg!("round_duration should be a positive number of seconds");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    validate_prize_split(input_data.fee_bps, &input_data.prize_split)?;
+    validate_game(
+        input_data.pick_count,
+        input_data.max_number,
+        &input_data.prize_split,
+    )?;
+    if input_data.claim_period > i64::MAX as u64 {
+        msg!("claim_period is too long");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    input_data.is_initialized = 1;
+    input_data.current_round = 0;
+
+    input_data.serialize(&mut &mut writing_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+/// Derives the PDA that owns a lottery's pot token account, `["pot", lottery]`.
+pub fn find_pot_authority(program_id: &Pubkey, lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pot", lottery.as_ref()], program_id)
+}
+
+/// Checks that `pot_authority` and `pot` are the lottery's pot authority PDA
+/// and its associated token account, returning the PDA's bump.
+fn check_pot(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    pot_authority: &AccountInfo,
+    pot: &AccountInfo,
+) -> Result<u8, ProgramError> {
+    let (pot_authority_key, bump) = find_pot_authority(program_id, lottery);
+    if pot_authority_key != *pot_authority.key {
+        msg!("pot_authority isn't the lottery's pot authority");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if get_associated_token_address(&pot_authority_key, token_mint) != *pot.key {
+        msg!("pot isn't the lottery's pot token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(bump)
+}
+
+/// Moves tokens out of a lottery's pot, signing as its pot authority.
+#[allow(clippy::too_many_arguments)]
+fn transfer_from_pot<'a>(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    token_mint: &Pubkey,
+    pot_authority: &AccountInfo<'a>,
+    pot: &AccountInfo<'a>,
+    destination: &AccountInfo<'a>,
+    token_program: &AccountInfo<'a>,
+    amount: u64,
+) -> ProgramResult {
+    let bump = check_pot(program_id, lottery, token_mint, pot_authority, pot)?;
+    let transfer_from_pot = transfer(
+        token_program.key,
+        pot.key,
+        destination.key,
+        pot_authority.key,
+        &[],
+        amount,
+    )?;
+    invoke_signed(
+        &transfer_from_pot,
+        &[
+            pot.clone(),
+            destination.clone(),
+            pot_authority.clone(),
+            token_program.clone(),
+        ],
+        &[&[b"pot", lottery.as_ref(), &[bump]]],
+    )
+}
+
+/// Creates the PDA `account`, signed for with `seeds`, with `space` bytes
+/// owned by `owner` and paid for by `payer`.
+///
+/// Anyone can send lamports to the address before it exists, which would make
+/// `create_account` fail, so an address that already holds some is topped up
+/// to rent exemption, allocated and assigned instead, as the associated token
+/// account program does.
+pub(crate) fn create_pda_account<'a>(
+    payer: &AccountInfo<'a>,
+    account: &AccountInfo<'a>,
+    system_program: &AccountInfo<'a>,
+    space: usize,
+    owner: &Pubkey,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent = Rent::get()?.minimum_balance(space);
+    if account.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(payer.key, account.key, rent, space as u64, owner),
+            &[payer.clone(), account.clone(), system_program.clone()],
+            &[seeds],
+        );
+    }
+    let top_up = rent.saturating_sub(account.lamports());
+    if top_up > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, account.key, top_up),
+            &[payer.clone(), account.clone(), system_program.clone()],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account.key, space as u64),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account.key, owner),
+        &[account.clone(), system_program.clone()],
+        &[seeds],
+    )
+}
+
+accounts! {
+    pub struct StartNextRound<'info> {
+        pub series: [mut, owner, has_one(LotterySeries::token_mint)],
+        pub payer: [mut, signer],
+        pub round: [mut],
+        pub round_pot_authority: [],
+        pub round_pot: [mut],
+        pub token_mint: [],
+        pub system_program: [address(system_program::id())],
+        pub token_program: [address(spl_token::id())],
+        pub associated_token_program: [address(spl_associated_token_account::id())],
+        pub rent: [address(sysvar::rent::id())],
+    }
+}
+
+accounts! {
+    /// The round before the one being started, passed after the other
+    /// accounts of `StartNextRound` unless it's the first round.
+    pub struct PreviousRound<'info> {
+        pub previous_round: [mut, owner],
+        pub previous_pot_authority: [],
+        pub previous_pot: [mut],
+    }
+}
+
+/// Opens the next round of a series. Anyone may call this once the previous
+/// round has been drawn; whatever is left in its pot is carried forward,
+/// except what sponsors of a refunding round can still reclaim.
+fn start_next_round<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, StartNextRound<'info>>,
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let StartNextRound {
+        series: series_account,
+        payer,
+        round: round_account,
+        round_pot_authority,
+        round_pot: round_pot_account,
+        token_mint,
+        system_program,
+        token_program,
+        associated_token_program,
+        rent: rent_sysvar,
+    } = ctx.accounts.clone();
+
+    let mut series = LotterySeries::try_from_slice(*series_account.data.borrow())?;
+    if series.is_initialized == 0 {
+        return Err(LotteryError::NotInitialized.into());
+    }
+
+    let round = series.current_round;
+    let (round_key, round_bump) = find_round_address(program_id, series_account.key, round);
+    if round_key != *round_account.key {
+        msg!("round_account isn't the next round of the series");
+        return Err(LotteryError::InvalidRoundAccount.into());
+    }
+    check_pot(
+        program_id,
+        round_account.key,
+        token_mint.key,
+        &round_pot_authority,
+        &round_pot_account,
+    )?;
+
+    let mut previous = None;
+    if round > 0 {
+        let PreviousRound {
+            previous_round: previous_round_account,
+            previous_pot_authority,
+            previous_pot: previous_pot_account,
+        } = ctx.try_remaining()?;
+        let (previous_key, _) = find_round_address(program_id, series_account.key, round - 1);
+        if previous_key != *previous_round_account.key {
+            msg!("previous_round_account isn't the previous round of the series");
+            return Err(LotteryError::InvalidRoundAccount.into());
+        }
+        let previous_data = LotteryDetails::try_from_slice(*previous_round_account.data.borrow())?;
+        if previous_data.is_ended == 0 {
+            msg!("The previous round hasn't been drawn yet");
+            return Err(LotteryError::RoundNotDrawn.into());
+        }
+        previous = Some((
+            previous_round_account,
+            previous_pot_authority,
+            previous_pot_account,
+            previous_data,
+        ));
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    let mut round_data = LotteryDetails {
+        admin: series.admin,
+        name: format!("{} #{}", series.name, round),
+        is_initialized: 1,
+        is_ended: 0,
+        lottery_start: now.to_string(),
+        lottery_end: now.saturating_add(series.round_duration as i64).to_string(),
+        ticket_price: series.ticket_price,
+        amount_in_pot: 0,
+        total_entries: 0,
+        token_mint: series.token_mint,
+        series: *series_account.key,
+        round,
+        fee_bps: series.fee_bps,
+        prize_split: series.prize_split.clone(),
+        pick_count: series.pick_count,
+        max_number: series.max_number,
+        winning_numbers: vec![0; series.pick_count as usize],
+        claim_period: series.claim_period,
+        fees_collected: 0,
+        bond_amount: 0,
+        randomness_commitment: [0; 32],
+        commit_slot: 0,
+        randomness: [0; 32],
+        is_revealed: 0,
+        is_refunding: 0,
+        randomness_oracle: series.randomness_oracle,
+        ticket_ledger: Pubkey::default(),
+        ticket_nfts: 0,
+        referral_bps: 0,
+        referral_rewards: 0,
+        sponsored_prizes: 0,
+        min_entries: 0,
+        max_entries: 0,
+        allowlist_root: [0; 32],
+        close_slot: 0,
+        sponsored_amount: 0,
+    };
+    create_pda_account(
+        &payer,
+        &round_account,
+        &system_program,
+        round_data.try_to_vec()?.len(),
+        program_id,
+        &[
+            series_account.key.as_ref(),
+            &round.to_le_bytes(),
+            &[round_bump],
+        ],
+    )?;
+    invoke(
+        &create_associated_token_account(payer.key, round_pot_authority.key, token_mint.key),
+        &[
+            payer.clone(),
+            round_pot_account.clone(),
+            round_pot_authority.clone(),
+            token_mint.clone(),
+            system_program.clone(),
+            token_program.clone(),
+            rent_sysvar.clone(),
+            associated_token_program.clone(),
+        ],
+    )?;
+
+    if let Some((
+        previous_round_account,
+        previous_pot_authority,
+        previous_pot_account,
+        mut previous_data,
+    )) = previous
+    {
+        let mut carried_over = previous_data.amount_in_pot;
+        if previous_data.is_refunding != 0 {
+            carried_over -= previous_data.sponsored_amount;
+        }
+        if carried_over > 0 {
+            msg!("Carrying {} over from round {}", carried_over, round - 1);
+            transfer_from_pot(
+                program_id,
+                previous_round_account.key,
+                token_mint.key,
+                &previous_pot_authority,
+                &previous_pot_account,
+                &round_pot_account,
+                &token_program,
+                carried_over,
+            )?;
+            previous_data.amount_in_pot -= carried_over;
+            round_data.amount_in_pot = carried_over;
+            previous_data.serialize(&mut &mut previous_round_account.try_borrow_mut_data()?[..])?;
+        }
+    }
+
+    series.current_round = round + 1;
+    round_data.serialize(&mut &mut round_account.try_borrow_mut_data()?[..])?;
+    emit(&LotteryCreated {
+        lottery: *round_account.key,
+        admin: round_data.admin,
+        token_mint: round_data.token_mint,
+        series: round_data.series,
+        round,
+        ticket_price: round_data.ticket_price,
+        lottery_start: now,
+        lottery_end: parse_timestamp(&round_data.lottery_end)?,
+        pick_count: round_data.pick_count,
+        max_number: round_data.max_number,
+        amount_in_pot: round_data.amount_in_pot,
+    })?;
+    series.serialize(&mut &mut series_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct WithdrawRequest {
+    pub amount: u64,
+}
+
+accounts! {
+    pub struct Withdraw<'info> {
+        pub lottery: [
+            mut,
+            owner,
+            has_one(LotteryDetails::admin),
+            has_one(LotteryDetails::token_mint),
+        ],
+        pub admin: [signer(ProgramError::IncorrectProgramId)],
+        pub token_mint: [],
+        pub token_program: [address(spl_token::id())],
+        pub pot: [mut],
+        pub admin_tokens: [mut, token_mint(*token_mint.key), token_authority(*admin.key)],
+        pub pot_authority: [],
+    }
+}
+
+/// Withdraws collected fees from a lottery's pot to the admin.
+fn withdraw(ctx: Context<Withdraw>, instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let Withdraw {
+        lottery: writing_account,
+        token_mint,
+        token_program,
+        pot: lotto_ata,
+        admin_tokens: admin_ata,
+        pot_authority,
+        ..
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    let input_data = WithdrawRequest::try_from_slice(instruction_data)?;
+
+    if input_data.amount > fanilotto_data.fees_collected {
+        msg!("Insufficent balance");
+        return Err(ProgramError::InsufficientFunds);
+    }
+
+    transfer_from_pot(
+        program_id,
+        writing_account.key,
+        token_mint.key,
+        pot_authority,
+        lotto_ata,
+        admin_ata,
+        token_program,
+        input_data.amount,
+    )?;
+    fanilotto_data.fees_collected -= input_data.amount;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+
+    Ok(())
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct TicketDetails {
+    pub player: String,
+    pub ticket_count: u64,
+    pub ticket_number_arr: [u8; 128],
+    pub lottery: Pubkey,
+    pub ticket_index: u64,
+    pub claimed: u64,
+}
+
+/// `TicketDetails::claimed` of a ticket whose token was burned. Tallying a
+/// pick-N draw skips void tickets, so they win nothing.
+pub const TICKET_VOID: u64 = 2;
+
+/// Derives the address of a lottery's ticket from `["ticket", lottery, index]`.
+pub fn find_ticket_address(program_id: &Pubkey, lottery: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"ticket", lottery.as_ref(), &index.to_le_bytes()],
+        program_id,
+    )
+}
+
+accounts! {
+    pub struct Play<'info> {
+        pub lottery: [mut, owner],
+        pub ticket: [mut],
+        /// Wallet of the player, paying for the ticket.
+        pub player: [mut, signer(ProgramError::IncorrectProgramId)],
+        pub system_program: [address(system_program::id())],
+        pub pot: [mut],
+        pub token_program: [address(spl_token::id())],
+        pub player_tokens: [mut, token_authority(*player.key)],
+    }
+}
+
+accounts! {
+    /// Mints the ticket as a token. Passed after the other accounts when the
+    /// lottery has `ticket_nfts` set.
+    pub struct TicketNftAccounts<'info> {
+        pub ticket_mint: [mut],
+        pub metadata: [mut, seeds(b"metadata", ticket_mint.key)],
+        /// Mint authority of the ticket token.
+        pub pot_authority: [],
+        pub player_ticket_tokens: [mut],
+        pub associated_token_program: [address(spl_associated_token_account::id())],
+        pub rent: [address(sysvar::rent::id())],
+    }
+}
+
+accounts! {
+    /// Counts the ticket against the player's allowlist allocation. Passed
+    /// after the ticket token accounts if any when the player has an
+    /// allocation, which their first ticket creates.
+    pub struct AllowlistAccounts<'info> {
+        /// The player's `AllowlistEntry`, at `["allowlist", lottery, player]`.
+        pub allowlist_entry: [mut],
+    }
+}
+
+accounts! {
+    /// Buys the ticket through `referrer`. Passed last, after the ticket token
+    /// and allowlist accounts if any. The player pays for referral records
+    /// that don't exist yet.
+    pub struct ReferralAccounts<'info> {
+        pub referrer: [],
+        /// The referrer's `ReferralRecord`, at `["referral", lottery, referrer]`.
+        pub referrer_record: [mut],
+        /// The player's `ReferralRecord`, at `["referral", lottery, player]`.
+        pub player_record: [mut],
+    }
+}
+
+fn play<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, Play<'info>>,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    // Cloned, since the ticket token and referral accounts are parsed from
+    // `ctx` further down.
+    let Play {
+        lottery: writing_account,
+        ticket: player_program_account,
+        player,
+        system_program,
+        pot: lottery_pool_token_account,
+        token_program,
+        player_tokens: player_token_account,
+    } = ctx.accounts.clone();
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+
+    // Allowlisted lotteries take the player's proof after the ticket.
+    let (mut ticket_data, allowlist_proof) = if fanilotto_data.allowlist_root == [0; 32] {
+        (TicketDetails::try_from_slice(instruction_data)?, None)
+    } else {
+        let mut data = instruction_data;
+        let ticket_data = TicketDetails::deserialize(&mut data)?;
+        (
+            ticket_data,
+            Some(allowlist::AllowlistProof::try_from_slice(data)?),
+        )
+    };
+
+    if fanilotto_data.is_ended != 0 {
+        msg!("The lottery has already been drawn");
+        return Err(LotteryError::AlreadyDrawn.into());
+    }
+    if Clock::get()?.unix_timestamp >= parse_timestamp(&fanilotto_data.lottery_end)? {
+        msg!("Ticket sales have closed");
+        return Err(LotteryError::LotteryClosed.into());
+    }
+    if fanilotto_data.max_entries != 0 && fanilotto_data.total_entries >= fanilotto_data.max_entries
+    {
+        msg!("All {} tickets have been sold", fanilotto_data.max_entries);
+        return Err(LotteryError::SoldOut.into());
+    }
+    if ticket_data.player != player.key.to_string() {
+        msg!("Ticket player should be the signer");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if let Some(proof) = &allowlist_proof {
+        let leaf = allowlist::leaf(player.key, proof.allocation);
+        if !allowlist::verify(&fanilotto_data.allowlist_root, leaf, &proof.proof) {
+            msg!("{} isn't on the lottery's allowlist", player.key);
+            return Err(LotteryError::NotAllowlisted.into());
+        }
+    }
+    if fanilotto_data.pick_count > 0 {
+        let game = PickGame::new(fanilotto_data.pick_count, fanilotto_data.max_number);
+        let numbers = &ticket_data.ticket_number_arr[..usize::from(game.pick_count)];
+        if !game.is_valid_pick(numbers) {
+            msg!("Ticket numbers aren't a valid pick");
+            return Err(LotteryError::InvalidTicketNumbers.into());
+        }
+    }
+    let (pot_authority, _) = find_pot_authority(program_id, writing_account.key);
+    if get_associated_token_address(&pot_authority, &fanilotto_data.token_mint)
+        != *lottery_pool_token_account.key
+    {
+        msg!("lottery_pool_token_account isn't the lottery's pot");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let ticket_index = fanilotto_data.total_entries;
+    let (ticket_key, ticket_bump) =
+        find_ticket_address(program_id, writing_account.key, ticket_index);
+    if fanilotto_data.ticket_ledger != Pubkey::default() {
+        if fanilotto_data.ticket_ledger != *player_program_account.key {
+            msg!("player_program_account isn't the lottery's ticket ledger");
+            return Err(ProgramError::InvalidArgument);
+        }
+    } else if ticket_key != *player_program_account.key {
+        msg!("player_program_account isn't the lottery's next ticket");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    ticket_data.lottery = *writing_account.key;
+    ticket_data.ticket_index = ticket_index;
+    ticket_data.claimed = 0;
+
+    let total_amount = fanilotto_data.ticket_price;
+
+    let transfer_to_lottery_pool = transfer(
+        token_program.key,
+        player_token_account.key,
+        lottery_pool_token_account.key,
+        player.key,
+        &[],
+        total_amount,
+    )?;
+
+    invoke(
+        &transfer_to_lottery_pool,
+        &[
+            player_token_account.clone(),
+            lottery_pool_token_account.clone(),
+            token_program.clone(),
+            player.clone(),
+        ],
+    )?;
+
+    if fanilotto_data.ticket_ledger != Pubkey::default() {
+        let pick_count = usize::from(fanilotto_data.pick_count);
+        let mut numbers = [0; ledger::MAX_LEDGER_PICKS];
+        numbers[..pick_count].copy_from_slice(&ticket_data.ticket_number_arr[..pick_count]);
+        ledger::append(
+            &mut player_program_account.try_borrow_mut_data()?,
+            &TicketRecord {
+                player: *player.key,
+                numbers,
+                slot: Clock::get()?.slot,
+                claimed: 0,
+            },
+        )?;
+    } else {
+        create_pda_account(
+            &player,
+            &player_program_account,
+            &system_program,
+            ticket_data.try_to_vec()?.len(),
+            program_id,
+            &[
+                b"ticket",
+                writing_account.key.as_ref(),
+                &ticket_index.to_le_bytes(),
+                &[ticket_bump],
+            ],
+        )?;
+        ticket_data.serialize(&mut &mut player_program_account.try_borrow_mut_data()?[..])?;
+    }
+    if fanilotto_data.ticket_nfts != 0 {
+        let TicketNftAccounts {
+            ticket_mint,
+            metadata,
+            pot_authority,
+            player_ticket_tokens,
+            associated_token_program,
+            rent,
+        } = ctx.try_remaining()?;
+        nft::mint_ticket(
+            program_id,
+            nft::MintAccounts {
+                lottery: &writing_account,
+                ticket: &player_program_account,
+                player: &player,
+                ticket_mint: &ticket_mint,
+                metadata: &metadata,
+                pot_authority: &pot_authority,
+                player_ticket_tokens: &player_ticket_tokens,
+                system_program: &system_program,
+                token_program: &token_program,
+                associated_token_program: &associated_token_program,
+                rent: &rent,
+            },
+            ctx.bumps["metadata"],
+            ticket_index,
+            &ticket_data.ticket_number_arr[..usize::from(fanilotto_data.pick_count)],
+        )?;
+    }
+    if let Some(allocation) = allowlist_proof
+        .map(|proof| proof.allocation)
+        .filter(|&allocation| allocation > 0)
+    {
+        let AllowlistAccounts {
+            allowlist_entry: entry_account,
+        } = ctx.try_remaining()?;
+        let mut entry = allowlist::load_or_create(
+            program_id,
+            writing_account.key,
+            player.key,
+            &entry_account,
+            &player,
+            &system_program,
+        )?;
+        if entry.tickets >= allocation {
+            msg!("The player bought all {} of their tickets", allocation);
+            return Err(LotteryError::AllocationUsed.into());
+        }
+        entry.tickets += 1;
+        entry.serialize(&mut &mut entry_account.try_borrow_mut_data()?[..])?;
+    }
+
+    let mut referral_cut = 0;
+    if !ctx.remaining_accounts.is_empty() {
+        let ReferralAccounts {
+            referrer,
+            referrer_record: referrer_record_account,
+            player_record: player_record_account,
+        } = ctx.try_remaining()?;
+        if fanilotto_data.referral_bps == 0 {
+            msg!("The lottery doesn't pay referral rewards");
+            return Err(ProgramError::InvalidArgument);
+        }
+        if referrer.key == player.key {
+            msg!("Players can't refer themselves");
+            return Err(LotteryError::SelfReferral.into());
+        }
+        let mut referrer_record = referral::load_or_create(
+            program_id,
+            writing_account.key,
+            referrer.key,
+            &referrer_record_account,
+            &player,
+            &system_program,
+        )?;
+        let mut player_record = referral::load_or_create(
+            program_id,
+            writing_account.key,
+            player.key,
+            &player_record_account,
+            &player,
+            &system_program,
+        )?;
+        referral::bind(&mut player_record, &referrer_record)?;
+
+        referral_cut = referral::referral_cut(total_amount, fanilotto_data.referral_bps);
+        referrer_record.referrals += 1;
+        referrer_record.rewards += referral_cut;
+        fanilotto_data.referral_rewards += referral_cut;
+        referrer_record.serialize(&mut &mut referrer_record_account.try_borrow_mut_data()?[..])?;
+        player_record.serialize(&mut &mut player_record_account.try_borrow_mut_data()?[..])?;
+    }
+
+    // The referrer's cut stays in the pot until they withdraw it, but isn't
+    // prize money, so neither the draw nor its fee see it.
+    fanilotto_data.amount_in_pot += total_amount - referral_cut;
+    fanilotto_data.total_entries += 1;
+
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    emit(&TicketPurchased {
+        lottery: *writing_account.key,
+        ticket: *player_program_account.key,
+        player: *player.key,
+        ticket_index,
+        numbers: ticket_data.ticket_number_arr[..usize::from(fanilotto_data.pick_count)].to_vec(),
+        price: total_amount,
+    })
+}
+
+/// Outcome of a draw. `DrawLottery` fixes what wins: the winning numbers of a
+/// pick-N lottery, or the winning tickets of a classic one. `TallyTickets`
+/// then counts the winners of a pick-N lottery in batches, so the draw never
+/// needs every ticket in one transaction. Once all tickets are counted the
+/// prizes are known, and winners pull theirs with `ClaimPrize` by showing
+/// their ticket matches the results.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct DrawResults {
+    pub lottery: Pubkey,
+    /// Winning numbers of a pick-N lottery, in draw order.
+    pub winning_numbers: Vec<u8>,
+    /// Winning ticket of each prize tier of a classic lottery, best tier
+    /// first.
+    pub winning_tickets: Vec<u64>,
+    /// Tickets counted so far, in index order.
+    pub tallied: u64,
+    /// Winning tickets counted in each tier.
+    pub winners_per_tier: Vec<u64>,
+    /// First winning ticket counted in each tier, or `u64::MAX` for none.
+    pub first_winners: Vec<u64>,
+    /// Prize of each winning ticket, by tier. Set once every ticket has been
+    /// counted.
+    pub prize_per_ticket: Vec<u64>,
+    pub claim_deadline: i64,
+    /// Prizes not yet claimed.
+    pub unclaimed: u64,
+    pub is_expired: u64,
+}
+
+impl DrawResults {
+    /// Prize tier `ticket_index` won, if any. Pick-N tickets show their
+    /// `numbers`; classic tickets win by index alone.
+    pub fn tier(&self, ticket_index: u64, numbers: &[u8]) -> Option<usize> {
+        if self.winning_numbers.is_empty() {
+            self.winning_tickets
+                .iter()
+                .position(|winner| *winner == ticket_index)
+        } else {
+            let matches = pick::count_matches(numbers, &self.winning_numbers);
+            Some(self.winning_numbers.len() - usize::from(matches))
+        }
+    }
+
+    /// Prize of a ticket in `tier`.
+    pub fn prize(&self, tier: Option<usize>) -> u64 {
+        tier.and_then(|tier| self.prize_per_ticket.get(tier))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Derives the address of a lottery's draw results from `["results", lottery]`.
+pub fn find_results_address(program_id: &Pubkey, lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"results", lottery.as_ref()], program_id)
+}
+
+accounts! {
+    pub struct DrawLottery<'info> {
+        pub lottery: [mut, owner],
+        pub payer: [mut, signer],
+        pub results: [mut, seeds(b"results", lottery.key)],
+        pub system_program: [address(system_program::id())],
+    }
+}
+
+/// Draws a lottery from its revealed randomness. Anyone can call this and
+/// pays for the results account. A pick-N lottery gets its winning numbers,
+/// and its tickets are then counted with `TallyTickets`; a classic lottery,
+/// with a `pick_count` of 0, gets a different winning ticket for each prize
+/// tier and pays out right away.
+fn draw_lottery(ctx: Context<DrawLottery>, _instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let DrawLottery {
+        lottery: writing_account,
+        payer,
+        results: results_account,
+        system_program,
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.is_revealed == 0 {
+        msg!("The lottery's randomness hasn't been revealed");
+        return Err(LotteryError::NotRevealed.into());
+    }
+    if fanilotto_data.is_ended != 0 || results_account.owner == program_id {
+        msg!("The lottery has already been drawn");
+        return Err(LotteryError::AlreadyDrawn.into());
+    }
+    if fanilotto_data.total_entries < fanilotto_data.min_entries {
+        msg!(
+            "Only {} of the lottery's {} min_entries were sold",
+            fanilotto_data.total_entries,
+            fanilotto_data.min_entries
+        );
+        return Err(LotteryError::MinEntriesNotReached.into());
+    }
+    let rng = Rng::from_inputs(&[&fanilotto_data.randomness], |inputs| {
+        hashv(inputs).to_bytes()
+    });
+    let mut results = DrawResults {
+        lottery: *writing_account.key,
+        winning_numbers: Vec::new(),
+        winning_tickets: Vec::new(),
+        tallied: 0,
+        winners_per_tier: Vec::new(),
+        first_winners: Vec::new(),
+        prize_per_ticket: vec![0; fanilotto_data.prize_split.len()],
+        claim_deadline: 0,
+        unclaimed: 0,
+        is_expired: 0,
+    };
+    if fanilotto_data.pick_count == 0 {
+        let tiers = fanilotto_data.prize_split.len();
+        results.winning_tickets =
+            pick::draw_winning_tickets(fanilotto_data.total_entries, tiers, &rng);
+        results.winners_per_tier = (0..tiers)
+            .map(|tier| u64::from(tier < results.winning_tickets.len()))
+            .collect();
+        results.first_winners = (0..tiers)
+            .map(|tier| {
+                results
+                    .winning_tickets
+                    .get(tier)
+                    .copied()
+                    .unwrap_or(u64::MAX)
+            })
+            .collect();
+        results.tallied = fanilotto_data.total_entries;
+    } else {
+        let game = PickGame::new(fanilotto_data.pick_count, fanilotto_data.max_number);
+        results.winning_numbers = pick::draw_winning_numbers(&game, &rng);
+        results.winners_per_tier = vec![0; game.tier_count()];
+        results.first_winners = vec![u64::MAX; game.tier_count()];
+        fanilotto_data.winning_numbers = results.winning_numbers.clone();
+    }
+
+    create_pda_account(
+        payer,
+        results_account,
+        system_program,
+        results.try_to_vec()?.len(),
+        program_id,
+        &[
+            b"results",
+            writing_account.key.as_ref(),
+            &[ctx.bumps["results"]],
+        ],
+    )?;
+    if results.tallied == fanilotto_data.total_entries {
+        pay_out(writing_account.key, &mut fanilotto_data, &mut results)?;
+    }
+    results.serialize(&mut &mut results_account.try_borrow_mut_data()?[..])?;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+/// Instruction data of `TallyTickets`.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct TallyRequest {
+    /// Tickets to count, from `DrawResults::tallied` on.
+    pub count: u64,
+}
+
+accounts! {
+    /// Followed by the `count` tickets being counted, in index order, or by
+    /// the ticket ledger of a compact lottery.
+    pub struct TallyTickets<'info> {
+        pub lottery: [mut, owner],
+        pub results: [mut, owner, seeds(b"results", lottery.key)],
+    }
+}
+
+/// Counts the winners among the next tickets of a drawn pick-N lottery.
+/// Anyone can call this, as often as it takes to get through every ticket.
+/// Void tickets don't count. The batch that counts the last ticket splits the
+/// pot: what tiers without winners leave in it rolls over to the next round
+/// of a series; a one-off lottery has none and books it as fees.
+fn tally_tickets(ctx: Context<TallyTickets>, instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let TallyTickets {
+        lottery: writing_account,
+        results: results_account,
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    let mut results = DrawResults::try_from_slice(*results_account.data.borrow())?;
+    if fanilotto_data.is_ended != 0 {
+        msg!("Every ticket has already been tallied");
+        return Err(LotteryError::AlreadyDrawn.into());
+    }
+    let TallyRequest { count } = TallyRequest::try_from_slice(instruction_data)?;
+    let first = results.tallied;
+    let end = first
+        .saturating_add(count)
+        .min(fanilotto_data.total_entries);
+    if end == first {
+        msg!("count should be positive");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let pick_count = usize::from(fanilotto_data.pick_count);
+    let mut tally = |index: u64, numbers: &[u8]| {
+        if let Some(tier) = results.tier(index, numbers) {
+            results.winners_per_tier[tier] += 1;
+            if results.first_winners[tier] == u64::MAX {
+                results.first_winners[tier] = index;
+            }
+        }
+    };
+    if fanilotto_data.ticket_ledger != Pubkey::default() {
+        let ledger_account = ctx
+            .remaining_accounts
+            .first()
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+        if *ledger_account.key != fanilotto_data.ticket_ledger {
+            msg!("The ticket ledger should follow the fixed accounts");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let data = ledger_account.data.borrow();
+        for index in first..end {
+            let record = ledger::record(&data, index)?;
+            tally(index, &record.numbers[..pick_count]);
+        }
+    } else {
+        for index in first..end {
+            let ticket_account = ctx
+                .remaining_accounts
+                .get((index - first) as usize)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?;
+            if find_ticket_address(program_id, writing_account.key, index).0 != *ticket_account.key
+            {
+                msg!("Tickets should be passed in index order");
+                return Err(ProgramError::InvalidSeeds);
+            }
+            let ticket_data = TicketDetails::try_from_slice(*ticket_account.data.borrow())?;
+            if ticket_data.claimed != TICKET_VOID {
+                tally(index, &ticket_data.ticket_number_arr[..pick_count]);
+            }
+        }
+    }
+    results.tallied = end;
+    msg!("Tallied tickets {} to {}", first, end);
+
+    if results.tallied == fanilotto_data.total_entries {
+        pay_out(writing_account.key, &mut fanilotto_data, &mut results)?;
+        fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    }
+    results.serialize(&mut &mut results_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+/// Splits the pot of a lottery whose winners have all been counted, opening
+/// the claim period.
+fn pay_out(
+    lottery: &Pubkey,
+    fanilotto_data: &mut LotteryDetails,
+    results: &mut DrawResults,
+) -> ProgramResult {
+    let payouts = pick::compute_payouts(
+        fanilotto_data.amount_in_pot,
+        fanilotto_data.fee_bps,
+        &fanilotto_data.prize_split,
+        &results.winners_per_tier,
+    );
+    let (fee, rollover) = if fanilotto_data.series == Pubkey::default() {
+        (payouts.fee + payouts.rollover, 0)
+    } else {
+        (payouts.fee, payouts.rollover)
+    };
+    results.claim_deadline = Clock::get()?
+        .unix_timestamp
+        .saturating_add(fanilotto_data.claim_period as i64);
+    results.unclaimed = payouts.paid_out(&results.winners_per_tier);
+    results.prize_per_ticket = payouts.prize_per_ticket;
+    fanilotto_data.amount_in_pot = rollover;
+    fanilotto_data.fees_collected += fee;
+    fanilotto_data.is_ended = 1;
+    emit(&LotteryDrawn {
+        lottery: *lottery,
+        winning_numbers: fanilotto_data.winning_numbers.clone(),
+        total_entries: fanilotto_data.total_entries,
+        winners_per_tier: results.winners_per_tier.clone(),
+        prize_per_ticket: results.prize_per_ticket.clone(),
+        fee,
+        rollover,
+        claim_deadline: results.claim_deadline,
+    })
+}
+
+/// Instruction data of `ClaimPrize` and `ClaimRefund` for compact lotteries,
+/// whose `ticket` account is the ledger. Other lotteries take no data.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct ClaimRequest {
+    pub ticket_index: u64,
+}
+
+/// Checks that `ticket_account` holds an unclaimed ticket of `player` and
+/// returns its index. See [`owned_ticket`].
+fn claimable_ticket(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    lottery_data: &LotteryDetails,
+    ticket_account: &AccountInfo,
+    player: &Pubkey,
+    instruction_data: &[u8],
+    remaining_accounts: &[AccountInfo],
+) -> Result<u64, ProgramError> {
+    let (ticket_index, claimed) = owned_ticket(
+        program_id,
+        lottery,
+        lottery_data,
+        ticket_account,
+        player,
+        instruction_data,
+        remaining_accounts,
+    )?;
+    if claimed != 0 {
+        msg!("Ticket has already been claimed");
+        return Err(LotteryError::PrizeAlreadyClaimed.into());
+    }
+    Ok(ticket_index)
+}
+
+accounts! {
+    /// The player's token account holding the ticket's token. Passed after
+    /// the other accounts to claim a ticket of a lottery with ticket tokens.
+    pub struct TicketHolder<'info> {
+        pub holder_tokens: [],
+    }
+}
+
+/// Checks that `ticket_account` holds a ticket of `player` that isn't void
+/// and returns its index and `claimed` flag. For compact lotteries,
+/// `ticket_account` is the ledger and the index comes from the instruction's
+/// `ClaimRequest`. For lotteries with ticket tokens, the ticket is `player`'s
+/// if the [`TicketHolder`] at the front of `remaining_accounts` holds its
+/// token.
+fn owned_ticket(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    lottery_data: &LotteryDetails,
+    ticket_account: &AccountInfo,
+    player: &Pubkey,
+    instruction_data: &[u8],
+    remaining_accounts: &[AccountInfo],
+) -> Result<(u64, u64), ProgramError> {
+    let (ticket_index, is_player, claimed) = if lottery_data.ticket_ledger != Pubkey::default() {
+        if *ticket_account.key != lottery_data.ticket_ledger {
+            msg!("ticket isn't the lottery's ticket ledger");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let ClaimRequest { ticket_index } = ClaimRequest::try_from_slice(instruction_data)?;
+        let record = ledger::record(&ticket_account.data.borrow(), ticket_index)?;
+        (ticket_index, record.player == *player, record.claimed)
+    } else {
+        let ticket_data = TicketDetails::try_from_slice(*ticket_account.data.borrow())?;
+        if find_ticket_address(program_id, lottery, ticket_data.ticket_index).0
+            != *ticket_account.key
+        {
+            msg!("ticket doesn't belong to this lottery");
+            return Err(ProgramError::InvalidSeeds);
+        }
+        let is_player = if lottery_data.ticket_nfts != 0 {
+            let TicketHolder { holder_tokens } = TicketHolder::try_accounts(
+                program_id,
+                &mut &remaining_accounts[..],
+                &[],
+                &mut BTreeMap::new(),
+            )?;
+            nft::check_holder(program_id, ticket_account.key, player, &holder_tokens)?;
+            true
+        } else {
+            ticket_data.player == player.to_string()
+        };
+        (ticket_data.ticket_index, is_player, ticket_data.claimed)
+    };
+    if !is_player {
+        msg!("Only the ticket's player can claim it");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if claimed == TICKET_VOID {
+        msg!("Ticket is void");
+        return Err(LotteryError::TicketVoid.into());
+    }
+    Ok((ticket_index, claimed))
+}
+
+/// Numbers of the ticket `owned_ticket` found at `ticket_index`.
+fn ticket_numbers(
+    lottery_data: &LotteryDetails,
+    ticket_account: &AccountInfo,
+    ticket_index: u64,
+) -> Result<Vec<u8>, ProgramError> {
+    let pick_count = usize::from(lottery_data.pick_count);
+    if lottery_data.ticket_ledger != Pubkey::default() {
+        let record = ledger::record(&ticket_account.data.borrow(), ticket_index)?;
+        Ok(record.numbers[..pick_count].to_vec())
+    } else {
+        let ticket_data = TicketDetails::try_from_slice(*ticket_account.data.borrow())?;
+        Ok(ticket_data.ticket_number_arr[..pick_count].to_vec())
+    }
+}
+
+/// Marks the ticket `claimable_ticket` returned as claimed.
+fn mark_claimed(
+    lottery_data: &LotteryDetails,
+    ticket_account: &AccountInfo,
+    ticket_index: u64,
+) -> ProgramResult {
+    if lottery_data.ticket_ledger != Pubkey::default() {
+        let mut data = ticket_account.try_borrow_mut_data()?;
+        let mut record = ledger::record(&data, ticket_index)?;
+        record.claimed = 1;
+        ledger::write_record(&mut data, ticket_index, &record)
+    } else {
+        let mut ticket_data = TicketDetails::try_from_slice(*ticket_account.data.borrow())?;
+        ticket_data.claimed = 1;
+        ticket_data.serialize(&mut &mut ticket_account.try_borrow_mut_data()?[..])?;
+        Ok(())
+    }
+}
+
+accounts! {
+    /// Followed, for lotteries with ticket tokens, by [`TicketHolder`].
+    pub struct ClaimPrize<'info> {
+        pub lottery: [owner],
+        pub results: [mut, owner, seeds(b"results", lottery.key)],
+        pub ticket: [mut, owner],
+        pub player: [signer],
+        pub pot_authority: [],
+        pub pot: [mut],
+        pub player_tokens: [mut],
+        pub token_program: [address(spl_token::id())],
+    }
+}
+
+/// Pays a winning ticket its prize, which follows from its numbers, or for a
+/// classic lottery its index, and the draw results. Each ticket can be
+/// claimed once, from when every ticket has been tallied until the results'
+/// claim deadline.
+fn claim_prize(ctx: Context<ClaimPrize>, instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let ClaimPrize {
+        lottery: writing_account,
+        results: results_account,
+        ticket: ticket_account,
+        player,
+        pot_authority,
+        pot: lottery_pool_token_account,
+        player_tokens: player_token_account,
+        token_program,
+    } = &*ctx.accounts;
+
+    let fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    let mut results = DrawResults::try_from_slice(*results_account.data.borrow())?;
+    if fanilotto_data.is_ended == 0 {
+        msg!("The draw's tickets haven't all been tallied");
+        return Err(LotteryError::NotTallied.into());
+    }
+    let ticket_index = claimable_ticket(
+        program_id,
+        writing_account.key,
+        &fanilotto_data,
+        ticket_account,
+        player.key,
+        instruction_data,
+        ctx.remaining_accounts,
+    )?;
+    if results.is_expired != 0 || Clock::get()?.unix_timestamp > results.claim_deadline {
+        msg!("The claim period has ended");
+        return Err(LotteryError::ClaimPeriodEnded.into());
+    }
+    let numbers = ticket_numbers(&fanilotto_data, ticket_account, ticket_index)?;
+    let amount = results.prize(results.tier(ticket_index, &numbers));
+    if amount == 0 {
+        msg!("Ticket didn't win a prize");
+        return Err(LotteryError::NotAWinner.into());
+    }
+    if get_associated_token_address(player.key, &fanilotto_data.token_mint)
+        != *player_token_account.key
+    {
+        msg!("player_token_account isn't the player's associated token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    transfer_from_pot(
+        program_id,
+        writing_account.key,
+        &fanilotto_data.token_mint,
+        pot_authority,
+        lottery_pool_token_account,
+        player_token_account,
+        token_program,
+        amount,
+    )?;
+
+    mark_claimed(&fanilotto_data, ticket_account, ticket_index)?;
+    results.unclaimed -= amount;
+    results.serialize(&mut &mut results_account.try_borrow_mut_data()?[..])?;
+    emit(&PrizeClaimed {
+        lottery: *writing_account.key,
+        ticket: *ticket_account.key,
+        player: *player.key,
+        amount,
+    })
+}
+
+accounts! {
+    /// Followed, for series rounds, by `SeriesAccount` and, unless the round
+    /// is the latest, `Rollover`.
+    pub struct ExpirePrizes<'info> {
+        pub lottery: [mut, owner],
+        pub results: [mut, owner, seeds(b"results", lottery.key)],
+    }
+}
+
+accounts! {
+    pub struct SeriesAccount<'info> {
+        pub series: [owner],
+    }
+}
+
+accounts! {
+    /// Moves a round's expired prizes to the latest round of its series.
+    pub struct Rollover<'info> {
+        pub pot_authority: [],
+        pub pot: [mut],
+        pub latest_round: [mut, owner],
+        pub latest_pot: [mut],
+        pub token_program: [address(spl_token::id())],
+    }
+}
+
+/// Returns prizes left unclaimed after the deadline. One-off lotteries keep
+/// them as fees for the admin; series rounds hand them to the latest round of
+/// the series, whose pot they join.
+fn expire_prizes<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, ExpirePrizes<'info>>,
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let ExpirePrizes {
+        lottery: writing_account,
+        results: results_account,
+    } = ctx.accounts.clone();
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    let mut results = DrawResults::try_from_slice(*results_account.data.borrow())?;
+    if fanilotto_data.is_ended == 0 {
+        msg!("The draw's tickets haven't all been tallied");
+        return Err(LotteryError::NotTallied.into());
+    }
+    if results.is_expired != 0 {
+        msg!("Prizes have already expired");
+        return Err(LotteryError::ClaimPeriodEnded.into());
+    }
+    if Clock::get()?.unix_timestamp <= results.claim_deadline {
+        msg!("The claim period hasn't ended yet");
+        return Err(LotteryError::ClaimPeriodNotEnded.into());
+    }
+
+    let unclaimed = results.unclaimed;
+    if fanilotto_data.series == Pubkey::default() {
+        fanilotto_data.fees_collected += unclaimed;
+    } else {
+        let SeriesAccount {
+            series: series_account,
+        } = ctx.try_remaining()?;
+        if *series_account.key != fanilotto_data.series {
+            msg!("series_account isn't the lottery's series");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let series = LotterySeries::try_from_slice(*series_account.data.borrow())?;
+        let latest_round = series.current_round - 1;
+        if latest_round == fanilotto_data.round {
+            fanilotto_data.amount_in_pot += unclaimed;
+        } else {
+            let Rollover {
+                pot_authority,
+                pot: lottery_pool_token_account,
+                latest_round: latest_round_account,
+                latest_pot: latest_pot_account,
+                token_program,
+            } = ctx.try_remaining()?;
+            if find_round_address(program_id, series_account.key, latest_round).0
+                != *latest_round_account.key
+            {
+                msg!("latest_round_account isn't the latest round of the series");
+                return Err(LotteryError::InvalidRoundAccount.into());
+            }
+            let (latest_pot_authority, _) =
+                find_pot_authority(program_id, latest_round_account.key);
+            if get_associated_token_address(&latest_pot_authority, &fanilotto_data.token_mint)
+                != *latest_pot_account.key
+            {
+                msg!("latest_pot_account isn't the latest round's pot");
+                return Err(ProgramError::InvalidAccountData);
+            }
+            let mut latest_round_data =
+                LotteryDetails::try_from_slice(*latest_round_account.data.borrow())?;
+            if unclaimed > 0 {
+                transfer_from_pot(
+                    program_id,
+                    writing_account.key,
+                    &fanilotto_data.token_mint,
+                    &pot_authority,
+                    &lottery_pool_token_account,
+                    &latest_pot_account,
+                    &token_program,
+                    unclaimed,
+                )?;
+            }
+            latest_round_data.amount_in_pot += unclaimed;
+            latest_round_data
+                .serialize(&mut &mut latest_round_account.try_borrow_mut_data()?[..])?;
+        }
+    }
+    msg!("{} in unclaimed prizes expired", unclaimed);
+
+    results.unclaimed = 0;
+    results.is_expired = 1;
+    results.serialize(&mut &mut results_account.try_borrow_mut_data()?[..])?;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct CommitRequest {
+    pub commitment: [u8; 32],
+}
+
+accounts! {
+    pub struct CommitRandomness<'info> {
+        pub lottery: [mut, owner, has_one(LotteryDetails::admin)],
+        pub admin: [signer],
+    }
+}
+
+/// Commits the admin to the hash of a secret. Must happen before ticket sales
+/// close, and only once per lottery.
+fn commit_randomness(ctx: Context<CommitRandomness>, instruction_data: &[u8]) -> ProgramResult {
+    let writing_account = &ctx.accounts.lottery;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.randomness_oracle != Pubkey::default() {
+        msg!("The lottery is drawn by a randomness oracle");
+        return Err(LotteryError::WrongRandomnessSource.into());
+    }
+    if fanilotto_data.commit_slot != 0 {
+        msg!("Randomness has already been committed");
+        return Err(LotteryError::AlreadyCommitted.into());
+    }
+    let clock = Clock::get()?;
+    if clock.unix_timestamp >= parse_timestamp(&fanilotto_data.lottery_end)? {
+        msg!("Randomness should be committed before lottery_end");
+        return Err(LotteryError::LotteryClosed.into());
+    }
+
+    let input_data = CommitRequest::try_from_slice(instruction_data)?;
+    fanilotto_data.randomness_commitment = input_data.commitment;
+    fanilotto_data.commit_slot = clock.slot;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+accounts! {
+    pub struct CloseSales<'info> {
+        pub lottery: [mut, owner],
+        /// The lottery's admin, or anyone once `CLOSE_GRACE` has passed.
+        pub closer: [signer],
+    }
+}
+
+/// Records the slot ticket sales closed at, once `lottery_end` has passed.
+/// The hash of the next slot, unknown until then, goes into the randomness
+/// revealed after it. Until `CLOSE_GRACE` after `lottery_end` only the admin
+/// can call this, so nobody else can start the reveal window on them.
+fn close_sales(ctx: Context<CloseSales>, _instruction_data: &[u8]) -> ProgramResult {
+    let CloseSales {
+        lottery: writing_account,
+        closer,
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.randomness_oracle != Pubkey::default() {
+        msg!("The lottery is drawn by a randomness oracle");
+        return Err(LotteryError::WrongRandomnessSource.into());
+    }
+    if fanilotto_data.commit_slot == 0 {
+        msg!("Randomness hasn't been committed");
+        return Err(LotteryError::NotCommitted.into());
+    }
+    if fanilotto_data.close_slot != 0 || fanilotto_data.is_refunding != 0 {
+        msg!("Sales have already been closed");
+        return Err(LotteryError::LotteryClosed.into());
+    }
+    let clock = Clock::get()?;
+    let lottery_end = parse_timestamp(&fanilotto_data.lottery_end)?;
+    if clock.unix_timestamp < lottery_end {
+        msg!("Sales can only be closed after lottery_end");
+        return Err(LotteryError::LotteryNotClosed.into());
+    }
+    if *closer.key != fanilotto_data.admin
+        && clock.unix_timestamp < lottery_end.saturating_add(randomness::CLOSE_GRACE)
+    {
+        msg!("Only the admin can close sales within CLOSE_GRACE of lottery_end");
+        return Err(LotteryError::CloseGraceNotPassed.into());
+    }
+
+    fanilotto_data.close_slot = clock.slot;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct RevealRequest {
+    pub secret: Vec<u8>,
+}
+
+accounts! {
+    /// Followed by `BondAccounts` if the lottery has a bond.
+    pub struct RevealRandomness<'info> {
+        pub lottery: [mut, owner, has_one(LotteryDetails::admin)],
+        pub admin: [signer],
+        pub slot_hashes: [address(slot_hashes::id())],
+    }
+}
+
+/// Reveals the committed secret within `REVEAL_SLOTS` of the close slot,
+/// fixing the lottery's randomness and returning the admin's bond.
+fn reveal_randomness<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, RevealRandomness<'info>>,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let RevealRandomness {
+        lottery: writing_account,
+        slot_hashes: slot_hashes_account,
+        ..
+    } = ctx.accounts.clone();
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.commit_slot == 0 {
+        msg!("Randomness hasn't been committed");
+        return Err(LotteryError::NotCommitted.into());
+    }
+    if fanilotto_data.is_revealed != 0 || fanilotto_data.is_refunding != 0 {
+        msg!("Randomness can no longer be revealed");
+        return Err(LotteryError::AlreadyRevealed.into());
+    }
+    if fanilotto_data.close_slot == 0 {
+        msg!("Randomness can only be revealed after CloseSales");
+        return Err(LotteryError::LotteryNotClosed.into());
+    }
+    if Clock::get()?.slot > fanilotto_data.close_slot + randomness::REVEAL_SLOTS {
+        msg!("The reveal window has passed");
+        return Err(LotteryError::RevealWindowPassed.into());
+    }
+
+    let input_data = RevealRequest::try_from_slice(instruction_data)?;
+    if randomness::commitment(&input_data.secret) != fanilotto_data.randomness_commitment {
+        msg!("Secret doesn't match the commitment");
+        return Err(LotteryError::InvalidReveal.into());
+    }
+    // Within the window SlotHashes reaches back to the close slot, so only
+    // the next slot's hash can be missing.
+    let slot_hash = randomness::slot_hash_after(
+        &slot_hashes_account.data.borrow(),
+        fanilotto_data.close_slot,
+    )
+    .ok_or_else(|| {
+        msg!("The slot after the close slot has no hash yet");
+        ProgramError::from(LotteryError::LotteryNotClosed)
+    })?;
+
+    if fanilotto_data.bond_amount > 0 {
+        let BondAccounts {
+            admin_tokens: admin_token_account,
+            pot_authority,
+            pot: lottery_pool_token_account,
+            token_program,
+        } = ctx.try_remaining()?;
+        transfer_from_pot(
+            program_id,
+            writing_account.key,
+            &fanilotto_data.token_mint,
+            &pot_authority,
+            &lottery_pool_token_account,
+            &admin_token_account,
+            &token_program,
+            fanilotto_data.bond_amount,
+        )?;
+    }
+
+    fanilotto_data.randomness =
+        randomness::derive_randomness(&slot_hash, writing_account.key, &input_data.secret);
+    fanilotto_data.is_revealed = 1;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
+}
+
+accounts! {
+    /// Followed, when part of the bond goes back to the admin, by
+    /// [`BondAccounts`]. See [`LotteryDetails::unshared_bond`].
+    pub struct SlashBond<'info> {
+        pub lottery: [mut, owner],
+    }
+}
+
+/// Puts a lottery whose randomness wasn't revealed in time into refund mode:
+/// within `REVEAL_SLOTS` of the close slot, or, if sales weren't closed,
+/// `REVEAL_WINDOW` of `lottery_end`. Anyone can call this; the admin's bond is
+/// shared evenly between the tickets, and what can't be goes back to them.
+fn slash_bond<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, SlashBond<'info>>,
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    // Cloned, since the bond accounts are parsed from `ctx` further down.
+    let writing_account = ctx.accounts.lottery.clone();
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.is_revealed != 0 || fanilotto_data.is_refunding != 0 {
+        msg!("Lottery randomness was revealed or is already refunding");
+        return Err(LotteryError::AlreadyRevealed.into());
+    }
+    let clock = Clock::get()?;
+    let window_passed = if fanilotto_data.close_slot != 0 {
+        clock.slot > fanilotto_data.close_slot + randomness::REVEAL_SLOTS
+    } else {
+        clock.unix_timestamp
+            > parse_timestamp(&fanilotto_data.lottery_end)?
+                .saturating_add(randomness::REVEAL_WINDOW)
+    };
+    if !window_passed {
+        msg!("The reveal window hasn't passed yet");
+        return Err(LotteryError::RevealWindowNotPassed.into());
+    }
+    msg!(
+        "Randomness wasn't revealed, slashing bond of {}",
+        fanilotto_data.bond_amount
+    );
+
+    let unshared_bond = fanilotto_data.unshared_bond();
+    if unshared_bond > 0 {
+        let BondAccounts {
+            admin_tokens: admin_token_account,
+            pot_authority,
+            pot: lottery_pool_token_account,
+            token_program,
+        } = ctx.try_remaining()?;
+        if get_associated_token_address(&fanilotto_data.admin, &fanilotto_data.token_mint)
+            != *admin_token_account.key
+        {
+            msg!("admin_token_account isn't the admin's associated token account");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        transfer_from_pot(
             program_id,
-            accounts,
-            &instruction_data[1..instruction_data.len()],
-        );
+            writing_account.key,
+            &fanilotto_data.token_mint,
+            &pot_authority,
+            &lottery_pool_token_account,
+            &admin_token_account,
+            &token_program,
+            unshared_bond,
+        )?;
+        fanilotto_data.bond_amount -= unshared_bond;
     }
-    msg!("Didn't found the entrypoint required");
-    Err(ProgramError::InvalidInstructionData)
+
+    start_refunding(&mut fanilotto_data)?;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
 }
-entrypoint!(process_instruction);
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-struct LotteryDetails {
-    pub admin: Pubkey,
-    pub name: String,
-    pub is_initialized: u64,
-    pub is_ended: u64,
-    pub lottery_start: String,
-    pub lottery_end: String,
-    pub ticket_price: u64,
-    pub amount_in_pot: u64,
-    pub total_entries: u64,
-    pub token_mint: Pubkey,
+/// Puts a lottery into refund mode, ending it. Ticket money is paid back,
+/// including referral rewards, which are forfeited. Sponsors can take back
+/// what they added to the pot with `ReclaimSponsorship`; the rest of a series
+/// round's pot, like a carried-over jackpot, rolls over to the next round.
+fn start_refunding(fanilotto_data: &mut LotteryDetails) -> ProgramResult {
+    let ticket_sales = fanilotto_data
+        .ticket_price
+        .checked_mul(fanilotto_data.total_entries)
+        .ok_or(ProgramError::InvalidArgument)?;
+    fanilotto_data.amount_in_pot = ticket_sales
+        .checked_sub(fanilotto_data.referral_rewards)
+        .and_then(|ticket_money| fanilotto_data.amount_in_pot.checked_sub(ticket_money))
+        .ok_or(ProgramError::InsufficientFunds)?;
+    fanilotto_data.referral_rewards = 0;
+    fanilotto_data.is_refunding = 1;
+    fanilotto_data.is_ended = 1;
+    Ok(())
 }
 
-fn create_lottery(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    instruction_data: &[u8],
+accounts! {
+    /// Followed, for lotteries with a bond that hasn't been returned, by the
+    /// admin's token account and the pot as [`BondAccounts`].
+    pub struct CancelLottery<'info> {
+        pub lottery: [mut, owner],
+    }
+}
+
+/// Puts a lottery that didn't sell its `min_entries` by `lottery_end` into
+/// refund mode. Anyone can call this. The admin didn't fail the lottery, so
+/// their bond goes back to them instead of to the tickets.
+fn cancel_lottery<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, CancelLottery<'info>>,
+    _instruction_data: &[u8],
 ) -> ProgramResult {
-    let accounts_iter = &mut accounts.iter();
-    let writing_account = next_account_info(accounts_iter)?;
-    let creator_account = next_account_info(accounts_iter)?;
-    if !creator_account.is_signer {
-        msg!("creator_account should be signer");
-        return Err(ProgramError::IncorrectProgramId);
+    let program_id = ctx.program_id;
+    // Cloned, since the bond accounts are parsed from `ctx` further down.
+    let writing_account = ctx.accounts.lottery.clone();
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.is_ended != 0 {
+        msg!("The lottery has already been drawn or is refunding");
+        return Err(LotteryError::AlreadyDrawn.into());
+    }
+    if Clock::get()?.unix_timestamp < parse_timestamp(&fanilotto_data.lottery_end)? {
+        msg!("Ticket sales are still running");
+        return Err(LotteryError::LotteryNotClosed.into());
+    }
+    if fanilotto_data.total_entries >= fanilotto_data.min_entries {
+        msg!(
+            "The lottery sold its {} min_entries",
+            fanilotto_data.min_entries
+        );
+        return Err(LotteryError::MinEntriesReached.into());
+    }
+
+    // A revealed lottery already returned the bond.
+    if fanilotto_data.bond_amount > 0 && fanilotto_data.is_revealed == 0 {
+        let BondAccounts {
+            admin_tokens: admin_token_account,
+            pot_authority,
+            pot: lottery_pool_token_account,
+            token_program,
+        } = ctx.try_remaining()?;
+        if get_associated_token_address(&fanilotto_data.admin, &fanilotto_data.token_mint)
+            != *admin_token_account.key
+        {
+            msg!("admin_token_account isn't the admin's associated token account");
+            return Err(ProgramError::InvalidAccountData);
+        }
+        transfer_from_pot(
+            program_id,
+            writing_account.key,
+            &fanilotto_data.token_mint,
+            &pot_authority,
+            &lottery_pool_token_account,
+            &admin_token_account,
+            &token_program,
+            fanilotto_data.bond_amount,
+        )?;
     }
+    // Nothing of the bond is left to share between the refunds.
+    fanilotto_data.bond_amount = 0;
+
+    msg!(
+        "Only {} of {} min_entries were sold, refunding the tickets",
+        fanilotto_data.total_entries,
+        fanilotto_data.min_entries
+    );
+    start_refunding(&mut fanilotto_data)?;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
+}
 
-    if writing_account.owner != program_id {
-        msg!("writing_account isn't owned by program");
-        return Err(ProgramError::IncorrectProgramId);
+accounts! {
+    /// Followed, for lotteries with ticket tokens, by [`TicketHolder`].
+    pub struct ClaimRefund<'info> {
+        pub lottery: [owner],
+        pub ticket: [mut, owner],
+        pub player: [signer],
+        pub pot_authority: [],
+        pub pot: [mut],
+        pub player_tokens: [mut],
+        pub token_program: [address(spl_token::id())],
     }
-    let mut input_data = LotteryDetails::try_from_slice(&instruction_data)
-        .expect("Instruction data not serialized properly");
+}
 
-    if input_data.admin != *creator_account.key {
-        msg!("Invaild instruction data");
-        return Err(ProgramError::InvalidInstructionData);
+/// Refunds a ticket of a lottery in refund mode: its price plus its share of
+/// the bond, if it was slashed, which `slash_bond` left a multiple of the
+/// tickets.
+fn claim_refund(ctx: Context<ClaimRefund>, instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let ClaimRefund {
+        lottery: writing_account,
+        ticket: ticket_account,
+        player,
+        pot_authority,
+        pot: lottery_pool_token_account,
+        player_tokens: player_token_account,
+        token_program,
+    } = &*ctx.accounts;
+
+    let fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.is_refunding == 0 {
+        msg!("Lottery isn't refunding");
+        return Err(LotteryError::NotRefunding.into());
     }
-    let rent_exemption = Rent::get()?.minimum_balance(writing_account.data_len());
-    if **writing_account.lamports.borrow() < rent_exemption {
-        msg!("The balance of writing_account should be more then rent_exemption");
-        return Err(ProgramError::InsufficientFunds);
+    let ticket_index = claimable_ticket(
+        program_id,
+        writing_account.key,
+        &fanilotto_data,
+        ticket_account,
+        player.key,
+        instruction_data,
+        ctx.remaining_accounts,
+    )?;
+    if get_associated_token_address(player.key, &fanilotto_data.token_mint)
+        != *player_token_account.key
+    {
+        msg!("player_token_account isn't the player's associated token account");
+        return Err(ProgramError::InvalidAccountData);
     }
-    input_data.amount_in_pot = 0;
-    input_data.total_entries = 0;
-    input_data.is_initialized = 1;
-    input_data.is_ended = 0;
 
-    input_data.serialize(&mut &mut writing_account.try_borrow_mut_data()?[..])?;
+    let refund =
+        fanilotto_data.ticket_price + fanilotto_data.bond_amount / fanilotto_data.total_entries;
+    transfer_from_pot(
+        program_id,
+        writing_account.key,
+        &fanilotto_data.token_mint,
+        pot_authority,
+        lottery_pool_token_account,
+        player_token_account,
+        token_program,
+        refund,
+    )?;
+    msg!("Refund of {} claimed", refund);
+
+    mark_claimed(&fanilotto_data, ticket_account, ticket_index)
+}
+
+accounts! {
+    pub struct VoidTicket<'info> {
+        pub lottery: [owner],
+        pub ticket: [mut, owner],
+        pub ticket_mint: [owner(spl_token::id())],
+    }
+}
+
+/// Voids a ticket whose token was burned, so the draw skips it and nobody can
+/// claim it. Anyone can call this.
+fn void_ticket(ctx: Context<VoidTicket>, _instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let VoidTicket {
+        lottery: writing_account,
+        ticket: ticket_account,
+        ticket_mint,
+    } = &*ctx.accounts;
+
+    let fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.ticket_nfts == 0 {
+        msg!("The lottery doesn't mint ticket tokens");
+        return Err(ProgramError::InvalidArgument);
+    }
+    let mut ticket_data = TicketDetails::try_from_slice(*ticket_account.data.borrow())?;
+    if find_ticket_address(program_id, writing_account.key, ticket_data.ticket_index).0
+        != *ticket_account.key
+    {
+        msg!("ticket doesn't belong to this lottery");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if nft::find_ticket_mint(program_id, ticket_account.key).0 != *ticket_mint.key {
+        msg!("ticket_mint isn't the ticket's mint");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if ticket_data.claimed != 0 {
+        msg!("Ticket has already been claimed or voided");
+        return Err(LotteryError::PrizeAlreadyClaimed.into());
+    }
+    let mint = spl_token::state::Mint::unpack(&ticket_mint.try_borrow_data()?)?;
+    if mint.supply != 0 {
+        msg!("The ticket's token hasn't been burned");
+        return Err(LotteryError::TicketNotBurned.into());
+    }
+
+    ticket_data.claimed = TICKET_VOID;
+    ticket_data.serialize(&mut &mut ticket_account.try_borrow_mut_data()?[..])?;
     Ok(())
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-struct WithdrawRequest {
-    pub amount: u64,
+accounts! {
+    pub struct ClaimReferralRewards<'info> {
+        pub lottery: [mut, owner],
+        pub referral: [mut, owner, seeds(b"referral", lottery.key, referrer.key)],
+        pub referrer: [signer],
+        pub pot_authority: [],
+        pub pot: [mut],
+        pub referrer_tokens: [mut],
+        pub token_program: [address(spl_token::id())],
+    }
 }
 
-fn withdraw(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    instruction_data: &[u8],
+/// Pays a referrer the rewards they earned in a lottery, once it has been
+/// drawn.
+fn claim_referral_rewards(
+    ctx: Context<ClaimReferralRewards>,
+    _instruction_data: &[u8],
 ) -> ProgramResult {
-    let accounts_iter = &mut accounts.iter();
-    let writing_account = next_account_info(accounts_iter)?;
-    let admin_account = next_account_info(accounts_iter)?;
-    let token_mint = next_account_info(accounts_iter)?;
-    let token_program = next_account_info(accounts_iter)?;
-    let lotto_ata = next_account_info(accounts_iter)?;
-    let admin_ata = next_account_info(accounts_iter)?;
-
-    if writing_account.owner != program_id {
-        msg!("writing_account isn't owned by program");
-        return Err(ProgramError::IncorrectProgramId);
-    }
-    if !admin_account.is_signer {
-        msg!("admin should be signer");
-        return Err(ProgramError::IncorrectProgramId);
-    }
-    let fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())
-        .expect("Error deserialaizing data");
-
-    if fanilotto_data.admin != *admin_account.key {
-        msg!("Only the account admin can withdraw");
+    let program_id = ctx.program_id;
+    let ClaimReferralRewards {
+        lottery: writing_account,
+        referral: referral_account,
+        referrer,
+        pot_authority,
+        pot: lottery_pool_token_account,
+        referrer_tokens: referrer_token_account,
+        token_program,
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.is_ended == 0 {
+        msg!("The lottery hasn't been drawn yet");
+        return Err(LotteryError::RoundNotDrawn.into());
+    }
+    if fanilotto_data.is_refunding != 0 {
+        msg!("Referral rewards of refunded lotteries are forfeited");
+        return Err(LotteryError::Refunding.into());
+    }
+    let mut record = referral::ReferralRecord::try_from_slice(*referral_account.data.borrow())?;
+    if record.rewards == 0 {
+        msg!("No referral rewards to claim");
+        return Err(ProgramError::InsufficientFunds);
+    }
+    if get_associated_token_address(referrer.key, &fanilotto_data.token_mint)
+        != *referrer_token_account.key
+    {
+        msg!("referrer_token_account isn't the referrer's associated token account");
         return Err(ProgramError::InvalidAccountData);
     }
-    let input_data = WithdrawRequest::try_from_slice(&instruction_data)
-        .expect("Instruction data serialization didn't worked");
 
-    // let rent_exemption = Rent::get()?.minimum_balance(writing_account.data_len());
-    // if **writing_account.lamports.borrow() - rent_exemption < input_data.amount {
-    //     msg!("Insufficent balance");
-    //     return Err(ProgramError::InsufficientFunds);
-    // }
+    let amount = record.rewards;
+    transfer_from_pot(
+        program_id,
+        writing_account.key,
+        &fanilotto_data.token_mint,
+        pot_authority,
+        lottery_pool_token_account,
+        referrer_token_account,
+        token_program,
+        amount,
+    )?;
+    msg!("Referral rewards of {} claimed", amount);
 
-    let winner_token_account_address =
-        get_associated_token_address(writing_account.key, token_mint.key);
-    let admin_account_address = get_associated_token_address(admin_account.key, token_mint.key);
-    msg!("lotto ATA: {:?}", winner_token_account_address);
-    msg!("Admin ATA: {:?}", &admin_account_address);
+    record.rewards = 0;
+    record.serialize(&mut &mut referral_account.try_borrow_mut_data()?[..])?;
+    fanilotto_data.referral_rewards -= amount;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
+}
 
-    let transfer_to_winning_player_account = transfer_checked(
-        token_program.key,
-        &lotto_ata.key,
-        token_mint.key,
-        &winner_token_account_address,
-        admin_account.key,
-        &[],
-        input_data.amount,
-        9,
-    )?;
+accounts! {
+    pub struct SponsorPot<'info> {
+        pub lottery: [mut, owner],
+        pub sponsorship: [mut, seeds(b"sponsor", lottery.key, sponsor.key)],
+        pub sponsor: [mut, signer],
+        pub sponsor_tokens: [mut, token_authority(*sponsor.key)],
+        pub pot: [mut],
+        pub system_program: [address(system_program::id())],
+        pub token_program: [address(spl_token::id())],
+    }
+}
 
-    invoke_signed(
-        &transfer_to_winning_player_account,
+/// Adds a sponsor's tokens to the pot of a lottery that hasn't been drawn,
+/// without a ticket. The sponsor pays for their sponsorship record on their
+/// first contribution.
+fn sponsor_pot(ctx: Context<SponsorPot>, instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let SponsorPot {
+        lottery: writing_account,
+        sponsorship: sponsorship_account,
+        sponsor,
+        sponsor_tokens: sponsor_token_account,
+        pot: lottery_pool_token_account,
+        system_program,
+        token_program,
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    let input_data = sponsor::SponsorRequest::try_from_slice(instruction_data)?;
+    if fanilotto_data.is_ended != 0 {
+        msg!("The lottery has already been drawn");
+        return Err(LotteryError::AlreadyDrawn.into());
+    }
+    if input_data.amount == 0 {
+        msg!("Sponsors should add at least one token");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.name.len() > sponsor::MAX_NAME_LEN {
+        msg!("Sponsor names are at most {} bytes", sponsor::MAX_NAME_LEN);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let (pot_authority, _) = find_pot_authority(program_id, writing_account.key);
+    if get_associated_token_address(&pot_authority, &fanilotto_data.token_mint)
+        != *lottery_pool_token_account.key
+    {
+        msg!("lottery_pool_token_account isn't the lottery's pot");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    invoke(
+        &transfer(
+            token_program.key,
+            sponsor_token_account.key,
+            lottery_pool_token_account.key,
+            sponsor.key,
+            &[],
+            input_data.amount,
+        )?,
         &[
+            sponsor_token_account.clone(),
+            lottery_pool_token_account.clone(),
+            sponsor.clone(),
             token_program.clone(),
-            admin_ata.clone(),
-            lotto_ata.clone(),
-            admin_account.clone(),
         ],
-        &[],
     )?;
-    // **writing_account.try_borrow_mut_lamports()? -= input_data.amount;
 
-    // **admin_account.try_borrow_mut_lamports()? += input_data.amount;
+    let mut sponsorship = if sponsorship_account.owner == program_id {
+        sponsor::Sponsorship::try_from_slice(*sponsorship_account.data.borrow())?
+    } else {
+        let sponsorship = sponsor::Sponsorship {
+            lottery: *writing_account.key,
+            sponsor: *sponsor.key,
+            amount: 0,
+            name: input_data.name,
+        };
+        create_pda_account(
+            sponsor,
+            sponsorship_account,
+            system_program,
+            sponsorship.try_to_vec()?.len(),
+            program_id,
+            &[
+                b"sponsor",
+                writing_account.key.as_ref(),
+                sponsor.key.as_ref(),
+                &[ctx.bumps["sponsorship"]],
+            ],
+        )?;
+        sponsorship
+    };
+    sponsorship.amount += input_data.amount;
+    sponsorship.serialize(&mut &mut sponsorship_account.try_borrow_mut_data()?[..])?;
+
+    fanilotto_data.amount_in_pot += input_data.amount;
+    fanilotto_data.sponsored_amount += input_data.amount;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    emit(&PotSponsored {
+        lottery: *writing_account.key,
+        sponsor: *sponsor.key,
+        name: sponsorship.name,
+        amount: input_data.amount,
+        amount_in_pot: fanilotto_data.amount_in_pot,
+    })
+}
 
-    Ok(())
+accounts! {
+    pub struct ReclaimSponsorship<'info> {
+        pub lottery: [mut, owner],
+        pub sponsorship: [mut, owner, seeds(b"sponsor", lottery.key, sponsor.key)],
+        pub sponsor: [signer],
+        pub pot_authority: [],
+        pub pot: [mut],
+        pub sponsor_tokens: [mut, token_authority(*sponsor.key)],
+        pub token_program: [address(spl_token::id())],
+    }
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-struct TicketDetails {
-    pub player: String,
-    pub ticket_count: u64,
-    pub ticket_number_arr: [u8; 128],
+/// Gives a sponsor of a lottery in refund mode back what they added to its
+/// pot. A refunding series round keeps it when the rest of its pot is carried
+/// over to the next round.
+fn reclaim_sponsorship(
+    ctx: Context<ReclaimSponsorship>,
+    _instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let ReclaimSponsorship {
+        lottery: writing_account,
+        sponsorship: sponsorship_account,
+        pot_authority,
+        pot: lottery_pool_token_account,
+        sponsor_tokens: sponsor_token_account,
+        token_program,
+        ..
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.is_refunding == 0 {
+        msg!("Lottery isn't refunding");
+        return Err(LotteryError::NotRefunding.into());
+    }
+    let mut sponsorship = sponsor::Sponsorship::try_from_slice(*sponsorship_account.data.borrow())?;
+    if sponsorship.amount == 0 {
+        msg!("The sponsorship has already been reclaimed");
+        return Err(LotteryError::PrizeAlreadyClaimed.into());
+    }
+    transfer_from_pot(
+        program_id,
+        writing_account.key,
+        &fanilotto_data.token_mint,
+        pot_authority,
+        lottery_pool_token_account,
+        sponsor_token_account,
+        token_program,
+        sponsorship.amount,
+    )?;
+    msg!("Sponsorship of {} reclaimed", sponsorship.amount);
+
+    fanilotto_data.amount_in_pot -= sponsorship.amount;
+    fanilotto_data.sponsored_amount -= sponsorship.amount;
+    sponsorship.amount = 0;
+    sponsorship.serialize(&mut &mut sponsorship_account.try_borrow_mut_data()?[..])?;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    Ok(())
 }
 
-fn play(program_id: &Pubkey, accounts: &[AccountInfo], instruction_data: &[u8]) -> ProgramResult {
-    let accounts_iter = &mut accounts.iter();
-    let writing_account = next_account_info(accounts_iter)?;
-    let player_program_account = next_account_info(accounts_iter)?;
-    let player = next_account_info(accounts_iter)?; //wallet of user //signer
-    let player_token_account = &accounts[6];
-    let token_program = &accounts[5];
-    let lottery_pool_token_account = &accounts[4];
+accounts! {
+    pub struct SponsorPrize<'info> {
+        pub lottery: [mut, owner],
+        /// The lottery's next sponsored prize, at its `sponsored_prizes`.
+        pub prize: [mut],
+        pub sponsor: [mut, signer],
+        pub prize_mint: [owner(spl_token::id())],
+        pub sponsor_tokens: [
+            mut,
+            token_mint(*prize_mint.key),
+            token_authority(*sponsor.key),
+        ],
+        pub pot_authority: [],
+        /// The pot authority's associated token account for `prize_mint`,
+        /// created if it doesn't exist yet.
+        pub vault: [mut],
+        pub system_program: [address(system_program::id())],
+        pub token_program: [address(spl_token::id())],
+        pub associated_token_program: [address(spl_associated_token_account::id())],
+        pub rent: [address(sysvar::rent::id())],
+    }
+}
 
-    if writing_account.owner != program_id {
-        msg!("writing_account isn't owned by program");
-        return Err(ProgramError::IncorrectProgramId);
+/// Puts up a prize in another mint than the lottery's for the winners of one
+/// prize tier. The sponsor pays for the prize record and, for a new mint, the
+/// vault.
+fn sponsor_prize(ctx: Context<SponsorPrize>, instruction_data: &[u8]) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let SponsorPrize {
+        lottery: writing_account,
+        prize: prize_account,
+        sponsor,
+        prize_mint,
+        sponsor_tokens: sponsor_token_account,
+        pot_authority,
+        vault,
+        system_program,
+        token_program,
+        associated_token_program,
+        rent,
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    let input_data = sponsor::PrizeRequest::try_from_slice(instruction_data)?;
+    if fanilotto_data.is_ended != 0 {
+        msg!("The lottery has already been drawn");
+        return Err(LotteryError::AlreadyDrawn.into());
+    }
+    if fanilotto_data.pick_count == 0 {
+        msg!("Only pick-N lotteries have prize tiers");
+        return Err(LotteryError::InvalidGameMode.into());
+    }
+    let game = PickGame::new(fanilotto_data.pick_count, fanilotto_data.max_number);
+    if usize::from(input_data.tier) >= game.tier_count() {
+        msg!("The lottery has {} prize tiers", game.tier_count());
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if input_data.amount == 0 {
+        msg!("Sponsored prizes should be at least one token");
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    if *prize_mint.key == fanilotto_data.token_mint {
+        msg!("Prizes in the lottery's own mint go to the pot with SponsorPot");
+        return Err(ProgramError::InvalidArgument);
     }
-    if player_program_account.owner != program_id {
-        msg!("player_program_account isn't owned by program");
-        return Err(ProgramError::IncorrectProgramId);
+    let prize_index = fanilotto_data.sponsored_prizes;
+    let (prize_key, prize_bump) =
+        sponsor::find_prize_address(program_id, writing_account.key, prize_index);
+    if prize_key != *prize_account.key {
+        msg!("prize_account isn't the lottery's next sponsored prize");
+        return Err(ProgramError::InvalidSeeds);
     }
-    if !player.is_signer {
-        msg!("player should be signer");
-        return Err(ProgramError::IncorrectProgramId);
+    if find_pot_authority(program_id, writing_account.key).0 != *pot_authority.key {
+        msg!("pot_authority isn't the lottery's pot authority");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if get_associated_token_address(pot_authority.key, prize_mint.key) != *vault.key {
+        msg!("vault isn't the lottery's vault for the prize's mint");
+        return Err(ProgramError::InvalidAccountData);
     }
 
-    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())
-        .expect("Error deserialaizing lotto data");
+    if vault.data_is_empty() {
+        invoke(
+            &create_associated_token_account(sponsor.key, pot_authority.key, prize_mint.key),
+            &[
+                sponsor.clone(),
+                vault.clone(),
+                pot_authority.clone(),
+                prize_mint.clone(),
+                system_program.clone(),
+                token_program.clone(),
+                rent.clone(),
+                associated_token_program.clone(),
+            ],
+        )?;
+    }
+    invoke(
+        &transfer(
+            token_program.key,
+            sponsor_token_account.key,
+            vault.key,
+            sponsor.key,
+            &[],
+            input_data.amount,
+        )?,
+        &[
+            sponsor_token_account.clone(),
+            vault.clone(),
+            sponsor.clone(),
+            token_program.clone(),
+        ],
+    )?;
 
-    let mut ticket_data =
-        TicketDetails::try_from_slice(&instruction_data).expect("Error deserialaizing ticket data");
+    let prize = sponsor::SponsoredPrize {
+        lottery: *writing_account.key,
+        sponsor: *sponsor.key,
+        mint: *prize_mint.key,
+        tier: input_data.tier,
+        amount: input_data.amount,
+        claimed: 0,
+        is_reclaimed: 0,
+    };
+    create_pda_account(
+        sponsor,
+        prize_account,
+        system_program,
+        prize.try_to_vec()?.len(),
+        program_id,
+        &[
+            b"prize",
+            writing_account.key.as_ref(),
+            &prize_index.to_le_bytes(),
+            &[prize_bump],
+        ],
+    )?;
+    prize.serialize(&mut &mut prize_account.try_borrow_mut_data()?[..])?;
+
+    fanilotto_data.sponsored_prizes += 1;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    emit(&PrizeSponsored {
+        lottery: *writing_account.key,
+        sponsor: *sponsor.key,
+        prize_index,
+        mint: prize.mint,
+        tier: prize.tier,
+        amount: prize.amount,
+    })
+}
 
-    let total_amount = fanilotto_data.ticket_price;
-    msg!("Ticket Purchase");
+accounts! {
+    /// Followed, for lotteries with ticket tokens, by [`TicketHolder`].
+    pub struct ClaimSponsoredPrize<'info> {
+        pub lottery: [owner],
+        pub results: [owner, seeds(b"results", lottery.key)],
+        pub prize: [mut, owner],
+        pub share: [mut],
+        pub ticket: [owner],
+        pub player: [mut, signer],
+        pub pot_authority: [],
+        pub vault: [mut],
+        pub player_prize_tokens: [mut],
+        pub token_program: [address(spl_token::id())],
+        pub system_program: [address(system_program::id())],
+    }
+}
 
-    let transfer_to_lottery_pool = transfer(
-        token_program.key,
-        player_token_account.key,
-        lottery_pool_token_account.key,
+/// Pays a winning ticket its share of a sponsored prize, from when every
+/// ticket has been tallied until the results' claim deadline. Shares are
+/// claimed separately from the ticket's prize; the player pays for the
+/// receipt that keeps them from claiming twice.
+fn claim_sponsored_prize(
+    ctx: Context<ClaimSponsoredPrize>,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let ClaimSponsoredPrize {
+        lottery: writing_account,
+        results: results_account,
+        prize: prize_account,
+        share: share_account,
+        ticket: ticket_account,
+        player,
+        pot_authority,
+        vault,
+        player_prize_tokens: player_prize_token_account,
+        token_program,
+        system_program,
+    } = &*ctx.accounts;
+
+    let fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    let results = DrawResults::try_from_slice(*results_account.data.borrow())?;
+    if fanilotto_data.is_ended == 0 {
+        msg!("The draw's tickets haven't all been tallied");
+        return Err(LotteryError::NotTallied.into());
+    }
+    let mut ticket_data = instruction_data;
+    let sponsor::PrizeClaimRequest { prize_index } =
+        sponsor::PrizeClaimRequest::deserialize(&mut ticket_data)?;
+    if sponsor::find_prize_address(program_id, writing_account.key, prize_index).0
+        != *prize_account.key
+    {
+        msg!(
+            "prize_account isn't the lottery's sponsored prize {}",
+            prize_index
+        );
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let mut prize = sponsor::SponsoredPrize::try_from_slice(*prize_account.data.borrow())?;
+    let (ticket_index, _) = owned_ticket(
+        program_id,
+        writing_account.key,
+        &fanilotto_data,
+        ticket_account,
         player.key,
-        &[],
-        total_amount,
+        ticket_data,
+        ctx.remaining_accounts,
     )?;
+    if Clock::get()?.unix_timestamp > results.claim_deadline {
+        msg!("The claim period has ended");
+        return Err(LotteryError::ClaimPeriodEnded.into());
+    }
+    let (share_key, share_bump) =
+        sponsor::find_share_address(program_id, prize_account.key, ticket_index);
+    if share_key != *share_account.key {
+        msg!("share_account isn't the ticket's share receipt");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if share_account.owner == program_id {
+        msg!("The ticket's share has already been claimed");
+        return Err(LotteryError::PrizeAlreadyClaimed.into());
+    }
+    let numbers = ticket_numbers(&fanilotto_data, ticket_account, ticket_index)?;
+    let tier = usize::from(prize.tier);
+    let amount = if results.tier(ticket_index, &numbers) == Some(tier) {
+        sponsor::share(
+            prize.amount,
+            results.winners_per_tier[tier],
+            results.first_winners[tier] == ticket_index,
+        )
+    } else {
+        0
+    };
+    if amount == 0 {
+        msg!("Ticket didn't win a share of the prize");
+        return Err(LotteryError::NotAWinner.into());
+    }
+    if get_associated_token_address(player.key, &prize.mint) != *player_prize_token_account.key {
+        msg!("player_prize_token_account isn't the player's associated token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
 
-    invoke(
-        &transfer_to_lottery_pool,
+    create_pda_account(
+        player,
+        share_account,
+        system_program,
+        sponsor::SHARE_RECEIPT_LEN,
+        program_id,
         &[
-            player_token_account.clone(),
-            lottery_pool_token_account.clone(),
-            token_program.clone(),
-            player.clone(),
+            b"share",
+            prize_account.key.as_ref(),
+            &ticket_index.to_le_bytes(),
+            &[share_bump],
         ],
     )?;
+    sponsor::ShareReceipt {
+        prize: *prize_account.key,
+        ticket_index,
+        amount,
+    }
+    .serialize(&mut &mut share_account.try_borrow_mut_data()?[..])?;
+    transfer_from_pot(
+        program_id,
+        writing_account.key,
+        &prize.mint,
+        pot_authority,
+        vault,
+        player_prize_token_account,
+        token_program,
+        amount,
+    )?;
+    msg!("{} of sponsored prize {} claimed", amount, prize_index);
+
+    prize.claimed += amount;
+    prize.serialize(&mut &mut prize_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
 
-    msg!("{:?}", ticket_data);
+accounts! {
+    /// Followed, unless the lottery is refunding, by [`PrizeResults`].
+    pub struct ReclaimSponsoredPrize<'info> {
+        pub lottery: [owner],
+        pub prize: [mut, owner],
+        pub sponsor: [signer],
+        pub pot_authority: [],
+        pub vault: [mut],
+        pub sponsor_tokens: [mut],
+        pub token_program: [address(spl_token::id())],
+    }
+}
 
-    ticket_data.ticket_number_arr = ticket_data.ticket_number_arr;
-    ticket_data.ticket_number_arr = ticket_data.ticket_number_arr;
-    ticket_data.ticket_count = ticket_data.ticket_count;
-    fanilotto_data.amount_in_pot += total_amount;
-    fanilotto_data.total_entries += 1;
+accounts! {
+    /// The lottery's results, which tell what winners claimed of a prize.
+    pub struct PrizeResults<'info> {
+        pub results: [owner],
+    }
+}
+
+/// Returns to its sponsor what winners didn't claim of a prize by the
+/// deadline, or the whole prize if the lottery is refunding.
+fn reclaim_sponsored_prize<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, ReclaimSponsoredPrize<'info>>,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    // Cloned, since the results are parsed from `ctx` further down.
+    let ReclaimSponsoredPrize {
+        lottery: writing_account,
+        prize: prize_account,
+        sponsor,
+        pot_authority,
+        vault,
+        sponsor_tokens: sponsor_token_account,
+        token_program,
+    } = ctx.accounts.clone();
+
+    let fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    let sponsor::PrizeClaimRequest { prize_index } =
+        sponsor::PrizeClaimRequest::try_from_slice(instruction_data)?;
+    if sponsor::find_prize_address(program_id, writing_account.key, prize_index).0
+        != *prize_account.key
+    {
+        msg!(
+            "prize_account isn't the lottery's sponsored prize {}",
+            prize_index
+        );
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let mut prize = sponsor::SponsoredPrize::try_from_slice(*prize_account.data.borrow())?;
+    if prize.sponsor != *sponsor.key {
+        msg!("Only the prize's sponsor can reclaim it");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if prize.is_reclaimed != 0 {
+        msg!("The prize has already been reclaimed");
+        return Err(LotteryError::PrizeAlreadyClaimed.into());
+    }
+
+    let amount = if fanilotto_data.is_refunding != 0 {
+        prize.amount
+    } else if fanilotto_data.is_ended == 0 {
+        msg!("The lottery hasn't been drawn yet");
+        return Err(LotteryError::RoundNotDrawn.into());
+    } else {
+        let PrizeResults {
+            results: results_account,
+        } = ctx.try_remaining()?;
+        if find_results_address(program_id, writing_account.key).0 != *results_account.key {
+            msg!("The lottery's results should follow the fixed accounts");
+            return Err(ProgramError::InvalidArgument);
+        }
+        let results = DrawResults::try_from_slice(*results_account.data.borrow())?;
+        if Clock::get()?.unix_timestamp <= results.claim_deadline {
+            msg!("The claim period hasn't ended yet");
+            return Err(LotteryError::ClaimPeriodNotEnded.into());
+        }
+        prize.amount - prize.claimed
+    };
+    if get_associated_token_address(sponsor.key, &prize.mint) != *sponsor_token_account.key {
+        msg!("sponsor_token_account isn't the sponsor's associated token account");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    if amount > 0 {
+        transfer_from_pot(
+            program_id,
+            writing_account.key,
+            &prize.mint,
+            &pot_authority,
+            &vault,
+            &sponsor_token_account,
+            &token_program,
+            amount,
+        )?;
+    }
+    msg!("{} of sponsored prize {} reclaimed", amount, prize_index);
 
-    **writing_account.try_borrow_mut_lamports()? += **player_program_account.lamports.borrow();
-    **player_program_account.try_borrow_mut_lamports()? = 0;
+    prize.is_reclaimed = 1;
+    prize.serialize(&mut &mut prize_account.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+accounts! {
+    pub struct RequestRandomness<'info> {
+        pub lottery: [mut, owner],
+        pub payer: [mut, signer],
+        pub requester: [seeds(b"oracle", lottery.key)],
+        pub proof: [mut],
+        pub oracle_program: [],
+        pub system_program: [address(system_program::id())],
+    }
+}
+
+/// Asks the lottery's VRF oracle for randomness once ticket sales have
+/// closed. Anyone can call this; the payer funds the oracle's proof account.
+fn request_randomness(ctx: Context<RequestRandomness>, _instruction_data: &[u8]) -> ProgramResult {
+    let RequestRandomness {
+        lottery: writing_account,
+        payer,
+        requester,
+        proof: proof_account,
+        oracle_program,
+        system_program,
+    } = &*ctx.accounts;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.randomness_oracle == Pubkey::default()
+        || fanilotto_data.randomness_oracle != *oracle_program.key
+    {
+        msg!("oracle_program isn't the lottery's randomness oracle");
+        return Err(LotteryError::WrongRandomnessSource.into());
+    }
+    if fanilotto_data.commit_slot != 0 {
+        msg!("Randomness has already been requested");
+        return Err(LotteryError::AlreadyCommitted.into());
+    }
+    let clock = Clock::get()?;
+    if clock.unix_timestamp < parse_timestamp(&fanilotto_data.lottery_end)? {
+        msg!("Randomness can only be requested after lottery_end");
+        return Err(LotteryError::LotteryNotClosed.into());
+    }
+    let requester_bump = ctx.bumps["requester"];
+
+    invoke_signed(
+        &oracle::request(
+            oracle_program.key,
+            proof_account.key,
+            requester.key,
+            payer.key,
+        ),
+        &[
+            proof_account.clone(),
+            requester.clone(),
+            payer.clone(),
+            system_program.clone(),
+            oracle_program.clone(),
+        ],
+        &[&[b"oracle", writing_account.key.as_ref(), &[requester_bump]]],
+    )?;
 
-    // fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
-    // ticket_data.serialize(&mut &mut player_program_account.try_borrow_mut_data()?[..])?;
+    fanilotto_data.commit_slot = clock.slot;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
     Ok(())
 }
 
+accounts! {
+    /// Followed by the accounts of `DrawLottery`.
+    pub struct FulfillRandomness<'info> {
+        pub proof: [],
+    }
+}
+
+/// Callback for a fulfilled oracle request: verifies the oracle's proof
+/// account and draws the lottery from it. Takes the proof account followed by
+/// the accounts of `DrawLottery`.
+fn fulfill_randomness<'info>(
+    mut ctx: Context<'_, '_, '_, 'info, FulfillRandomness<'info>>,
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let program_id = ctx.program_id;
+    let mut draw: DrawLottery = ctx.try_remaining()?;
+    let proof_account = &ctx.accounts.proof;
+    let writing_account = &draw.lottery;
+
+    let mut fanilotto_data = LotteryDetails::try_from_slice(*writing_account.data.borrow())?;
+    if fanilotto_data.randomness_oracle == Pubkey::default()
+        || fanilotto_data.randomness_oracle != *proof_account.owner
+    {
+        msg!("proof_account isn't owned by the lottery's randomness oracle");
+        return Err(LotteryError::WrongRandomnessSource.into());
+    }
+    if fanilotto_data.commit_slot == 0 {
+        msg!("Randomness hasn't been requested");
+        return Err(LotteryError::NotCommitted.into());
+    }
+    if fanilotto_data.is_revealed != 0 || fanilotto_data.is_refunding != 0 {
+        msg!("Randomness can no longer be fulfilled");
+        return Err(LotteryError::AlreadyRevealed.into());
+    }
+    let requester = oracle::find_requester_address(program_id, writing_account.key).0;
+    if oracle::find_proof_address(proof_account.owner, &requester).0 != *proof_account.key {
+        msg!("proof_account isn't the lottery's proof account");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let proof = oracle::RandomnessProof::try_from_slice(*proof_account.data.borrow())?;
+    if proof.requester != requester || proof.request_slot < fanilotto_data.commit_slot {
+        msg!("proof_account doesn't answer the lottery's request");
+        return Err(LotteryError::InvalidReveal.into());
+    }
+    if proof.is_fulfilled == 0 {
+        msg!("The oracle hasn't fulfilled the request yet");
+        return Err(LotteryError::NotRevealed.into());
+    }
+
+    fanilotto_data.randomness = proof.randomness;
+    fanilotto_data.is_revealed = 1;
+    fanilotto_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
+    draw_lottery(
+        Context::new(program_id, &mut draw, ctx.remaining_accounts, ctx.bumps),
+        instruction_data,
+    )
+}
+
 // Fanitrax
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-struct TraxDetails {
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct TraxDetails {
     pub admin: Pubkey,
     pub is_initialized: u64,
     pub trax_pool_amount: u64,
@@ -285,37 +3165,27 @@ struct TraxDetails {
     pub active_markets: u64,
 }
 
-fn initialize_trax(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    instruction_data: &[u8],
-) -> ProgramResult {
-    let accounts_iter = &mut accounts.iter();
-    let writing_account = next_account_info(accounts_iter)?;
-    let creator_account = next_account_info(accounts_iter)?;
-    if !creator_account.is_signer {
-        msg!("creator_account should be signer");
-        return Err(ProgramError::IncorrectProgramId);
+accounts! {
+    pub struct InitializeTrax<'info> {
+        pub trax: [mut, owner, zero, rent_exempt],
+        pub admin: [signer(ProgramError::IncorrectProgramId)],
     }
+}
+
+fn initialize_trax(ctx: Context<InitializeTrax>, instruction_data: &[u8]) -> ProgramResult {
+    let InitializeTrax {
+        trax: writing_account,
+        admin: creator_account,
+    } = &*ctx.accounts;
 
     // let (escrow_pubkey, bump_seed) = Pubkey::find_program_address(&[&["fanitraxacc"]], program_id);
 
-    if writing_account.owner != program_id {
-        msg!("writing_account isn't owned by program");
-        return Err(ProgramError::IncorrectProgramId);
-    }
-    let mut input_data = TraxDetails::try_from_slice(&instruction_data)
-        .expect("Instruction data not serialized properly");
+    let mut input_data = TraxDetails::try_from_slice(instruction_data)?;
 
     if input_data.admin != *creator_account.key {
         msg!("Invaild instruction data");
         return Err(ProgramError::InvalidInstructionData);
     }
-    let rent_exemption = Rent::get()?.minimum_balance(writing_account.data_len());
-    if **writing_account.lamports.borrow() < rent_exemption {
-        msg!("The balance of writing_account should be more then rent_exemption");
-        return Err(ProgramError::InsufficientFunds);
-    }
 
     input_data.is_initialized = 1;
     input_data.trax_pool_amount = 0;
@@ -326,59 +3196,89 @@ fn initialize_trax(
     Ok(())
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-struct MarketDetails {
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct MarketDetails {
     pub admin: Pubkey,
     pub trax_pub: String,
     pub market_pair: String,
     pub last_price: u64,
     pub upper_floor_limit: u64,
     pub lower_floor_limit: u64,
+    /// `MARKET_OPEN` or `MARKET_PAUSED`.
     pub market_status: u64,
     pub markey_apy: u64,
     pub options_count: u64,
     pub amount_in_pool: u64,
 }
 
-fn create_market(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    instruction_data: &[u8],
-) -> ProgramResult {
-    let accounts_iter = &mut accounts.iter();
-    let writing_account = next_account_info(accounts_iter)?;
-    let creator_account = next_account_info(accounts_iter)?;
-    if !creator_account.is_signer {
-        msg!("creator_account should be signer");
-        return Err(ProgramError::IncorrectProgramId);
-    }
+/// Market status that stops new bets.
+pub const MARKET_PAUSED: u64 = 0;
+/// Market status that takes bets.
+pub const MARKET_OPEN: u64 = 1;
 
-    if writing_account.owner != program_id {
-        msg!("writing_account isn't owned by program");
-        return Err(ProgramError::IncorrectProgramId);
+accounts! {
+    pub struct CreateMarket<'info> {
+        pub market: [mut, owner, zero, rent_exempt],
+        pub admin: [signer(ProgramError::IncorrectProgramId)],
     }
+}
+
+fn create_market(ctx: Context<CreateMarket>, instruction_data: &[u8]) -> ProgramResult {
+    let CreateMarket {
+        market: writing_account,
+        admin: creator_account,
+    } = &*ctx.accounts;
 
-    let mut input_data = MarketDetails::try_from_slice(&instruction_data)
-        .expect("Instruction data serialization didn't worked");
+    let mut input_data = MarketDetails::try_from_slice(instruction_data)?;
 
     if input_data.admin != *creator_account.key {
         msg!("Invaild instruction data");
         return Err(ProgramError::InvalidInstructionData);
     }
-    let rent_exemption = Rent::get()?.minimum_balance(writing_account.data_len());
-    if **writing_account.lamports.borrow() < rent_exemption {
-        msg!("The balance of writing_account should be more then rent_exemption");
-        return Err(ProgramError::InsufficientFunds);
-    }
     input_data.options_count = 0;
     input_data.amount_in_pool = 0;
 
     input_data.serialize(&mut &mut writing_account.try_borrow_mut_data()?[..])?;
+    emit(&MarketCreated {
+        market: *writing_account.key,
+        admin: input_data.admin,
+        market_pair: input_data.market_pair,
+        last_price: input_data.last_price,
+        upper_floor_limit: input_data.upper_floor_limit,
+        lower_floor_limit: input_data.lower_floor_limit,
+    })
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct MarketStatusRequest {
+    pub market_status: u64,
+}
+
+accounts! {
+    pub struct SetMarketStatus<'info> {
+        pub market: [mut, owner, has_one(MarketDetails::admin)],
+        pub admin: [signer],
+    }
+}
+
+/// Lets a market's admin pause or reopen it.
+fn set_market_status(ctx: Context<SetMarketStatus>, instruction_data: &[u8]) -> ProgramResult {
+    let writing_account = &ctx.accounts.market;
+
+    let mut market_data = MarketDetails::try_from_slice(*writing_account.data.borrow())?;
+    let input_data = MarketStatusRequest::try_from_slice(instruction_data)?;
+    if input_data.market_status != MARKET_PAUSED && input_data.market_status != MARKET_OPEN {
+        msg!("Unknown market status {}", input_data.market_status);
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    market_data.market_status = input_data.market_status;
+    market_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
     Ok(())
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-struct OptionsBetDetails {
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct OptionsBetDetails {
     pub player: String,
     pub options_market: String,
     pub options_bet: u64,
@@ -391,34 +3291,28 @@ struct OptionsBetDetails {
     pub options_bet_result: String, //undecided won lost
 }
 
-fn place_option(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    instruction_data: &[u8],
-) -> ProgramResult {
-    let accounts_iter = &mut accounts.iter();
-    let writing_account = next_account_info(accounts_iter)?;
-    let trader_program_account = next_account_info(accounts_iter)?;
-    let trader = next_account_info(accounts_iter)?;
-
-    if writing_account.owner != program_id {
-        msg!("writing_account isn't owned by program");
-        return Err(ProgramError::IncorrectProgramId);
-    }
-    if trader_program_account.owner != program_id {
-        msg!("trader_program_account isn't owned by program");
-        return Err(ProgramError::IncorrectProgramId);
-    }
-    if !trader.is_signer {
-        msg!("trader should be signer");
-        return Err(ProgramError::IncorrectProgramId);
+accounts! {
+    pub struct PlaceOption<'info> {
+        pub market: [mut, owner],
+        pub bet: [mut, owner],
+        pub trader: [signer(ProgramError::IncorrectProgramId)],
     }
+}
 
-    let mut options_market_data = MarketDetails::try_from_slice(*writing_account.data.borrow())
-        .expect("Error deserialaizing data");
+fn place_option(ctx: Context<PlaceOption>, instruction_data: &[u8]) -> ProgramResult {
+    let PlaceOption {
+        market: writing_account,
+        bet: trader_program_account,
+        trader,
+    } = &*ctx.accounts;
+
+    let mut options_market_data = MarketDetails::try_from_slice(*writing_account.data.borrow())?;
+    if options_market_data.market_status != MARKET_OPEN {
+        msg!("The market is paused");
+        return Err(LotteryError::MarketPaused.into());
+    }
 
-    let options_bet_data =
-        OptionsBetDetails::try_from_slice(&instruction_data).expect("Error deserialaizing data");
+    let options_bet_data = OptionsBetDetails::try_from_slice(instruction_data)?;
 
     options_market_data.amount_in_pool += **trader_program_account.lamports.borrow();
     options_market_data.options_count += 1;
@@ -429,36 +3323,43 @@ fn place_option(
     options_market_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
     options_bet_data.serialize(&mut &mut trader_program_account.data.borrow_mut()[..])?;
 
-    Ok(())
+    emit(&OptionPlaced {
+        market: *writing_account.key,
+        bet: *trader_program_account.key,
+        trader: *trader.key,
+        options_bet: options_bet_data.options_bet,
+        options_strike: options_bet_data.options_strike,
+        options_bet_end: options_bet_data.options_bet_end,
+        amount: trader_program_account.lamports(),
+    })
 }
 
-#[derive(BorshSerialize, BorshDeserialize, Debug)]
-struct ResultDetails {
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct ResultDetails {
     pub player: Pubkey,
     pub options_market: Pubkey,
     pub final_price: String,
     pub result_status: String,
 }
 
-fn settle_option(
-    program_id: &Pubkey,
-    accounts: &[AccountInfo],
-    instruction_data: &[u8],
-) -> ProgramResult {
-    let accounts_iter = &mut accounts.iter();
-    let writing_account = next_account_info(accounts_iter)?;
-    let settler_account = next_account_info(accounts_iter)?;
+accounts! {
+    pub struct SettleOption<'info> {
+        pub bet: [mut, owner],
+        pub settler: [mut, signer],
+    }
+}
+
+fn settle_option(ctx: Context<SettleOption>, instruction_data: &[u8]) -> ProgramResult {
+    let SettleOption {
+        bet: writing_account,
+        settler: settler_account,
+    } = &*ctx.accounts;
 
     // match market.get_price("BNBETH") {
     //     Ok(answer) => println!("{:?}", answer),
     //     Err(e) => println!("Error: {:?}", e),
     // }
 
-    // if writing_account.owner != program_id {
-    //     msg!("writing_account isn't owned by program");
-    //     return Err(ProgramError::IncorrectProgramId);
-    // }
-
     // if player_program_account.owner != program_id {
     //     msg!("player_program_account isn't owned by program");
     //     return Err(ProgramError::IncorrectProgramId);
@@ -472,21 +3373,27 @@ fn settle_option(
     //     let mut options_market_data = MarketDetails::try_from_slice(*writing_account.data.borrow())
     //     .expect("Error deserialaizing data");
 
-    let settle_option_data =
-        ResultDetails::try_from_slice(&instruction_data).expect("Error deserialaizing data");
-
-    println!("Fetching Market: {:?}", settle_option_data.options_market);
+    let settle_option_data = ResultDetails::try_from_slice(instruction_data)?;
 
     // Latest price for resulting
 
     // options_market_data.amount_in_pool += **player_program_account.lamports.borrow();
     // options_market_data.options_count += 1;
 
-    **settler_account.try_borrow_mut_lamports()? += **writing_account.lamports.borrow();
+    let payout = writing_account.lamports();
+    **settler_account.try_borrow_mut_lamports()? += payout;
     **writing_account.try_borrow_mut_lamports()? = 0;
 
     // options_market_data.serialize(&mut &mut writing_account.data.borrow_mut()[..])?;
     // options_bet_data.serialize(&mut &mut player_program_account.data.borrow_mut()[..])?;
 
-    Ok(())
+    emit(&OptionSettled {
+        bet: *writing_account.key,
+        market: settle_option_data.options_market,
+        player: settle_option_data.player,
+        settler: *settler_account.key,
+        final_price: settle_option_data.final_price,
+        result: settle_option_data.result_status,
+        payout,
+    })
 }
@@ -0,0 +1,201 @@
+//! Ticket tokens: a pick-N lottery with `ticket_nfts` set mints every ticket it
+//! sells as a supply-1 SPL token, so players see their tickets in their
+//! wallets and can gift or trade them. Classic lotteries, whose draw picks
+//! tickets rather than numbers, can't mint them.
+//!
+//! * The mint is the ticket's PDA `["ticket_mint", ticket]`, with no decimals
+//!   and the lottery's pot authority as mint authority. The program mints the
+//!   single token to the buyer when the ticket is sold and never again.
+//! * A [`TicketMetadata`] account at `["metadata", mint]` describes the ticket.
+//! * Prizes and refunds go to whoever holds the token, not the buyer.
+//! * Burning the token voids the ticket: once `VoidTicket` records it,
+//!   tallying the draw skips the ticket.
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    msg,
+    program::{invoke, invoke_signed},
+    program_error::ProgramError,
+    program_pack::Pack,
+    pubkey::Pubkey,
+};
+use spl_associated_token_account::create_associated_token_account;
+
+use crate::accounts::unpack_token_account;
+use crate::{create_pda_account, find_pot_authority};
+
+/// What a ticket token stands for, for wallets and marketplaces.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, PartialEq, Eq)]
+pub struct TicketMetadata {
+    pub mint: Pubkey,
+    pub lottery: Pubkey,
+    pub ticket: Pubkey,
+    pub ticket_index: u64,
+    pub numbers: Vec<u8>,
+}
+
+/// Derives the mint of a ticket's token, `["ticket_mint", ticket]`.
+pub fn find_ticket_mint(program_id: &Pubkey, ticket: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"ticket_mint", ticket.as_ref()], program_id)
+}
+
+/// Derives the metadata account of a ticket token, `["metadata", mint]`.
+pub fn find_metadata_address(program_id: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"metadata", mint.as_ref()], program_id)
+}
+
+/// Accounts `mint_ticket` creates or signs with, besides the ticket itself.
+pub(crate) struct MintAccounts<'a, 'info> {
+    pub lottery: &'a AccountInfo<'info>,
+    pub ticket: &'a AccountInfo<'info>,
+    pub player: &'a AccountInfo<'info>,
+    pub ticket_mint: &'a AccountInfo<'info>,
+    pub metadata: &'a AccountInfo<'info>,
+    pub pot_authority: &'a AccountInfo<'info>,
+    pub player_ticket_tokens: &'a AccountInfo<'info>,
+    pub system_program: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub associated_token_program: &'a AccountInfo<'info>,
+    pub rent: &'a AccountInfo<'info>,
+}
+
+/// Creates the ticket's mint and metadata, paid by the player, and mints the
+/// token to the player's associated token account. `metadata_bump` is the
+/// bump of the already checked metadata PDA.
+pub(crate) fn mint_ticket(
+    program_id: &Pubkey,
+    accounts: MintAccounts,
+    metadata_bump: u8,
+    ticket_index: u64,
+    numbers: &[u8],
+) -> ProgramResult {
+    let (mint_key, mint_bump) = find_ticket_mint(program_id, accounts.ticket.key);
+    if mint_key != *accounts.ticket_mint.key {
+        msg!("ticket_mint isn't the ticket's mint");
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let (pot_authority, pot_bump) = find_pot_authority(program_id, accounts.lottery.key);
+    if pot_authority != *accounts.pot_authority.key {
+        msg!("pot_authority isn't the lottery's pot authority");
+        return Err(ProgramError::InvalidSeeds);
+    }
+
+    create_pda_account(
+        accounts.player,
+        accounts.ticket_mint,
+        accounts.system_program,
+        spl_token::state::Mint::LEN,
+        &spl_token::id(),
+        &[b"ticket_mint", accounts.ticket.key.as_ref(), &[mint_bump]],
+    )?;
+    invoke(
+        &spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &mint_key,
+            &pot_authority,
+            None,
+            0,
+        )?,
+        &[
+            accounts.ticket_mint.clone(),
+            accounts.rent.clone(),
+            accounts.token_program.clone(),
+        ],
+    )?;
+    invoke(
+        &create_associated_token_account(accounts.player.key, accounts.player.key, &mint_key),
+        &[
+            accounts.player.clone(),
+            accounts.player_ticket_tokens.clone(),
+            accounts.ticket_mint.clone(),
+            accounts.system_program.clone(),
+            accounts.token_program.clone(),
+            accounts.rent.clone(),
+            accounts.associated_token_program.clone(),
+        ],
+    )?;
+    invoke_signed(
+        &spl_token::instruction::mint_to(
+            &spl_token::id(),
+            &mint_key,
+            accounts.player_ticket_tokens.key,
+            &pot_authority,
+            &[],
+            1,
+        )?,
+        &[
+            accounts.ticket_mint.clone(),
+            accounts.player_ticket_tokens.clone(),
+            accounts.pot_authority.clone(),
+            accounts.token_program.clone(),
+        ],
+        &[&[b"pot", accounts.lottery.key.as_ref(), &[pot_bump]]],
+    )?;
+
+    let metadata = TicketMetadata {
+        mint: mint_key,
+        lottery: *accounts.lottery.key,
+        ticket: *accounts.ticket.key,
+        ticket_index,
+        numbers: numbers.to_vec(),
+    };
+    create_pda_account(
+        accounts.player,
+        accounts.metadata,
+        accounts.system_program,
+        metadata.try_to_vec()?.len(),
+        program_id,
+        &[b"metadata", mint_key.as_ref(), &[metadata_bump]],
+    )?;
+    metadata.serialize(&mut &mut accounts.metadata.try_borrow_mut_data()?[..])?;
+    Ok(())
+}
+
+/// Checks that `holder_tokens` holds the token of `ticket` and is owned by
+/// `holder`.
+pub(crate) fn check_holder(
+    program_id: &Pubkey,
+    ticket: &Pubkey,
+    holder: &Pubkey,
+    holder_tokens: &AccountInfo,
+) -> ProgramResult {
+    let token_account = unpack_token_account("holder_tokens", holder_tokens)?;
+    if token_account.mint != find_ticket_mint(program_id, ticket).0 {
+        msg!("holder_tokens doesn't hold the ticket's token");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if token_account.owner != *holder || token_account.amount == 0 {
+        msg!("Only the holder of the ticket's token can claim it");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_ticket_has_its_own_mint_and_metadata() {
+        let program_id = Pubkey::new_unique();
+        let (mint_a, _) = find_ticket_mint(&program_id, &Pubkey::new_unique());
+        let (mint_b, _) = find_ticket_mint(&program_id, &Pubkey::new_unique());
+        assert_ne!(mint_a, mint_b);
+        assert_ne!(
+            find_metadata_address(&program_id, &mint_a),
+            find_metadata_address(&program_id, &mint_b)
+        );
+
+        let metadata = TicketMetadata {
+            mint: mint_a,
+            lottery: Pubkey::new_unique(),
+            ticket: Pubkey::new_unique(),
+            ticket_index: 3,
+            numbers: vec![4, 8, 15],
+        };
+        let data = metadata.try_to_vec().unwrap();
+        assert_eq!(data.len(), 3 * 32 + 8 + 4 + 3);
+        assert_eq!(TicketMetadata::try_from_slice(&data).unwrap(), metadata);
+    }
+}
@@ -0,0 +1,108 @@
+//! Interface between lotteries and a VRF randomness oracle.
+//!
+//! A lottery with `randomness_oracle` set draws from an oracle program instead
+//! of commit-reveal. Any oracle program implementing this interface can be
+//! plugged in:
+//!
+//! * `request` (tag 0) creates the proof account at
+//!   `["randomness", requester]` and stores a [`RandomnessProof`] for the
+//!   signing requester. Lotteries request with their `["oracle", lottery]` PDA,
+//!   so nobody else can request on a lottery's behalf.
+//! * `fulfill` (tag 1) is the oracle's own business: it verifies its VRF proof
+//!   and fills in `randomness`.
+//!
+//! The lottery's `FulfillRandomness` instruction then reads the proof account,
+//! trusting it only if it is owned by the configured oracle program.
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+/// Proof account written by the oracle program.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Default, PartialEq, Eq)]
+pub struct RandomnessProof {
+    /// Account that requested the randomness.
+    pub requester: Pubkey,
+    pub request_slot: u64,
+    pub is_fulfilled: u64,
+    /// VRF output, valid once `is_fulfilled` is set.
+    pub randomness: [u8; 32],
+}
+
+/// Derives the requester PDA a lottery signs oracle requests with,
+/// `["oracle", lottery]`.
+pub fn find_requester_address(program_id: &Pubkey, lottery: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"oracle", lottery.as_ref()], program_id)
+}
+
+/// Derives the oracle's proof account for `requester`, `["randomness", requester]`.
+pub fn find_proof_address(oracle_program_id: &Pubkey, requester: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"randomness", requester.as_ref()], oracle_program_id)
+}
+
+/// Builds the oracle's `request` instruction.
+pub fn request(
+    oracle_program_id: &Pubkey,
+    proof: &Pubkey,
+    requester: &Pubkey,
+    payer: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: *oracle_program_id,
+        accounts: vec![
+            AccountMeta::new(*proof, false),
+            AccountMeta::new_readonly(*requester, true),
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: vec![0],
+    }
+}
+
+/// Builds the oracle's `fulfill` instruction. What `authority` has to prove is
+/// up to the oracle program.
+pub fn fulfill(
+    oracle_program_id: &Pubkey,
+    proof: &Pubkey,
+    authority: &Pubkey,
+    randomness: [u8; 32],
+) -> Instruction {
+    let mut data = vec![1];
+    data.extend_from_slice(&randomness);
+    Instruction {
+        program_id: *oracle_program_id,
+        accounts: vec![
+            AccountMeta::new(*proof, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_are_bound_to_the_lottery() {
+        let program_id = Pubkey::new_unique();
+        let oracle_program_id = Pubkey::new_unique();
+        let (requester_a, _) = find_requester_address(&program_id, &Pubkey::new_unique());
+        let (requester_b, _) = find_requester_address(&program_id, &Pubkey::new_unique());
+        assert_ne!(
+            find_proof_address(&oracle_program_id, &requester_a),
+            find_proof_address(&oracle_program_id, &requester_b)
+        );
+
+        let (proof, _) = find_proof_address(&oracle_program_id, &requester_a);
+        let instruction = request(&oracle_program_id, &proof, &requester_a, &program_id);
+        assert_eq!(instruction.data, vec![0]);
+        assert!(instruction.accounts[1].is_signer);
+
+        let instruction = fulfill(&oracle_program_id, &proof, &program_id, [7; 32]);
+        assert_eq!(instruction.data[0], 1);
+        assert_eq!(instruction.data[1..], [7; 32]);
+    }
+}
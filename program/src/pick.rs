@@ -0,0 +1,255 @@
+//! Pick-N number matching and prize calculation.
+//!
+//! Nothing in here touches accounts, so the frontend and any off-chain tooling
+//! can run the exact same code to preview a draw.
+use crate::rng::Rng;
+
+/// Most numbers a ticket can hold, the size of `TicketDetails::ticket_number_arr`.
+pub const MAX_PICKS: usize = 128;
+
+const BPS_DENOMINATOR: u128 = 10_000;
+
+/// Rules of a pick-N lottery: every ticket picks `pick_count` distinct numbers
+/// from `1..=max_number`, and the draw produces `pick_count` winning numbers
+/// the same way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PickGame {
+    pub pick_count: u8,
+    pub max_number: u8,
+}
+
+impl PickGame {
+    pub fn new(pick_count: u8, max_number: u8) -> Self {
+        Self {
+            pick_count,
+            max_number,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.pick_count > 0
+            && self.pick_count <= self.max_number
+            && usize::from(self.pick_count) <= MAX_PICKS
+    }
+
+    /// Number of prize tiers, from matching every number (tier 0, the
+    /// jackpot) down to matching none.
+    pub fn tier_count(&self) -> usize {
+        usize::from(self.pick_count) + 1
+    }
+
+    /// Prize tier for a ticket with `matches` matching numbers.
+    pub fn tier(&self, matches: u8) -> usize {
+        usize::from(self.pick_count.saturating_sub(matches))
+    }
+
+    /// Whether `numbers` is a legal set of picks (or winning numbers) for this game.
+    pub fn is_valid_pick(&self, numbers: &[u8]) -> bool {
+        if numbers.len() != usize::from(self.pick_count) {
+            return false;
+        }
+        let mut seen = [false; 256];
+        for &number in numbers {
+            if number == 0 || number > self.max_number || seen[usize::from(number)] {
+                return false;
+            }
+            seen[usize::from(number)] = true;
+        }
+        true
+    }
+}
+
+/// Draws the winning numbers of `game`, in draw order.
+pub fn draw_winning_numbers(game: &PickGame, rng: &Rng) -> Vec<u8> {
+    let mut pool: Vec<u8> = (1..=game.max_number).collect();
+    for drawn in 0..usize::from(game.pick_count) {
+        let remaining = (pool.len() - drawn) as u64;
+        let pick = drawn + rng.u64_less_than(remaining) as usize;
+        pool.swap(drawn, pick);
+    }
+    pool.truncate(usize::from(game.pick_count));
+    pool
+}
+
+/// Draws the winning tickets of a classic lottery with `total_entries`
+/// tickets: a different ticket for each of its `tiers` prize tiers, best tier
+/// first, for as long as there are tickets left.
+pub fn draw_winning_tickets(total_entries: u64, tiers: usize, rng: &Rng) -> Vec<u64> {
+    let count = (tiers as u64).min(total_entries) as usize;
+    let mut winners = Vec::with_capacity(count);
+    while winners.len() < count {
+        let ticket = rng.u64_less_than(total_entries);
+        if !winners.contains(&ticket) {
+            winners.push(ticket);
+        }
+    }
+    winners
+}
+
+/// Counts how many of `numbers` appear in `winning_numbers`.
+pub fn count_matches(numbers: &[u8], winning_numbers: &[u8]) -> u8 {
+    numbers
+        .iter()
+        .filter(|number| winning_numbers.contains(number))
+        .count() as u8
+}
+
+/// Counts the winning tickets in each prize tier.
+pub fn winners_per_tier<'a, I>(game: &PickGame, winning_numbers: &[u8], tickets: I) -> Vec<u64>
+where
+    I: IntoIterator<Item = &'a [u8]>,
+{
+    let mut winners = vec![0; game.tier_count()];
+    for numbers in tickets {
+        winners[game.tier(count_matches(numbers, winning_numbers))] += 1;
+    }
+    winners
+}
+
+/// How a pot is divided once the winners of each tier are known.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Payouts {
+    /// Amount paid to each winning ticket, indexed by tier.
+    pub prize_per_ticket: Vec<u64>,
+    /// Admin fee kept back from the pot.
+    pub fee: u64,
+    /// Everything neither paid out nor taken as fee: tiers without winners,
+    /// the unallocated share of the pot and rounding dust. It rolls over into
+    /// the next round.
+    pub rollover: u64,
+}
+
+impl Payouts {
+    /// Total paid out to winners.
+    pub fn paid_out(&self, winners_per_tier: &[u64]) -> u64 {
+        self.prize_per_ticket
+            .iter()
+            .zip(winners_per_tier)
+            .map(|(prize, winners)| prize * winners)
+            .sum()
+    }
+}
+
+/// Splits `pot` according to `prize_split` (basis points per tier), sharing
+/// each tier equally between its winners.
+///
+/// `fee_bps` plus the sum of `prize_split` must not exceed 10000.
+pub fn compute_payouts(
+    pot: u64,
+    fee_bps: u16,
+    prize_split: &[u16],
+    winners_per_tier: &[u64],
+) -> Payouts {
+    let share = |bps: u16| (u128::from(pot) * u128::from(bps) / BPS_DENOMINATOR) as u64;
+    let fee = share(fee_bps);
+    let prize_per_ticket: Vec<u64> = prize_split
+        .iter()
+        .enumerate()
+        .map(|(tier, bps)| match winners_per_tier.get(tier) {
+            Some(&winners) if winners > 0 => share(*bps) / winners,
+            _ => 0,
+        })
+        .collect();
+    let mut payouts = Payouts {
+        prize_per_ticket,
+        fee,
+        rollover: 0,
+    };
+    payouts.rollover = pot - fee - payouts.paid_out(winners_per_tier);
+    payouts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_game_rules() {
+        assert!(PickGame::new(6, 49).is_valid());
+        assert!(!PickGame::new(0, 49).is_valid());
+        assert!(!PickGame::new(7, 6).is_valid());
+        assert!(!PickGame::new(200, 255).is_valid());
+    }
+
+    #[test]
+    fn validates_picks() {
+        let game = PickGame::new(3, 10);
+        assert!(game.is_valid_pick(&[1, 5, 10]));
+        assert!(!game.is_valid_pick(&[1, 5]));
+        assert!(!game.is_valid_pick(&[1, 5, 11]));
+        assert!(!game.is_valid_pick(&[0, 5, 10]));
+        assert!(!game.is_valid_pick(&[5, 5, 10]));
+    }
+
+    #[test]
+    fn draws_valid_reproducible_numbers() {
+        let game = PickGame::new(6, 49);
+        for seed in 0..100 {
+            let numbers = draw_winning_numbers(&game, &Rng::with_seed(seed));
+            assert!(game.is_valid_pick(&numbers));
+            assert_eq!(numbers, draw_winning_numbers(&game, &Rng::with_seed(seed)));
+        }
+        let everything = PickGame::new(5, 5);
+        let mut numbers = draw_winning_numbers(&everything, &Rng::with_seed(1));
+        numbers.sort_unstable();
+        assert_eq!(numbers, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn draws_distinct_winning_tickets() {
+        for seed in 0..100 {
+            let winners = draw_winning_tickets(5, 3, &Rng::with_seed(seed));
+            assert_eq!(winners.len(), 3);
+            assert!(winners.iter().all(|ticket| *ticket < 5));
+            assert!(
+                winners[0] != winners[1] && winners[1] != winners[2] && winners[0] != winners[2]
+            );
+            assert_eq!(winners, draw_winning_tickets(5, 3, &Rng::with_seed(seed)));
+        }
+        let mut winners = draw_winning_tickets(2, 3, &Rng::with_seed(1));
+        winners.sort_unstable();
+        assert_eq!(winners, vec![0, 1]);
+        assert!(draw_winning_tickets(0, 3, &Rng::with_seed(1)).is_empty());
+    }
+
+    #[test]
+    fn counts_matches_and_tiers() {
+        let game = PickGame::new(3, 10);
+        assert_eq!(count_matches(&[1, 2, 3], &[3, 2, 1]), 3);
+        assert_eq!(count_matches(&[1, 2, 3], &[3, 9, 8]), 1);
+        assert_eq!(game.tier(3), 0);
+        assert_eq!(game.tier(0), 3);
+
+        let tickets: [&[u8]; 4] = [&[1, 2, 3], &[1, 2, 4], &[1, 5, 6], &[7, 8, 9]];
+        assert_eq!(
+            winners_per_tier(&game, &[1, 2, 4], tickets),
+            vec![1, 1, 1, 1]
+        );
+    }
+
+    #[test]
+    fn splits_pot_between_winners() {
+        let payouts = compute_payouts(10_000, 1_000, &[5_000, 3_000, 1_000], &[1, 2, 5]);
+        assert_eq!(payouts.fee, 1_000);
+        assert_eq!(payouts.prize_per_ticket, vec![5_000, 1_500, 200]);
+        assert_eq!(payouts.rollover, 0);
+    }
+
+    #[test]
+    fn rolls_over_jackpot_without_winners() {
+        let payouts = compute_payouts(10_000, 1_000, &[5_000, 3_000, 1_000], &[0, 2, 5]);
+        assert_eq!(payouts.prize_per_ticket, vec![0, 1_500, 200]);
+        assert_eq!(payouts.rollover, 5_000);
+    }
+
+    #[test]
+    fn rolls_over_unallocated_share_and_dust() {
+        let payouts = compute_payouts(1_000, 0, &[5_000], &[3]);
+        assert_eq!(payouts.prize_per_ticket, vec![166]);
+        assert_eq!(payouts.rollover, 502);
+        assert_eq!(
+            payouts.paid_out(&[3]) + payouts.fee + payouts.rollover,
+            1_000
+        );
+    }
+}
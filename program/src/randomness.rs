@@ -0,0 +1,98 @@
+//! Commit-reveal randomness for draws.
+//!
+//! Before ticket sales close the admin commits to the hash of a secret. After
+//! `lottery_end` the admin, or anyone once [`CLOSE_GRACE`] has passed, records
+//! the slot sales closed at, and the secret is revealed and mixed with the
+//! hash of the first slot after it, which nobody knew while tickets were on
+//! sale, and with the lottery's address. The result seeds
+//! [`crate::rng::Rng`] for the draw.
+use solana_program::{hash::hashv, pubkey::Pubkey};
+
+/// Seconds after `lottery_end` sales have to be closed in before the admin's
+/// bond is slashed and refunds open up.
+pub const REVEAL_WINDOW: i64 = 24 * 60 * 60;
+
+/// Seconds after `lottery_end` only the admin can close sales in. Closing
+/// starts the short [`REVEAL_SLOTS`] window, so the admin closes when they're
+/// ready to reveal; anyone can close after this, for an admin who is around
+/// to reveal but not to close.
+pub const CLOSE_GRACE: i64 = 12 * 60 * 60;
+
+/// Slots after the close slot the admin has to reveal in. `SlotHashes` keeps
+/// the hashes of the last 512 slots, so the close slot is still in it. The
+/// next slot's hash joins it once the slot after that has started.
+pub const REVEAL_SLOTS: u64 = 256;
+
+const SLOT_HASH_ENTRY_LEN: usize = 8 + 32;
+
+/// Commitment stored for `secret`.
+pub fn commitment(secret: &[u8]) -> [u8; 32] {
+    hashv(&[secret]).to_bytes()
+}
+
+/// Randomness of a draw, from the revealed secret, the slot hash after the
+/// close slot and the lottery's address.
+pub fn derive_randomness(slot_hash: &[u8; 32], lottery: &Pubkey, secret: &[u8]) -> [u8; 32] {
+    hashv(&[slot_hash, lottery.as_ref(), secret]).to_bytes()
+}
+
+/// Finds the hash of the first slot after `slot` in the raw data of the
+/// `SlotHashes` sysvar (newest entries first).
+///
+/// Returns `None` unless the sysvar still reaches back to `slot` itself,
+/// otherwise an old enough reveal could pick a later slot instead.
+pub fn slot_hash_after(slot_hashes_data: &[u8], slot: u64) -> Option<[u8; 32]> {
+    let len = u64::from_le_bytes(slot_hashes_data.get(..8)?.try_into().ok()?) as usize;
+    let mut next = None;
+    for index in 0..len {
+        let start = 8 + index * SLOT_HASH_ENTRY_LEN;
+        let entry = slot_hashes_data.get(start..start + SLOT_HASH_ENTRY_LEN)?;
+        let entry_slot = u64::from_le_bytes(entry[..8].try_into().ok()?);
+        if entry_slot <= slot {
+            return next;
+        }
+        let mut hash = [0u8; 32];
+        hash.copy_from_slice(&entry[8..]);
+        next = Some(hash);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slot_hashes(entries: &[(u64, u8)]) -> Vec<u8> {
+        let mut data = (entries.len() as u64).to_le_bytes().to_vec();
+        for (slot, hash) in entries {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[*hash; 32]);
+        }
+        data
+    }
+
+    #[test]
+    fn finds_next_slot() {
+        let data = slot_hashes(&[(14, 4), (13, 3), (11, 1), (10, 0)]);
+        assert_eq!(slot_hash_after(&data, 10), Some([1; 32]));
+        assert_eq!(slot_hash_after(&data, 11), Some([3; 32]));
+        assert_eq!(slot_hash_after(&data, 12), Some([3; 32]));
+    }
+
+    #[test]
+    fn needs_history_back_to_the_slot() {
+        let data = slot_hashes(&[(14, 4), (13, 3)]);
+        assert_eq!(slot_hash_after(&data, 10), None);
+        assert_eq!(slot_hash_after(&data, 14), None);
+    }
+
+    #[test]
+    fn commitment_binds_secret() {
+        let lottery = Pubkey::new_unique();
+        assert_ne!(commitment(b"secret"), commitment(b"secreT"));
+        assert_ne!(
+            derive_randomness(&[1; 32], &lottery, b"secret"),
+            derive_randomness(&[2; 32], &lottery, b"secret")
+        );
+    }
+}
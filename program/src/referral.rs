@@ -0,0 +1,165 @@
+//! Referrals: a lottery with `referral_bps` set pays that share of every ticket
+//! bought through a referrer to the referrer, instead of to the pot.
+//!
+//! * Each wallet taking part in a lottery's referrals has a [`ReferralRecord`]
+//!   at `["referral", lottery, wallet]`, created by the player buying the
+//!   ticket.
+//! * A player's first referred ticket binds them to its referrer for the rest
+//!   of the lottery. Players can't refer themselves, and only players who
+//!   haven't referred anyone yet can be referred, so referrals never form a
+//!   cycle.
+//! * Rewards stay in the pot, outside `amount_in_pot`, until the referrer
+//!   withdraws them with `ClaimReferralRewards` once the lottery is drawn. A
+//!   lottery that refunds its tickets pays the rewards back to the players.
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::{account_info::AccountInfo, msg, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::create_pda_account;
+use crate::error::LotteryError;
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ReferralRecord {
+    pub lottery: Pubkey,
+    pub wallet: Pubkey,
+    /// Wallet that referred this one, or the default pubkey.
+    pub referred_by: Pubkey,
+    /// Tickets bought through this wallet.
+    pub referrals: u64,
+    /// Rewards not yet withdrawn.
+    pub rewards: u64,
+}
+
+/// Account size of a [`ReferralRecord`].
+pub const RECORD_LEN: usize = 3 * 32 + 8 + 8;
+
+/// Derives a wallet's referral record, `["referral", lottery, wallet]`.
+pub fn find_referral_address(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    wallet: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"referral", lottery.as_ref(), wallet.as_ref()],
+        program_id,
+    )
+}
+
+/// Referrer's share of a ticket sold for `ticket_price`.
+pub fn referral_cut(ticket_price: u64, referral_bps: u16) -> u64 {
+    (u128::from(ticket_price) * u128::from(referral_bps) / 10_000) as u64
+}
+
+/// Binds `player` to `referrer`, or checks that it already is.
+pub fn bind(player: &mut ReferralRecord, referrer: &ReferralRecord) -> Result<(), ProgramError> {
+    if referrer.wallet == Pubkey::default() {
+        msg!("Referrer can't be the default pubkey");
+        return Err(ProgramError::InvalidArgument);
+    }
+    if player.wallet == referrer.wallet {
+        msg!("Players can't refer themselves");
+        return Err(LotteryError::SelfReferral.into());
+    }
+    if player.referred_by == referrer.wallet {
+        return Ok(());
+    }
+    if player.referred_by != Pubkey::default() {
+        msg!("Player was referred by {}", player.referred_by);
+        return Err(LotteryError::ReferrerChanged.into());
+    }
+    if player.referrals > 0 {
+        msg!("Players who referred others can't be referred");
+        return Err(LotteryError::ReferralCycle.into());
+    }
+    player.referred_by = referrer.wallet;
+    Ok(())
+}
+
+/// Reads `wallet`'s referral record for `lottery`, creating it paid by `payer`
+/// if it doesn't exist yet.
+pub(crate) fn load_or_create<'info>(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    wallet: &Pubkey,
+    record_account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<ReferralRecord, ProgramError> {
+    let (address, bump) = find_referral_address(program_id, lottery, wallet);
+    if address != *record_account.key {
+        msg!(
+            "{} isn't the referral record of {}",
+            record_account.key,
+            wallet
+        );
+        return Err(ProgramError::InvalidSeeds);
+    }
+    if record_account.owner == program_id {
+        return Ok(ReferralRecord::try_from_slice(
+            &record_account.data.borrow(),
+        )?);
+    }
+    create_pda_account(
+        payer,
+        record_account,
+        system_program,
+        RECORD_LEN,
+        program_id,
+        &[b"referral", lottery.as_ref(), wallet.as_ref(), &[bump]],
+    )?;
+    Ok(ReferralRecord {
+        lottery: *lottery,
+        wallet: *wallet,
+        ..ReferralRecord::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(wallet: Pubkey) -> ReferralRecord {
+        ReferralRecord {
+            wallet,
+            ..ReferralRecord::default()
+        }
+    }
+
+    #[test]
+    fn referrals_bind_once_and_never_cycle() {
+        let mut alice = record(Pubkey::new_unique());
+        let mut bob = record(Pubkey::new_unique());
+        let carol = record(Pubkey::new_unique());
+
+        let itself = alice.clone();
+        assert_eq!(
+            bind(&mut alice, &itself),
+            Err(LotteryError::SelfReferral.into())
+        );
+        assert_eq!(bind(&mut bob, &alice), Ok(()));
+        assert_eq!(bind(&mut bob, &alice), Ok(()));
+        assert_eq!(
+            bind(&mut bob, &carol),
+            Err(LotteryError::ReferrerChanged.into())
+        );
+        bob.referrals = 1;
+        let mut dave = record(Pubkey::new_unique());
+        assert_eq!(bind(&mut dave, &bob), Ok(()));
+        alice.referrals = 1;
+        assert_eq!(
+            bind(&mut alice, &bob),
+            Err(LotteryError::ReferralCycle.into())
+        );
+        assert_eq!(
+            RECORD_LEN,
+            record(Pubkey::default()).try_to_vec().unwrap().len()
+        );
+    }
+
+    #[test]
+    fn cut_is_a_share_of_the_ticket_price() {
+        assert_eq!(referral_cut(100, 500), 5);
+        assert_eq!(referral_cut(99, 500), 4);
+        assert_eq!(referral_cut(u64::MAX, 10_000), u64::MAX);
+        assert_eq!(referral_cut(100, 0), 0);
+    }
+}
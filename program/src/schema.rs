@@ -0,0 +1,1107 @@
+//! Borsh schemas of the program's accounts, instruction data and events, and
+//! its IDL, for clients that can't use the Rust types.
+//!
+//! The `schema` binary writes them as `schema.json`, as `schema.js`, a module
+//! of borsh-js classes and their schema map, with its TypeScript declarations
+//! in `schema.d.ts`, and as `idl.json`, so the frontend and IDL clients
+//! regenerate them instead of keeping their own copies in step:
+//!
+//! ```sh
+//! cargo run --features schema --bin schema -- ../src/solana
+//! ```
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use borsh::schema::{BorshSchema, Declaration, Definition, Fields};
+use num_traits::FromPrimitive;
+
+use crate::accounts::IdlAccounts;
+use crate::allowlist::{AllowlistEntry, AllowlistProof};
+use crate::crowdfunding::{
+    self, CampaignDetails, CreateCampaign, Donate, DonateRequest, DonationReceipt, Refund,
+    WithdrawDonations,
+};
+use crate::error::LotteryError;
+use crate::events::{
+    LotteryCreated, LotteryDrawn, MarketCreated, OptionPlaced, OptionSettled, PotSponsored,
+    PrizeClaimed, PrizeSponsored, TicketPurchased,
+};
+use crate::nft::TicketMetadata;
+use crate::oracle::RandomnessProof;
+use crate::referral::ReferralRecord;
+use crate::sponsor::{
+    PrizeClaimRequest, PrizeRequest, ShareReceipt, SponsorRequest, SponsoredPrize, Sponsorship,
+};
+use crate::{
+    AllowlistAccounts, BondAccounts, CancelLottery, ClaimPrize, ClaimReferralRewards, ClaimRefund,
+    ClaimRequest, ClaimSponsoredPrize, CloseSales, CommitRandomness, CommitRequest, CreateLottery,
+    CreateMarket, CreateSeries, DrawLottery, DrawResults, ExpirePrizes, FulfillRandomness,
+    InitializeTrax, LedgerAccount, LotteryDetails, LotterySeries, MarketDetails,
+    MarketStatusRequest, OptionsBetDetails, PlaceOption, Play, PreviousRound, PrizeResults,
+    ReclaimSponsoredPrize, ReclaimSponsorship, ReferralAccounts, RequestRandomness, ResultDetails,
+    RevealRandomness, RevealRequest, Rollover, SeriesAccount, SetMarketStatus, SettleOption,
+    SlashBond, SponsorPot, SponsorPrize, StartNextRound, TallyRequest, TallyTickets, TicketDetails,
+    TicketHolder, TicketNftAccounts, TraxDetails, VoidTicket, Withdraw, WithdrawRequest,
+};
+
+/// Definitions of every exported type and the types they use, by declaration.
+pub fn definitions() -> BTreeMap<Declaration, Definition> {
+    let mut definitions = HashMap::new();
+    LotteryDetails::add_definitions_recursively(&mut definitions);
+    LotterySeries::add_definitions_recursively(&mut definitions);
+    WithdrawRequest::add_definitions_recursively(&mut definitions);
+    TicketDetails::add_definitions_recursively(&mut definitions);
+    DrawResults::add_definitions_recursively(&mut definitions);
+    TallyRequest::add_definitions_recursively(&mut definitions);
+    ClaimRequest::add_definitions_recursively(&mut definitions);
+    CommitRequest::add_definitions_recursively(&mut definitions);
+    RevealRequest::add_definitions_recursively(&mut definitions);
+    TraxDetails::add_definitions_recursively(&mut definitions);
+    MarketDetails::add_definitions_recursively(&mut definitions);
+    MarketStatusRequest::add_definitions_recursively(&mut definitions);
+    OptionsBetDetails::add_definitions_recursively(&mut definitions);
+    ResultDetails::add_definitions_recursively(&mut definitions);
+    CampaignDetails::add_definitions_recursively(&mut definitions);
+    DonationReceipt::add_definitions_recursively(&mut definitions);
+    DonateRequest::add_definitions_recursively(&mut definitions);
+    TicketMetadata::add_definitions_recursively(&mut definitions);
+    ReferralRecord::add_definitions_recursively(&mut definitions);
+    AllowlistProof::add_definitions_recursively(&mut definitions);
+    AllowlistEntry::add_definitions_recursively(&mut definitions);
+    SponsorRequest::add_definitions_recursively(&mut definitions);
+    Sponsorship::add_definitions_recursively(&mut definitions);
+    PrizeRequest::add_definitions_recursively(&mut definitions);
+    PrizeClaimRequest::add_definitions_recursively(&mut definitions);
+    SponsoredPrize::add_definitions_recursively(&mut definitions);
+    ShareReceipt::add_definitions_recursively(&mut definitions);
+    RandomnessProof::add_definitions_recursively(&mut definitions);
+    LotteryCreated::add_definitions_recursively(&mut definitions);
+    TicketPurchased::add_definitions_recursively(&mut definitions);
+    LotteryDrawn::add_definitions_recursively(&mut definitions);
+    PrizeClaimed::add_definitions_recursively(&mut definitions);
+    PotSponsored::add_definitions_recursively(&mut definitions);
+    PrizeSponsored::add_definitions_recursively(&mut definitions);
+    MarketCreated::add_definitions_recursively(&mut definitions);
+    OptionPlaced::add_definitions_recursively(&mut definitions);
+    OptionSettled::add_definitions_recursively(&mut definitions);
+    definitions.into_iter().collect()
+}
+
+/// Writes `definitions` as a JSON object from declaration to definition.
+pub fn to_json(definitions: &BTreeMap<Declaration, Definition>) -> String {
+    let mut json = String::from("{\n");
+    for (index, (declaration, definition)) in definitions.iter().enumerate() {
+        if index > 0 {
+            json.push_str(",\n");
+        }
+        let _ = write!(json, "  \"{}\": ", declaration);
+        match definition {
+            Definition::Array { length, elements } => {
+                let _ = write!(
+                    json,
+                    "{{ \"kind\": \"array\", \"length\": {}, \"elements\": \"{}\" }}",
+                    length, elements
+                );
+            }
+            Definition::Sequence { elements } => {
+                let _ = write!(
+                    json,
+                    "{{ \"kind\": \"sequence\", \"elements\": \"{}\" }}",
+                    elements
+                );
+            }
+            Definition::Tuple { elements } => {
+                let _ = write!(
+                    json,
+                    "{{ \"kind\": \"tuple\", \"elements\": [{}] }}",
+                    quoted(elements.iter())
+                );
+            }
+            Definition::Enum { variants } => {
+                let variants: Vec<String> = variants
+                    .iter()
+                    .map(|(name, declaration)| format!("[\"{}\", \"{}\"]", name, declaration))
+                    .collect();
+                let _ = write!(
+                    json,
+                    "{{ \"kind\": \"enum\", \"variants\": [{}] }}",
+                    variants.join(", ")
+                );
+            }
+            Definition::Struct {
+                fields: Fields::NamedFields(fields),
+            } => {
+                json.push_str("{\n    \"kind\": \"struct\",\n    \"fields\": [\n");
+                for (index, (name, declaration)) in fields.iter().enumerate() {
+                    let separator = if index + 1 < fields.len() { "," } else { "" };
+                    let _ = writeln!(
+                        json,
+                        "      [\"{}\", \"{}\"]{}",
+                        name, declaration, separator
+                    );
+                }
+                json.push_str("    ]\n  }");
+            }
+            Definition::Struct {
+                fields: Fields::UnnamedFields(elements),
+            } => {
+                let _ = write!(
+                    json,
+                    "{{ \"kind\": \"struct\", \"elements\": [{}] }}",
+                    quoted(elements.iter())
+                );
+            }
+            Definition::Struct {
+                fields: Fields::Empty,
+            } => json.push_str("{ \"kind\": \"struct\", \"fields\": [] }"),
+        }
+    }
+    json.push_str("\n}\n");
+    json
+}
+
+/// Writes `definitions` as a JS module exporting a class per struct and
+/// `SCHEMA`, their borsh-js schema map.
+///
+/// borsh-js has no signed integers or booleans, so `iN` fields are written as
+/// `uN` and `bool` as `u8`: values come back as their unsigned bit pattern.
+pub fn to_js(definitions: &BTreeMap<Declaration, Definition>) -> Result<String, String> {
+    let mut js = String::from(
+        "// Generated from the program's Borsh types by its `schema` binary. Don't edit.\n",
+    );
+    let structs: Vec<(&Declaration, &Vec<(String, Declaration)>)> = definitions
+        .iter()
+        .filter_map(|(declaration, definition)| match definition {
+            Definition::Struct {
+                fields: Fields::NamedFields(fields),
+            } => Some((declaration, fields)),
+            _ => None,
+        })
+        .collect();
+
+    for (declaration, _) in &structs {
+        let _ = write!(
+            js,
+            "\nexport class {} {{\n    constructor(properties) {{\n        \
+             Object.keys(properties).forEach((key) => {{\n            \
+             this[key] = properties[key];\n        }});\n    }}\n}}\n",
+            declaration
+        );
+    }
+
+    js.push_str("\nexport const SCHEMA = new Map([\n");
+    for (declaration, fields) in &structs {
+        let _ = writeln!(js, "    [{}, {{", declaration);
+        js.push_str("        kind: 'struct',\n        fields: [\n");
+        for (name, field) in fields.iter() {
+            let _ = writeln!(
+                js,
+                "            ['{}', {}],",
+                name,
+                js_type(field, definitions)?
+            );
+        }
+        js.push_str("        ]\n    }],\n");
+    }
+    js.push_str("]);\n");
+    Ok(js)
+}
+
+/// The borsh-js field type of `declaration`.
+fn js_type(
+    declaration: &str,
+    definitions: &BTreeMap<Declaration, Definition>,
+) -> Result<String, String> {
+    match declaration {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "string" => {
+            return Ok(format!("'{}'", declaration))
+        }
+        "i8" | "i16" | "i32" | "i64" | "i128" => return Ok(format!("'u{}'", &declaration[1..])),
+        "bool" => return Ok("'u8'".to_string()),
+        "Pubkey" => return Ok("[32]".to_string()),
+        _ => {}
+    }
+    match definitions.get(declaration) {
+        Some(Definition::Array { length, elements }) if elements == "u8" => {
+            Ok(format!("[{}]", length))
+        }
+        Some(Definition::Array { length, elements }) => {
+            Ok(format!("[{}, {}]", js_type(elements, definitions)?, length))
+        }
+        Some(Definition::Sequence { elements }) => {
+            Ok(format!("[{}]", js_type(elements, definitions)?))
+        }
+        Some(Definition::Enum { variants })
+            if declaration.starts_with("Option<") && variants.len() == 2 =>
+        {
+            Ok(format!(
+                "{{ kind: 'option', type: {} }}",
+                js_type(&variants[1].1, definitions)?
+            ))
+        }
+        Some(Definition::Struct {
+            fields: Fields::NamedFields(_),
+        }) => Ok(declaration.to_string()),
+        _ => Err(format!("{} has no borsh-js equivalent", declaration)),
+    }
+}
+
+/// Writes the TypeScript declarations of [`to_js`]'s module: each class with
+/// its fields as borsh-js deserializes them, and `SCHEMA`.
+pub fn to_ts(definitions: &BTreeMap<Declaration, Definition>) -> Result<String, String> {
+    let mut ts = String::from(
+        "// Generated from the program's Borsh types by its `schema` binary. Don't edit.\n\n\
+         import BN from 'bn.js';\n",
+    );
+    for (declaration, definition) in definitions {
+        if let Definition::Struct {
+            fields: Fields::NamedFields(fields),
+        } = definition
+        {
+            let _ = writeln!(
+                ts,
+                "\nexport class {0} {{\n    constructor(properties: {0});",
+                declaration
+            );
+            for (name, field) in fields {
+                let _ = writeln!(ts, "    {}: {};", name, ts_type(field, definitions)?);
+            }
+            ts.push_str("}\n");
+        }
+    }
+    ts.push_str(
+        "\nexport const SCHEMA: Map<Function, { kind: 'struct'; fields: [string, unknown][] }>;\n",
+    );
+    Ok(ts)
+}
+
+/// The TypeScript type borsh-js deserializes `declaration` to: integers wider
+/// than 32 bits as `BN`, byte arrays as `Uint8Array`.
+fn ts_type(
+    declaration: &str,
+    definitions: &BTreeMap<Declaration, Definition>,
+) -> Result<String, String> {
+    match declaration {
+        "u8" | "u16" | "u32" | "i8" | "i16" | "i32" | "bool" => return Ok("number".to_string()),
+        "u64" | "u128" | "i64" | "i128" => return Ok("BN".to_string()),
+        "string" => return Ok("string".to_string()),
+        "Pubkey" => return Ok("Uint8Array".to_string()),
+        _ => {}
+    }
+    match definitions.get(declaration) {
+        Some(Definition::Array { elements, .. }) if elements == "u8" => {
+            Ok("Uint8Array".to_string())
+        }
+        Some(Definition::Array { elements, .. }) | Some(Definition::Sequence { elements }) => {
+            Ok(format!("{}[]", ts_type(elements, definitions)?))
+        }
+        Some(Definition::Enum { variants })
+            if declaration.starts_with("Option<") && variants.len() == 2 =>
+        {
+            Ok(format!(
+                "{} | undefined",
+                ts_type(&variants[1].1, definitions)?
+            ))
+        }
+        Some(Definition::Struct {
+            fields: Fields::NamedFields(_),
+        }) => Ok(declaration.to_string()),
+        _ => Err(format!("{} has no borsh-js equivalent", declaration)),
+    }
+}
+
+fn quoted<'a>(declarations: impl Iterator<Item = &'a Declaration>) -> String {
+    declarations
+        .map(|declaration| format!("\"{}\"", declaration))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// An instruction of the IDL, by its leading instruction bytes.
+struct Instruction {
+    /// The tag, or [`NAMESPACE`](crowdfunding::NAMESPACE) and the tag for
+    /// campaign instructions.
+    discriminant: Vec<u8>,
+    name: &'static str,
+    accounts: IdlAccounts,
+    /// Name and declaration of the instruction data, if any.
+    args: Option<(&'static str, Declaration)>,
+    /// Docs on what the accounts struct doesn't say, like optional data.
+    docs: &'static [&'static str],
+    /// Accounts passed after `accounts`, and whether they're only passed in
+    /// the cases the docs tell.
+    tails: Vec<(IdlAccounts, bool)>,
+}
+
+impl Instruction {
+    /// Adds accounts the instruction always takes after the others.
+    fn then(mut self, accounts: IdlAccounts) -> Self {
+        self.tails.push((accounts, false));
+        self
+    }
+
+    /// Adds accounts the instruction takes after the others only in some
+    /// cases.
+    fn then_optional(mut self, accounts: IdlAccounts) -> Self {
+        self.tails.push((accounts, true));
+        self
+    }
+}
+
+/// The program's instructions, campaign ones included.
+fn instructions() -> Vec<Instruction> {
+    fn instruction(
+        tag: u8,
+        name: &'static str,
+        accounts: IdlAccounts,
+        args: Option<(&'static str, Declaration)>,
+        docs: &'static [&'static str],
+    ) -> Instruction {
+        Instruction {
+            discriminant: vec![tag],
+            name,
+            accounts,
+            args,
+            docs,
+            tails: Vec::new(),
+        }
+    }
+
+    vec![
+        instruction(
+            0,
+            "create_lottery",
+            CreateLottery::idl(),
+            Some(("details", LotteryDetails::declaration())),
+            &[
+                "Followed by `BondAccounts` when the lottery has a bond, then by",
+                "`LedgerAccount` for compact lotteries.",
+            ],
+        )
+        .then_optional(BondAccounts::idl())
+        .then_optional(LedgerAccount::idl()),
+        instruction(
+            1,
+            "play",
+            Play::idl(),
+            Some(("ticket", TicketDetails::declaration())),
+            &[
+                "Allowlisted lotteries take the player's `AllowlistProof` after the",
+                "ticket. Followed by `TicketNftAccounts`, `AllowlistAccounts` and",
+                "`ReferralAccounts` where they apply.",
+            ],
+        )
+        .then_optional(TicketNftAccounts::idl())
+        .then_optional(AllowlistAccounts::idl())
+        .then_optional(ReferralAccounts::idl()),
+        instruction(
+            2,
+            "withdraw",
+            Withdraw::idl(),
+            Some(("request", WithdrawRequest::declaration())),
+            &[],
+        ),
+        instruction(
+            3,
+            "create_series",
+            CreateSeries::idl(),
+            Some(("details", LotterySeries::declaration())),
+            &[],
+        ),
+        instruction(
+            4,
+            "start_next_round",
+            StartNextRound::idl(),
+            None,
+            &["Followed by `PreviousRound` unless the round is the series' first."],
+        )
+        .then_optional(PreviousRound::idl()),
+        instruction(
+            5,
+            "initialize_trax",
+            InitializeTrax::idl(),
+            Some(("details", TraxDetails::declaration())),
+            &[],
+        ),
+        instruction(
+            6,
+            "create_market",
+            CreateMarket::idl(),
+            Some(("details", MarketDetails::declaration())),
+            &[],
+        ),
+        instruction(
+            7,
+            "place_option",
+            PlaceOption::idl(),
+            Some(("bet", OptionsBetDetails::declaration())),
+            &[],
+        ),
+        instruction(
+            8,
+            "settle_option",
+            SettleOption::idl(),
+            Some(("result", ResultDetails::declaration())),
+            &[],
+        ),
+        instruction(9, "draw_lottery", DrawLottery::idl(), None, &[]),
+        instruction(
+            10,
+            "claim_prize",
+            ClaimPrize::idl(),
+            None,
+            &["Compact lotteries take the ticket's `ClaimRequest`."],
+        )
+        .then_optional(TicketHolder::idl()),
+        instruction(11, "expire_prizes", ExpirePrizes::idl(), None, &[])
+            .then_optional(SeriesAccount::idl())
+            .then_optional(Rollover::idl()),
+        instruction(
+            12,
+            "commit_randomness",
+            CommitRandomness::idl(),
+            Some(("request", CommitRequest::declaration())),
+            &[],
+        ),
+        instruction(
+            13,
+            "reveal_randomness",
+            RevealRandomness::idl(),
+            Some(("request", RevealRequest::declaration())),
+            &[],
+        )
+        .then_optional(BondAccounts::idl()),
+        instruction(14, "slash_bond", SlashBond::idl(), None, &[])
+            .then_optional(BondAccounts::idl()),
+        instruction(
+            15,
+            "claim_refund",
+            ClaimRefund::idl(),
+            None,
+            &["Compact lotteries take the ticket's `ClaimRequest`."],
+        )
+        .then_optional(TicketHolder::idl()),
+        instruction(
+            16,
+            "request_randomness",
+            RequestRandomness::idl(),
+            None,
+            &[],
+        ),
+        instruction(
+            17,
+            "fulfill_randomness",
+            FulfillRandomness::idl(),
+            None,
+            &[],
+        )
+        .then(DrawLottery::idl()),
+        instruction(
+            18,
+            "set_market_status",
+            SetMarketStatus::idl(),
+            Some(("request", MarketStatusRequest::declaration())),
+            &[],
+        ),
+        instruction(19, "void_ticket", VoidTicket::idl(), None, &[]),
+        instruction(
+            20,
+            "claim_referral_rewards",
+            ClaimReferralRewards::idl(),
+            None,
+            &[],
+        ),
+        instruction(
+            21,
+            "sponsor_pot",
+            SponsorPot::idl(),
+            Some(("request", SponsorRequest::declaration())),
+            &[],
+        ),
+        instruction(
+            22,
+            "sponsor_prize",
+            SponsorPrize::idl(),
+            Some(("request", PrizeRequest::declaration())),
+            &[],
+        ),
+        instruction(
+            23,
+            "claim_sponsored_prize",
+            ClaimSponsoredPrize::idl(),
+            Some(("request", PrizeClaimRequest::declaration())),
+            &["Compact lotteries follow the request with the ticket's `ClaimRequest`."],
+        )
+        .then_optional(TicketHolder::idl()),
+        instruction(
+            24,
+            "reclaim_sponsored_prize",
+            ReclaimSponsoredPrize::idl(),
+            Some(("request", PrizeClaimRequest::declaration())),
+            &[],
+        )
+        .then_optional(PrizeResults::idl()),
+        instruction(25, "cancel_lottery", CancelLottery::idl(), None, &[])
+            .then_optional(BondAccounts::idl()),
+        instruction(
+            26,
+            "tally_tickets",
+            TallyTickets::idl(),
+            Some(("request", TallyRequest::declaration())),
+            &[],
+        ),
+        instruction(27, "close_sales", CloseSales::idl(), None, &[]),
+        instruction(
+            28,
+            "reclaim_sponsorship",
+            ReclaimSponsorship::idl(),
+            None,
+            &[],
+        ),
+        campaign_instruction(
+            crowdfunding::CREATE_CAMPAIGN,
+            "create_campaign",
+            CreateCampaign::idl(),
+            Some(("details", CampaignDetails::declaration())),
+        ),
+        campaign_instruction(
+            crowdfunding::WITHDRAW,
+            "withdraw_donations",
+            WithdrawDonations::idl(),
+            Some(("request", WithdrawRequest::declaration())),
+        ),
+        campaign_instruction(
+            crowdfunding::DONATE,
+            "donate",
+            Donate::idl(),
+            Some(("request", DonateRequest::declaration())),
+        ),
+        campaign_instruction(crowdfunding::REFUND, "refund", Refund::idl(), None),
+    ]
+}
+
+fn campaign_instruction(
+    tag: u8,
+    name: &'static str,
+    accounts: IdlAccounts,
+    args: Option<(&'static str, Declaration)>,
+) -> Instruction {
+    Instruction {
+        discriminant: vec![crowdfunding::NAMESPACE, tag],
+        name,
+        accounts,
+        args,
+        docs: &[],
+        tails: Vec::new(),
+    }
+}
+
+/// Program accounts of the IDL, which unlike Anchor's have no discriminator.
+const ACCOUNTS: &[&str] = &[
+    "LotteryDetails",
+    "LotterySeries",
+    "TicketDetails",
+    "DrawResults",
+    "TraxDetails",
+    "MarketDetails",
+    "OptionsBetDetails",
+    "TicketMetadata",
+    "ReferralRecord",
+    "AllowlistEntry",
+    "Sponsorship",
+    "SponsoredPrize",
+    "ShareReceipt",
+    "CampaignDetails",
+    "DonationReceipt",
+];
+
+/// Events of the IDL, logged with Anchor's discriminators.
+const EVENTS: &[&str] = &[
+    "LotteryCreated",
+    "TicketPurchased",
+    "LotteryDrawn",
+    "PrizeClaimed",
+    "PotSponsored",
+    "PrizeSponsored",
+    "MarketCreated",
+    "OptionPlaced",
+    "OptionSettled",
+];
+
+/// A JSON value, written indented by [`Json::write`].
+enum Json {
+    String(String),
+    Number(u64),
+    Bool(bool),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn string(value: &str) -> Self {
+        Json::String(value.to_string())
+    }
+
+    fn write(&self, out: &mut String, indent: usize) {
+        match self {
+            Json::String(value) => {
+                out.push('"');
+                for c in value.chars() {
+                    match c {
+                        '"' => out.push_str("\\\""),
+                        '\\' => out.push_str("\\\\"),
+                        '\n' => out.push_str("\\n"),
+                        c if (c as u32) < 0x20 => {
+                            let _ = write!(out, "\\u{:04x}", c as u32);
+                        }
+                        c => out.push(c),
+                    }
+                }
+                out.push('"');
+            }
+            Json::Number(value) => {
+                let _ = write!(out, "{}", value);
+            }
+            Json::Bool(value) => {
+                let _ = write!(out, "{}", value);
+            }
+            Json::Array(values) if values.is_empty() => out.push_str("[]"),
+            Json::Array(values) => {
+                out.push_str("[\n");
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        out.push_str(",\n");
+                    }
+                    out.push_str(&"  ".repeat(indent + 1));
+                    value.write(out, indent + 1);
+                }
+                let _ = write!(out, "\n{}]", "  ".repeat(indent));
+            }
+            Json::Object(entries) => {
+                out.push_str("{\n");
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    if index > 0 {
+                        out.push_str(",\n");
+                    }
+                    let _ = write!(out, "{}\"{}\": ", "  ".repeat(indent + 1), key);
+                    value.write(out, indent + 1);
+                }
+                let _ = write!(out, "\n{}}}", "  ".repeat(indent));
+            }
+        }
+    }
+}
+
+/// Writes the program's IDL: its instructions, accounts, types, events and
+/// errors, in the layout of Anchor's IDL with the instruction tags Shank adds
+/// for native programs, so IDL clients like Solita can call it.
+pub fn to_idl(definitions: &BTreeMap<Declaration, Definition>) -> Result<String, String> {
+    let mut idl_instructions = Vec::new();
+    for instruction in instructions() {
+        let mut instruction_docs = docs(instruction.accounts.attributes);
+        instruction_docs.extend(instruction.docs.iter().map(|line| line.to_string()));
+        let tails = instruction.tails.iter().flat_map(|(tail, optional)| {
+            tail.accounts
+                .iter()
+                .map(move |account| (account, *optional))
+        });
+        let accounts = instruction
+            .accounts
+            .accounts
+            .iter()
+            .map(|account| (account, false))
+            .chain(tails)
+            .map(|(account, optional)| {
+                let mut entries = vec![
+                    ("name", Json::String(camel_case(account.name))),
+                    ("isMut", Json::Bool(account.constraints.contains(&"mut"))),
+                    (
+                        "isSigner",
+                        Json::Bool(account.constraints.contains(&"signer")),
+                    ),
+                ];
+                if optional {
+                    entries.push(("isOptional", Json::Bool(true)));
+                }
+                with_docs(&mut entries, docs(account.attributes));
+                Json::Object(entries)
+            })
+            .collect();
+        let args = match &instruction.args {
+            Some((name, declaration)) => vec![Json::Object(vec![
+                ("name", Json::string(name)),
+                ("type", idl_type(declaration, definitions)?),
+            ])],
+            None => Vec::new(),
+        };
+        let mut entries = vec![("name", Json::String(camel_case(instruction.name)))];
+        with_docs(&mut entries, instruction_docs);
+        entries.extend([
+            ("accounts", Json::Array(accounts)),
+            ("args", Json::Array(args)),
+            ("discriminant", discriminant(&instruction.discriminant)),
+        ]);
+        idl_instructions.push(Json::Object(entries));
+    }
+
+    let mut accounts = Vec::new();
+    let mut types = Vec::new();
+    let mut events = Vec::new();
+    for (declaration, definition) in definitions {
+        let fields = match definition {
+            Definition::Struct {
+                fields: Fields::NamedFields(fields),
+            } => fields,
+            _ => continue,
+        };
+        if EVENTS.contains(&declaration.as_str()) {
+            let mut idl_fields = Vec::new();
+            for (name, field) in fields {
+                idl_fields.push(Json::Object(vec![
+                    ("name", Json::String(camel_case(name))),
+                    ("type", idl_type(field, definitions)?),
+                    ("index", Json::Bool(false)),
+                ]));
+            }
+            events.push(Json::Object(vec![
+                ("name", Json::string(declaration)),
+                ("fields", Json::Array(idl_fields)),
+            ]));
+            continue;
+        }
+        let mut idl_fields = Vec::new();
+        for (name, field) in fields {
+            idl_fields.push(Json::Object(vec![
+                ("name", Json::String(camel_case(name))),
+                ("type", idl_type(field, definitions)?),
+            ]));
+        }
+        let entry = Json::Object(vec![
+            ("name", Json::string(declaration)),
+            (
+                "type",
+                Json::Object(vec![
+                    ("kind", Json::string("struct")),
+                    ("fields", Json::Array(idl_fields)),
+                ]),
+            ),
+        ]);
+        if ACCOUNTS.contains(&declaration.as_str()) {
+            accounts.push(entry);
+        } else {
+            types.push(entry);
+        }
+    }
+
+    let errors = (0..)
+        .map_while(LotteryError::from_u32)
+        .map(|error| {
+            Json::Object(vec![
+                ("code", Json::Number(error as u64)),
+                ("name", Json::String(format!("{:?}", error))),
+                ("msg", Json::String(error.to_string())),
+            ])
+        })
+        .collect();
+
+    let idl = Json::Object(vec![
+        ("version", Json::string(env!("CARGO_PKG_VERSION"))),
+        ("name", Json::string("fanitrade")),
+        ("instructions", Json::Array(idl_instructions)),
+        ("accounts", Json::Array(accounts)),
+        ("types", Json::Array(types)),
+        ("events", Json::Array(events)),
+        ("errors", Json::Array(errors)),
+        (
+            "metadata",
+            Json::Object(vec![("origin", Json::string("shank"))]),
+        ),
+    ]);
+    let mut out = String::new();
+    idl.write(&mut out, 0);
+    out.push('\n');
+    Ok(out)
+}
+
+/// An instruction's leading bytes as a Shank discriminant: a `u8` for a tag,
+/// a byte array for a namespaced one.
+fn discriminant(bytes: &[u8]) -> Json {
+    let (discriminant_type, value) = match bytes {
+        [tag] => (Json::string("u8"), Json::Number((*tag).into())),
+        _ => (
+            Json::Object(vec![(
+                "array",
+                Json::Array(vec![Json::string("u8"), Json::Number(bytes.len() as u64)]),
+            )]),
+            Json::Array(
+                bytes
+                    .iter()
+                    .map(|byte| Json::Number((*byte).into()))
+                    .collect(),
+            ),
+        ),
+    };
+    Json::Object(vec![("type", discriminant_type), ("value", value)])
+}
+
+/// The IDL type of `declaration`.
+fn idl_type(
+    declaration: &str,
+    definitions: &BTreeMap<Declaration, Definition>,
+) -> Result<Json, String> {
+    match declaration {
+        "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128" | "bool"
+        | "string" => return Ok(Json::string(declaration)),
+        "Pubkey" => return Ok(Json::string("publicKey")),
+        _ => {}
+    }
+    match definitions.get(declaration) {
+        Some(Definition::Array { length, elements }) => Ok(Json::Object(vec![(
+            "array",
+            Json::Array(vec![
+                idl_type(elements, definitions)?,
+                Json::Number((*length).into()),
+            ]),
+        )])),
+        Some(Definition::Sequence { elements }) => Ok(Json::Object(vec![(
+            "vec",
+            idl_type(elements, definitions)?,
+        )])),
+        Some(Definition::Enum { variants })
+            if declaration.starts_with("Option<") && variants.len() == 2 =>
+        {
+            Ok(Json::Object(vec![(
+                "option",
+                idl_type(&variants[1].1, definitions)?,
+            )]))
+        }
+        Some(Definition::Struct {
+            fields: Fields::NamedFields(_),
+        }) => Ok(Json::Object(vec![("defined", Json::string(declaration))])),
+        _ => Err(format!("{} has no IDL equivalent", declaration)),
+    }
+}
+
+/// Lines of the `doc` attributes among `attributes`, as `stringify!` wrote
+/// them, which may break long ones after the `=`.
+fn docs(attributes: &[&str]) -> Vec<String> {
+    attributes
+        .iter()
+        .filter_map(|attribute| {
+            attribute
+                .strip_prefix("doc")?
+                .trim_start()
+                .strip_prefix('=')
+        })
+        .map(|literal| {
+            let literal = literal.trim_start();
+            let line = match literal.strip_prefix('r') {
+                Some(raw) => {
+                    let hashes = raw.len() - raw.trim_start_matches('#').len();
+                    raw[hashes + 1..raw.len() - hashes - 1].to_string()
+                }
+                None => literal[1..literal.len() - 1]
+                    .replace("\\\"", "\"")
+                    .replace("\\'", "'")
+                    .replace("\\\\", "\\"),
+            };
+            line.strip_prefix(' ').unwrap_or(&line).to_string()
+        })
+        .collect()
+}
+
+fn with_docs(entries: &mut Vec<(&'static str, Json)>, docs: Vec<String>) {
+    if !docs.is_empty() {
+        entries.push((
+            "docs",
+            Json::Array(docs.into_iter().map(Json::String).collect()),
+        ));
+    }
+}
+
+/// `snake_case` as `camelCase`, as Anchor names instructions, accounts and
+/// fields.
+fn camel_case(name: &str) -> String {
+    let mut words = name.split('_');
+    let mut camel = words.next().unwrap_or_default().to_string();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel.extend(first.to_uppercase());
+            camel.push_str(chars.as_str());
+        }
+    }
+    camel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn js_schema_follows_the_rust_field_order() {
+        let definitions = definitions();
+        let js = to_js(&definitions).unwrap();
+
+        assert!(js.contains("export class LotteryDetails {"));
+        assert!(js.contains(
+            "    [WithdrawRequest, {\n        kind: 'struct',\n        fields: [\n            \
+             ['amount', 'u64'],\n        ]\n    }],"
+        ));
+        assert!(js.contains("['admin', [32]],\n            ['name', 'string'],"));
+        assert!(js.contains("['prize_split', ['u16']],"));
+        assert!(js.contains("['ticket_number_arr', [128]],"));
+        assert!(js.contains("['winners_per_tier', ['u64']],"));
+        assert!(js.contains("['claim_deadline', 'u64'],"));
+        assert!(!js.contains("class Pubkey"));
+
+        let json = to_json(&definitions);
+        assert!(json.contains(
+            "\"Array<u8, 32>\": { \"kind\": \"array\", \"length\": 32, \"elements\": \"u8\" }"
+        ));
+        assert!(json.contains("      [\"amount\", \"u64\"]\n"));
+    }
+
+    #[test]
+    fn ts_declarations_type_the_js_classes() {
+        let ts = to_ts(&definitions()).unwrap();
+
+        assert!(ts.contains(
+            "export class WithdrawRequest {\n    \
+             constructor(properties: WithdrawRequest);\n    amount: BN;\n}\n"
+        ));
+        assert!(ts.contains("    admin: Uint8Array;\n    name: string;\n"));
+        assert!(ts.contains("    prize_split: number[];\n"));
+        assert!(ts.contains("    ticket_number_arr: Uint8Array;\n"));
+        assert!(ts.contains("    winners_per_tier: BN[];\n"));
+        assert!(ts.contains("export const SCHEMA: Map<Function,"));
+        assert!(!ts.contains("class Pubkey"));
+    }
+
+    #[test]
+    fn idl_lists_instructions_by_tag() {
+        let idl = to_idl(&definitions()).unwrap();
+
+        assert!(idl.contains(
+            "      \"name\": \"createLottery\",\n      \"docs\": [\n        \
+             \"Followed by `BondAccounts` when the lottery has a bond, then by\","
+        ));
+        assert!(idl.contains(
+            "        {\n          \"name\": \"player\",\n          \"isMut\": true,\n          \
+             \"isSigner\": true,\n          \"docs\": [\n            \
+             \"Wallet of the player, paying for the ticket.\"\n          ]\n        },"
+        ));
+        assert!(idl.contains(
+            "\"discriminant\": {\n        \"type\": \"u8\",\n        \"value\": 28\n      }"
+        ));
+        assert!(idl.contains("\"name\": \"reclaimSponsorship\""));
+        assert!(idl.contains(
+            "\"discriminant\": {\n        \"type\": {\n          \"array\": [\n            \
+             \"u8\",\n            2\n          ]\n        },\n        \"value\": [\n          \
+             128,\n          3\n        ]\n      }"
+        ));
+        assert!(idl.contains("\"name\": \"ticketPrice\",\n"));
+        assert!(idl.contains("\"type\": \"publicKey\""));
+        assert!(idl.contains("\"array\": [\n"));
+        assert!(idl.contains(
+            "      \"code\": 0,\n      \"name\": \"InvalidInstruction\",\n      \
+             \"msg\": \"Invalid Instruction\"\n"
+        ));
+        assert!(idl.contains("\"origin\": \"shank\""));
+    }
+
+    #[test]
+    fn optional_accounts_follow_the_instructions_own() {
+        let idl = to_idl(&definitions()).unwrap();
+
+        assert!(idl.contains(
+            "        {\n          \"name\": \"ledger\",\n          \"isMut\": true,\n          \
+             \"isSigner\": false,\n          \"isOptional\": true\n        }\n      ],\n      \
+             \"args\": [\n        {\n          \"name\": \"details\","
+        ));
+        assert!(idl.contains(
+            "\"name\": \"holderTokens\",\n          \"isMut\": false,\n          \
+             \"isSigner\": false,\n          \"isOptional\": true\n"
+        ));
+        assert!(idl.contains(
+            "\"name\": \"ticketMint\",\n          \"isMut\": true,\n          \
+             \"isSigner\": false,\n          \"isOptional\": true\n"
+        ));
+        assert!(idl.contains(
+            "\"name\": \"playerRecord\",\n          \"isMut\": true,\n          \
+             \"isSigner\": false,\n          \"isOptional\": true,\n          \"docs\": [\n            \
+             \"The player's `ReferralRecord`, at `[\\\"referral\\\", lottery, player]`.\"\n          ]\n"
+        ));
+        assert!(idl.contains(
+            "        {\n          \"name\": \"results\",\n          \"isMut\": false,\n          \
+             \"isSigner\": false,\n          \"isOptional\": true\n        }\n      ],\n      \
+             \"args\": [\n        {\n          \"name\": \"request\",\n          \
+             \"type\": {\n            \"defined\": \"PrizeClaimRequest\""
+        ));
+        assert!(idl.contains(
+            "\"name\": \"allowlistEntry\",\n          \"isMut\": true,\n          \
+             \"isSigner\": false,\n          \"isOptional\": true,\n          \"docs\": [\n            \
+             \"The player's `AllowlistEntry`, at `[\\\"allowlist\\\", lottery, player]`.\"\n          ]\n        },\n        {\n          \
+             \"name\": \"referrer\","
+        ));
+        for instruction in instructions() {
+            let mut names: Vec<&str> = instruction
+                .accounts
+                .accounts
+                .iter()
+                .chain(
+                    instruction
+                        .tails
+                        .iter()
+                        .flat_map(|(tail, _)| &tail.accounts),
+                )
+                .map(|account| account.name)
+                .collect();
+            let count = names.len();
+            names.sort_unstable();
+            names.dedup();
+            assert_eq!(
+                names.len(),
+                count,
+                "{} repeats an account",
+                instruction.name
+            );
+        }
+    }
+
+    #[test]
+    fn docs_are_read_from_stringified_attributes() {
+        assert_eq!(
+            docs(&[
+                "doc = r\" Wallet of the player.\"",
+                "doc = r#\" The \"pot\".\"#",
+                "doc =\nr#\" A \"long\" line.\"#",
+                "doc = \" Escaped \\\"quotes\\\".\"",
+                "allow(dead_code)",
+            ]),
+            vec![
+                "Wallet of the player.",
+                "The \"pot\".",
+                "A \"long\" line.",
+                "Escaped \"quotes\"."
+            ]
+        );
+        assert_eq!(camel_case("claim_referral_rewards"), "claimReferralRewards");
+    }
+
+    #[test]
+    fn unsupported_types_are_reported() {
+        let mut definitions = BTreeMap::new();
+        definitions.insert(
+            "Tuple<u8, u8>".to_string(),
+            Definition::Tuple {
+                elements: vec!["u8".to_string(), "u8".to_string()],
+            },
+        );
+        assert_eq!(
+            js_type("Tuple<u8, u8>", &definitions),
+            Err("Tuple<u8, u8> has no borsh-js equivalent".to_string())
+        );
+        assert_eq!(js_type("i64", &definitions), Ok("'u64'".to_string()));
+    }
+}
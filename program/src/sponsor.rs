@@ -0,0 +1,138 @@
+//! Sponsors: wallets that fund a lottery without buying tickets.
+//!
+//! * `SponsorPot` adds tokens of the lottery's mint to its pot. Each sponsor
+//!   has a [`Sponsorship`] at `["sponsor", lottery, sponsor]` with the name
+//!   shown for them and what they gave in total. If the lottery refunds its
+//!   tickets, sponsors take that back with `ReclaimSponsorship`.
+//! * `SponsorPrize` puts up a prize in another mint, e.g. an NFT, for one prize
+//!   tier. The tokens go to a secondary vault, the pot authority's associated
+//!   token account for that mint, and the prize is recorded as a
+//!   [`SponsoredPrize`] at `["prize", lottery, index]`.
+//! * Once the draw's tickets are all tallied, each winning ticket of a prize's
+//!   tier claims its [`share`] with `ClaimSponsoredPrize`, which records a
+//!   [`ShareReceipt`] at `["share", prize, ticket_index]` so it can't claim
+//!   twice. Sponsors take back what wasn't claimed in time, or the whole prize
+//!   if the lottery refunds its tickets, with `ReclaimSponsoredPrize`.
+use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
+use solana_program::pubkey::Pubkey;
+
+/// Longest sponsor name, in bytes.
+pub const MAX_NAME_LEN: usize = 32;
+
+/// Instruction data of `SponsorPot`.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct SponsorRequest {
+    pub amount: u64,
+    /// Name shown for the sponsor. Only the first contribution's counts.
+    pub name: String,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Sponsorship {
+    pub lottery: Pubkey,
+    pub sponsor: Pubkey,
+    /// Tokens the sponsor added to the pot, or 0 once they reclaimed them.
+    pub amount: u64,
+    pub name: String,
+}
+
+/// Instruction data of `SponsorPrize`.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct PrizeRequest {
+    /// Prize tier whose winners get the prize, best tier first.
+    pub tier: u8,
+    pub amount: u64,
+}
+
+/// Instruction data of `ReclaimSponsoredPrize`, and of `ClaimSponsoredPrize`,
+/// where compact lotteries follow it with the ticket's `ClaimRequest`.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug)]
+pub struct PrizeClaimRequest {
+    pub prize_index: u64,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SponsoredPrize {
+    pub lottery: Pubkey,
+    pub sponsor: Pubkey,
+    pub mint: Pubkey,
+    pub tier: u8,
+    pub amount: u64,
+    /// Share of `amount` winning tickets claimed.
+    pub claimed: u64,
+    /// Nonzero once the sponsor took back what wasn't paid out.
+    pub is_reclaimed: u64,
+}
+
+/// Records that a ticket claimed its share of a sponsored prize.
+#[derive(BorshSerialize, BorshDeserialize, BorshSchema, Debug, Default, Clone, PartialEq, Eq)]
+pub struct ShareReceipt {
+    pub prize: Pubkey,
+    pub ticket_index: u64,
+    pub amount: u64,
+}
+
+/// Account size of a [`ShareReceipt`].
+pub const SHARE_RECEIPT_LEN: usize = 32 + 8 + 8;
+
+/// Derives a sponsor's sponsorship, `["sponsor", lottery, sponsor]`.
+pub fn find_sponsorship_address(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    sponsor: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"sponsor", lottery.as_ref(), sponsor.as_ref()],
+        program_id,
+    )
+}
+
+/// Derives a lottery's sponsored prize `index`, `["prize", lottery, index]`.
+pub fn find_prize_address(program_id: &Pubkey, lottery: &Pubkey, index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"prize", lottery.as_ref(), &index.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Derives the receipt of ticket `ticket_index`'s share of `prize`,
+/// `["share", prize, ticket_index]`.
+pub fn find_share_address(program_id: &Pubkey, prize: &Pubkey, ticket_index: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"share", prize.as_ref(), &ticket_index.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Share of a prize of `amount` for one of its tier's `winners`. The prize is
+/// split evenly and what doesn't divide evenly goes to the tier's first
+/// winner, so a single NFT goes to the first winner alone.
+pub fn share(amount: u64, winners: u64, is_first_winner: bool) -> u64 {
+    if winners == 0 {
+        return 0;
+    }
+    let share = amount / winners;
+    if is_first_winner {
+        share + amount % winners
+    } else {
+        share
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prizes_split_between_the_tiers_winners() {
+        assert_eq!(share(10, 3, true), 4);
+        assert_eq!(share(10, 3, false), 3);
+        assert_eq!(share(1, 2, true), 1);
+        assert_eq!(share(1, 2, false), 0);
+        assert_eq!(share(5, 0, true), 0);
+        assert_eq!(
+            SHARE_RECEIPT_LEN,
+            ShareReceipt::default().try_to_vec().unwrap().len()
+        );
+    }
+}
@@ -0,0 +1,602 @@
+//! Shared setup for the program-test suites: deploys the program in-process
+//! next to the SPL token programs and wraps the usual account plumbing.
+#![allow(dead_code)]
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use fanitrade_utils::{
+    find_pot_authority, find_results_address, process_instruction, randomness,
+    sponsor::{find_sponsorship_address, SponsorRequest},
+    CommitRequest, LotteryDetails, RevealRequest, TallyRequest, TicketDetails,
+};
+use solana_program::{
+    clock::{Clock, DEFAULT_MS_PER_SLOT},
+    hash::Hash,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    system_instruction, system_program, sysvar,
+};
+use solana_program_test::{processor, ProgramTest, ProgramTestBanksClientExt, ProgramTestContext};
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+    transport::TransportError,
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+
+pub const TICKET_PRICE: u64 = 100;
+
+/// Secret the admins of test lotteries commit to.
+pub const SECRET: &[u8] = b"test secret";
+
+pub struct Env {
+    pub context: ProgramTestContext,
+    pub program_id: Pubkey,
+    /// The mock VRF oracle, for lotteries drawn by an oracle.
+    pub oracle_program_id: Pubkey,
+    /// A program buying tickets for its vault through `cpi`.
+    pub cpi_caller_program_id: Pubkey,
+    last_blockhash: Hash,
+}
+
+impl Env {
+    pub async fn start() -> Self {
+        let program_id = Pubkey::new_unique();
+        let oracle_program_id = Pubkey::new_unique();
+        let cpi_caller_program_id = Pubkey::new_unique();
+        let mut program_test = ProgramTest::new(
+            "fanitrade_utils",
+            program_id,
+            processor!(process_instruction),
+        );
+        program_test.add_program(
+            "fanitrade_mock_oracle",
+            oracle_program_id,
+            processor!(fanitrade_mock_oracle::process_instruction),
+        );
+        program_test.add_program(
+            "fanitrade_cpi_caller",
+            cpi_caller_program_id,
+            processor!(fanitrade_cpi_caller::process_instruction),
+        );
+        // The bundled SPL programs are BPF builds, which the pinned runtime
+        // cannot run under current compilers, so they are swapped for their
+        // native processors.
+        program_test.add_program(
+            "spl_token",
+            spl_token::id(),
+            processor!(spl_token::processor::Processor::process),
+        );
+        program_test.add_program(
+            "spl_associated_token_account",
+            spl_associated_token_account::id(),
+            processor!(spl_associated_token_account::processor::process_instruction),
+        );
+        Self {
+            context: program_test.start_with_context().await,
+            program_id,
+            oracle_program_id,
+            cpi_caller_program_id,
+            last_blockhash: Hash::default(),
+        }
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    /// Sends `instructions` in one transaction paid by the test payer.
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), TransportError> {
+        let mut blockhash = self
+            .context
+            .banks_client
+            .get_latest_blockhash()
+            .await
+            .unwrap();
+        // Resending a transaction, e.g. retrying a rejected one, under the
+        // same blockhash would fail as already processed.
+        if blockhash == self.last_blockhash {
+            blockhash = self
+                .context
+                .banks_client
+                .get_new_latest_blockhash(&blockhash)
+                .await
+                .unwrap();
+        }
+        self.last_blockhash = blockhash;
+        let mut all_signers = vec![&self.context.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    /// Sends a single instruction to the program, prefixed with its tag.
+    pub async fn call(
+        &mut self,
+        tag: u8,
+        data: &[u8],
+        accounts: Vec<AccountMeta>,
+        signers: &[&Keypair],
+    ) -> Result<(), TransportError> {
+        let mut instruction_data = vec![tag];
+        instruction_data.extend_from_slice(data);
+        let instruction = Instruction {
+            program_id: self.program_id,
+            accounts,
+            data: instruction_data,
+        };
+        self.process(&[instruction], signers).await
+    }
+
+    pub async fn rent_exempt(&mut self, space: usize) -> u64 {
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        rent.minimum_balance(space)
+    }
+
+    /// Creates `account` with `space` bytes owned by `owner`, funded with
+    /// `lamports` or the rent-exempt minimum.
+    pub async fn create_account(
+        &mut self,
+        account: &Keypair,
+        space: usize,
+        lamports: Option<u64>,
+        owner: &Pubkey,
+    ) {
+        let rent_exempt = self.rent_exempt(space).await;
+        let lamports = lamports.unwrap_or(rent_exempt);
+        if lamports < rent_exempt {
+            // The runtime refuses to create rent-paying accounts, so they are
+            // written directly.
+            self.context.set_account(
+                &account.pubkey(),
+                &AccountSharedData::new(lamports, space, owner),
+            );
+            return;
+        }
+        let payer = self.payer();
+        self.process(
+            &[system_instruction::create_account(
+                &payer,
+                &account.pubkey(),
+                lamports,
+                space as u64,
+                owner,
+            )],
+            &[account],
+        )
+        .await
+        .unwrap();
+    }
+
+    pub async fn fund(&mut self, wallet: &Pubkey, lamports: u64) {
+        let payer = self.payer();
+        self.process(
+            &[system_instruction::transfer(&payer, wallet, lamports)],
+            &[],
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Sends an empty `address` the least it can hold, as anyone can before
+    /// the program creates an account there.
+    pub async fn prefund(&mut self, address: &Pubkey) {
+        let lamports = self.rent_exempt(0).await;
+        self.fund(address, lamports).await;
+    }
+
+    pub async fn create_mint(&mut self, authority: &Pubkey) -> Pubkey {
+        let mint = Keypair::new();
+        let lamports = self.rent_exempt(spl_token::state::Mint::LEN).await;
+        let payer = self.payer();
+        self.process(
+            &[
+                system_instruction::create_account(
+                    &payer,
+                    &mint.pubkey(),
+                    lamports,
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::id(),
+                ),
+                spl_token::instruction::initialize_mint(
+                    &spl_token::id(),
+                    &mint.pubkey(),
+                    authority,
+                    None,
+                    0,
+                )
+                .unwrap(),
+            ],
+            &[&mint],
+        )
+        .await
+        .unwrap();
+        mint.pubkey()
+    }
+
+    pub async fn create_ata(&mut self, wallet: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let payer = self.payer();
+        self.process(
+            &[create_associated_token_account(&payer, wallet, mint)],
+            &[],
+        )
+        .await
+        .unwrap();
+        get_associated_token_address(wallet, mint)
+    }
+
+    pub async fn mint_to(
+        &mut self,
+        mint: &Pubkey,
+        account: &Pubkey,
+        authority: &Keypair,
+        amount: u64,
+    ) {
+        self.process(
+            &[spl_token::instruction::mint_to(
+                &spl_token::id(),
+                mint,
+                account,
+                &authority.pubkey(),
+                &[],
+                amount,
+            )
+            .unwrap()],
+            &[authority],
+        )
+        .await
+        .unwrap();
+    }
+
+    pub async fn account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+    }
+
+    pub async fn token_balance(&mut self, address: &Pubkey) -> u64 {
+        let account = self.account(address).await.expect("token account missing");
+        spl_token::state::Account::unpack(&account.data)
+            .unwrap()
+            .amount
+    }
+
+    pub async fn state<T: BorshDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self.account(address).await.expect("account missing");
+        T::try_from_slice(&account.data).unwrap()
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+    }
+
+    /// Warps `slots` slots ahead; the slots left behind join SlotHashes.
+    pub async fn warp_slots(&mut self, slots: u64) {
+        let before = self.clock().await;
+        self.context.warp_to_slot(before.slot + slots).unwrap();
+        // Without votes the runtime keeps the genesis timestamp, so the
+        // clock is moved on by the nominal slot time.
+        let mut clock = self.clock().await;
+        clock.unix_timestamp = before.unix_timestamp + (slots * DEFAULT_MS_PER_SLOT / 1_000) as i64;
+        self.context.set_sysvar(&clock);
+    }
+
+    /// Moves the `lottery_end` of `lottery` to now.
+    pub async fn end_sales(&mut self, lottery: &Pubkey) {
+        let mut details: LotteryDetails = self.state(lottery).await;
+        details.lottery_end = self.clock().await.unix_timestamp.to_string();
+        self.set_state(lottery, &details).await;
+    }
+
+    /// Records the slot the sales of `lottery` closed at, as `closer`.
+    pub async fn close_sales(
+        &mut self,
+        lottery: &Pubkey,
+        closer: &Keypair,
+    ) -> Result<(), TransportError> {
+        self.call(
+            27,
+            &[],
+            vec![
+                AccountMeta::new(*lottery, false),
+                AccountMeta::new_readonly(closer.pubkey(), true),
+            ],
+            &[closer],
+        )
+        .await
+    }
+
+    /// Commits `admin` to [`SECRET`], ends and closes ticket sales and
+    /// reveals, readying a lottery without a bond for its draw.
+    pub async fn reveal(&mut self, lottery: &Pubkey, admin: &Keypair) {
+        let commit = CommitRequest {
+            commitment: randomness::commitment(SECRET),
+        };
+        self.call(
+            12,
+            &commit.try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(*lottery, false),
+                AccountMeta::new_readonly(admin.pubkey(), true),
+            ],
+            &[admin],
+        )
+        .await
+        .unwrap();
+        self.end_sales(lottery).await;
+        self.close_sales(lottery, admin).await.unwrap();
+        // SlotHashes gets the next slot's hash once the slot after it starts.
+        self.warp_slots(1).await;
+        self.warp_slots(1).await;
+        let reveal = RevealRequest {
+            secret: SECRET.to_vec(),
+        };
+        self.call(
+            13,
+            &reveal.try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(*lottery, false),
+                AccountMeta::new_readonly(admin.pubkey(), true),
+                AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+            ],
+            &[admin],
+        )
+        .await
+        .unwrap();
+    }
+
+    /// Draws `lottery`, with the test payer paying for its results.
+    pub async fn draw(&mut self, lottery: &Pubkey) -> Result<(), TransportError> {
+        let accounts = vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new(self.payer(), true),
+            AccountMeta::new(find_results_address(&self.program_id, lottery).0, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ];
+        self.call(9, &[], accounts, &[]).await
+    }
+
+    /// Tallies the next `count` tickets of a drawn `lottery`, passing
+    /// `accounts`, the tickets or the ticket ledger, after the fixed ones.
+    pub async fn tally(
+        &mut self,
+        lottery: &Pubkey,
+        count: u64,
+        accounts: &[Pubkey],
+    ) -> Result<(), TransportError> {
+        let mut tally_accounts = vec![
+            AccountMeta::new(*lottery, false),
+            AccountMeta::new(find_results_address(&self.program_id, lottery).0, false),
+        ];
+        tally_accounts.extend(
+            accounts
+                .iter()
+                .map(|account| AccountMeta::new_readonly(*account, false)),
+        );
+        let request = TallyRequest { count };
+        self.call(26, &request.try_to_vec().unwrap(), tally_accounts, &[])
+            .await
+    }
+
+    /// Has a new sponsor put `amount` of `mint` into the pot of `lottery`.
+    pub async fn sponsor_pot(
+        &mut self,
+        lottery: &Pubkey,
+        mint: &Pubkey,
+        mint_authority: &Keypair,
+        amount: u64,
+    ) -> Keypair {
+        let sponsor = Keypair::new();
+        self.fund(&sponsor.pubkey(), 1_000_000_000).await;
+        let sponsor_tokens = self.create_ata(&sponsor.pubkey(), mint).await;
+        self.mint_to(mint, &sponsor_tokens, mint_authority, amount)
+            .await;
+        let (pot_authority, _) = find_pot_authority(&self.program_id, lottery);
+        let request = SponsorRequest {
+            amount,
+            name: "Sponsor".to_string(),
+        };
+        self.call(
+            21,
+            &request.try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(*lottery, false),
+                AccountMeta::new(
+                    find_sponsorship_address(&self.program_id, lottery, &sponsor.pubkey()).0,
+                    false,
+                ),
+                AccountMeta::new(sponsor.pubkey(), true),
+                AccountMeta::new(sponsor_tokens, false),
+                AccountMeta::new(get_associated_token_address(&pot_authority, mint), false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+            &[&sponsor],
+        )
+        .await
+        .unwrap();
+        sponsor
+    }
+
+    /// Overwrites the state of a program account, for setups that would
+    /// otherwise need a whole draw.
+    pub async fn set_state<T: BorshSerialize>(&mut self, address: &Pubkey, state: &T) {
+        let mut account = self.account(address).await.expect("account missing");
+        account.data = state.try_to_vec().unwrap();
+        self.context
+            .set_account(address, &AccountSharedData::from(account));
+    }
+}
+
+/// A one-off pick-3 lottery that stays open for ticket sales.
+pub fn lottery_details(admin: Pubkey, token_mint: Pubkey) -> LotteryDetails {
+    LotteryDetails {
+        admin,
+        name: "Test lottery".to_string(),
+        is_initialized: 0,
+        is_ended: 0,
+        lottery_start: "0".to_string(),
+        lottery_end: i64::MAX.to_string(),
+        ticket_price: TICKET_PRICE,
+        amount_in_pot: 0,
+        total_entries: 0,
+        token_mint,
+        series: Pubkey::default(),
+        round: 0,
+        fee_bps: 1_000,
+        prize_split: vec![5_000, 3_000, 1_000],
+        pick_count: 3,
+        max_number: 10,
+        winning_numbers: vec![0; 3],
+        claim_period: 3_600,
+        fees_collected: 0,
+        bond_amount: 0,
+        randomness_commitment: [0; 32],
+        commit_slot: 0,
+        randomness: [0; 32],
+        is_revealed: 0,
+        is_refunding: 0,
+        randomness_oracle: Pubkey::default(),
+        ticket_ledger: Pubkey::default(),
+        ticket_nfts: 0,
+        referral_bps: 0,
+        referral_rewards: 0,
+        sponsored_prizes: 0,
+        min_entries: 0,
+        max_entries: 0,
+        allowlist_root: [0; 32],
+        close_slot: 0,
+        sponsored_amount: 0,
+    }
+}
+
+pub fn ticket_details(player: &Pubkey, numbers: &[u8]) -> TicketDetails {
+    let mut ticket_number_arr = [0; 128];
+    ticket_number_arr[..numbers.len()].copy_from_slice(numbers);
+    TicketDetails {
+        player: player.to_string(),
+        ticket_count: 1,
+        ticket_number_arr,
+        lottery: Pubkey::default(),
+        ticket_index: 0,
+        claimed: 0,
+    }
+}
+
+/// A created lottery with its pot and a funded player.
+pub struct LotteryFixture {
+    pub admin: Keypair,
+    pub mint_authority: Keypair,
+    pub mint: Pubkey,
+    pub lottery: Keypair,
+    pub pot: Pubkey,
+    pub player: Keypair,
+    pub player_tokens: Pubkey,
+}
+
+impl LotteryFixture {
+    pub async fn new(env: &mut Env) -> Self {
+        Self::with_details(env, |_| {}, Vec::new()).await
+    }
+
+    /// Creates the lottery from `lottery_details` as changed by `customize`,
+    /// passing `extra_accounts` to `CreateLottery` after the fixed ones.
+    pub async fn with_details(
+        env: &mut Env,
+        customize: impl FnOnce(&mut LotteryDetails),
+        extra_accounts: Vec<AccountMeta>,
+    ) -> Self {
+        let admin = Keypair::new();
+        let mint_authority = Keypair::new();
+        let mint = env.create_mint(&mint_authority.pubkey()).await;
+        let lottery = Keypair::new();
+        let mut details = lottery_details(admin.pubkey(), mint);
+        customize(&mut details);
+        let program_id = env.program_id;
+        env.create_account(
+            &lottery,
+            details.try_to_vec().unwrap().len(),
+            None,
+            &program_id,
+        )
+        .await;
+        let mut accounts = vec![
+            AccountMeta::new(lottery.pubkey(), false),
+            AccountMeta::new_readonly(admin.pubkey(), true),
+        ];
+        accounts.extend(extra_accounts);
+        env.call(0, &details.try_to_vec().unwrap(), accounts, &[&admin])
+            .await
+            .unwrap();
+
+        let (pot_authority, _) = find_pot_authority(&program_id, &lottery.pubkey());
+        let pot = env.create_ata(&pot_authority, &mint).await;
+
+        let player = Keypair::new();
+        env.fund(&player.pubkey(), 1_000_000_000).await;
+        let player_tokens = env.create_ata(&player.pubkey(), &mint).await;
+        env.mint_to(&mint, &player_tokens, &mint_authority, 10 * TICKET_PRICE)
+            .await;
+
+        Self {
+            admin,
+            mint_authority,
+            mint,
+            lottery,
+            pot,
+            player,
+            player_tokens,
+        }
+    }
+
+    pub fn play_accounts(&self, ticket: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.lottery.pubkey(), false),
+            AccountMeta::new(*ticket, false),
+            AccountMeta::new(self.player.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(self.pot, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(self.player_tokens, false),
+        ]
+    }
+}
+
+pub fn assert_instruction_error(result: Result<(), TransportError>, expected: InstructionError) {
+    match result {
+        Err(TransportError::TransactionError(TransactionError::InstructionError(_, error))) => {
+            assert_eq!(error, expected)
+        }
+        other => panic!("expected {:?}, got {:?}", expected, other),
+    }
+}
+
+/// Malformed instruction data has to fail the instruction, not panic.
+pub fn assert_borsh_error(result: Result<(), TransportError>) {
+    match result {
+        Err(TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::BorshIoError(_),
+        ))) => {}
+        other => panic!("expected a Borsh error, got {:?}", other),
+    }
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::{assert_instruction_error, ticket_details, Env, LotteryFixture, TICKET_PRICE};
+use fanitrade_cpi_caller::find_vault_address;
+use fanitrade_utils::{find_ticket_address, LotteryDetails, TicketDetails};
+use solana_program::{
+    instruction::{AccountMeta, Instruction, InstructionError},
+    pubkey::Pubkey,
+    system_instruction::SystemError,
+    system_program,
+};
+use solana_program_test::tokio;
+use solana_sdk::{signature::Signer, transport::TransportError};
+
+/// Has the caller program buy the lottery's next ticket for its vault.
+async fn play_from_vault(
+    env: &mut Env,
+    fixture: &LotteryFixture,
+    vault_tokens: &Pubkey,
+) -> Result<(), TransportError> {
+    let lottery = fixture.lottery.pubkey();
+    let details: LotteryDetails = env.state(&lottery).await;
+    let (ticket, _) = find_ticket_address(&env.program_id, &lottery, details.total_entries);
+    let (vault, _) = find_vault_address(&env.cpi_caller_program_id);
+    let instruction = Instruction {
+        program_id: env.cpi_caller_program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(env.program_id, false),
+            AccountMeta::new(lottery, false),
+            AccountMeta::new(ticket, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(fixture.pot, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*vault_tokens, false),
+        ],
+        data: ticket_details(&vault, &[1, 2, 3]).try_to_vec().unwrap(),
+    };
+    env.process(&[instruction], &[]).await
+}
+
+#[tokio::test]
+async fn a_program_plays_for_its_vault() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let (vault, _) = find_vault_address(&env.cpi_caller_program_id);
+    let vault_tokens = env.create_ata(&vault, &fixture.mint).await;
+    env.mint_to(
+        &fixture.mint,
+        &vault_tokens,
+        &fixture.mint_authority,
+        TICKET_PRICE,
+    )
+    .await;
+
+    // The vault pays the ticket account's rent, so it needs lamports.
+    assert_instruction_error(
+        play_from_vault(&mut env, &fixture, &vault_tokens).await,
+        InstructionError::Custom(SystemError::ResultWithNegativeLamports as u32),
+    );
+
+    env.fund(&vault, 1_000_000_000).await;
+    play_from_vault(&mut env, &fixture, &vault_tokens)
+        .await
+        .unwrap();
+
+    let lottery = fixture.lottery.pubkey();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.total_entries, 1);
+    let (ticket, _) = find_ticket_address(&env.program_id, &lottery, 0);
+    let ticket: TicketDetails = env.state(&ticket).await;
+    assert_eq!(ticket.player, vault.to_string());
+    assert_eq!(env.token_balance(&fixture.pot).await, TICKET_PRICE);
+    assert_eq!(env.token_balance(&vault_tokens).await, 0);
+}
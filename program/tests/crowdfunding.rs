@@ -0,0 +1,386 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::{assert_instruction_error, Env};
+use fanitrade_utils::{
+    crowdfunding::{
+        find_receipt_address, CampaignDetails, DonateRequest, DonationReceipt, CREATE_CAMPAIGN,
+        DONATE, NAMESPACE, REFUND, WITHDRAW,
+    },
+    error::LotteryError,
+    WithdrawRequest,
+};
+use solana_program::{
+    instruction::{AccountMeta, InstructionError},
+    pubkey::Pubkey,
+    system_program,
+};
+use solana_program_test::tokio;
+use solana_sdk::{
+    signature::{Keypair, Signer},
+    transport::TransportError,
+};
+
+const GOAL: u64 = 10_000_000;
+
+fn campaign_details(admin: Pubkey) -> CampaignDetails {
+    CampaignDetails {
+        admin,
+        name: "Test campaign".to_string(),
+        description: "Raising lamports".to_string(),
+        image_link: "https://example.com/campaign.png".to_string(),
+        amount_donated: 42,
+        goal: GOAL,
+        deadline: u64::MAX,
+    }
+}
+
+fn custom(error: LotteryError) -> InstructionError {
+    InstructionError::Custom(error as u32)
+}
+
+/// Sends campaign instruction `tag` under the crowdfunding namespace.
+async fn call(
+    env: &mut Env,
+    tag: u8,
+    data: &[u8],
+    accounts: Vec<AccountMeta>,
+    signers: &[&Keypair],
+) -> Result<(), TransportError> {
+    let mut campaign_data = vec![tag];
+    campaign_data.extend_from_slice(data);
+    env.call(NAMESPACE, &campaign_data, accounts, signers).await
+}
+
+async fn create_campaign(env: &mut Env, admin: &Keypair) -> Keypair {
+    let campaign = Keypair::new();
+    let data = campaign_details(admin.pubkey()).try_to_vec().unwrap();
+    let program_id = env.program_id;
+    env.create_account(&campaign, data.len(), None, &program_id)
+        .await;
+    call(
+        env,
+        CREATE_CAMPAIGN,
+        &data,
+        vec![
+            AccountMeta::new(campaign.pubkey(), false),
+            AccountMeta::new_readonly(admin.pubkey(), true),
+        ],
+        &[admin],
+    )
+    .await
+    .unwrap();
+    campaign
+}
+
+/// Moves the campaign's deadline into the past.
+async fn end_campaign(env: &mut Env, campaign: &Pubkey) {
+    let mut state: CampaignDetails = env.state(campaign).await;
+    state.deadline = 1;
+    env.set_state(campaign, &state).await;
+}
+
+async fn donate(
+    env: &mut Env,
+    campaign: &Pubkey,
+    donor: &Keypair,
+    amount: u64,
+) -> Result<(), TransportError> {
+    let program_id = env.program_id;
+    call(
+        env,
+        DONATE,
+        &DonateRequest { amount }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*campaign, false),
+            AccountMeta::new(
+                find_receipt_address(&program_id, campaign, &donor.pubkey()).0,
+                false,
+            ),
+            AccountMeta::new(donor.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        &[donor],
+    )
+    .await
+}
+
+async fn withdraw(
+    env: &mut Env,
+    campaign: &Pubkey,
+    admin: &Keypair,
+    amount: u64,
+) -> Result<(), TransportError> {
+    call(
+        env,
+        WITHDRAW,
+        &WithdrawRequest { amount }.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(*campaign, false),
+            AccountMeta::new(admin.pubkey(), true),
+        ],
+        &[admin],
+    )
+    .await
+}
+
+/// Refunds `donor`, sent by the test payer.
+async fn refund(env: &mut Env, campaign: &Pubkey, donor: &Pubkey) -> Result<(), TransportError> {
+    let program_id = env.program_id;
+    call(
+        env,
+        REFUND,
+        &[],
+        vec![
+            AccountMeta::new(*campaign, false),
+            AccountMeta::new(find_receipt_address(&program_id, campaign, donor).0, false),
+            AccountMeta::new(*donor, false),
+        ],
+        &[],
+    )
+    .await
+}
+
+async fn funded_keypair(env: &mut Env) -> Keypair {
+    let keypair = Keypair::new();
+    env.fund(&keypair.pubkey(), 1_000_000_000).await;
+    keypair
+}
+
+async fn lamports(env: &mut Env, address: &Pubkey) -> u64 {
+    env.account(address)
+        .await
+        .map_or(0, |account| account.lamports)
+}
+
+#[tokio::test]
+async fn funded_campaign_can_be_withdrawn_after_the_deadline() {
+    let mut env = Env::start().await;
+    let admin = funded_keypair(&mut env).await;
+    let campaign = create_campaign(&mut env, &admin).await;
+    let state: CampaignDetails = env.state(&campaign.pubkey()).await;
+    assert_eq!(state.amount_donated, 0);
+    assert_eq!(state.goal, GOAL);
+
+    let donor = funded_keypair(&mut env).await;
+    donate(&mut env, &campaign.pubkey(), &donor, 6_000_000)
+        .await
+        .unwrap();
+    donate(&mut env, &campaign.pubkey(), &donor, 4_000_000)
+        .await
+        .unwrap();
+    let state: CampaignDetails = env.state(&campaign.pubkey()).await;
+    assert_eq!(state.amount_donated, GOAL);
+    let program_id = env.program_id;
+    let (receipt, _) = find_receipt_address(&program_id, &campaign.pubkey(), &donor.pubkey());
+    let receipt_state: DonationReceipt = env.state(&receipt).await;
+    assert_eq!(receipt_state.donor, donor.pubkey());
+    assert_eq!(receipt_state.amount, GOAL);
+
+    assert_instruction_error(
+        withdraw(&mut env, &campaign.pubkey(), &admin, 1).await,
+        custom(LotteryError::CampaignNotEnded),
+    );
+    end_campaign(&mut env, &campaign.pubkey()).await;
+    assert_instruction_error(
+        donate(&mut env, &campaign.pubkey(), &donor, 1).await,
+        custom(LotteryError::CampaignEnded),
+    );
+    assert_instruction_error(
+        refund(&mut env, &campaign.pubkey(), &donor.pubkey()).await,
+        custom(LotteryError::GoalReached),
+    );
+
+    assert_instruction_error(
+        withdraw(&mut env, &campaign.pubkey(), &admin, GOAL + 1).await,
+        InstructionError::InsufficientFunds,
+    );
+    let admin_before = lamports(&mut env, &admin.pubkey()).await;
+    withdraw(&mut env, &campaign.pubkey(), &admin, GOAL)
+        .await
+        .unwrap();
+    assert_eq!(
+        lamports(&mut env, &admin.pubkey()).await,
+        admin_before + GOAL
+    );
+    let campaign_account = env.account(&campaign.pubkey()).await.unwrap();
+    assert_eq!(
+        campaign_account.lamports,
+        env.rent_exempt(campaign_account.data.len()).await
+    );
+}
+
+#[tokio::test]
+async fn unfunded_campaign_refunds_donors() {
+    let mut env = Env::start().await;
+    let admin = funded_keypair(&mut env).await;
+    let campaign = create_campaign(&mut env, &admin).await;
+    let donor = funded_keypair(&mut env).await;
+    let other_donor = funded_keypair(&mut env).await;
+    donate(&mut env, &campaign.pubkey(), &donor, 3_000_000)
+        .await
+        .unwrap();
+    donate(&mut env, &campaign.pubkey(), &other_donor, 2_000_000)
+        .await
+        .unwrap();
+
+    assert_instruction_error(
+        refund(&mut env, &campaign.pubkey(), &donor.pubkey()).await,
+        custom(LotteryError::CampaignNotEnded),
+    );
+    end_campaign(&mut env, &campaign.pubkey()).await;
+    assert_instruction_error(
+        withdraw(&mut env, &campaign.pubkey(), &admin, 1).await,
+        custom(LotteryError::GoalNotReached),
+    );
+
+    let program_id = env.program_id;
+    let (receipt, _) = find_receipt_address(&program_id, &campaign.pubkey(), &donor.pubkey());
+    let receipt_rent = lamports(&mut env, &receipt).await;
+    let campaign_before = lamports(&mut env, &campaign.pubkey()).await;
+    let donor_before = lamports(&mut env, &donor.pubkey()).await;
+    refund(&mut env, &campaign.pubkey(), &donor.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(
+        lamports(&mut env, &donor.pubkey()).await,
+        donor_before + 3_000_000 + receipt_rent
+    );
+    assert_eq!(
+        lamports(&mut env, &campaign.pubkey()).await,
+        campaign_before - 3_000_000
+    );
+    assert!(env.account(&receipt).await.is_none());
+
+    // The closed receipt can't be refunded twice.
+    assert_instruction_error(
+        refund(&mut env, &campaign.pubkey(), &donor.pubkey()).await,
+        InstructionError::IncorrectProgramId,
+    );
+    refund(&mut env, &campaign.pubkey(), &other_donor.pubkey())
+        .await
+        .unwrap();
+    let campaign_account = env.account(&campaign.pubkey()).await.unwrap();
+    assert_eq!(
+        campaign_account.lamports,
+        env.rent_exempt(campaign_account.data.len()).await
+    );
+}
+
+#[tokio::test]
+async fn prefunded_receipt_does_not_block_donations() {
+    let mut env = Env::start().await;
+    let admin = funded_keypair(&mut env).await;
+    let campaign = create_campaign(&mut env, &admin).await;
+    let donor = funded_keypair(&mut env).await;
+    let program_id = env.program_id;
+    let (receipt, _) = find_receipt_address(&program_id, &campaign.pubkey(), &donor.pubkey());
+    env.prefund(&receipt).await;
+
+    donate(&mut env, &campaign.pubkey(), &donor, 3_000_000)
+        .await
+        .unwrap();
+    let receipt_account = env.account(&receipt).await.unwrap();
+    assert_eq!(receipt_account.owner, program_id);
+    assert_eq!(
+        receipt_account.lamports,
+        env.rent_exempt(receipt_account.data.len()).await
+    );
+    let receipt_data: DonationReceipt = env.state(&receipt).await;
+    assert_eq!(receipt_data.amount, 3_000_000);
+    assert_eq!(receipt_data.donor, donor.pubkey());
+}
+
+#[tokio::test]
+async fn campaign_instructions_reject_invalid_requests() {
+    let mut env = Env::start().await;
+    let admin = funded_keypair(&mut env).await;
+    let campaign = create_campaign(&mut env, &admin).await;
+    let donor = funded_keypair(&mut env).await;
+    donate(&mut env, &campaign.pubkey(), &donor, GOAL)
+        .await
+        .unwrap();
+    end_campaign(&mut env, &campaign.pubkey()).await;
+
+    // Only the admin withdraws.
+    let stranger = funded_keypair(&mut env).await;
+    assert_instruction_error(
+        withdraw(&mut env, &campaign.pubkey(), &stranger, 1).await,
+        InstructionError::InvalidAccountData,
+    );
+
+    // A campaign can't be initialized twice.
+    assert_instruction_error(
+        call(
+            &mut env,
+            CREATE_CAMPAIGN,
+            &campaign_details(admin.pubkey()).try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(campaign.pubkey(), false),
+                AccountMeta::new_readonly(admin.pubkey(), true),
+            ],
+            &[&admin],
+        )
+        .await,
+        InstructionError::AccountAlreadyInitialized,
+    );
+
+    // Campaigns need a goal and a deadline still to come.
+    let program_id = env.program_id;
+    for details in [
+        CampaignDetails {
+            goal: 0,
+            ..campaign_details(admin.pubkey())
+        },
+        CampaignDetails {
+            deadline: 1,
+            ..campaign_details(admin.pubkey())
+        },
+    ] {
+        let data = details.try_to_vec().unwrap();
+        let account = Keypair::new();
+        env.create_account(&account, data.len(), None, &program_id)
+            .await;
+        assert_instruction_error(
+            call(
+                &mut env,
+                CREATE_CAMPAIGN,
+                &data,
+                vec![
+                    AccountMeta::new(account.pubkey(), false),
+                    AccountMeta::new_readonly(admin.pubkey(), true),
+                ],
+                &[&admin],
+            )
+            .await,
+            InstructionError::InvalidInstructionData,
+        );
+    }
+
+    // Receipts are per donor.
+    let other = create_campaign(&mut env, &admin).await;
+    assert_instruction_error(
+        call(
+            &mut env,
+            DONATE,
+            &DonateRequest { amount: 1 }.try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(other.pubkey(), false),
+                AccountMeta::new(
+                    find_receipt_address(&program_id, &other.pubkey(), &stranger.pubkey()).0,
+                    false,
+                ),
+                AccountMeta::new(donor.pubkey(), true),
+                AccountMeta::new_readonly(system_program::id(), false),
+            ],
+            &[&donor],
+        )
+        .await,
+        InstructionError::InvalidSeeds,
+    );
+
+    assert_instruction_error(
+        call(&mut env, 4, &[], Vec::new(), &[]).await,
+        InstructionError::InvalidInstructionData,
+    );
+}
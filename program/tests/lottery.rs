@@ -0,0 +1,2345 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::{
+    assert_borsh_error, assert_instruction_error, lottery_details, ticket_details, Env,
+    LotteryFixture, SECRET, TICKET_PRICE,
+};
+use fanitrade_utils::{
+    allowlist, error::LotteryError, find_pot_authority, find_results_address, find_ticket_address,
+    ledger, nft, oracle, randomness, referral, sponsor, CommitRequest, DrawResults, LotteryDetails,
+    RevealRequest, TicketDetails, WithdrawRequest, TICKET_VOID,
+};
+use solana_program::{
+    instruction::{AccountMeta, InstructionError},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use solana_program_test::tokio;
+use solana_sdk::{
+    account::{Account, AccountSharedData},
+    signature::{Keypair, Signer},
+    transport::TransportError,
+};
+use spl_associated_token_account::get_associated_token_address;
+
+async fn create_lottery(
+    env: &mut Env,
+    details: &LotteryDetails,
+    lamports: Option<u64>,
+    owner: &Pubkey,
+    signer: &Keypair,
+    is_signer: bool,
+) -> (Keypair, Result<(), TransportError>) {
+    let lottery = Keypair::new();
+    let space = details.try_to_vec().unwrap().len();
+    env.create_account(&lottery, space, lamports, owner).await;
+    let signers: &[&Keypair] = if is_signer { &[signer] } else { &[] };
+    let result = env
+        .call(
+            0,
+            &details.try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(lottery.pubkey(), false),
+                AccountMeta::new_readonly(signer.pubkey(), is_signer),
+            ],
+            signers,
+        )
+        .await;
+    (lottery, result)
+}
+
+#[tokio::test]
+async fn create_lottery_initializes_state() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let mut details = lottery_details(admin.pubkey(), Pubkey::new_unique());
+    details.amount_in_pot = 1_000;
+    details.total_entries = 7;
+    let program_id = env.program_id;
+    let (lottery, result) =
+        create_lottery(&mut env, &details, None, &program_id, &admin, true).await;
+    result.unwrap();
+
+    let state: LotteryDetails = env.state(&lottery.pubkey()).await;
+    assert_eq!(state.admin, admin.pubkey());
+    assert_eq!(state.is_initialized, 1);
+    assert_eq!(state.amount_in_pot, 0);
+    assert_eq!(state.total_entries, 0);
+}
+
+#[tokio::test]
+async fn create_lottery_rejects_wrong_owner() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let details = lottery_details(admin.pubkey(), Pubkey::new_unique());
+    let (_, result) = create_lottery(
+        &mut env,
+        &details,
+        None,
+        &Pubkey::new_unique(),
+        &admin,
+        true,
+    )
+    .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn create_lottery_rejects_missing_signer() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let details = lottery_details(admin.pubkey(), Pubkey::new_unique());
+    let program_id = env.program_id;
+    let (_, result) = create_lottery(&mut env, &details, None, &program_id, &admin, false).await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn create_lottery_rejects_wrong_admin() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let details = lottery_details(Pubkey::new_unique(), Pubkey::new_unique());
+    let program_id = env.program_id;
+    let (_, result) = create_lottery(&mut env, &details, None, &program_id, &admin, true).await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+}
+
+#[tokio::test]
+async fn create_lottery_rejects_bad_data() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let lottery = Keypair::new();
+    let program_id = env.program_id;
+    env.create_account(&lottery, 512, None, &program_id).await;
+    let result = env
+        .call(
+            0,
+            &[1, 2, 3],
+            vec![
+                AccountMeta::new(lottery.pubkey(), false),
+                AccountMeta::new_readonly(admin.pubkey(), true),
+            ],
+            &[&admin],
+        )
+        .await;
+    assert_borsh_error(result);
+}
+
+#[tokio::test]
+async fn create_lottery_rejects_insufficient_rent() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let details = lottery_details(admin.pubkey(), Pubkey::new_unique());
+    let program_id = env.program_id;
+    let half_rent = env.rent_exempt(details.try_to_vec().unwrap().len()).await / 2;
+    let (_, result) = create_lottery(
+        &mut env,
+        &details,
+        Some(half_rent),
+        &program_id,
+        &admin,
+        true,
+    )
+    .await;
+    assert_instruction_error(result, InstructionError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn create_lottery_rejects_ticket_tokens_for_classic_lotteries() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let mut details = lottery_details(admin.pubkey(), Pubkey::new_unique());
+    details.pick_count = 0;
+    details.max_number = 0;
+    details.winning_numbers = Vec::new();
+    details.ticket_nfts = 1;
+    let program_id = env.program_id;
+    let (_, result) = create_lottery(&mut env, &details, None, &program_id, &admin, true).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::InvalidGameMode as u32),
+    );
+
+    details.ticket_nfts = 0;
+    let (_, result) = create_lottery(&mut env, &details, None, &program_id, &admin, true).await;
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn create_lottery_rejects_an_initialized_lottery() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    buy_tickets(&mut env, &fixture, &[&[1, 2, 3]]).await;
+
+    let attacker = Keypair::new();
+    let details = lottery_details(attacker.pubkey(), fixture.mint);
+    let result = env
+        .call(
+            0,
+            &details.try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(lottery, false),
+                AccountMeta::new_readonly(attacker.pubkey(), true),
+            ],
+            &[&attacker],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::AccountAlreadyInitialized);
+    let state: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(state.admin, fixture.admin.pubkey());
+    assert_eq!(state.amount_in_pot, TICKET_PRICE);
+}
+
+#[tokio::test]
+async fn play_buys_a_ticket() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let program_id = env.program_id;
+    let (ticket, _) = find_ticket_address(&program_id, &fixture.lottery.pubkey(), 0);
+    let data = ticket_details(&fixture.player.pubkey(), &[1, 2, 3]);
+    env.call(
+        1,
+        &data.try_to_vec().unwrap(),
+        fixture.play_accounts(&ticket),
+        &[&fixture.player],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(env.token_balance(&fixture.pot).await, TICKET_PRICE);
+    assert_eq!(
+        env.token_balance(&fixture.player_tokens).await,
+        9 * TICKET_PRICE
+    );
+    // Both accounts have to be persisted, not just the token transfer.
+    let lottery: LotteryDetails = env.state(&fixture.lottery.pubkey()).await;
+    assert_eq!(lottery.total_entries, 1);
+    assert_eq!(lottery.amount_in_pot, TICKET_PRICE);
+    let ticket: TicketDetails = env.state(&ticket).await;
+    assert_eq!(ticket.player, fixture.player.pubkey().to_string());
+    assert_eq!(ticket.lottery, fixture.lottery.pubkey());
+    assert_eq!(ticket.ticket_index, 0);
+    assert_eq!(ticket.ticket_number_arr[..3], [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn play_rejects_wrong_owner() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let program_id = env.program_id;
+    let foreign_lottery = Keypair::new();
+    env.create_account(&foreign_lottery, 16, None, &system_program::id())
+        .await;
+    let (ticket, _) = find_ticket_address(&program_id, &foreign_lottery.pubkey(), 0);
+    let mut accounts = fixture.play_accounts(&ticket);
+    accounts[0] = AccountMeta::new(foreign_lottery.pubkey(), false);
+    let data = ticket_details(&fixture.player.pubkey(), &[1, 2, 3]);
+    let result = env
+        .call(1, &data.try_to_vec().unwrap(), accounts, &[&fixture.player])
+        .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn play_rejects_missing_signer() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let program_id = env.program_id;
+    let (ticket, _) = find_ticket_address(&program_id, &fixture.lottery.pubkey(), 0);
+    let mut accounts = fixture.play_accounts(&ticket);
+    accounts[2] = AccountMeta::new(fixture.player.pubkey(), false);
+    let data = ticket_details(&fixture.player.pubkey(), &[1, 2, 3]);
+    let result = env
+        .call(1, &data.try_to_vec().unwrap(), accounts, &[])
+        .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn play_rejects_ticket_for_another_player() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let program_id = env.program_id;
+    let (ticket, _) = find_ticket_address(&program_id, &fixture.lottery.pubkey(), 0);
+    let data = ticket_details(&Pubkey::new_unique(), &[1, 2, 3]);
+    let result = env
+        .call(
+            1,
+            &data.try_to_vec().unwrap(),
+            fixture.play_accounts(&ticket),
+            &[&fixture.player],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+}
+
+#[tokio::test]
+async fn play_rejects_wrong_ticket_address() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let program_id = env.program_id;
+    let (ticket, _) = find_ticket_address(&program_id, &fixture.lottery.pubkey(), 1);
+    let data = ticket_details(&fixture.player.pubkey(), &[1, 2, 3]);
+    let result = env
+        .call(
+            1,
+            &data.try_to_vec().unwrap(),
+            fixture.play_accounts(&ticket),
+            &[&fixture.player],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidSeeds);
+}
+
+#[tokio::test]
+async fn play_rejects_invalid_numbers() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let program_id = env.program_id;
+    let (ticket, _) = find_ticket_address(&program_id, &fixture.lottery.pubkey(), 0);
+    let data = ticket_details(&fixture.player.pubkey(), &[1, 1, 11]);
+    let result = env
+        .call(
+            1,
+            &data.try_to_vec().unwrap(),
+            fixture.play_accounts(&ticket),
+            &[&fixture.player],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::InvalidTicketNumbers as u32),
+    );
+}
+
+#[tokio::test]
+async fn play_rejects_bad_data() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let program_id = env.program_id;
+    let (ticket, _) = find_ticket_address(&program_id, &fixture.lottery.pubkey(), 0);
+    let result = env
+        .call(
+            1,
+            &[9, 9, 9],
+            fixture.play_accounts(&ticket),
+            &[&fixture.player],
+        )
+        .await;
+    assert_borsh_error(result);
+}
+
+#[tokio::test]
+async fn play_rejects_insufficient_tokens() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let program_id = env.program_id;
+    let mut lottery: LotteryDetails = env.state(&fixture.lottery.pubkey()).await;
+    lottery.ticket_price = 100 * TICKET_PRICE;
+    env.set_state(&fixture.lottery.pubkey(), &lottery).await;
+
+    let (ticket, _) = find_ticket_address(&program_id, &fixture.lottery.pubkey(), 0);
+    let data = ticket_details(&fixture.player.pubkey(), &[1, 2, 3]);
+    let result = env
+        .call(
+            1,
+            &data.try_to_vec().unwrap(),
+            fixture.play_accounts(&ticket),
+            &[&fixture.player],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(spl_token::error::TokenError::InsufficientFunds as u32),
+    );
+}
+
+#[tokio::test]
+async fn play_appends_to_a_compact_lottery_ledger() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let admin = Keypair::new();
+    let mint_authority = Keypair::new();
+    let mint = env.create_mint(&mint_authority.pubkey()).await;
+    let ledger_account = Keypair::new();
+    env.create_account(&ledger_account, ledger::space(2), None, &program_id)
+        .await;
+    let mut details = lottery_details(admin.pubkey(), mint);
+    details.ticket_ledger = ledger_account.pubkey();
+    let lottery = Keypair::new();
+    env.create_account(
+        &lottery,
+        details.try_to_vec().unwrap().len(),
+        None,
+        &program_id,
+    )
+    .await;
+    env.call(
+        0,
+        &details.try_to_vec().unwrap(),
+        vec![
+            AccountMeta::new(lottery.pubkey(), false),
+            AccountMeta::new_readonly(admin.pubkey(), true),
+            AccountMeta::new(ledger_account.pubkey(), false),
+        ],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    let (pot_authority, _) = find_pot_authority(&program_id, &lottery.pubkey());
+    let pot = env.create_ata(&pot_authority, &mint).await;
+    let player = Keypair::new();
+    env.fund(&player.pubkey(), 1_000_000_000).await;
+    let player_tokens = env.create_ata(&player.pubkey(), &mint).await;
+    env.mint_to(&mint, &player_tokens, &mint_authority, 10 * TICKET_PRICE)
+        .await;
+    let accounts = vec![
+        AccountMeta::new(lottery.pubkey(), false),
+        AccountMeta::new(ledger_account.pubkey(), false),
+        AccountMeta::new(player.pubkey(), true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(pot, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(player_tokens, false),
+    ];
+    for numbers in [[1, 2, 3], [4, 5, 6]] {
+        let data = ticket_details(&player.pubkey(), &numbers);
+        env.call(1, &data.try_to_vec().unwrap(), accounts.clone(), &[&player])
+            .await
+            .unwrap();
+    }
+    let data = ticket_details(&player.pubkey(), &[7, 8, 9]);
+    let result = env
+        .call(1, &data.try_to_vec().unwrap(), accounts, &[&player])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::LedgerFull as u32),
+    );
+
+    let state: LotteryDetails = env.state(&lottery.pubkey()).await;
+    assert_eq!(state.total_entries, 2);
+    assert_eq!(state.amount_in_pot, 2 * TICKET_PRICE);
+    let data = env.account(&ledger_account.pubkey()).await.unwrap().data;
+    assert_eq!(ledger::header(&data).unwrap().lottery, lottery.pubkey());
+    let ticket = ledger::record(&data, 1).unwrap();
+    assert_eq!(ticket.player, player.pubkey());
+    assert_eq!(ticket.numbers[..4], [4, 5, 6, 0]);
+    assert_eq!(ticket.claimed, 0);
+}
+
+#[tokio::test]
+async fn play_mints_a_ticket_token_to_the_player() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture =
+        LotteryFixture::with_details(&mut env, |details| details.ticket_nfts = 1, Vec::new()).await;
+    let lottery = fixture.lottery.pubkey();
+    let player = fixture.player.pubkey();
+    let (ticket, _) = find_ticket_address(&program_id, &lottery, 0);
+    let (ticket_mint, _) = nft::find_ticket_mint(&program_id, &ticket);
+    let (metadata, _) = nft::find_metadata_address(&program_id, &ticket_mint);
+    let player_ticket_tokens = get_associated_token_address(&player, &ticket_mint);
+    let mut accounts = fixture.play_accounts(&ticket);
+    accounts.extend([
+        AccountMeta::new(ticket_mint, false),
+        AccountMeta::new(metadata, false),
+        AccountMeta::new_readonly(find_pot_authority(&program_id, &lottery).0, false),
+        AccountMeta::new(player_ticket_tokens, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ]);
+    let data = ticket_details(&player, &[1, 2, 3]);
+    env.call(1, &data.try_to_vec().unwrap(), accounts, &[&fixture.player])
+        .await
+        .unwrap();
+
+    assert_eq!(env.token_balance(&player_ticket_tokens).await, 1);
+    let metadata: nft::TicketMetadata = env.state(&metadata).await;
+    assert_eq!(metadata.mint, ticket_mint);
+    assert_eq!(metadata.ticket, ticket);
+    assert_eq!(metadata.ticket_index, 0);
+    assert_eq!(metadata.numbers, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn play_rejects_purchases_beyond_max_entries() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture =
+        LotteryFixture::with_details(&mut env, |details| details.max_entries = 1, Vec::new()).await;
+    let lottery = fixture.lottery.pubkey();
+    let data = ticket_details(&fixture.player.pubkey(), &[1, 2, 3])
+        .try_to_vec()
+        .unwrap();
+
+    let (ticket, _) = find_ticket_address(&program_id, &lottery, 0);
+    let accounts = fixture.play_accounts(&ticket);
+    env.call(1, &data, accounts, &[&fixture.player])
+        .await
+        .unwrap();
+    let (ticket, _) = find_ticket_address(&program_id, &lottery, 1);
+    let accounts = fixture.play_accounts(&ticket);
+    let result = env.call(1, &data, accounts, &[&fixture.player]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::SoldOut as u32),
+    );
+}
+
+#[tokio::test]
+async fn play_checks_the_allowlist_and_allocations() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let player = Keypair::new();
+    let leaves = [
+        allowlist::leaf(&Pubkey::new_unique(), 0),
+        allowlist::leaf(&player.pubkey(), 1),
+    ];
+    let root = allowlist::root(&leaves);
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| details.allowlist_root = root,
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    let (entry, _) =
+        allowlist::find_allowlist_entry_address(&program_id, &lottery, &player.pubkey());
+    // The fixture's player isn't on the allowlist, so play as another wallet.
+    env.fund(&player.pubkey(), 1_000_000_000).await;
+    let player_tokens = env.create_ata(&player.pubkey(), &fixture.mint).await;
+    env.mint_to(
+        &fixture.mint,
+        &player_tokens,
+        &fixture.mint_authority,
+        2 * TICKET_PRICE,
+    )
+    .await;
+    let play = |ticket_index: u64, allocation: u64| {
+        let mut data = ticket_details(&player.pubkey(), &[1, 2, 3])
+            .try_to_vec()
+            .unwrap();
+        allowlist::AllowlistProof {
+            allocation,
+            proof: allowlist::proof(&leaves, 1),
+        }
+        .serialize(&mut data)
+        .unwrap();
+        let accounts = vec![
+            AccountMeta::new(lottery, false),
+            AccountMeta::new(
+                find_ticket_address(&program_id, &lottery, ticket_index).0,
+                false,
+            ),
+            AccountMeta::new(player.pubkey(), true),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(fixture.pot, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(player_tokens, false),
+            AccountMeta::new(entry, false),
+        ];
+        (data, accounts)
+    };
+
+    let (data, mut accounts) = play(0, 1);
+    accounts.pop();
+    let data = &data[..data.len() - 8 - 4 - 32];
+    let result = env.call(1, data, accounts, &[&player]).await;
+    assert_borsh_error(result);
+    let (data, accounts) = play(0, 2);
+    let result = env.call(1, &data, accounts, &[&player]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::NotAllowlisted as u32),
+    );
+    let (data, accounts) = play(0, 1);
+    env.call(1, &data, accounts, &[&player]).await.unwrap();
+    let record: allowlist::AllowlistEntry = env.state(&entry).await;
+    assert_eq!(record.tickets, 1);
+    let (data, accounts) = play(1, 1);
+    let result = env.call(1, &data, accounts, &[&player]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::AllocationUsed as u32),
+    );
+}
+
+#[tokio::test]
+async fn play_pays_the_referrers_cut_after_the_draw() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture =
+        LotteryFixture::with_details(&mut env, |details| details.referral_bps = 1_000, Vec::new())
+            .await;
+    let lottery = fixture.lottery.pubkey();
+    let player = fixture.player.pubkey();
+    let referrer = Keypair::new();
+    let referral_accounts = |referrer: &Pubkey| {
+        vec![
+            AccountMeta::new_readonly(*referrer, false),
+            AccountMeta::new(
+                referral::find_referral_address(&program_id, &lottery, referrer).0,
+                false,
+            ),
+            AccountMeta::new(
+                referral::find_referral_address(&program_id, &lottery, &player).0,
+                false,
+            ),
+        ]
+    };
+    let data = ticket_details(&player, &[1, 2, 3]).try_to_vec().unwrap();
+
+    let (ticket, _) = find_ticket_address(&program_id, &lottery, 0);
+    let mut accounts = fixture.play_accounts(&ticket);
+    accounts.extend(referral_accounts(&player));
+    let result = env.call(1, &data, accounts, &[&fixture.player]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::SelfReferral as u32),
+    );
+
+    // Lamports sent to the record's address beforehand don't block it.
+    let (record, _) = referral::find_referral_address(&program_id, &lottery, &referrer.pubkey());
+    env.prefund(&record).await;
+    let mut accounts = fixture.play_accounts(&ticket);
+    accounts.extend(referral_accounts(&referrer.pubkey()));
+    env.call(1, &data, accounts, &[&fixture.player])
+        .await
+        .unwrap();
+
+    assert_eq!(env.token_balance(&fixture.pot).await, TICKET_PRICE);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.amount_in_pot, TICKET_PRICE * 9 / 10);
+    assert_eq!(details.referral_rewards, TICKET_PRICE / 10);
+    let referrer_record: referral::ReferralRecord = env.state(&record).await;
+    assert_eq!(referrer_record.referrals, 1);
+    assert_eq!(referrer_record.rewards, TICKET_PRICE / 10);
+    let (player_record, _) = referral::find_referral_address(&program_id, &lottery, &player);
+    let player_record: referral::ReferralRecord = env.state(&player_record).await;
+    assert_eq!(player_record.referred_by, referrer.pubkey());
+
+    let referrer_tokens = env.create_ata(&referrer.pubkey(), &fixture.mint).await;
+    let claim_accounts = vec![
+        AccountMeta::new(lottery, false),
+        AccountMeta::new(record, false),
+        AccountMeta::new_readonly(referrer.pubkey(), true),
+        AccountMeta::new_readonly(find_pot_authority(&program_id, &lottery).0, false),
+        AccountMeta::new(fixture.pot, false),
+        AccountMeta::new(referrer_tokens, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let result = env
+        .call(20, &[], claim_accounts.clone(), &[&referrer])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::RoundNotDrawn as u32),
+    );
+
+    env.reveal(&lottery, &fixture.admin).await;
+    env.draw(&lottery).await.unwrap();
+    env.tally(&lottery, 1, &[ticket]).await.unwrap();
+    env.call(20, &[], claim_accounts, &[&referrer])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&referrer_tokens).await, TICKET_PRICE / 10);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.referral_rewards, 0);
+    let referrer_record: referral::ReferralRecord = env.state(&record).await;
+    assert_eq!(referrer_record.rewards, 0);
+}
+
+#[tokio::test]
+async fn sponsor_pot_adds_to_the_pot_and_records_the_sponsor() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    let sponsor = Keypair::new();
+    env.fund(&sponsor.pubkey(), 1_000_000_000).await;
+    let sponsor_tokens = env.create_ata(&sponsor.pubkey(), &fixture.mint).await;
+    env.mint_to(&fixture.mint, &sponsor_tokens, &fixture.mint_authority, 500)
+        .await;
+    let (sponsorship, _) =
+        sponsor::find_sponsorship_address(&program_id, &lottery, &sponsor.pubkey());
+    let accounts = vec![
+        AccountMeta::new(lottery, false),
+        AccountMeta::new(sponsorship, false),
+        AccountMeta::new(sponsor.pubkey(), true),
+        AccountMeta::new(sponsor_tokens, false),
+        AccountMeta::new(fixture.pot, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let request = |amount: u64, name: &str| {
+        sponsor::SponsorRequest {
+            amount,
+            name: name.to_string(),
+        }
+        .try_to_vec()
+        .unwrap()
+    };
+
+    let result = env
+        .call(21, &request(0, "Acme"), accounts.clone(), &[&sponsor])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+
+    // Lamports sent to the record's address beforehand don't block it.
+    env.prefund(&sponsorship).await;
+    env.call(21, &request(300, "Acme"), accounts.clone(), &[&sponsor])
+        .await
+        .unwrap();
+    env.call(21, &request(200, "Renamed"), accounts, &[&sponsor])
+        .await
+        .unwrap();
+
+    assert_eq!(env.token_balance(&fixture.pot).await, 500);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.amount_in_pot, 500);
+    let record: sponsor::Sponsorship = env.state(&sponsorship).await;
+    assert_eq!(record.sponsor, sponsor.pubkey());
+    assert_eq!(record.amount, 500);
+    assert_eq!(record.name, "Acme");
+}
+
+#[tokio::test]
+async fn cancel_lottery_refunds_tickets_below_min_entries() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture =
+        LotteryFixture::with_details(&mut env, |details| details.min_entries = 2, Vec::new()).await;
+    let lottery = fixture.lottery.pubkey();
+    let (ticket, _) = find_ticket_address(&program_id, &lottery, 0);
+    let data = ticket_details(&fixture.player.pubkey(), &[1, 2, 3]);
+    env.call(
+        1,
+        &data.try_to_vec().unwrap(),
+        fixture.play_accounts(&ticket),
+        &[&fixture.player],
+    )
+    .await
+    .unwrap();
+
+    let cancel_accounts = vec![AccountMeta::new(lottery, false)];
+    let result = env.call(25, &[], cancel_accounts.clone(), &[]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::LotteryNotClosed as u32),
+    );
+
+    let mut details: LotteryDetails = env.state(&lottery).await;
+    details.lottery_end = "0".to_string();
+    env.set_state(&lottery, &details).await;
+    env.call(25, &[], cancel_accounts, &[]).await.unwrap();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_refunding, 1);
+    assert_eq!(details.amount_in_pot, 0);
+
+    let refund_accounts = vec![
+        AccountMeta::new_readonly(lottery, false),
+        AccountMeta::new(ticket, false),
+        AccountMeta::new_readonly(fixture.player.pubkey(), true),
+        AccountMeta::new_readonly(find_pot_authority(&program_id, &lottery).0, false),
+        AccountMeta::new(fixture.pot, false),
+        AccountMeta::new(fixture.player_tokens, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    env.call(15, &[], refund_accounts, &[&fixture.player])
+        .await
+        .unwrap();
+    assert_eq!(
+        env.token_balance(&fixture.player_tokens).await,
+        10 * TICKET_PRICE
+    );
+    assert_eq!(env.token_balance(&fixture.pot).await, 0);
+}
+
+#[tokio::test]
+async fn cancel_lottery_rejects_ticket_sales_the_pot_cant_hold() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    let mut details: LotteryDetails = env.state(&lottery).await;
+    details.lottery_end = "0".to_string();
+    details.min_entries = 3;
+    details.total_entries = 2;
+    details.ticket_price = u64::MAX;
+    env.set_state(&lottery, &details).await;
+    let cancel_accounts = vec![AccountMeta::new(lottery, false)];
+    let result = env.call(25, &[], cancel_accounts.clone(), &[]).await;
+    assert_instruction_error(result, InstructionError::InvalidArgument);
+
+    details.ticket_price = TICKET_PRICE;
+    details.amount_in_pot = TICKET_PRICE;
+    env.set_state(&lottery, &details).await;
+    let result = env.call(25, &[], cancel_accounts, &[]).await;
+    assert_instruction_error(result, InstructionError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn sponsors_reclaim_their_contributions_when_the_lottery_refunds() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture =
+        LotteryFixture::with_details(&mut env, |details| details.min_entries = 2, Vec::new()).await;
+    let lottery = fixture.lottery.pubkey();
+    let sponsor = env
+        .sponsor_pot(&lottery, &fixture.mint, &fixture.mint_authority, 300)
+        .await;
+    buy_tickets(&mut env, &fixture, &[&[1, 2, 3]]).await;
+    let (sponsorship, _) =
+        sponsor::find_sponsorship_address(&program_id, &lottery, &sponsor.pubkey());
+    let sponsor_tokens = get_associated_token_address(&sponsor.pubkey(), &fixture.mint);
+    let accounts = vec![
+        AccountMeta::new(lottery, false),
+        AccountMeta::new(sponsorship, false),
+        AccountMeta::new_readonly(sponsor.pubkey(), true),
+        AccountMeta::new_readonly(find_pot_authority(&program_id, &lottery).0, false),
+        AccountMeta::new(fixture.pot, false),
+        AccountMeta::new(sponsor_tokens, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+
+    let result = env.call(28, &[], accounts.clone(), &[&sponsor]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::NotRefunding as u32),
+    );
+
+    env.end_sales(&lottery).await;
+    env.call(25, &[], vec![AccountMeta::new(lottery, false)], &[])
+        .await
+        .unwrap();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.amount_in_pot, 300);
+    env.call(28, &[], accounts.clone(), &[&sponsor])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&sponsor_tokens).await, 300);
+    assert_eq!(env.token_balance(&fixture.pot).await, TICKET_PRICE);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.amount_in_pot, 0);
+    let record: sponsor::Sponsorship = env.state(&sponsorship).await;
+    assert_eq!(record.amount, 0);
+
+    let result = env.call(28, &[], accounts, &[&sponsor]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::PrizeAlreadyClaimed as u32),
+    );
+}
+
+/// Has the fixture's player buy a ticket with each of `picks`, returning the
+/// ticket addresses.
+async fn buy_tickets(env: &mut Env, fixture: &LotteryFixture, picks: &[&[u8]]) -> Vec<Pubkey> {
+    let program_id = env.program_id;
+    let mut tickets = Vec::new();
+    for (index, numbers) in picks.iter().enumerate() {
+        let (ticket, _) = find_ticket_address(&program_id, &fixture.lottery.pubkey(), index as u64);
+        let data = ticket_details(&fixture.player.pubkey(), numbers);
+        env.call(
+            1,
+            &data.try_to_vec().unwrap(),
+            fixture.play_accounts(&ticket),
+            &[&fixture.player],
+        )
+        .await
+        .unwrap();
+        tickets.push(ticket);
+    }
+    tickets
+}
+
+fn claim_accounts(
+    program_id: &Pubkey,
+    fixture: &LotteryFixture,
+    ticket: &Pubkey,
+) -> Vec<AccountMeta> {
+    let lottery = fixture.lottery.pubkey();
+    vec![
+        AccountMeta::new_readonly(lottery, false),
+        AccountMeta::new(find_results_address(program_id, &lottery).0, false),
+        AccountMeta::new(*ticket, false),
+        AccountMeta::new_readonly(fixture.player.pubkey(), true),
+        AccountMeta::new_readonly(find_pot_authority(program_id, &lottery).0, false),
+        AccountMeta::new(fixture.pot, false),
+        AccountMeta::new(fixture.player_tokens, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+}
+
+#[tokio::test]
+async fn draw_tallies_tickets_in_batches_before_paying_out() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| {
+            details.pick_count = 1;
+            details.max_number = 3;
+            details.winning_numbers = vec![0];
+            details.prize_split = vec![5_000, 2_000];
+            details.claim_period = 60;
+        },
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    // Every number is on a ticket, so one ticket matches and two don't.
+    let tickets = buy_tickets(&mut env, &fixture, &[&[1], &[2], &[3]]).await;
+    env.reveal(&lottery, &fixture.admin).await;
+    env.draw(&lottery).await.unwrap();
+    assert_instruction_error(
+        env.draw(&lottery).await,
+        InstructionError::Custom(LotteryError::AlreadyDrawn as u32),
+    );
+
+    let (results_address, _) = find_results_address(&program_id, &lottery);
+    let results: DrawResults = env.state(&results_address).await;
+    let winner = usize::from(results.winning_numbers[0] - 1);
+    let loser = (winner + 1) % 3;
+    let result = env
+        .call(
+            10,
+            &[],
+            claim_accounts(&program_id, &fixture, &tickets[winner]),
+            &[&fixture.player],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::NotTallied as u32),
+    );
+
+    env.tally(&lottery, 2, &tickets[..2]).await.unwrap();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_ended, 0);
+    assert_instruction_error(
+        env.tally(&lottery, 1, &tickets[..1]).await,
+        InstructionError::InvalidSeeds,
+    );
+    env.tally(&lottery, 5, &tickets[2..]).await.unwrap();
+    assert_instruction_error(
+        env.tally(&lottery, 1, &tickets[2..]).await,
+        InstructionError::Custom(LotteryError::AlreadyDrawn as u32),
+    );
+
+    // 300 in the pot: 30 in fees, 150 for the match and 60 shared by the
+    // others, with the unwon 60 booked as fees of this one-off lottery.
+    let results: DrawResults = env.state(&results_address).await;
+    assert_eq!(results.tallied, 3);
+    assert_eq!(results.winners_per_tier, vec![1, 2]);
+    assert_eq!(results.prize_per_ticket, vec![150, 30]);
+    assert_eq!(results.unclaimed, 210);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_ended, 1);
+    assert_eq!(details.fees_collected, 90);
+
+    for ticket in [winner, loser] {
+        env.call(
+            10,
+            &[],
+            claim_accounts(&program_id, &fixture, &tickets[ticket]),
+            &[&fixture.player],
+        )
+        .await
+        .unwrap();
+    }
+    assert_eq!(
+        env.token_balance(&fixture.player_tokens).await,
+        7 * TICKET_PRICE + 180
+    );
+    let result = env
+        .call(
+            10,
+            &[],
+            claim_accounts(&program_id, &fixture, &tickets[winner]),
+            &[&fixture.player],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::PrizeAlreadyClaimed as u32),
+    );
+
+    let expire_accounts = vec![
+        AccountMeta::new(lottery, false),
+        AccountMeta::new(results_address, false),
+    ];
+    let result = env.call(11, &[], expire_accounts.clone(), &[]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::ClaimPeriodNotEnded as u32),
+    );
+    // Slots are 400ms apart.
+    env.warp_slots(200).await;
+    env.call(11, &[], expire_accounts, &[]).await.unwrap();
+    let results: DrawResults = env.state(&results_address).await;
+    assert_eq!(results.unclaimed, 0);
+    assert_eq!(results.is_expired, 1);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.fees_collected, 120);
+}
+
+#[tokio::test]
+async fn classic_draw_picks_a_ticket_per_tier_and_pays_out() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| {
+            details.pick_count = 0;
+            details.max_number = 0;
+            details.winning_numbers = Vec::new();
+            details.prize_split = vec![5_000, 3_000];
+        },
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    let tickets = buy_tickets(&mut env, &fixture, &[&[], &[], &[]]).await;
+    env.reveal(&lottery, &fixture.admin).await;
+    env.draw(&lottery).await.unwrap();
+
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_ended, 1);
+    let results: DrawResults = env
+        .state(&find_results_address(&program_id, &lottery).0)
+        .await;
+    let winners = results.winning_tickets.clone();
+    assert_eq!(winners.len(), 2);
+    assert_ne!(winners[0], winners[1]);
+    assert_eq!(results.prize_per_ticket, vec![150, 90]);
+    assert_instruction_error(
+        env.tally(&lottery, 1, &tickets[..1]).await,
+        InstructionError::Custom(LotteryError::AlreadyDrawn as u32),
+    );
+
+    let loser = (0..3).find(|index| !winners.contains(index)).unwrap() as usize;
+    let result = env
+        .call(
+            10,
+            &[],
+            claim_accounts(&program_id, &fixture, &tickets[loser]),
+            &[&fixture.player],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::NotAWinner as u32),
+    );
+    for winner in &winners {
+        env.call(
+            10,
+            &[],
+            claim_accounts(&program_id, &fixture, &tickets[*winner as usize]),
+            &[&fixture.player],
+        )
+        .await
+        .unwrap();
+    }
+    assert_eq!(
+        env.token_balance(&fixture.player_tokens).await,
+        7 * TICKET_PRICE + 240
+    );
+}
+
+/// A sponsor funded with `amount` tokens of a new mint, other than the
+/// lottery's, returning the sponsor, the mint and their token account.
+async fn prize_sponsor(env: &mut Env, amount: u64) -> (Keypair, Pubkey, Pubkey) {
+    let sponsor = Keypair::new();
+    env.fund(&sponsor.pubkey(), 1_000_000_000).await;
+    let mint_authority = Keypair::new();
+    let prize_mint = env.create_mint(&mint_authority.pubkey()).await;
+    let sponsor_tokens = env.create_ata(&sponsor.pubkey(), &prize_mint).await;
+    env.mint_to(&prize_mint, &sponsor_tokens, &mint_authority, amount)
+        .await;
+    (sponsor, prize_mint, sponsor_tokens)
+}
+
+fn sponsor_prize_accounts(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    prize_index: u64,
+    sponsor: &Pubkey,
+    prize_mint: &Pubkey,
+    sponsor_tokens: &Pubkey,
+) -> Vec<AccountMeta> {
+    let (pot_authority, _) = find_pot_authority(program_id, lottery);
+    vec![
+        AccountMeta::new(*lottery, false),
+        AccountMeta::new(
+            sponsor::find_prize_address(program_id, lottery, prize_index).0,
+            false,
+        ),
+        AccountMeta::new(*sponsor, true),
+        AccountMeta::new_readonly(*prize_mint, false),
+        AccountMeta::new(*sponsor_tokens, false),
+        AccountMeta::new_readonly(pot_authority, false),
+        AccountMeta::new(
+            get_associated_token_address(&pot_authority, prize_mint),
+            false,
+        ),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ]
+}
+
+fn claim_share_accounts(
+    program_id: &Pubkey,
+    fixture: &LotteryFixture,
+    ticket: &Pubkey,
+    ticket_index: u64,
+    prize_mint: &Pubkey,
+) -> Vec<AccountMeta> {
+    let lottery = fixture.lottery.pubkey();
+    let (prize, _) = sponsor::find_prize_address(program_id, &lottery, 0);
+    let (pot_authority, _) = find_pot_authority(program_id, &lottery);
+    let player = fixture.player.pubkey();
+    vec![
+        AccountMeta::new_readonly(lottery, false),
+        AccountMeta::new_readonly(find_results_address(program_id, &lottery).0, false),
+        AccountMeta::new(prize, false),
+        AccountMeta::new(
+            sponsor::find_share_address(program_id, &prize, ticket_index).0,
+            false,
+        ),
+        AccountMeta::new_readonly(*ticket, false),
+        AccountMeta::new(player, true),
+        AccountMeta::new_readonly(pot_authority, false),
+        AccountMeta::new(
+            get_associated_token_address(&pot_authority, prize_mint),
+            false,
+        ),
+        AccountMeta::new(get_associated_token_address(&player, prize_mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]
+}
+
+fn reclaim_prize_accounts(
+    program_id: &Pubkey,
+    lottery: &Pubkey,
+    sponsor: &Pubkey,
+    prize_mint: &Pubkey,
+) -> Vec<AccountMeta> {
+    let (pot_authority, _) = find_pot_authority(program_id, lottery);
+    vec![
+        AccountMeta::new_readonly(*lottery, false),
+        AccountMeta::new(sponsor::find_prize_address(program_id, lottery, 0).0, false),
+        AccountMeta::new_readonly(*sponsor, true),
+        AccountMeta::new_readonly(pot_authority, false),
+        AccountMeta::new(
+            get_associated_token_address(&pot_authority, prize_mint),
+            false,
+        ),
+        AccountMeta::new(get_associated_token_address(sponsor, prize_mint), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+}
+
+#[tokio::test]
+async fn sponsored_prize_pays_its_tier_and_returns_the_rest_to_its_sponsor() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| {
+            details.pick_count = 1;
+            details.max_number = 3;
+            details.winning_numbers = vec![0];
+            details.prize_split = vec![5_000, 2_000];
+            details.claim_period = 60;
+        },
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    let (sponsor, prize_mint, sponsor_tokens) = prize_sponsor(&mut env, 5).await;
+    let accounts = sponsor_prize_accounts(
+        &program_id,
+        &lottery,
+        0,
+        &sponsor.pubkey(),
+        &prize_mint,
+        &sponsor_tokens,
+    );
+    let request = |tier, amount| sponsor::PrizeRequest { tier, amount }.try_to_vec().unwrap();
+    let result = env
+        .call(22, &request(2, 5), accounts.clone(), &[&sponsor])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+    // Lamports sent to the prize's address beforehand don't block it.
+    env.prefund(&accounts[1].pubkey).await;
+    env.call(22, &request(1, 5), accounts.clone(), &[&sponsor])
+        .await
+        .unwrap();
+    let prize: sponsor::SponsoredPrize = env.state(&accounts[1].pubkey).await;
+    assert_eq!(prize.sponsor, sponsor.pubkey());
+    assert_eq!((prize.tier, prize.amount), (1, 5));
+    assert_eq!(env.token_balance(&accounts[6].pubkey).await, 5);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.sponsored_prizes, 1);
+
+    let reclaim_accounts =
+        reclaim_prize_accounts(&program_id, &lottery, &sponsor.pubkey(), &prize_mint);
+    let prize_index = sponsor::PrizeClaimRequest { prize_index: 0 }
+        .try_to_vec()
+        .unwrap();
+    let result = env
+        .call(24, &prize_index, reclaim_accounts.clone(), &[&sponsor])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::RoundNotDrawn as u32),
+    );
+
+    // One ticket matches and the other two share the second tier's prize.
+    let tickets = buy_tickets(&mut env, &fixture, &[&[1], &[2], &[3]]).await;
+    env.create_ata(&fixture.player.pubkey(), &prize_mint).await;
+    env.reveal(&lottery, &fixture.admin).await;
+    env.draw(&lottery).await.unwrap();
+    env.tally(&lottery, 3, &tickets).await.unwrap();
+    let results: DrawResults = env
+        .state(&find_results_address(&program_id, &lottery).0)
+        .await;
+    let winner = u64::from(results.winning_numbers[0] - 1);
+    let mut others = (0..3).filter(|index| *index != winner);
+    let (first, second) = (others.next().unwrap(), others.next().unwrap());
+    let claim_share = |index: u64| {
+        claim_share_accounts(
+            &program_id,
+            &fixture,
+            &tickets[index as usize],
+            index,
+            &prize_mint,
+        )
+    };
+    let result = env
+        .call(23, &prize_index, claim_share(winner), &[&fixture.player])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::NotAWinner as u32),
+    );
+    env.call(23, &prize_index, claim_share(first), &[&fixture.player])
+        .await
+        .unwrap();
+    let player_prize_tokens = get_associated_token_address(&fixture.player.pubkey(), &prize_mint);
+    // The first winner gets what doesn't split evenly.
+    assert_eq!(env.token_balance(&player_prize_tokens).await, 3);
+    let result = env
+        .call(23, &prize_index, claim_share(first), &[&fixture.player])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::PrizeAlreadyClaimed as u32),
+    );
+
+    let mut reclaim_accounts = reclaim_accounts;
+    reclaim_accounts.push(AccountMeta::new_readonly(
+        find_results_address(&program_id, &lottery).0,
+        false,
+    ));
+    let result = env
+        .call(24, &prize_index, reclaim_accounts.clone(), &[&sponsor])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::ClaimPeriodNotEnded as u32),
+    );
+    // Slots are 400ms apart.
+    env.warp_slots(200).await;
+    let result = env
+        .call(23, &prize_index, claim_share(second), &[&fixture.player])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::ClaimPeriodEnded as u32),
+    );
+    env.call(24, &prize_index, reclaim_accounts.clone(), &[&sponsor])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&sponsor_tokens).await, 2);
+    let result = env
+        .call(24, &prize_index, reclaim_accounts, &[&sponsor])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::PrizeAlreadyClaimed as u32),
+    );
+}
+
+#[tokio::test]
+async fn sponsored_prize_goes_back_whole_when_the_lottery_refunds() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture =
+        LotteryFixture::with_details(&mut env, |details| details.min_entries = 2, Vec::new()).await;
+    let lottery = fixture.lottery.pubkey();
+    let (sponsor, prize_mint, sponsor_tokens) = prize_sponsor(&mut env, 5).await;
+    let request = sponsor::PrizeRequest { tier: 0, amount: 5 }
+        .try_to_vec()
+        .unwrap();
+    env.call(
+        22,
+        &request,
+        sponsor_prize_accounts(
+            &program_id,
+            &lottery,
+            0,
+            &sponsor.pubkey(),
+            &prize_mint,
+            &sponsor_tokens,
+        ),
+        &[&sponsor],
+    )
+    .await
+    .unwrap();
+    buy_tickets(&mut env, &fixture, &[&[1, 2, 3]]).await;
+    env.end_sales(&lottery).await;
+    env.call(25, &[], vec![AccountMeta::new(lottery, false)], &[])
+        .await
+        .unwrap();
+
+    let prize_index = sponsor::PrizeClaimRequest { prize_index: 0 }
+        .try_to_vec()
+        .unwrap();
+    let reclaim_accounts =
+        reclaim_prize_accounts(&program_id, &lottery, &sponsor.pubkey(), &prize_mint);
+    let impostor = Keypair::new();
+    let mut impostor_accounts = reclaim_accounts.clone();
+    impostor_accounts[2] = AccountMeta::new_readonly(impostor.pubkey(), true);
+    let result = env
+        .call(24, &prize_index, impostor_accounts, &[&impostor])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+    env.call(24, &prize_index, reclaim_accounts, &[&sponsor])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&sponsor_tokens).await, 5);
+    let prize: sponsor::SponsoredPrize = env
+        .state(&sponsor::find_prize_address(&program_id, &lottery, 0).0)
+        .await;
+    assert_eq!(prize.is_reclaimed, 1);
+}
+
+/// Has the fixture's player buy ticket `index` of a lottery with ticket
+/// tokens, returning the ticket and the player's account holding its token.
+async fn buy_ticket_token(
+    env: &mut Env,
+    fixture: &LotteryFixture,
+    index: u64,
+    numbers: &[u8],
+) -> (Pubkey, Pubkey) {
+    let program_id = env.program_id;
+    let lottery = fixture.lottery.pubkey();
+    let player = fixture.player.pubkey();
+    let (ticket, _) = find_ticket_address(&program_id, &lottery, index);
+    let (ticket_mint, _) = nft::find_ticket_mint(&program_id, &ticket);
+    let player_ticket_tokens = get_associated_token_address(&player, &ticket_mint);
+    let mut accounts = fixture.play_accounts(&ticket);
+    accounts.extend([
+        AccountMeta::new(ticket_mint, false),
+        AccountMeta::new(
+            nft::find_metadata_address(&program_id, &ticket_mint).0,
+            false,
+        ),
+        AccountMeta::new_readonly(find_pot_authority(&program_id, &lottery).0, false),
+        AccountMeta::new(player_ticket_tokens, false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ]);
+    let data = ticket_details(&player, numbers);
+    env.call(1, &data.try_to_vec().unwrap(), accounts, &[&fixture.player])
+        .await
+        .unwrap();
+    (ticket, player_ticket_tokens)
+}
+
+/// Sends the token of `ticket` from the fixture's player to a new holder,
+/// returning the holder, their account holding the token and their account
+/// of the lottery's mint.
+async fn give_ticket_token(
+    env: &mut Env,
+    fixture: &LotteryFixture,
+    ticket: &Pubkey,
+    player_ticket_tokens: &Pubkey,
+) -> (Keypair, Pubkey, Pubkey) {
+    let program_id = env.program_id;
+    let holder = Keypair::new();
+    let (ticket_mint, _) = nft::find_ticket_mint(&program_id, ticket);
+    let holder_ticket_tokens = env.create_ata(&holder.pubkey(), &ticket_mint).await;
+    env.process(
+        &[spl_token::instruction::transfer(
+            &spl_token::id(),
+            player_ticket_tokens,
+            &holder_ticket_tokens,
+            &fixture.player.pubkey(),
+            &[],
+            1,
+        )
+        .unwrap()],
+        &[&fixture.player],
+    )
+    .await
+    .unwrap();
+    let holder_tokens = env.create_ata(&holder.pubkey(), &fixture.mint).await;
+    (holder, holder_ticket_tokens, holder_tokens)
+}
+
+/// Claim accounts of `ticket` for `claimer`, paid to `claimer_tokens`, with
+/// their account holding the ticket's token at the end.
+fn token_claim_accounts(
+    mut accounts: Vec<AccountMeta>,
+    claimer: &Pubkey,
+    claimer_tokens: &Pubkey,
+    claimer_ticket_tokens: &Pubkey,
+) -> Vec<AccountMeta> {
+    accounts[3] = AccountMeta::new_readonly(*claimer, true);
+    let last = accounts.len() - 2;
+    accounts[last] = AccountMeta::new(*claimer_tokens, false);
+    accounts.push(AccountMeta::new_readonly(*claimer_ticket_tokens, false));
+    accounts
+}
+
+#[tokio::test]
+async fn ticket_token_holder_claims_the_prize() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| {
+            details.ticket_nfts = 1;
+            details.pick_count = 1;
+            details.max_number = 3;
+            details.winning_numbers = vec![0];
+            details.prize_split = vec![5_000, 2_000];
+        },
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    let (ticket, player_ticket_tokens) = buy_ticket_token(&mut env, &fixture, 0, &[1]).await;
+    let (holder, holder_ticket_tokens, holder_tokens) =
+        give_ticket_token(&mut env, &fixture, &ticket, &player_ticket_tokens).await;
+    env.reveal(&lottery, &fixture.admin).await;
+    env.draw(&lottery).await.unwrap();
+    env.tally(&lottery, 1, &[ticket]).await.unwrap();
+
+    // The buyer no longer holds the token.
+    let result = env
+        .call(
+            10,
+            &[],
+            token_claim_accounts(
+                claim_accounts(&program_id, &fixture, &ticket),
+                &fixture.player.pubkey(),
+                &fixture.player_tokens,
+                &player_ticket_tokens,
+            ),
+            &[&fixture.player],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    env.call(
+        10,
+        &[],
+        token_claim_accounts(
+            claim_accounts(&program_id, &fixture, &ticket),
+            &holder.pubkey(),
+            &holder_tokens,
+            &holder_ticket_tokens,
+        ),
+        &[&holder],
+    )
+    .await
+    .unwrap();
+    // 100 in the pot: 10 in fees, 50 for a match and 20 otherwise.
+    let results: DrawResults = env
+        .state(&find_results_address(&program_id, &lottery).0)
+        .await;
+    let prize = if results.winning_numbers[0] == 1 {
+        50
+    } else {
+        20
+    };
+    assert_eq!(env.token_balance(&holder_tokens).await, prize);
+    assert_eq!(
+        env.token_balance(&fixture.player_tokens).await,
+        9 * TICKET_PRICE
+    );
+}
+
+#[tokio::test]
+async fn ticket_token_holder_claims_the_refund() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| {
+            details.ticket_nfts = 1;
+            details.min_entries = 2;
+        },
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    // Lamports sent to the token's addresses beforehand don't block the sale.
+    let (ticket, _) = find_ticket_address(&program_id, &lottery, 0);
+    let (ticket_mint, _) = nft::find_ticket_mint(&program_id, &ticket);
+    env.prefund(&ticket_mint).await;
+    env.prefund(&nft::find_metadata_address(&program_id, &ticket_mint).0)
+        .await;
+    let (ticket, player_ticket_tokens) = buy_ticket_token(&mut env, &fixture, 0, &[1, 2, 3]).await;
+    let (holder, holder_ticket_tokens, holder_tokens) =
+        give_ticket_token(&mut env, &fixture, &ticket, &player_ticket_tokens).await;
+    env.end_sales(&lottery).await;
+    env.call(25, &[], vec![AccountMeta::new(lottery, false)], &[])
+        .await
+        .unwrap();
+
+    let refund_accounts = vec![
+        AccountMeta::new_readonly(lottery, false),
+        AccountMeta::new(ticket, false),
+        AccountMeta::new_readonly(fixture.player.pubkey(), true),
+        AccountMeta::new_readonly(find_pot_authority(&program_id, &lottery).0, false),
+        AccountMeta::new(fixture.pot, false),
+        AccountMeta::new(fixture.player_tokens, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    let mut player_accounts = refund_accounts.clone();
+    player_accounts.push(AccountMeta::new_readonly(player_ticket_tokens, false));
+    let result = env.call(15, &[], player_accounts, &[&fixture.player]).await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    let mut holder_accounts = refund_accounts;
+    holder_accounts[2] = AccountMeta::new_readonly(holder.pubkey(), true);
+    holder_accounts[5] = AccountMeta::new(holder_tokens, false);
+    holder_accounts.push(AccountMeta::new_readonly(holder_ticket_tokens, false));
+    env.call(15, &[], holder_accounts, &[&holder])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&holder_tokens).await, TICKET_PRICE);
+    assert_eq!(env.token_balance(&fixture.pot).await, 0);
+}
+
+#[tokio::test]
+async fn void_ticket_needs_its_token_burned_and_the_draw_skips_it() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| {
+            details.ticket_nfts = 1;
+            details.pick_count = 1;
+            details.max_number = 3;
+            details.winning_numbers = vec![0];
+            details.prize_split = vec![5_000, 2_000];
+        },
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    let (ticket, player_ticket_tokens) = buy_ticket_token(&mut env, &fixture, 0, &[1]).await;
+    let (other_ticket, _) = buy_ticket_token(&mut env, &fixture, 1, &[2]).await;
+    let (ticket_mint, _) = nft::find_ticket_mint(&program_id, &ticket);
+    let void_accounts = vec![
+        AccountMeta::new_readonly(lottery, false),
+        AccountMeta::new(ticket, false),
+        AccountMeta::new_readonly(ticket_mint, false),
+    ];
+    let result = env.call(19, &[], void_accounts.clone(), &[]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::TicketNotBurned as u32),
+    );
+
+    env.process(
+        &[spl_token::instruction::burn(
+            &spl_token::id(),
+            &player_ticket_tokens,
+            &ticket_mint,
+            &fixture.player.pubkey(),
+            &[],
+            1,
+        )
+        .unwrap()],
+        &[&fixture.player],
+    )
+    .await
+    .unwrap();
+    env.call(19, &[], void_accounts.clone(), &[]).await.unwrap();
+    let ticket_data: TicketDetails = env.state(&ticket).await;
+    assert_eq!(ticket_data.claimed, TICKET_VOID);
+    let result = env.call(19, &[], void_accounts, &[]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::PrizeAlreadyClaimed as u32),
+    );
+
+    env.reveal(&lottery, &fixture.admin).await;
+    env.draw(&lottery).await.unwrap();
+    env.tally(&lottery, 2, &[ticket, other_ticket])
+        .await
+        .unwrap();
+    let results: DrawResults = env
+        .state(&find_results_address(&program_id, &lottery).0)
+        .await;
+    assert_eq!(results.tallied, 2);
+    assert_eq!(results.winners_per_tier.iter().sum::<u64>(), 1);
+}
+
+fn commit_accounts(fixture: &LotteryFixture) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(fixture.lottery.pubkey(), false),
+        AccountMeta::new_readonly(fixture.admin.pubkey(), true),
+    ]
+}
+
+fn reveal_accounts(fixture: &LotteryFixture) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(fixture.lottery.pubkey(), false),
+        AccountMeta::new_readonly(fixture.admin.pubkey(), true),
+        AccountMeta::new_readonly(sysvar::slot_hashes::id(), false),
+    ]
+}
+
+async fn reveal(
+    env: &mut Env,
+    fixture: &LotteryFixture,
+    secret: &[u8],
+) -> Result<(), TransportError> {
+    let request = RevealRequest {
+        secret: secret.to_vec(),
+    };
+    env.call(
+        13,
+        &request.try_to_vec().unwrap(),
+        reveal_accounts(fixture),
+        &[&fixture.admin],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn reveal_mixes_in_the_hash_of_the_slot_after_sales_close() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    let commit = CommitRequest {
+        commitment: randomness::commitment(SECRET),
+    };
+    env.call(
+        12,
+        &commit.try_to_vec().unwrap(),
+        commit_accounts(&fixture),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    let result = env
+        .call(
+            12,
+            &commit.try_to_vec().unwrap(),
+            commit_accounts(&fixture),
+            &[&fixture.admin],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::AlreadyCommitted as u32),
+    );
+
+    // Sales close after lottery_end, and only once.
+    assert_instruction_error(
+        env.close_sales(&lottery, &fixture.admin).await,
+        InstructionError::Custom(LotteryError::LotteryNotClosed as u32),
+    );
+    assert_instruction_error(
+        reveal(&mut env, &fixture, SECRET).await,
+        InstructionError::Custom(LotteryError::LotteryNotClosed as u32),
+    );
+    env.end_sales(&lottery).await;
+    env.close_sales(&lottery, &fixture.admin).await.unwrap();
+    let close_slot = env.clock().await.slot;
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.close_slot, close_slot);
+    env.warp_slots(1).await;
+    assert_instruction_error(
+        env.close_sales(&lottery, &fixture.admin).await,
+        InstructionError::Custom(LotteryError::LotteryClosed as u32),
+    );
+    // The next slot's hash isn't in SlotHashes until the slot after it.
+    assert_instruction_error(
+        reveal(&mut env, &fixture, SECRET).await,
+        InstructionError::Custom(LotteryError::LotteryNotClosed as u32),
+    );
+
+    env.warp_slots(1).await;
+    assert_instruction_error(
+        reveal(&mut env, &fixture, b"another secret").await,
+        InstructionError::Custom(LotteryError::InvalidReveal as u32),
+    );
+    reveal(&mut env, &fixture, SECRET).await.unwrap();
+    let slot_hashes = env.account(&sysvar::slot_hashes::id()).await.unwrap().data;
+    let slot_hash = randomness::slot_hash_after(&slot_hashes, close_slot).unwrap();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_revealed, 1);
+    assert_eq!(
+        details.randomness,
+        randomness::derive_randomness(&slot_hash, &lottery, SECRET)
+    );
+    let result = env
+        .call(14, &[], vec![AccountMeta::new(lottery, false)], &[])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::AlreadyRevealed as u32),
+    );
+}
+
+#[tokio::test]
+async fn slash_bond_refunds_once_the_reveal_window_passes() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    let commit = CommitRequest {
+        commitment: randomness::commitment(SECRET),
+    };
+    env.call(
+        12,
+        &commit.try_to_vec().unwrap(),
+        commit_accounts(&fixture),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    let slash_accounts = vec![AccountMeta::new(lottery, false)];
+    let result = env.call(14, &[], slash_accounts.clone(), &[]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::RevealWindowNotPassed as u32),
+    );
+
+    env.end_sales(&lottery).await;
+    env.close_sales(&lottery, &fixture.admin).await.unwrap();
+    env.warp_slots(randomness::REVEAL_SLOTS).await;
+    let result = env.call(14, &[], slash_accounts.clone(), &[]).await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::RevealWindowNotPassed as u32),
+    );
+    env.warp_slots(1).await;
+    assert_instruction_error(
+        reveal(&mut env, &fixture, SECRET).await,
+        InstructionError::Custom(LotteryError::RevealWindowPassed as u32),
+    );
+    env.call(14, &[], slash_accounts, &[]).await.unwrap();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_refunding, 1);
+    assert_eq!(details.is_ended, 1);
+
+    // Sales nobody closed leave the admin REVEAL_WINDOW after lottery_end.
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    env.call(
+        12,
+        &commit.try_to_vec().unwrap(),
+        commit_accounts(&fixture),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    let mut details: LotteryDetails = env.state(&lottery).await;
+    let now = env.clock().await.unix_timestamp;
+    details.lottery_end = (now - randomness::REVEAL_WINDOW - 1).to_string();
+    env.set_state(&lottery, &details).await;
+    env.call(14, &[], vec![AccountMeta::new(lottery, false)], &[])
+        .await
+        .unwrap();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_refunding, 1);
+}
+
+/// Gives `fixture`'s lottery a bond of `bond` in its pot, commits the admin's
+/// secret and lets the reveal window pass.
+async fn miss_the_reveal(env: &mut Env, fixture: &LotteryFixture, bond: u64) {
+    let lottery = fixture.lottery.pubkey();
+    let mut details: LotteryDetails = env.state(&lottery).await;
+    details.bond_amount = bond;
+    env.set_state(&lottery, &details).await;
+    env.mint_to(&fixture.mint, &fixture.pot, &fixture.mint_authority, bond)
+        .await;
+    let commit = CommitRequest {
+        commitment: randomness::commitment(SECRET),
+    };
+    env.call(
+        12,
+        &commit.try_to_vec().unwrap(),
+        commit_accounts(fixture),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    env.end_sales(&lottery).await;
+    env.close_sales(&lottery, &fixture.admin).await.unwrap();
+    env.warp_slots(randomness::REVEAL_SLOTS + 1).await;
+}
+
+fn slash_accounts(program_id: &Pubkey, fixture: &LotteryFixture) -> Vec<AccountMeta> {
+    let lottery = fixture.lottery.pubkey();
+    vec![
+        AccountMeta::new(lottery, false),
+        AccountMeta::new(
+            get_associated_token_address(&fixture.admin.pubkey(), &fixture.mint),
+            false,
+        ),
+        AccountMeta::new_readonly(find_pot_authority(program_id, &lottery).0, false),
+        AccountMeta::new(fixture.pot, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ]
+}
+
+#[tokio::test]
+async fn slash_bond_returns_what_the_tickets_cant_share_to_the_admin() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+
+    // Without tickets, the whole bond goes back.
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    let admin_tokens = env.create_ata(&fixture.admin.pubkey(), &fixture.mint).await;
+    miss_the_reveal(&mut env, &fixture, 100).await;
+    let result = env
+        .call(14, &[], vec![AccountMeta::new(lottery, false)], &[])
+        .await;
+    assert_instruction_error(result, InstructionError::NotEnoughAccountKeys);
+    env.call(14, &[], slash_accounts(&program_id, &fixture), &[])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&admin_tokens).await, 100);
+    assert_eq!(env.token_balance(&fixture.pot).await, 0);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.bond_amount, 0);
+    assert_eq!(details.is_refunding, 1);
+
+    // With two tickets, the odd token of a bond of 101 goes back and the
+    // tickets refund 50 each on top of their price.
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    let admin_tokens = env.create_ata(&fixture.admin.pubkey(), &fixture.mint).await;
+    let tickets = buy_tickets(&mut env, &fixture, &[&[1, 2, 3], &[4, 5, 6]]).await;
+    miss_the_reveal(&mut env, &fixture, 101).await;
+    env.call(14, &[], slash_accounts(&program_id, &fixture), &[])
+        .await
+        .unwrap();
+    assert_eq!(env.token_balance(&admin_tokens).await, 1);
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.bond_amount, 100);
+    for ticket in tickets {
+        let refund_accounts = vec![
+            AccountMeta::new_readonly(lottery, false),
+            AccountMeta::new(ticket, false),
+            AccountMeta::new_readonly(fixture.player.pubkey(), true),
+            AccountMeta::new_readonly(find_pot_authority(&program_id, &lottery).0, false),
+            AccountMeta::new(fixture.pot, false),
+            AccountMeta::new(fixture.player_tokens, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ];
+        env.call(15, &[], refund_accounts, &[&fixture.player])
+            .await
+            .unwrap();
+    }
+    assert_eq!(
+        env.token_balance(&fixture.player_tokens).await,
+        10 * TICKET_PRICE + 100
+    );
+    assert_eq!(env.token_balance(&fixture.pot).await, 0);
+}
+
+#[tokio::test]
+async fn close_sales_leaves_the_admin_the_grace_period_to_reveal() {
+    let mut env = Env::start().await;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    let commit = CommitRequest {
+        commitment: randomness::commitment(SECRET),
+    };
+    env.call(
+        12,
+        &commit.try_to_vec().unwrap(),
+        commit_accounts(&fixture),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    env.end_sales(&lottery).await;
+
+    // A stranger can't start the reveal window right after lottery_end...
+    let stranger = Keypair::new();
+    assert_instruction_error(
+        env.close_sales(&lottery, &stranger).await,
+        InstructionError::Custom(LotteryError::CloseGraceNotPassed as u32),
+    );
+    // ...or long after the REVEAL_SLOTS it would have left the admin.
+    env.warp_slots(4 * randomness::REVEAL_SLOTS).await;
+    assert_instruction_error(
+        env.close_sales(&lottery, &stranger).await,
+        InstructionError::Custom(LotteryError::CloseGraceNotPassed as u32),
+    );
+    let result = env
+        .call(14, &[], vec![AccountMeta::new(lottery, false)], &[])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::RevealWindowNotPassed as u32),
+    );
+    env.close_sales(&lottery, &fixture.admin).await.unwrap();
+    env.warp_slots(2).await;
+    reveal(&mut env, &fixture, SECRET).await.unwrap();
+
+    // Once the grace period has passed, a stranger closes for the admin, who
+    // still reveals within REVEAL_SLOTS.
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    env.call(
+        12,
+        &commit.try_to_vec().unwrap(),
+        commit_accounts(&fixture),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    let mut details: LotteryDetails = env.state(&lottery).await;
+    let now = env.clock().await.unix_timestamp;
+    details.lottery_end = (now - randomness::CLOSE_GRACE).to_string();
+    env.set_state(&lottery, &details).await;
+    env.close_sales(&lottery, &stranger).await.unwrap();
+    env.warp_slots(randomness::REVEAL_SLOTS).await;
+    reveal(&mut env, &fixture, SECRET).await.unwrap();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_revealed, 1);
+}
+
+fn request_accounts(env: &Env, lottery: &Pubkey, oracle_program_id: &Pubkey) -> Vec<AccountMeta> {
+    let (requester, _) = oracle::find_requester_address(&env.program_id, lottery);
+    vec![
+        AccountMeta::new(*lottery, false),
+        AccountMeta::new(env.payer(), true),
+        AccountMeta::new_readonly(requester, false),
+        AccountMeta::new(
+            oracle::find_proof_address(oracle_program_id, &requester).0,
+            false,
+        ),
+        AccountMeta::new_readonly(*oracle_program_id, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]
+}
+
+fn fulfill_accounts(env: &Env, lottery: &Pubkey, proof: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(*proof, false),
+        AccountMeta::new(*lottery, false),
+        AccountMeta::new(env.payer(), true),
+        AccountMeta::new(find_results_address(&env.program_id, lottery).0, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ]
+}
+
+#[tokio::test]
+async fn oracle_request_is_fulfilled_and_drawn_from() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let oracle_program_id = env.oracle_program_id;
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| details.randomness_oracle = oracle_program_id,
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    let tickets = buy_tickets(&mut env, &fixture, &[&[1, 2, 3]]).await;
+    let (requester, _) = oracle::find_requester_address(&program_id, &lottery);
+    let (proof, _) = oracle::find_proof_address(&oracle_program_id, &requester);
+
+    // Oracle lotteries don't take commit-reveal.
+    let commit = CommitRequest {
+        commitment: randomness::commitment(SECRET),
+    };
+    let result = env
+        .call(
+            12,
+            &commit.try_to_vec().unwrap(),
+            commit_accounts(&fixture),
+            &[&fixture.admin],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::WrongRandomnessSource as u32),
+    );
+    let result = env
+        .call(
+            16,
+            &[],
+            request_accounts(&env, &lottery, &oracle_program_id),
+            &[],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::LotteryNotClosed as u32),
+    );
+
+    env.end_sales(&lottery).await;
+    let other_oracle = Pubkey::new_unique();
+    let result = env
+        .call(
+            16,
+            &[],
+            request_accounts(&env, &lottery, &other_oracle),
+            &[],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::WrongRandomnessSource as u32),
+    );
+    env.call(
+        16,
+        &[],
+        request_accounts(&env, &lottery, &oracle_program_id),
+        &[],
+    )
+    .await
+    .unwrap();
+    let request: oracle::RandomnessProof = env.state(&proof).await;
+    assert_eq!(request.requester, requester);
+    assert_eq!(request.is_fulfilled, 0);
+    let result = env
+        .call(
+            16,
+            &[],
+            request_accounts(&env, &lottery, &oracle_program_id),
+            &[],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::AlreadyCommitted as u32),
+    );
+    let result = env
+        .call(17, &[], fulfill_accounts(&env, &lottery, &proof), &[])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::NotRevealed as u32),
+    );
+
+    let authority = Keypair::new();
+    env.process(
+        &[oracle::fulfill(
+            &oracle_program_id,
+            &proof,
+            &authority.pubkey(),
+            [7; 32],
+        )],
+        &[&authority],
+    )
+    .await
+    .unwrap();
+    env.call(17, &[], fulfill_accounts(&env, &lottery, &proof), &[])
+        .await
+        .unwrap();
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_revealed, 1);
+    assert_eq!(details.randomness, [7; 32]);
+    let results: DrawResults = env
+        .state(&find_results_address(&program_id, &lottery).0)
+        .await;
+    assert_eq!(results.winning_numbers.len(), 3);
+    env.tally(&lottery, 1, &tickets).await.unwrap();
+    let result = env
+        .call(17, &[], fulfill_accounts(&env, &lottery, &proof), &[])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::AlreadyRevealed as u32),
+    );
+}
+
+#[tokio::test]
+async fn fulfill_randomness_rejects_forged_proofs() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let oracle_program_id = env.oracle_program_id;
+    let fixture = LotteryFixture::with_details(
+        &mut env,
+        |details| details.randomness_oracle = oracle_program_id,
+        Vec::new(),
+    )
+    .await;
+    let lottery = fixture.lottery.pubkey();
+    env.end_sales(&lottery).await;
+    env.call(
+        16,
+        &[],
+        request_accounts(&env, &lottery, &oracle_program_id),
+        &[],
+    )
+    .await
+    .unwrap();
+    let (requester, _) = oracle::find_requester_address(&program_id, &lottery);
+    let forged = oracle::RandomnessProof {
+        requester,
+        request_slot: env.clock().await.slot,
+        is_fulfilled: 1,
+        randomness: [9; 32],
+    };
+    let data = forged.try_to_vec().unwrap();
+    let lamports = env.rent_exempt(data.len()).await;
+
+    // A fulfilled proof from another oracle, at that oracle's proof address.
+    let other_oracle = Pubkey::new_unique();
+    let (other_proof, _) = oracle::find_proof_address(&other_oracle, &requester);
+    let account = Account {
+        lamports,
+        data: data.clone(),
+        owner: other_oracle,
+        ..Account::default()
+    };
+    env.context
+        .set_account(&other_proof, &AccountSharedData::from(account));
+    let result = env
+        .call(17, &[], fulfill_accounts(&env, &lottery, &other_proof), &[])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::WrongRandomnessSource as u32),
+    );
+
+    // An account of the right oracle that isn't the lottery's proof account.
+    let stray_proof = Pubkey::new_unique();
+    let account = Account {
+        lamports,
+        data,
+        owner: oracle_program_id,
+        ..Account::default()
+    };
+    env.context
+        .set_account(&stray_proof, &AccountSharedData::from(account));
+    let result = env
+        .call(17, &[], fulfill_accounts(&env, &lottery, &stray_proof), &[])
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidSeeds);
+
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.is_revealed, 0);
+}
+
+/// Lottery fixture with `fees` collected and matching tokens in the pot.
+async fn lottery_with_fees(env: &mut Env, fees: u64) -> (LotteryFixture, Pubkey) {
+    let fixture = LotteryFixture::new(env).await;
+    let mut lottery: LotteryDetails = env.state(&fixture.lottery.pubkey()).await;
+    lottery.fees_collected = fees;
+    env.set_state(&fixture.lottery.pubkey(), &lottery).await;
+    env.mint_to(&fixture.mint, &fixture.pot, &fixture.mint_authority, fees)
+        .await;
+    let admin_tokens = env.create_ata(&fixture.admin.pubkey(), &fixture.mint).await;
+    (fixture, admin_tokens)
+}
+
+fn withdraw_accounts(
+    program_id: &Pubkey,
+    fixture: &LotteryFixture,
+    admin: &Pubkey,
+    admin_tokens: &Pubkey,
+) -> Vec<AccountMeta> {
+    let (pot_authority, _) = find_pot_authority(program_id, &fixture.lottery.pubkey());
+    vec![
+        AccountMeta::new(fixture.lottery.pubkey(), false),
+        AccountMeta::new_readonly(*admin, true),
+        AccountMeta::new_readonly(fixture.mint, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(fixture.pot, false),
+        AccountMeta::new(*admin_tokens, false),
+        AccountMeta::new_readonly(pot_authority, false),
+    ]
+}
+
+#[tokio::test]
+async fn withdraw_pays_collected_fees() {
+    let mut env = Env::start().await;
+    let (fixture, admin_tokens) = lottery_with_fees(&mut env, 500).await;
+    let program_id = env.program_id;
+    let request = WithdrawRequest { amount: 300 };
+    env.call(
+        2,
+        &request.try_to_vec().unwrap(),
+        withdraw_accounts(
+            &program_id,
+            &fixture,
+            &fixture.admin.pubkey(),
+            &admin_tokens,
+        ),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(env.token_balance(&admin_tokens).await, 300);
+    assert_eq!(env.token_balance(&fixture.pot).await, 200);
+    let lottery: LotteryDetails = env.state(&fixture.lottery.pubkey()).await;
+    assert_eq!(lottery.fees_collected, 200);
+}
+
+#[tokio::test]
+async fn withdraw_pays_a_one_off_lotterys_unwon_pot() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let fixture = LotteryFixture::new(&mut env).await;
+    let lottery = fixture.lottery.pubkey();
+    env.sponsor_pot(&lottery, &fixture.mint, &fixture.mint_authority, 500)
+        .await;
+    env.reveal(&lottery, &fixture.admin).await;
+    env.draw(&lottery).await.unwrap();
+
+    // Without tickets no tier is won, and there's no next round to take it.
+    let details: LotteryDetails = env.state(&lottery).await;
+    assert_eq!(details.amount_in_pot, 0);
+    assert_eq!(details.fees_collected, 500);
+    let admin_tokens = env.create_ata(&fixture.admin.pubkey(), &fixture.mint).await;
+    let request = WithdrawRequest { amount: 500 };
+    env.call(
+        2,
+        &request.try_to_vec().unwrap(),
+        withdraw_accounts(
+            &program_id,
+            &fixture,
+            &fixture.admin.pubkey(),
+            &admin_tokens,
+        ),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.token_balance(&admin_tokens).await, 500);
+    assert_eq!(env.token_balance(&fixture.pot).await, 0);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_more_than_fees() {
+    let mut env = Env::start().await;
+    let (fixture, admin_tokens) = lottery_with_fees(&mut env, 500).await;
+    let program_id = env.program_id;
+    let request = WithdrawRequest { amount: 501 };
+    let result = env
+        .call(
+            2,
+            &request.try_to_vec().unwrap(),
+            withdraw_accounts(
+                &program_id,
+                &fixture,
+                &fixture.admin.pubkey(),
+                &admin_tokens,
+            ),
+            &[&fixture.admin],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_wrong_admin() {
+    let mut env = Env::start().await;
+    let (fixture, _) = lottery_with_fees(&mut env, 500).await;
+    let program_id = env.program_id;
+    let impostor = Keypair::new();
+    let impostor_tokens = env.create_ata(&impostor.pubkey(), &fixture.mint).await;
+    let request = WithdrawRequest { amount: 500 };
+    let result = env
+        .call(
+            2,
+            &request.try_to_vec().unwrap(),
+            withdraw_accounts(&program_id, &fixture, &impostor.pubkey(), &impostor_tokens),
+            &[&impostor],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_missing_signer() {
+    let mut env = Env::start().await;
+    let (fixture, admin_tokens) = lottery_with_fees(&mut env, 500).await;
+    let program_id = env.program_id;
+    let mut accounts = withdraw_accounts(
+        &program_id,
+        &fixture,
+        &fixture.admin.pubkey(),
+        &admin_tokens,
+    );
+    accounts[1] = AccountMeta::new_readonly(fixture.admin.pubkey(), false);
+    let request = WithdrawRequest { amount: 500 };
+    let result = env
+        .call(2, &request.try_to_vec().unwrap(), accounts, &[])
+        .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_wrong_owner() {
+    let mut env = Env::start().await;
+    let (fixture, admin_tokens) = lottery_with_fees(&mut env, 500).await;
+    let program_id = env.program_id;
+    let foreign_lottery = Keypair::new();
+    env.create_account(&foreign_lottery, 16, None, &system_program::id())
+        .await;
+    let mut accounts = withdraw_accounts(
+        &program_id,
+        &fixture,
+        &fixture.admin.pubkey(),
+        &admin_tokens,
+    );
+    accounts[0] = AccountMeta::new(foreign_lottery.pubkey(), false);
+    let request = WithdrawRequest { amount: 500 };
+    let result = env
+        .call(
+            2,
+            &request.try_to_vec().unwrap(),
+            accounts,
+            &[&fixture.admin],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn withdraw_rejects_bad_data() {
+    let mut env = Env::start().await;
+    let (fixture, admin_tokens) = lottery_with_fees(&mut env, 500).await;
+    let program_id = env.program_id;
+    let result = env
+        .call(
+            2,
+            &[1],
+            withdraw_accounts(
+                &program_id,
+                &fixture,
+                &fixture.admin.pubkey(),
+                &admin_tokens,
+            ),
+            &[&fixture.admin],
+        )
+        .await;
+    assert_borsh_error(result);
+}
@@ -0,0 +1,230 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::{assert_instruction_error, Env, TICKET_PRICE};
+use fanitrade_utils::{
+    error::LotteryError, find_pot_authority, find_round_address, sponsor, LotteryDetails,
+    LotterySeries,
+};
+use solana_program::{
+    instruction::{AccountMeta, InstructionError},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+use solana_program_test::tokio;
+use solana_sdk::signature::{Keypair, Signer};
+use spl_associated_token_account::get_associated_token_address;
+
+fn series_details(admin: Pubkey, token_mint: Pubkey) -> LotterySeries {
+    LotterySeries {
+        admin,
+        name: "Weekly".to_string(),
+        is_initialized: 0,
+        token_mint,
+        ticket_price: TICKET_PRICE,
+        round_duration: 7 * 24 * 60 * 60,
+        fee_bps: 1_000,
+        prize_split: vec![5_000, 3_000, 1_000],
+        pick_count: 3,
+        max_number: 10,
+        claim_period: 3_600,
+        current_round: 0,
+        randomness_oracle: Pubkey::default(),
+    }
+}
+
+/// Round `round` of `series` and its pot.
+fn round_accounts(program_id: &Pubkey, series: &Pubkey, mint: &Pubkey, round: u64) -> [Pubkey; 3] {
+    let (round, _) = find_round_address(program_id, series, round);
+    let (pot_authority, _) = find_pot_authority(program_id, &round);
+    let pot = get_associated_token_address(&pot_authority, mint);
+    [round, pot_authority, pot]
+}
+
+fn start_round_accounts(env: &Env, series: &Pubkey, mint: &Pubkey, round: u64) -> Vec<AccountMeta> {
+    let [round_account, pot_authority, pot] = round_accounts(&env.program_id, series, mint, round);
+    let mut accounts = vec![
+        AccountMeta::new(*series, false),
+        AccountMeta::new(env.payer(), true),
+        AccountMeta::new(round_account, false),
+        AccountMeta::new_readonly(pot_authority, false),
+        AccountMeta::new(pot, false),
+        AccountMeta::new_readonly(*mint, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new_readonly(spl_associated_token_account::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+    ];
+    if round > 0 {
+        let [previous, previous_pot_authority, previous_pot] =
+            round_accounts(&env.program_id, series, mint, round - 1);
+        accounts.extend([
+            AccountMeta::new(previous, false),
+            AccountMeta::new_readonly(previous_pot_authority, false),
+            AccountMeta::new(previous_pot, false),
+        ]);
+    }
+    accounts
+}
+
+#[tokio::test]
+async fn series_rounds_carry_their_pot_over() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let admin = Keypair::new();
+    let mint_authority = Keypair::new();
+    let mint = env.create_mint(&mint_authority.pubkey()).await;
+    let series = Keypair::new();
+    let details = series_details(admin.pubkey(), mint);
+    let data = details.try_to_vec().unwrap();
+    env.create_account(&series, data.len(), None, &program_id)
+        .await;
+    let create_accounts = vec![
+        AccountMeta::new(series.pubkey(), false),
+        AccountMeta::new_readonly(admin.pubkey(), true),
+    ];
+
+    let mut zero_duration = series_details(admin.pubkey(), mint);
+    zero_duration.round_duration = 0;
+    let result = env
+        .call(
+            3,
+            &zero_duration.try_to_vec().unwrap(),
+            create_accounts.clone(),
+            &[&admin],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+    env.call(3, &data, create_accounts, &[&admin])
+        .await
+        .unwrap();
+
+    let series = series.pubkey();
+    env.call(4, &[], start_round_accounts(&env, &series, &mint, 0), &[])
+        .await
+        .unwrap();
+    let [first_round, _, first_pot] = round_accounts(&program_id, &series, &mint, 0);
+    let round: LotteryDetails = env.state(&first_round).await;
+    assert_eq!(round.name, "Weekly #0");
+    assert_eq!(round.series, series);
+    assert_eq!(round.admin, admin.pubkey());
+    assert_eq!(round.ticket_price, TICKET_PRICE);
+    let state: LotterySeries = env.state(&series).await;
+    assert_eq!(state.is_initialized, 1);
+    assert_eq!(state.current_round, 1);
+
+    let result = env
+        .call(4, &[], start_round_accounts(&env, &series, &mint, 1), &[])
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::RoundNotDrawn as u32),
+    );
+
+    // Nobody plays the first round, so its whole pot but the fee rolls over.
+    env.sponsor_pot(&first_round, &mint, &mint_authority, 1_000)
+        .await;
+    env.reveal(&first_round, &admin).await;
+    env.draw(&first_round).await.unwrap();
+    let round: LotteryDetails = env.state(&first_round).await;
+    assert_eq!(round.fees_collected, 100);
+    assert_eq!(round.amount_in_pot, 900);
+
+    // Lamports sent to the next round's address don't stop it being started.
+    let [second_round, _, second_pot] = round_accounts(&program_id, &series, &mint, 1);
+    env.prefund(&second_round).await;
+    env.call(4, &[], start_round_accounts(&env, &series, &mint, 1), &[])
+        .await
+        .unwrap();
+    let round: LotteryDetails = env.state(&second_round).await;
+    assert_eq!(round.name, "Weekly #1");
+    assert_eq!(round.round, 1);
+    assert_eq!(round.amount_in_pot, 900);
+    assert_eq!(env.token_balance(&second_pot).await, 900);
+    assert_eq!(env.token_balance(&first_pot).await, 100);
+    let round: LotteryDetails = env.state(&first_round).await;
+    assert_eq!(round.amount_in_pot, 0);
+}
+
+#[tokio::test]
+async fn refunding_rounds_keep_their_sponsorships_when_the_next_round_starts() {
+    let mut env = Env::start().await;
+    let program_id = env.program_id;
+    let admin = Keypair::new();
+    let mint_authority = Keypair::new();
+    let mint = env.create_mint(&mint_authority.pubkey()).await;
+    let series = Keypair::new();
+    let data = series_details(admin.pubkey(), mint).try_to_vec().unwrap();
+    env.create_account(&series, data.len(), None, &program_id)
+        .await;
+    env.call(
+        3,
+        &data,
+        vec![
+            AccountMeta::new(series.pubkey(), false),
+            AccountMeta::new_readonly(admin.pubkey(), true),
+        ],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    let series = series.pubkey();
+    env.call(4, &[], start_round_accounts(&env, &series, &mint, 0), &[])
+        .await
+        .unwrap();
+    let [first_round, first_pot_authority, first_pot] =
+        round_accounts(&program_id, &series, &mint, 0);
+
+    // The round holds a jackpot carried over from before as well as what a
+    // sponsor added, then its randomness is never revealed.
+    let sponsor = env
+        .sponsor_pot(&first_round, &mint, &mint_authority, 1_000)
+        .await;
+    env.mint_to(&mint, &first_pot, &mint_authority, 400).await;
+    let mut round: LotteryDetails = env.state(&first_round).await;
+    assert_eq!(round.sponsored_amount, 1_000);
+    round.amount_in_pot += 400;
+    round.lottery_end = "0".to_string();
+    env.set_state(&first_round, &round).await;
+    env.call(14, &[], vec![AccountMeta::new(first_round, false)], &[])
+        .await
+        .unwrap();
+
+    // Only the jackpot rolls over.
+    env.call(4, &[], start_round_accounts(&env, &series, &mint, 1), &[])
+        .await
+        .unwrap();
+    let [second_round, _, second_pot] = round_accounts(&program_id, &series, &mint, 1);
+    let round: LotteryDetails = env.state(&second_round).await;
+    assert_eq!(round.amount_in_pot, 400);
+    assert_eq!(env.token_balance(&second_pot).await, 400);
+    let round: LotteryDetails = env.state(&first_round).await;
+    assert_eq!(round.amount_in_pot, 1_000);
+    assert_eq!(env.token_balance(&first_pot).await, 1_000);
+
+    let sponsor_tokens = get_associated_token_address(&sponsor.pubkey(), &mint);
+    env.call(
+        28,
+        &[],
+        vec![
+            AccountMeta::new(first_round, false),
+            AccountMeta::new(
+                sponsor::find_sponsorship_address(&program_id, &first_round, &sponsor.pubkey()).0,
+                false,
+            ),
+            AccountMeta::new_readonly(sponsor.pubkey(), true),
+            AccountMeta::new_readonly(first_pot_authority, false),
+            AccountMeta::new(first_pot, false),
+            AccountMeta::new(sponsor_tokens, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        &[&sponsor],
+    )
+    .await
+    .unwrap();
+    assert_eq!(env.token_balance(&sponsor_tokens).await, 1_000);
+    assert_eq!(env.token_balance(&first_pot).await, 0);
+    let round: LotteryDetails = env.state(&first_round).await;
+    assert_eq!(round.amount_in_pot, 0);
+    assert_eq!(round.sponsored_amount, 0);
+}
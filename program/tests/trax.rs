@@ -0,0 +1,516 @@
+mod common;
+
+use borsh::BorshSerialize;
+use common::{assert_borsh_error, assert_instruction_error, Env};
+use fanitrade_utils::{
+    error::LotteryError, MarketDetails, MarketStatusRequest, OptionsBetDetails, ResultDetails,
+    TraxDetails, MARKET_OPEN, MARKET_PAUSED,
+};
+use solana_program::{
+    instruction::{AccountMeta, InstructionError},
+    pubkey::Pubkey,
+};
+use solana_program_test::tokio;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn trax_details(admin: Pubkey) -> TraxDetails {
+    TraxDetails {
+        admin,
+        is_initialized: 0,
+        trax_pool_amount: 42,
+        total_entries: 0,
+        total_markets: 3,
+        active_markets: 3,
+    }
+}
+
+fn market_details(admin: Pubkey) -> MarketDetails {
+    MarketDetails {
+        admin,
+        trax_pub: Pubkey::new_unique().to_string(),
+        market_pair: "SOL/USDC".to_string(),
+        last_price: 100,
+        upper_floor_limit: 120,
+        lower_floor_limit: 80,
+        market_status: 1,
+        markey_apy: 5,
+        options_count: 9,
+        amount_in_pool: 9,
+    }
+}
+
+fn bet_details(player: &Pubkey, market: &Pubkey) -> OptionsBetDetails {
+    OptionsBetDetails {
+        player: player.to_string(),
+        options_market: market.to_string(),
+        options_bet: 1,
+        options_strike: 100,
+        options_spread: 5,
+        options_bet_start: 0,
+        options_bet_end: 60,
+        options_duration: 60,
+        options_bet_amount: 1_000,
+        options_bet_result: "undecided".to_string(),
+    }
+}
+
+/// Creates a program account sized for `data` and runs the instruction `tag`
+/// that initializes it with `data`, signed by `creator` if `is_signer`.
+async fn initialize(
+    env: &mut Env,
+    tag: u8,
+    data: &[u8],
+    lamports: Option<u64>,
+    owner: &Pubkey,
+    creator: &Keypair,
+    is_signer: bool,
+) -> (Keypair, Result<(), solana_sdk::transport::TransportError>) {
+    let account = Keypair::new();
+    env.create_account(&account, data.len(), lamports, owner)
+        .await;
+    let signers: &[&Keypair] = if is_signer { &[creator] } else { &[] };
+    let result = env
+        .call(
+            tag,
+            data,
+            vec![
+                AccountMeta::new(account.pubkey(), false),
+                AccountMeta::new_readonly(creator.pubkey(), is_signer),
+            ],
+            signers,
+        )
+        .await;
+    (account, result)
+}
+
+#[tokio::test]
+async fn initialize_trax_resets_counters() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let data = trax_details(admin.pubkey()).try_to_vec().unwrap();
+    let program_id = env.program_id;
+    let (trax, result) = initialize(&mut env, 5, &data, None, &program_id, &admin, true).await;
+    result.unwrap();
+
+    let state: TraxDetails = env.state(&trax.pubkey()).await;
+    assert_eq!(state.is_initialized, 1);
+    assert_eq!(state.trax_pool_amount, 0);
+    assert_eq!(state.total_markets, 0);
+    assert_eq!(state.active_markets, 0);
+}
+
+#[tokio::test]
+async fn initialize_trax_rejects_invalid_requests() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let program_id = env.program_id;
+    let data = trax_details(admin.pubkey()).try_to_vec().unwrap();
+
+    let (_, result) = initialize(
+        &mut env,
+        5,
+        &data,
+        None,
+        &Pubkey::new_unique(),
+        &admin,
+        true,
+    )
+    .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+
+    let (_, result) = initialize(&mut env, 5, &data, None, &program_id, &admin, false).await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+
+    let other_admin = trax_details(Pubkey::new_unique()).try_to_vec().unwrap();
+    let (_, result) = initialize(&mut env, 5, &other_admin, None, &program_id, &admin, true).await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+
+    let (_, result) = initialize(&mut env, 5, &[1, 2], None, &program_id, &admin, true).await;
+    assert_borsh_error(result);
+
+    let half_rent = env.rent_exempt(data.len()).await / 2;
+    let (_, result) = initialize(
+        &mut env,
+        5,
+        &data,
+        Some(half_rent),
+        &program_id,
+        &admin,
+        true,
+    )
+    .await;
+    assert_instruction_error(result, InstructionError::InsufficientFunds);
+}
+
+#[tokio::test]
+async fn create_market_resets_pool() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let data = market_details(admin.pubkey()).try_to_vec().unwrap();
+    let program_id = env.program_id;
+    let (market, result) = initialize(&mut env, 6, &data, None, &program_id, &admin, true).await;
+    result.unwrap();
+
+    let state: MarketDetails = env.state(&market.pubkey()).await;
+    assert_eq!(state.market_pair, "SOL/USDC");
+    assert_eq!(state.options_count, 0);
+    assert_eq!(state.amount_in_pool, 0);
+}
+
+#[tokio::test]
+async fn create_market_rejects_invalid_requests() {
+    let mut env = Env::start().await;
+    let admin = Keypair::new();
+    let program_id = env.program_id;
+    let data = market_details(admin.pubkey()).try_to_vec().unwrap();
+
+    let (_, result) = initialize(
+        &mut env,
+        6,
+        &data,
+        None,
+        &Pubkey::new_unique(),
+        &admin,
+        true,
+    )
+    .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+
+    let (_, result) = initialize(&mut env, 6, &data, None, &program_id, &admin, false).await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+
+    let other_admin = market_details(Pubkey::new_unique()).try_to_vec().unwrap();
+    let (_, result) = initialize(&mut env, 6, &other_admin, None, &program_id, &admin, true).await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+
+    let (_, result) = initialize(&mut env, 6, &[1, 2], None, &program_id, &admin, true).await;
+    assert_borsh_error(result);
+
+    let (market, result) = initialize(&mut env, 6, &data, None, &program_id, &admin, true).await;
+    result.unwrap();
+    let attacker = Keypair::new();
+    let result = env
+        .call(
+            6,
+            &market_details(attacker.pubkey()).try_to_vec().unwrap(),
+            vec![
+                AccountMeta::new(market.pubkey(), false),
+                AccountMeta::new_readonly(attacker.pubkey(), true),
+            ],
+            &[&attacker],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::AccountAlreadyInitialized);
+
+    let half_rent = env.rent_exempt(data.len()).await / 2;
+    let (_, result) = initialize(
+        &mut env,
+        6,
+        &data,
+        Some(half_rent),
+        &program_id,
+        &admin,
+        true,
+    )
+    .await;
+    assert_instruction_error(result, InstructionError::InsufficientFunds);
+}
+
+/// A created market and a trader with an empty program-owned bet account.
+struct MarketFixture {
+    admin: Keypair,
+    market: Keypair,
+    trader: Keypair,
+    bet: Keypair,
+    bet_data: Vec<u8>,
+    bet_lamports: u64,
+}
+
+impl MarketFixture {
+    async fn new(env: &mut Env) -> Self {
+        let admin = Keypair::new();
+        let program_id = env.program_id;
+        let data = market_details(admin.pubkey()).try_to_vec().unwrap();
+        let (market, result) = initialize(env, 6, &data, None, &program_id, &admin, true).await;
+        result.unwrap();
+
+        let trader = Keypair::new();
+        let bet = Keypair::new();
+        let bet_data = bet_details(&trader.pubkey(), &market.pubkey())
+            .try_to_vec()
+            .unwrap();
+        let bet_lamports = env.rent_exempt(bet_data.len()).await + 1_000;
+        env.create_account(&bet, bet_data.len(), Some(bet_lamports), &program_id)
+            .await;
+        Self {
+            admin,
+            market,
+            trader,
+            bet,
+            bet_data,
+            bet_lamports,
+        }
+    }
+
+    fn place_accounts(&self) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(self.market.pubkey(), false),
+            AccountMeta::new(self.bet.pubkey(), false),
+            AccountMeta::new_readonly(self.trader.pubkey(), true),
+        ]
+    }
+}
+
+#[tokio::test]
+async fn place_option_records_bet() {
+    let mut env = Env::start().await;
+    let fixture = MarketFixture::new(&mut env).await;
+    env.call(
+        7,
+        &fixture.bet_data,
+        fixture.place_accounts(),
+        &[&fixture.trader],
+    )
+    .await
+    .unwrap();
+
+    let market: MarketDetails = env.state(&fixture.market.pubkey()).await;
+    assert_eq!(market.options_count, 1);
+    assert_eq!(market.amount_in_pool, fixture.bet_lamports);
+    let bet: OptionsBetDetails = env.state(&fixture.bet.pubkey()).await;
+    assert_eq!(bet.player, fixture.trader.pubkey().to_string());
+    assert_eq!(bet.options_bet_amount, 1_000);
+}
+
+#[tokio::test]
+async fn place_option_rejects_wrong_owner() {
+    let mut env = Env::start().await;
+    let fixture = MarketFixture::new(&mut env).await;
+    let foreign_bet = Keypair::new();
+    env.create_account(
+        &foreign_bet,
+        fixture.bet_data.len(),
+        None,
+        &Pubkey::new_unique(),
+    )
+    .await;
+    let mut accounts = fixture.place_accounts();
+    accounts[1] = AccountMeta::new(foreign_bet.pubkey(), false);
+    let result = env
+        .call(7, &fixture.bet_data, accounts, &[&fixture.trader])
+        .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn place_option_rejects_missing_signer() {
+    let mut env = Env::start().await;
+    let fixture = MarketFixture::new(&mut env).await;
+    let mut accounts = fixture.place_accounts();
+    accounts[2] = AccountMeta::new_readonly(fixture.trader.pubkey(), false);
+    let result = env.call(7, &fixture.bet_data, accounts, &[]).await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+}
+
+#[tokio::test]
+async fn place_option_rejects_bad_data() {
+    let mut env = Env::start().await;
+    let fixture = MarketFixture::new(&mut env).await;
+    let result = env
+        .call(7, &[3], fixture.place_accounts(), &[&fixture.trader])
+        .await;
+    assert_borsh_error(result);
+}
+
+fn status_accounts(fixture: &MarketFixture, admin: &Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(fixture.market.pubkey(), false),
+        AccountMeta::new_readonly(*admin, true),
+    ]
+}
+
+#[tokio::test]
+async fn paused_market_rejects_bets_until_reopened() {
+    let mut env = Env::start().await;
+    let fixture = MarketFixture::new(&mut env).await;
+    let admin = fixture.admin.pubkey();
+    let pause = MarketStatusRequest {
+        market_status: MARKET_PAUSED,
+    }
+    .try_to_vec()
+    .unwrap();
+    env.call(
+        18,
+        &pause,
+        status_accounts(&fixture, &admin),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    let market: MarketDetails = env.state(&fixture.market.pubkey()).await;
+    assert_eq!(market.market_status, MARKET_PAUSED);
+
+    let result = env
+        .call(
+            7,
+            &fixture.bet_data,
+            fixture.place_accounts(),
+            &[&fixture.trader],
+        )
+        .await;
+    assert_instruction_error(
+        result,
+        InstructionError::Custom(LotteryError::MarketPaused as u32),
+    );
+
+    let reopen = MarketStatusRequest {
+        market_status: MARKET_OPEN,
+    }
+    .try_to_vec()
+    .unwrap();
+    env.call(
+        18,
+        &reopen,
+        status_accounts(&fixture, &admin),
+        &[&fixture.admin],
+    )
+    .await
+    .unwrap();
+    env.call(
+        7,
+        &fixture.bet_data,
+        fixture.place_accounts(),
+        &[&fixture.trader],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn set_market_status_rejects_invalid_requests() {
+    let mut env = Env::start().await;
+    let fixture = MarketFixture::new(&mut env).await;
+    let pause = MarketStatusRequest {
+        market_status: MARKET_PAUSED,
+    }
+    .try_to_vec()
+    .unwrap();
+
+    let impostor = Keypair::new();
+    let result = env
+        .call(
+            18,
+            &pause,
+            status_accounts(&fixture, &impostor.pubkey()),
+            &[&impostor],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidAccountData);
+
+    let unknown = MarketStatusRequest { market_status: 7 }
+        .try_to_vec()
+        .unwrap();
+    let result = env
+        .call(
+            18,
+            &unknown,
+            status_accounts(&fixture, &fixture.admin.pubkey()),
+            &[&fixture.admin],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::InvalidInstructionData);
+
+    let result = env
+        .call(
+            18,
+            &[1],
+            status_accounts(&fixture, &fixture.admin.pubkey()),
+            &[&fixture.admin],
+        )
+        .await;
+    assert_borsh_error(result);
+}
+
+fn result_details(player: &Pubkey, market: &Pubkey) -> Vec<u8> {
+    ResultDetails {
+        player: *player,
+        options_market: *market,
+        final_price: "101".to_string(),
+        result_status: "won".to_string(),
+    }
+    .try_to_vec()
+    .unwrap()
+}
+
+#[tokio::test]
+async fn settle_option_pays_out_bet() {
+    let mut env = Env::start().await;
+    let fixture = MarketFixture::new(&mut env).await;
+    let settler = Keypair::new();
+    let data = result_details(&fixture.trader.pubkey(), &fixture.market.pubkey());
+    env.call(
+        8,
+        &data,
+        vec![
+            AccountMeta::new(fixture.bet.pubkey(), false),
+            AccountMeta::new(settler.pubkey(), true),
+        ],
+        &[&settler],
+    )
+    .await
+    .unwrap();
+
+    assert!(env.account(&fixture.bet.pubkey()).await.is_none());
+    let settler_account = env.account(&settler.pubkey()).await.unwrap();
+    assert_eq!(settler_account.lamports, fixture.bet_lamports);
+}
+
+#[tokio::test]
+async fn settle_option_rejects_invalid_requests() {
+    let mut env = Env::start().await;
+    let fixture = MarketFixture::new(&mut env).await;
+    let settler = Keypair::new();
+    let data = result_details(&fixture.trader.pubkey(), &fixture.market.pubkey());
+
+    let result = env
+        .call(
+            8,
+            &data,
+            vec![
+                AccountMeta::new(fixture.bet.pubkey(), false),
+                AccountMeta::new(settler.pubkey(), false),
+            ],
+            &[],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::MissingRequiredSignature);
+
+    let foreign_bet = Keypair::new();
+    env.create_account(&foreign_bet, 8, None, &Pubkey::new_unique())
+        .await;
+    let result = env
+        .call(
+            8,
+            &data,
+            vec![
+                AccountMeta::new(foreign_bet.pubkey(), false),
+                AccountMeta::new(settler.pubkey(), true),
+            ],
+            &[&settler],
+        )
+        .await;
+    assert_instruction_error(result, InstructionError::IncorrectProgramId);
+
+    let result = env
+        .call(
+            8,
+            &[0],
+            vec![
+                AccountMeta::new(fixture.bet.pubkey(), false),
+                AccountMeta::new(settler.pubkey(), true),
+            ],
+            &[&settler],
+        )
+        .await;
+    assert_borsh_error(result);
+}
@@ -25,6 +25,8 @@ const App = () => {
                 title: e.name,
                 description: e.description,
                 amount: (e.amount_donated).toString(),
+                goal: (e.goal).toString(),
+                deadline: Number(e.deadline),
                 image: e.image_link,
                 id: e.pubId,
               }}
@@ -1,5 +1,5 @@
 import { useState } from "react";
-import { donateToCampaign, getAllCampaigns, withdraw } from "../solana";
+import { donateToCampaign, getAllCampaigns, refund, withdraw } from "../solana";
 
 const Card = ({ data, setCards }) => {
     const [amount, setAmount] = useState(0);
@@ -16,12 +16,24 @@ const Card = ({ data, setCards }) => {
             alert('Withdraw successful!');
         } catch (e) {
             console.log(e);
-            alert("only admin can withdraw");
+            alert("only admin can withdraw, once the goal is reached by the deadline");
         }
         let newCards = await getAllCampaigns();
         setCards(newCards);
 
     }
+    const onRefund = async (e) => {
+        e.preventDefault();
+        try {
+            await refund(data.id);
+            alert('Refund successful!');
+        } catch (e) {
+            console.log(e);
+            alert("refunds open once the deadline passes without the goal reached");
+        }
+        let newCards = await getAllCampaigns();
+        setCards(newCards);
+    }
     return (
         <div>
             <div className="ui card fluid">
@@ -32,7 +44,10 @@ const Card = ({ data, setCards }) => {
                     <div className="header">{data.title}</div>
 
                     <div>
-                        <span>Raised: {data.amount}</span>
+                        <span>Raised: {data.amount} of {data.goal}</span>
+                    </div>
+                    <div>
+                        <span>Deadline: {new Date(data.deadline * 1000).toLocaleString()}</span>
                     </div>
                     <p>{data.description}</p>
 
@@ -50,6 +65,7 @@ const Card = ({ data, setCards }) => {
                             </div>
                         </div>
                     </form>
+                    <button className="ui button" onClick={(e) => onRefund(e)} >Refund my donations</button>
                     <div>Only admin can withdraw </div>
                     <form className="ui form container">
                         <div className="ui grid">
@@ -4,10 +4,12 @@ const Form = ({setRoute}) => {
     const [name, setName] = useState('')
     const [description, setDescription] = useState('')
     const [image, setImageLink] = useState('')
+    const [goal, setGoal] = useState(0)
+    const [deadline, setDeadline] = useState('')
 
     const onSubmit = async (e) => {
         e.preventDefault();
-        await createCampaign(name, description, image);
+        await createCampaign(name, description, image, goal, Math.floor(new Date(deadline).getTime() / 1000));
         setRoute(0);
     }
 
@@ -26,6 +28,14 @@ const Form = ({setRoute}) => {
                 <label>Image Link</label>
                 <input type="text" name="imageLink" placeholder="imageLink" onChange={(e) => setImageLink(e.target.value)} />
             </div>
+            <div className="field">
+                <label>Goal (lamports)</label>
+                <input type="text" name="goal" placeholder="Goal" onChange={(e) => setGoal(e.target.value)} />
+            </div>
+            <div className="field">
+                <label>Deadline</label>
+                <input type="datetime-local" name="deadline" onChange={(e) => setDeadline(e.target.value)} />
+            </div>
             <button className="ui button" type="submit" onClick={onSubmit} >Submit</button>
         </form>
     );
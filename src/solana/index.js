@@ -7,6 +7,7 @@ import {
     TransactionInstruction
 } from "@solana/web3.js";
 import { deserialize, serialize } from "borsh";
+import { CampaignDetails, DonateRequest, SCHEMA, WithdrawRequest } from "./schema";
 
 const cluster = "http://localhost:8899";
 const connection = new Connection(cluster, "confirmed");
@@ -14,6 +15,8 @@ const wallet = new Wallet("https://www.sollet.io", cluster);
 const programId = new PublicKey(
     "54AoQDpavKaoNhd47tayPgL688NEXRFfKjuAjgoN2Btp"
 );
+// First byte of every campaign instruction; the rest of the program is the lottery.
+const CROWDFUNDING = 128;
 
 
 export async function setPayerAndBlockhashTransaction(instructions) {
@@ -43,31 +46,15 @@ export async function signAndSendTransaction(transaction) {
     }
 }
 
-class CampaignDetails {
-    constructor(properties) {
-        Object.keys(properties).forEach((key) => {
-            this[key] = properties[key];
-        });
-    }
-    static schema = new Map([[CampaignDetails,
-        {
-            kind: 'struct',
-            fields: [
-                ['admin', [32]],
-                ['name', 'string'],
-                ['description', 'string'],
-                ['image_link', 'string'],
-                ['amount_donated', 'u64']]
-        }]]);
-}
 async function checkWallet() {
     if (!wallet.connected) {
         await wallet.connect();
     }
 }
 
+// goal is in lamports, deadline a unix timestamp in seconds.
 export async function createCampaign(
-    name, description, image_link
+    name, description, image_link, goal, deadline
 ) {
     await checkWallet();
 
@@ -83,11 +70,13 @@ export async function createCampaign(
         description: description,
         image_link: image_link,
         admin: wallet.publicKey.toBuffer(),
-        amount_donated: 0
+        amount_donated: 0,
+        goal: goal,
+        deadline: deadline
     })
 
-    let data = serialize(CampaignDetails.schema, campaign);
-    let data_to_send = new Uint8Array([0, ...data]);
+    let data = serialize(SCHEMA, campaign);
+    let data_to_send = new Uint8Array([CROWDFUNDING, 0, ...data]);
 
     const lamports =
         (await connection.getMinimumBalanceForRentExemption(data.length));
@@ -125,13 +114,15 @@ export async function getAllCampaigns() {
     let x = []
     accounts.forEach((e) => {
         try {
-            let campData = deserialize(CampaignDetails.schema, CampaignDetails, e.account.data);
+            let campData = deserialize(SCHEMA, CampaignDetails, e.account.data);
             x.push({
                 pubId: e.pubkey,
                 name: campData.name,
                 description: campData.description,
                 image_link: campData.image_link,
                 amount_donated: campData.amount_donated,
+                goal: campData.goal,
+                deadline: campData.deadline,
                 admin: campData.admin,
             });
         } catch (err) {
@@ -143,72 +134,75 @@ export async function getAllCampaigns() {
 
 
 
+async function receiptAddress(campaignPubKey, donorPubKey) {
+    const [receipt] = await PublicKey.findProgramAddress(
+        [Buffer.from("receipt"), campaignPubKey.toBuffer(), donorPubKey.toBuffer()],
+        programId
+    );
+    return receipt;
+}
+
+
 export async function donateToCampaign(
     campaignPubKey, amount
 ) {
     await checkWallet();
-
-    const SEED = "abcdef" + Math.random().toString();
-    let newAccount = await PublicKey.createWithSeed(
-        wallet.publicKey,
-        SEED,
-        programId
-    );
-
-    const createProgramAccount = SystemProgram.createAccountWithSeed({
-        fromPubkey: wallet.publicKey,
-        basePubkey: wallet.publicKey,
-        seed: SEED,
-        newAccountPubkey: newAccount,
-        lamports: amount,
-        space: 1,
-        programId: programId,
-    });
+    let donateRequest = new DonateRequest({ amount: amount });
+    let data = serialize(SCHEMA, donateRequest);
+    let data_to_send = new Uint8Array([CROWDFUNDING, 2, ...data]);
 
     const instructionTOOurProgram = new TransactionInstruction({
         keys: [
             { pubkey: campaignPubKey, isSigner: false, isWritable: true },
-            { pubkey: newAccount, isSigner: false, },
-            { pubkey: wallet.publicKey, isSigner: true, }
+            { pubkey: await receiptAddress(campaignPubKey, wallet.publicKey), isSigner: false, isWritable: true },
+            { pubkey: wallet.publicKey, isSigner: true, isWritable: true },
+            { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
         ],
         programId: programId,
-        data: new Uint8Array([2])
+        data: data_to_send
     });
 
 
     const trans = await setPayerAndBlockhashTransaction(
-        [createProgramAccount, instructionTOOurProgram]
+        [instructionTOOurProgram]
     );
     const signature = await signAndSendTransaction(trans);
     const result = await connection.confirmTransaction(signature);
     console.log("end sendMessage", result);
 }
 
+// Pays the wallet's donations back once a campaign has missed its goal.
+export async function refund(
+    campaignPubKey
+) {
+    await checkWallet();
+    const instructionTOOurProgram = new TransactionInstruction({
+        keys: [
+            { pubkey: campaignPubKey, isSigner: false, isWritable: true },
+            { pubkey: await receiptAddress(campaignPubKey, wallet.publicKey), isSigner: false, isWritable: true },
+            { pubkey: wallet.publicKey, isSigner: false, isWritable: true },
+        ],
+        programId: programId,
+        data: new Uint8Array([CROWDFUNDING, 3])
+    });
+    const trans = await setPayerAndBlockhashTransaction(
+        [instructionTOOurProgram]
+    );
+    const signature = await signAndSendTransaction(trans);
+    const result = await connection.confirmTransaction(signature);
+    console.log("end sendMessage", result);
+}
 
 
-class WithdrawRequest {
-    constructor(properties) {
-        Object.keys(properties).forEach((key) => {
-            this[key] = properties[key];
-        });
-    }
-    static schema = new Map([[WithdrawRequest,
-        {
-            kind: 'struct',
-            fields: [
-                ['amount', 'u64'],
-            ]
-        }]]);
 
-}
 
 export async function withdraw(
     campaignPubKey, amount
 ) {
     await checkWallet();
     let withdrawRequest = new WithdrawRequest({ amount: amount });
-    let data = serialize(WithdrawRequest.schema, withdrawRequest);
-    let data_to_send = new Uint8Array([1, ...data]);
+    let data = serialize(SCHEMA, withdrawRequest);
+    let data_to_send = new Uint8Array([CROWDFUNDING, 1, ...data]);
 
     const instructionTOOurProgram = new TransactionInstruction({
         keys: [
@@ -0,0 +1,363 @@
+// Generated from the program's Borsh types by its `schema` binary. Don't edit.
+
+import BN from 'bn.js';
+
+export class AllowlistEntry {
+    constructor(properties: AllowlistEntry);
+    lottery: Uint8Array;
+    wallet: Uint8Array;
+    tickets: BN;
+}
+
+export class AllowlistProof {
+    constructor(properties: AllowlistProof);
+    allocation: BN;
+    proof: Uint8Array[];
+}
+
+export class CampaignDetails {
+    constructor(properties: CampaignDetails);
+    admin: Uint8Array;
+    name: string;
+    description: string;
+    image_link: string;
+    amount_donated: BN;
+    goal: BN;
+    deadline: BN;
+}
+
+export class ClaimRequest {
+    constructor(properties: ClaimRequest);
+    ticket_index: BN;
+}
+
+export class CommitRequest {
+    constructor(properties: CommitRequest);
+    commitment: Uint8Array;
+}
+
+export class DonateRequest {
+    constructor(properties: DonateRequest);
+    amount: BN;
+}
+
+export class DonationReceipt {
+    constructor(properties: DonationReceipt);
+    campaign: Uint8Array;
+    donor: Uint8Array;
+    amount: BN;
+}
+
+export class DrawResults {
+    constructor(properties: DrawResults);
+    lottery: Uint8Array;
+    winning_numbers: number[];
+    winning_tickets: BN[];
+    tallied: BN;
+    winners_per_tier: BN[];
+    first_winners: BN[];
+    prize_per_ticket: BN[];
+    claim_deadline: BN;
+    unclaimed: BN;
+    is_expired: BN;
+}
+
+export class LotteryCreated {
+    constructor(properties: LotteryCreated);
+    lottery: Uint8Array;
+    admin: Uint8Array;
+    token_mint: Uint8Array;
+    series: Uint8Array;
+    round: BN;
+    ticket_price: BN;
+    lottery_start: BN;
+    lottery_end: BN;
+    pick_count: number;
+    max_number: number;
+    amount_in_pot: BN;
+}
+
+export class LotteryDetails {
+    constructor(properties: LotteryDetails);
+    admin: Uint8Array;
+    name: string;
+    is_initialized: BN;
+    is_ended: BN;
+    lottery_start: string;
+    lottery_end: string;
+    ticket_price: BN;
+    amount_in_pot: BN;
+    total_entries: BN;
+    token_mint: Uint8Array;
+    series: Uint8Array;
+    round: BN;
+    fee_bps: number;
+    prize_split: number[];
+    pick_count: number;
+    max_number: number;
+    winning_numbers: number[];
+    claim_period: BN;
+    fees_collected: BN;
+    bond_amount: BN;
+    randomness_commitment: Uint8Array;
+    commit_slot: BN;
+    randomness: Uint8Array;
+    is_revealed: BN;
+    is_refunding: BN;
+    randomness_oracle: Uint8Array;
+    ticket_ledger: Uint8Array;
+    ticket_nfts: BN;
+    referral_bps: number;
+    referral_rewards: BN;
+    sponsored_prizes: BN;
+    min_entries: BN;
+    max_entries: BN;
+    allowlist_root: Uint8Array;
+    close_slot: BN;
+    sponsored_amount: BN;
+}
+
+export class LotteryDrawn {
+    constructor(properties: LotteryDrawn);
+    lottery: Uint8Array;
+    winning_numbers: number[];
+    total_entries: BN;
+    winners_per_tier: BN[];
+    prize_per_ticket: BN[];
+    fee: BN;
+    rollover: BN;
+    claim_deadline: BN;
+}
+
+export class LotterySeries {
+    constructor(properties: LotterySeries);
+    admin: Uint8Array;
+    name: string;
+    is_initialized: BN;
+    token_mint: Uint8Array;
+    ticket_price: BN;
+    round_duration: BN;
+    fee_bps: number;
+    prize_split: number[];
+    pick_count: number;
+    max_number: number;
+    claim_period: BN;
+    current_round: BN;
+    randomness_oracle: Uint8Array;
+}
+
+export class MarketCreated {
+    constructor(properties: MarketCreated);
+    market: Uint8Array;
+    admin: Uint8Array;
+    market_pair: string;
+    last_price: BN;
+    upper_floor_limit: BN;
+    lower_floor_limit: BN;
+}
+
+export class MarketDetails {
+    constructor(properties: MarketDetails);
+    admin: Uint8Array;
+    trax_pub: string;
+    market_pair: string;
+    last_price: BN;
+    upper_floor_limit: BN;
+    lower_floor_limit: BN;
+    market_status: BN;
+    markey_apy: BN;
+    options_count: BN;
+    amount_in_pool: BN;
+}
+
+export class MarketStatusRequest {
+    constructor(properties: MarketStatusRequest);
+    market_status: BN;
+}
+
+export class OptionPlaced {
+    constructor(properties: OptionPlaced);
+    market: Uint8Array;
+    bet: Uint8Array;
+    trader: Uint8Array;
+    options_bet: BN;
+    options_strike: BN;
+    options_bet_end: BN;
+    amount: BN;
+}
+
+export class OptionSettled {
+    constructor(properties: OptionSettled);
+    bet: Uint8Array;
+    market: Uint8Array;
+    player: Uint8Array;
+    settler: Uint8Array;
+    final_price: string;
+    result: string;
+    payout: BN;
+}
+
+export class OptionsBetDetails {
+    constructor(properties: OptionsBetDetails);
+    player: string;
+    options_market: string;
+    options_bet: BN;
+    options_strike: BN;
+    options_spread: BN;
+    options_bet_start: BN;
+    options_bet_end: BN;
+    options_duration: BN;
+    options_bet_amount: BN;
+    options_bet_result: string;
+}
+
+export class PotSponsored {
+    constructor(properties: PotSponsored);
+    lottery: Uint8Array;
+    sponsor: Uint8Array;
+    name: string;
+    amount: BN;
+    amount_in_pot: BN;
+}
+
+export class PrizeClaimRequest {
+    constructor(properties: PrizeClaimRequest);
+    prize_index: BN;
+}
+
+export class PrizeClaimed {
+    constructor(properties: PrizeClaimed);
+    lottery: Uint8Array;
+    ticket: Uint8Array;
+    player: Uint8Array;
+    amount: BN;
+}
+
+export class PrizeRequest {
+    constructor(properties: PrizeRequest);
+    tier: number;
+    amount: BN;
+}
+
+export class PrizeSponsored {
+    constructor(properties: PrizeSponsored);
+    lottery: Uint8Array;
+    sponsor: Uint8Array;
+    prize_index: BN;
+    mint: Uint8Array;
+    tier: number;
+    amount: BN;
+}
+
+export class RandomnessProof {
+    constructor(properties: RandomnessProof);
+    requester: Uint8Array;
+    request_slot: BN;
+    is_fulfilled: BN;
+    randomness: Uint8Array;
+}
+
+export class ReferralRecord {
+    constructor(properties: ReferralRecord);
+    lottery: Uint8Array;
+    wallet: Uint8Array;
+    referred_by: Uint8Array;
+    referrals: BN;
+    rewards: BN;
+}
+
+export class ResultDetails {
+    constructor(properties: ResultDetails);
+    player: Uint8Array;
+    options_market: Uint8Array;
+    final_price: string;
+    result_status: string;
+}
+
+export class RevealRequest {
+    constructor(properties: RevealRequest);
+    secret: number[];
+}
+
+export class ShareReceipt {
+    constructor(properties: ShareReceipt);
+    prize: Uint8Array;
+    ticket_index: BN;
+    amount: BN;
+}
+
+export class SponsorRequest {
+    constructor(properties: SponsorRequest);
+    amount: BN;
+    name: string;
+}
+
+export class SponsoredPrize {
+    constructor(properties: SponsoredPrize);
+    lottery: Uint8Array;
+    sponsor: Uint8Array;
+    mint: Uint8Array;
+    tier: number;
+    amount: BN;
+    claimed: BN;
+    is_reclaimed: BN;
+}
+
+export class Sponsorship {
+    constructor(properties: Sponsorship);
+    lottery: Uint8Array;
+    sponsor: Uint8Array;
+    amount: BN;
+    name: string;
+}
+
+export class TallyRequest {
+    constructor(properties: TallyRequest);
+    count: BN;
+}
+
+export class TicketDetails {
+    constructor(properties: TicketDetails);
+    player: string;
+    ticket_count: BN;
+    ticket_number_arr: Uint8Array;
+    lottery: Uint8Array;
+    ticket_index: BN;
+    claimed: BN;
+}
+
+export class TicketMetadata {
+    constructor(properties: TicketMetadata);
+    mint: Uint8Array;
+    lottery: Uint8Array;
+    ticket: Uint8Array;
+    ticket_index: BN;
+    numbers: number[];
+}
+
+export class TicketPurchased {
+    constructor(properties: TicketPurchased);
+    lottery: Uint8Array;
+    ticket: Uint8Array;
+    player: Uint8Array;
+    ticket_index: BN;
+    numbers: number[];
+    price: BN;
+}
+
+export class TraxDetails {
+    constructor(properties: TraxDetails);
+    admin: Uint8Array;
+    is_initialized: BN;
+    trax_pool_amount: BN;
+    total_entries: BN;
+    total_markets: BN;
+    active_markets: BN;
+}
+
+export class WithdrawRequest {
+    constructor(properties: WithdrawRequest);
+    amount: BN;
+}
+
+export const SCHEMA: Map<Function, { kind: 'struct'; fields: [string, unknown][] }>;
@@ -0,0 +1,695 @@
+// Generated from the program's Borsh types by its `schema` binary. Don't edit.
+
+export class AllowlistEntry {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class AllowlistProof {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class CampaignDetails {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class ClaimRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class CommitRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class DonateRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class DonationReceipt {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class DrawResults {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class LotteryCreated {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class LotteryDetails {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class LotteryDrawn {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class LotterySeries {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class MarketCreated {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class MarketDetails {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class MarketStatusRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class OptionPlaced {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class OptionSettled {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class OptionsBetDetails {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class PotSponsored {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class PrizeClaimRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class PrizeClaimed {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class PrizeRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class PrizeSponsored {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class RandomnessProof {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class ReferralRecord {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class ResultDetails {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class RevealRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class ShareReceipt {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class SponsorRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class SponsoredPrize {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class Sponsorship {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class TallyRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class TicketDetails {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class TicketMetadata {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class TicketPurchased {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class TraxDetails {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export class WithdrawRequest {
+    constructor(properties) {
+        Object.keys(properties).forEach((key) => {
+            this[key] = properties[key];
+        });
+    }
+}
+
+export const SCHEMA = new Map([
+    [AllowlistEntry, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['wallet', [32]],
+            ['tickets', 'u64'],
+        ]
+    }],
+    [AllowlistProof, {
+        kind: 'struct',
+        fields: [
+            ['allocation', 'u64'],
+            ['proof', [[32]]],
+        ]
+    }],
+    [CampaignDetails, {
+        kind: 'struct',
+        fields: [
+            ['admin', [32]],
+            ['name', 'string'],
+            ['description', 'string'],
+            ['image_link', 'string'],
+            ['amount_donated', 'u64'],
+            ['goal', 'u64'],
+            ['deadline', 'u64'],
+        ]
+    }],
+    [ClaimRequest, {
+        kind: 'struct',
+        fields: [
+            ['ticket_index', 'u64'],
+        ]
+    }],
+    [CommitRequest, {
+        kind: 'struct',
+        fields: [
+            ['commitment', [32]],
+        ]
+    }],
+    [DonateRequest, {
+        kind: 'struct',
+        fields: [
+            ['amount', 'u64'],
+        ]
+    }],
+    [DonationReceipt, {
+        kind: 'struct',
+        fields: [
+            ['campaign', [32]],
+            ['donor', [32]],
+            ['amount', 'u64'],
+        ]
+    }],
+    [DrawResults, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['winning_numbers', ['u8']],
+            ['winning_tickets', ['u64']],
+            ['tallied', 'u64'],
+            ['winners_per_tier', ['u64']],
+            ['first_winners', ['u64']],
+            ['prize_per_ticket', ['u64']],
+            ['claim_deadline', 'u64'],
+            ['unclaimed', 'u64'],
+            ['is_expired', 'u64'],
+        ]
+    }],
+    [LotteryCreated, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['admin', [32]],
+            ['token_mint', [32]],
+            ['series', [32]],
+            ['round', 'u64'],
+            ['ticket_price', 'u64'],
+            ['lottery_start', 'u64'],
+            ['lottery_end', 'u64'],
+            ['pick_count', 'u8'],
+            ['max_number', 'u8'],
+            ['amount_in_pot', 'u64'],
+        ]
+    }],
+    [LotteryDetails, {
+        kind: 'struct',
+        fields: [
+            ['admin', [32]],
+            ['name', 'string'],
+            ['is_initialized', 'u64'],
+            ['is_ended', 'u64'],
+            ['lottery_start', 'string'],
+            ['lottery_end', 'string'],
+            ['ticket_price', 'u64'],
+            ['amount_in_pot', 'u64'],
+            ['total_entries', 'u64'],
+            ['token_mint', [32]],
+            ['series', [32]],
+            ['round', 'u64'],
+            ['fee_bps', 'u16'],
+            ['prize_split', ['u16']],
+            ['pick_count', 'u8'],
+            ['max_number', 'u8'],
+            ['winning_numbers', ['u8']],
+            ['claim_period', 'u64'],
+            ['fees_collected', 'u64'],
+            ['bond_amount', 'u64'],
+            ['randomness_commitment', [32]],
+            ['commit_slot', 'u64'],
+            ['randomness', [32]],
+            ['is_revealed', 'u64'],
+            ['is_refunding', 'u64'],
+            ['randomness_oracle', [32]],
+            ['ticket_ledger', [32]],
+            ['ticket_nfts', 'u64'],
+            ['referral_bps', 'u16'],
+            ['referral_rewards', 'u64'],
+            ['sponsored_prizes', 'u64'],
+            ['min_entries', 'u64'],
+            ['max_entries', 'u64'],
+            ['allowlist_root', [32]],
+            ['close_slot', 'u64'],
+            ['sponsored_amount', 'u64'],
+        ]
+    }],
+    [LotteryDrawn, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['winning_numbers', ['u8']],
+            ['total_entries', 'u64'],
+            ['winners_per_tier', ['u64']],
+            ['prize_per_ticket', ['u64']],
+            ['fee', 'u64'],
+            ['rollover', 'u64'],
+            ['claim_deadline', 'u64'],
+        ]
+    }],
+    [LotterySeries, {
+        kind: 'struct',
+        fields: [
+            ['admin', [32]],
+            ['name', 'string'],
+            ['is_initialized', 'u64'],
+            ['token_mint', [32]],
+            ['ticket_price', 'u64'],
+            ['round_duration', 'u64'],
+            ['fee_bps', 'u16'],
+            ['prize_split', ['u16']],
+            ['pick_count', 'u8'],
+            ['max_number', 'u8'],
+            ['claim_period', 'u64'],
+            ['current_round', 'u64'],
+            ['randomness_oracle', [32]],
+        ]
+    }],
+    [MarketCreated, {
+        kind: 'struct',
+        fields: [
+            ['market', [32]],
+            ['admin', [32]],
+            ['market_pair', 'string'],
+            ['last_price', 'u64'],
+            ['upper_floor_limit', 'u64'],
+            ['lower_floor_limit', 'u64'],
+        ]
+    }],
+    [MarketDetails, {
+        kind: 'struct',
+        fields: [
+            ['admin', [32]],
+            ['trax_pub', 'string'],
+            ['market_pair', 'string'],
+            ['last_price', 'u64'],
+            ['upper_floor_limit', 'u64'],
+            ['lower_floor_limit', 'u64'],
+            ['market_status', 'u64'],
+            ['markey_apy', 'u64'],
+            ['options_count', 'u64'],
+            ['amount_in_pool', 'u64'],
+        ]
+    }],
+    [MarketStatusRequest, {
+        kind: 'struct',
+        fields: [
+            ['market_status', 'u64'],
+        ]
+    }],
+    [OptionPlaced, {
+        kind: 'struct',
+        fields: [
+            ['market', [32]],
+            ['bet', [32]],
+            ['trader', [32]],
+            ['options_bet', 'u64'],
+            ['options_strike', 'u64'],
+            ['options_bet_end', 'u64'],
+            ['amount', 'u64'],
+        ]
+    }],
+    [OptionSettled, {
+        kind: 'struct',
+        fields: [
+            ['bet', [32]],
+            ['market', [32]],
+            ['player', [32]],
+            ['settler', [32]],
+            ['final_price', 'string'],
+            ['result', 'string'],
+            ['payout', 'u64'],
+        ]
+    }],
+    [OptionsBetDetails, {
+        kind: 'struct',
+        fields: [
+            ['player', 'string'],
+            ['options_market', 'string'],
+            ['options_bet', 'u64'],
+            ['options_strike', 'u64'],
+            ['options_spread', 'u64'],
+            ['options_bet_start', 'u64'],
+            ['options_bet_end', 'u64'],
+            ['options_duration', 'u64'],
+            ['options_bet_amount', 'u64'],
+            ['options_bet_result', 'string'],
+        ]
+    }],
+    [PotSponsored, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['sponsor', [32]],
+            ['name', 'string'],
+            ['amount', 'u64'],
+            ['amount_in_pot', 'u64'],
+        ]
+    }],
+    [PrizeClaimRequest, {
+        kind: 'struct',
+        fields: [
+            ['prize_index', 'u64'],
+        ]
+    }],
+    [PrizeClaimed, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['ticket', [32]],
+            ['player', [32]],
+            ['amount', 'u64'],
+        ]
+    }],
+    [PrizeRequest, {
+        kind: 'struct',
+        fields: [
+            ['tier', 'u8'],
+            ['amount', 'u64'],
+        ]
+    }],
+    [PrizeSponsored, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['sponsor', [32]],
+            ['prize_index', 'u64'],
+            ['mint', [32]],
+            ['tier', 'u8'],
+            ['amount', 'u64'],
+        ]
+    }],
+    [RandomnessProof, {
+        kind: 'struct',
+        fields: [
+            ['requester', [32]],
+            ['request_slot', 'u64'],
+            ['is_fulfilled', 'u64'],
+            ['randomness', [32]],
+        ]
+    }],
+    [ReferralRecord, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['wallet', [32]],
+            ['referred_by', [32]],
+            ['referrals', 'u64'],
+            ['rewards', 'u64'],
+        ]
+    }],
+    [ResultDetails, {
+        kind: 'struct',
+        fields: [
+            ['player', [32]],
+            ['options_market', [32]],
+            ['final_price', 'string'],
+            ['result_status', 'string'],
+        ]
+    }],
+    [RevealRequest, {
+        kind: 'struct',
+        fields: [
+            ['secret', ['u8']],
+        ]
+    }],
+    [ShareReceipt, {
+        kind: 'struct',
+        fields: [
+            ['prize', [32]],
+            ['ticket_index', 'u64'],
+            ['amount', 'u64'],
+        ]
+    }],
+    [SponsorRequest, {
+        kind: 'struct',
+        fields: [
+            ['amount', 'u64'],
+            ['name', 'string'],
+        ]
+    }],
+    [SponsoredPrize, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['sponsor', [32]],
+            ['mint', [32]],
+            ['tier', 'u8'],
+            ['amount', 'u64'],
+            ['claimed', 'u64'],
+            ['is_reclaimed', 'u64'],
+        ]
+    }],
+    [Sponsorship, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['sponsor', [32]],
+            ['amount', 'u64'],
+            ['name', 'string'],
+        ]
+    }],
+    [TallyRequest, {
+        kind: 'struct',
+        fields: [
+            ['count', 'u64'],
+        ]
+    }],
+    [TicketDetails, {
+        kind: 'struct',
+        fields: [
+            ['player', 'string'],
+            ['ticket_count', 'u64'],
+            ['ticket_number_arr', [128]],
+            ['lottery', [32]],
+            ['ticket_index', 'u64'],
+            ['claimed', 'u64'],
+        ]
+    }],
+    [TicketMetadata, {
+        kind: 'struct',
+        fields: [
+            ['mint', [32]],
+            ['lottery', [32]],
+            ['ticket', [32]],
+            ['ticket_index', 'u64'],
+            ['numbers', ['u8']],
+        ]
+    }],
+    [TicketPurchased, {
+        kind: 'struct',
+        fields: [
+            ['lottery', [32]],
+            ['ticket', [32]],
+            ['player', [32]],
+            ['ticket_index', 'u64'],
+            ['numbers', ['u8']],
+            ['price', 'u64'],
+        ]
+    }],
+    [TraxDetails, {
+        kind: 'struct',
+        fields: [
+            ['admin', [32]],
+            ['is_initialized', 'u64'],
+            ['trax_pool_amount', 'u64'],
+            ['total_entries', 'u64'],
+            ['total_markets', 'u64'],
+            ['active_markets', 'u64'],
+        ]
+    }],
+    [WithdrawRequest, {
+        kind: 'struct',
+        fields: [
+            ['amount', 'u64'],
+        ]
+    }],
+]);